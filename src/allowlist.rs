@@ -0,0 +1,158 @@
+//! Restricting which registered operations untrusted callers may dispatch.
+
+use crate::{ApiExecutor, DispatchError, Registry};
+use std::any::Any;
+
+impl<C> ApiExecutor<C> {
+    /// Permits `execute_with_operation_allowlist` to dispatch the operations registered
+    /// under `names`, in addition to any already allowed.
+    ///
+    /// Once an allowlist is configured it applies to every dispatch by name, including
+    /// [`execute_dynamic`](Self::execute_dynamic), the encoded and schema-validated
+    /// variants, and pipelines and write-ahead log replays built from configuration,
+    /// which reject other names with [`DispatchError::OperationForbidden`].
+    pub fn with_allowlist<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.allowlist.extend(names.into_iter().map(Into::into));
+        self
+    }
+
+    /// Returns true if `name` is on the allowlist.
+    pub fn is_allowed(&self, name: &str) -> bool {
+        self.allowlist.contains(name)
+    }
+
+    /// Rejects `name` if an allowlist is configured and does not include it.
+    pub(crate) fn check_allowlist(&self, name: &str) -> Result<(), DispatchError> {
+        if self.allowlist.is_empty() || self.is_allowed(name) {
+            Ok(())
+        } else {
+            Err(DispatchError::OperationForbidden(name.to_string()))
+        }
+    }
+
+    /// Executes an operation from `registry` by name, provided it is on the allowlist.
+    ///
+    /// Meant for names supplied by untrusted callers, such as plugins or tenants:
+    /// operations are denied by default, so an executor without an allowlist rejects
+    /// every name with [`DispatchError::OperationForbidden`] before running anything.
+    pub fn execute_with_operation_allowlist(
+        &mut self,
+        registry: &Registry<C>,
+        name: &str,
+        parameters: &dyn Any,
+    ) -> Result<Box<dyn Any + Send>, DispatchError> {
+        if !self.is_allowed(name) {
+            return Err(DispatchError::OperationForbidden(name.to_string()));
+        }
+        self.execute_dynamic(registry, name, parameters)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+    use crate::{ApiOperation, Identified, OperationId};
+
+    struct ReadUser;
+    struct DropTables;
+
+    impl Identified for ReadUser {
+        const OP_ID: OperationId = OperationId::new("read_user");
+    }
+
+    impl Identified for DropTables {
+        const OP_ID: OperationId = OperationId::new("drop_tables");
+    }
+
+    impl ApiOperation<DatabaseContext, String> for ReadUser {
+        type Output = Option<String>;
+        type Error = ();
+
+        fn execute(context: &mut DatabaseContext, parameters: &String) -> Result<Self::Output, ()> {
+            Ok(context.cache().get(parameters).cloned())
+        }
+    }
+
+    impl ApiOperation<DatabaseContext, ()> for DropTables {
+        type Output = ();
+        type Error = ();
+
+        fn execute(context: &mut DatabaseContext, _parameters: &()) -> Result<(), ()> {
+            context.cache_mut().clear();
+            Ok(())
+        }
+    }
+
+    fn registry() -> Registry<DatabaseContext> {
+        let mut registry = Registry::new();
+        registry.register(ReadUser).unwrap();
+        registry.register(DropTables).unwrap();
+        registry
+    }
+
+    #[test]
+    fn test_allowlisted_operation_runs() {
+        let registry = registry();
+        let mut executor = ApiExecutor::new(DatabaseContext::new("sandbox".to_string()))
+            .with_allowlist(["read_user"]);
+        executor
+            .context_mut()
+            .cache_mut()
+            .insert("user_1".to_string(), "alice".to_string());
+
+        let output = executor
+            .execute_with_operation_allowlist(&registry, "read_user", &"user_1".to_string())
+            .unwrap();
+
+        assert_eq!(
+            output.downcast_ref::<Option<String>>(),
+            Some(&Some("alice".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_operation_off_the_allowlist_is_rejected_without_running() {
+        let registry = registry();
+        let mut executor = ApiExecutor::new(DatabaseContext::new("sandbox".to_string()))
+            .with_allowlist(["read_user"]);
+        executor
+            .context_mut()
+            .cache_mut()
+            .insert("user_1".to_string(), "alice".to_string());
+
+        let result = executor.execute_with_operation_allowlist(&registry, "drop_tables", &());
+
+        assert!(matches!(
+            result,
+            Err(DispatchError::OperationForbidden(name)) if name == "drop_tables"
+        ));
+        assert_eq!(executor.context().cache().len(), 1);
+    }
+
+    #[test]
+    fn test_configured_allowlist_applies_to_execute_dynamic() {
+        let registry = registry();
+        let mut executor = ApiExecutor::new(DatabaseContext::new("sandbox".to_string()))
+            .with_allowlist(["read_user"]);
+        executor
+            .context_mut()
+            .cache_mut()
+            .insert("user_1".to_string(), "alice".to_string());
+
+        let result = executor.execute_dynamic(&registry, "drop_tables", &());
+
+        assert!(matches!(
+            result,
+            Err(DispatchError::OperationForbidden(name)) if name == "drop_tables"
+        ));
+        assert_eq!(executor.context().cache().len(), 1);
+        assert!(executor
+            .execute_dynamic(&registry, "read_user", &"user_1".to_string())
+            .is_ok());
+    }
+}
@@ -0,0 +1,416 @@
+//! An executor that runs operations as tasks on a tokio runtime.
+
+use crate::{ApiOperation, ApiQuery, CancellationFlag, CooperativeOperation};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::{JoinError, JoinHandle, JoinSet};
+
+/// An executor for use from async code, backed by the ambient tokio runtime.
+///
+/// Operations are synchronous, so work handed to the runtime runs on its blocking
+/// thread pool.
+#[derive(Debug, Clone)]
+pub struct AsyncApiExecutor<C> {
+    /// The context instance owned by this executor.
+    context: C,
+}
+
+impl<C> AsyncApiExecutor<C> {
+    /// Creates a new `AsyncApiExecutor` that owns the provided context.
+    pub fn new(context: C) -> Self {
+        Self { context }
+    }
+
+    /// Executes an operation against this executor's context.
+    pub fn execute<P, Op>(&mut self, _op: Op, parameters: &P) -> Result<Op::Output, Op::Error>
+    where
+        Op: ApiOperation<C, P>,
+    {
+        Op::execute(&mut self.context, parameters)
+    }
+
+    /// Starts an operation on the runtime and returns a handle to await its result.
+    ///
+    /// The operation runs against a clone of the context, so its mutations are not
+    /// merged back into this executor. This suits independent work that can proceed in
+    /// parallel. Must be called from within a tokio runtime.
+    pub fn spawn<P, Op>(
+        &mut self,
+        _op: Op,
+        parameters: P,
+    ) -> JoinHandle<Result<Op::Output, Op::Error>>
+    where
+        C: Clone + Send + 'static,
+        P: Send + 'static,
+        Op: ApiOperation<C, P>,
+        Op::Output: Send + 'static,
+        Op::Error: Send + 'static,
+    {
+        let mut context = self.context.clone();
+        tokio::task::spawn_blocking(move || Op::execute(&mut context, &parameters))
+    }
+
+    /// Runs a query, starting a second attempt against another clone of the context if
+    /// the first has not finished after `hedge_after`, and returns whichever result
+    /// arrives first.
+    ///
+    /// Hedging trades extra load for lower tail latency, so it is limited to read-only
+    /// queries. Operations cannot be interrupted once running, so the slower attempt
+    /// runs to completion in the background and its result is discarded.
+    pub async fn execute_hedged<P, Q>(
+        &mut self,
+        _query: Q,
+        parameters: &P,
+        hedge_after: Duration,
+    ) -> Result<Q::Output, Q::Error>
+    where
+        C: Clone + Send + 'static,
+        P: Clone + Send + 'static,
+        Q: ApiQuery<C, P>,
+        Q::Output: Send + 'static,
+        Q::Error: Send + 'static,
+    {
+        let mut primary = self.spawn_query::<P, Q>(parameters.clone());
+        if let Ok(joined) = tokio::time::timeout(hedge_after, &mut primary).await {
+            return unwrap_joined(joined);
+        }
+
+        let mut hedge = self.spawn_query::<P, Q>(parameters.clone());
+        let joined = tokio::select! {
+            joined = &mut primary => joined,
+            joined = &mut hedge => joined,
+        };
+        unwrap_joined(joined)
+    }
+
+    /// Runs `f` to spawn child operations through a [`Scope`], then waits for every
+    /// child before returning.
+    ///
+    /// No child outlives the call. If a child fails, the scope raises its cancellation
+    /// flag, aborts children that have not started yet and still waits for running ones
+    /// to stop; the first child error is returned. Otherwise `f`'s value is returned,
+    /// and child outputs can be read from their [`ScopedOutput`] handles. Children run
+    /// against clones of the context, so their mutations are not merged back. Must be
+    /// called from within a tokio runtime.
+    pub async fn execute_with_structured_concurrency_scope<F, T, E>(&mut self, f: F) -> Result<T, E>
+    where
+        C: Clone + Send + 'static,
+        E: Send + 'static,
+        F: FnOnce(&mut Scope<C, E>) -> T,
+    {
+        let mut scope = Scope {
+            context: self.context.clone(),
+            tasks: JoinSet::new(),
+            cancel: CancellationFlag::new(),
+        };
+        let value = f(&mut scope);
+        scope.join().await.map(|()| value)
+    }
+
+    /// Starts a query on the blocking pool against a clone of the context.
+    fn spawn_query<P, Q>(&self, parameters: P) -> JoinHandle<Result<Q::Output, Q::Error>>
+    where
+        C: Clone + Send + 'static,
+        P: Send + 'static,
+        Q: ApiQuery<C, P>,
+        Q::Output: Send + 'static,
+        Q::Error: Send + 'static,
+    {
+        let context = self.context.clone();
+        tokio::task::spawn_blocking(move || Q::query(&context, &parameters))
+    }
+
+    /// Returns an immutable reference to the executor's context.
+    pub fn context(&self) -> &C {
+        &self.context
+    }
+
+    /// Returns a mutable reference to the executor's context.
+    pub fn context_mut(&mut self) -> &mut C {
+        &mut self.context
+    }
+}
+
+/// A handle for spawning child operations inside
+/// [`AsyncApiExecutor::execute_with_structured_concurrency_scope`].
+pub struct Scope<C, E> {
+    /// The context cloned for each child.
+    context: C,
+
+    /// The running children, each reporting only success or its error.
+    tasks: JoinSet<Result<(), E>>,
+
+    /// Raised when a child fails, asking cooperative siblings to stop.
+    cancel: CancellationFlag,
+}
+
+impl<C, E> Scope<C, E>
+where
+    C: Clone + Send + 'static,
+    E: Send + 'static,
+{
+    /// Starts a child operation against a clone of the scope's context.
+    pub fn spawn<P, Op>(&mut self, _op: Op, parameters: P) -> ScopedOutput<Op::Output>
+    where
+        P: Send + 'static,
+        Op: ApiOperation<C, P>,
+        Op::Output: Send + 'static,
+        Op::Error: Into<E>,
+    {
+        let output = ScopedOutput::default();
+        let slot = Arc::clone(&output.slot);
+        let mut context = self.context.clone();
+        self.tasks.spawn_blocking(move || {
+            let value = Op::execute(&mut context, &parameters).map_err(Into::into)?;
+            *slot.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(value);
+            Ok(())
+        });
+        output
+    }
+
+    /// Starts a child operation that observes the scope's cancellation flag, so it can
+    /// stop early when a sibling fails.
+    pub fn spawn_cooperative<P, Op>(&mut self, _op: Op, parameters: P) -> ScopedOutput<Op::Output>
+    where
+        P: Send + 'static,
+        Op: CooperativeOperation<C, P>,
+        Op::Output: Send + 'static,
+        Op::Error: Into<E>,
+    {
+        let output = ScopedOutput::default();
+        let slot = Arc::clone(&output.slot);
+        let mut context = self.context.clone();
+        let cancel = self.cancel.clone();
+        self.tasks.spawn_blocking(move || {
+            let value = Op::execute(&mut context, &parameters, &cancel).map_err(Into::into)?;
+            *slot.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(value);
+            Ok(())
+        });
+        output
+    }
+
+    /// Waits for every child, cancelling the rest after the first failure.
+    async fn join(mut self) -> Result<(), E> {
+        let mut first_error = None;
+        while let Some(joined) = self.tasks.join_next().await {
+            match joined {
+                Ok(Ok(())) => {}
+                Ok(Err(error)) => {
+                    if first_error.is_none() {
+                        first_error = Some(error);
+                        self.cancel.cancel();
+                        self.tasks.abort_all();
+                    }
+                }
+                Err(error) if error.is_cancelled() => {}
+                Err(error) => std::panic::resume_unwind(error.into_panic()),
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+}
+
+impl<C, E> fmt::Debug for Scope<C, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Scope")
+            .field("children", &self.tasks.len())
+            .field("cancelled", &self.cancel.is_cancelled())
+            .finish()
+    }
+}
+
+/// The output of a child spawned in a [`Scope`], available once the scope returns.
+#[derive(Debug)]
+pub struct ScopedOutput<T> {
+    /// Filled by the child when it succeeds.
+    slot: Arc<Mutex<Option<T>>>,
+}
+
+impl<T> ScopedOutput<T> {
+    /// Returns the child's output, or `None` if it failed or was cancelled.
+    pub fn into_inner(self) -> Option<T> {
+        self.slot
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .take()
+    }
+}
+
+impl<T> Default for ScopedOutput<T> {
+    fn default() -> Self {
+        Self {
+            slot: Arc::new(Mutex::new(None)),
+        }
+    }
+}
+
+/// Returns a finished task's result, propagating its panic if it panicked.
+fn unwrap_joined<T>(joined: Result<T, JoinError>) -> T {
+    joined.unwrap_or_else(|error| std::panic::resume_unwind(error.into_panic()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tokio::runtime::{Builder, Runtime};
+
+    struct Increment;
+
+    impl ApiOperation<DatabaseContext, u32> for Increment {
+        type Output = u32;
+        type Error = ();
+
+        fn execute(context: &mut DatabaseContext, parameters: &u32) -> Result<u32, ()> {
+            for _ in 0..*parameters {
+                context.increment_transaction();
+            }
+            Ok(context.transaction_count())
+        }
+    }
+
+    /// A context whose attempt counter is shared by every clone.
+    #[derive(Clone, Default)]
+    struct ReplicaContext {
+        attempts: Arc<AtomicUsize>,
+    }
+
+    /// Answers with its attempt number; only the first attempt is slow.
+    struct ReadReplica;
+
+    impl ApiQuery<ReplicaContext, ()> for ReadReplica {
+        type Output = usize;
+        type Error = ();
+
+        fn query(context: &ReplicaContext, _parameters: &()) -> Result<usize, ()> {
+            let attempt = context.attempts.fetch_add(1, Ordering::SeqCst) + 1;
+            if attempt == 1 {
+                std::thread::sleep(Duration::from_millis(500));
+            }
+            Ok(attempt)
+        }
+    }
+
+    /// Fails immediately when asked to.
+    struct Validate;
+
+    impl ApiOperation<DatabaseContext, bool> for Validate {
+        type Output = ();
+        type Error = String;
+
+        fn execute(_context: &mut DatabaseContext, parameters: &bool) -> Result<(), String> {
+            if *parameters {
+                Ok(())
+            } else {
+                Err("validation failed".to_string())
+            }
+        }
+    }
+
+    /// Polls the cancellation flag for up to five seconds, recording whether it stopped
+    /// because it was cancelled.
+    struct LongImport;
+
+    impl CooperativeOperation<DatabaseContext, Arc<AtomicUsize>> for LongImport {
+        type Output = ();
+        type Error = String;
+
+        fn execute(
+            _context: &mut DatabaseContext,
+            parameters: &Arc<AtomicUsize>,
+            cancel: &CancellationFlag,
+        ) -> Result<(), String> {
+            for _ in 0..500 {
+                if cancel.is_cancelled() {
+                    parameters.fetch_add(1, Ordering::SeqCst);
+                    return Err("cancelled".to_string());
+                }
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            Ok(())
+        }
+    }
+
+    fn runtime() -> Runtime {
+        Builder::new_current_thread().enable_all().build().unwrap()
+    }
+
+    #[test]
+    fn test_spawned_operations_run_on_cloned_contexts() {
+        let mut executor = AsyncApiExecutor::new(DatabaseContext::new("async".to_string()));
+        executor.execute(Increment, &1).unwrap();
+
+        let (first, second) = runtime().block_on(async {
+            let first = executor.spawn(Increment, 2);
+            let second = executor.spawn(Increment, 5);
+            (first.await.unwrap(), second.await.unwrap())
+        });
+
+        assert_eq!(first, Ok(3));
+        assert_eq!(second, Ok(6));
+        assert_eq!(executor.context().transaction_count(), 1);
+    }
+
+    #[test]
+    fn test_slow_primary_is_hedged() {
+        let mut executor = AsyncApiExecutor::new(ReplicaContext::default());
+
+        let attempt = runtime().block_on(executor.execute_hedged(
+            ReadReplica,
+            &(),
+            Duration::from_millis(20),
+        ));
+
+        assert_eq!(attempt, Ok(2));
+    }
+
+    #[test]
+    fn test_fast_primary_is_not_hedged() {
+        let mut executor = AsyncApiExecutor::new(ReplicaContext::default());
+        executor.context().attempts.store(1, Ordering::SeqCst);
+
+        let attempt =
+            runtime().block_on(executor.execute_hedged(ReadReplica, &(), Duration::from_secs(5)));
+
+        assert_eq!(attempt, Ok(2));
+        assert_eq!(executor.context().attempts.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_scope_waits_for_every_child() {
+        let mut executor = AsyncApiExecutor::new(DatabaseContext::new("async".to_string()));
+
+        let (first, second) = runtime()
+            .block_on(
+                executor.execute_with_structured_concurrency_scope::<_, _, ()>(|scope| {
+                    (scope.spawn(Increment, 2), scope.spawn(Increment, 3))
+                }),
+            )
+            .unwrap();
+
+        assert_eq!(first.into_inner(), Some(2));
+        assert_eq!(second.into_inner(), Some(3));
+        assert_eq!(executor.context().transaction_count(), 0);
+    }
+
+    #[test]
+    fn test_failing_child_cancels_sibling() {
+        let mut executor = AsyncApiExecutor::new(DatabaseContext::new("async".to_string()));
+        let cancelled = Arc::new(AtomicUsize::new(0));
+        let started = std::time::Instant::now();
+
+        let result = runtime().block_on(executor.execute_with_structured_concurrency_scope(
+            |scope: &mut Scope<DatabaseContext, String>| {
+                scope.spawn_cooperative(LongImport, cancelled.clone());
+                scope.spawn(Validate, false);
+            },
+        ));
+
+        assert_eq!(result, Err("validation failed".to_string()));
+        assert_eq!(cancelled.load(Ordering::SeqCst), 1);
+        assert!(started.elapsed() < Duration::from_secs(4));
+    }
+}
@@ -0,0 +1,203 @@
+//! Executor-held audit trails of executed operations.
+
+use crate::{ApiExecutor, ApiOperation};
+use std::time::Instant;
+
+/// A single record in an executor's audit trail.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditEntry {
+    /// The name of the executed operation.
+    pub operation: &'static str,
+
+    /// When the operation finished, according to the executor's clock.
+    pub timestamp: Instant,
+
+    /// Whether the operation succeeded.
+    pub success: bool,
+
+    /// Details reported by the operation through its context's [`AuditHook`].
+    pub details: Option<String>,
+}
+
+/// A context through which operations can attach details to their audit entry.
+pub trait AuditHook {
+    /// Takes the details recorded by the operation that just ran, if any.
+    fn take_audit_details(&mut self) -> Option<String>;
+}
+
+impl<C> ApiExecutor<C> {
+    /// Records an audit entry for every `execute` call from now on.
+    pub fn with_audit_log(mut self) -> Self {
+        self.audit.get_or_insert_with(Vec::new);
+        self
+    }
+
+    /// Returns the audit entries recorded so far, oldest first.
+    pub fn audit_entries(&self) -> &[AuditEntry] {
+        self.audit.as_deref().unwrap_or_default()
+    }
+
+    /// Removes and returns the audit entries recorded so far, oldest first.
+    ///
+    /// The audit log stays enabled, so long-running executors can ship entries elsewhere
+    /// without the log growing forever.
+    pub fn take_audit_entries(&mut self) -> Vec<AuditEntry> {
+        self.audit.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    /// Executes an operation and records an audit entry including the details the
+    /// operation left on the context's [`AuditHook`].
+    ///
+    /// Details left behind by earlier calls are discarded first, so each entry only
+    /// carries its own operation's details. Calling this method enables the audit log
+    /// as [`with_audit_log`](Self::with_audit_log) does.
+    pub fn execute_collecting_audit<P, Op>(
+        &mut self,
+        op: Op,
+        parameters: &P,
+    ) -> Result<Op::Output, Op::Error>
+    where
+        Op: ApiOperation<C, P>,
+        C: AuditHook,
+    {
+        self.context.take_audit_details();
+        self.audit.get_or_insert_with(Vec::new);
+        let result = self.execute(op, parameters);
+        let details = self.context.take_audit_details();
+        if let Some(entry) = self.audit.as_mut().and_then(|audit| audit.last_mut()) {
+            entry.details = details;
+        }
+        result
+    }
+
+    /// Appends an entry without details if the audit log is enabled.
+    pub(crate) fn record_audit(&mut self, operation: &'static str, success: bool) {
+        if let Some(audit) = &mut self.audit {
+            audit.push(AuditEntry {
+                operation,
+                timestamp: self.clock.now(),
+                success,
+                details: None,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+    use crate::MockClock;
+    use std::time::Duration;
+
+    /// Wraps a database context with a slot for audit details.
+    struct AuditedContext {
+        database: DatabaseContext,
+        details: Option<String>,
+    }
+
+    impl AuditHook for AuditedContext {
+        fn take_audit_details(&mut self) -> Option<String> {
+            self.details.take()
+        }
+    }
+
+    struct CreateUser;
+
+    impl ApiOperation<AuditedContext, String> for CreateUser {
+        type Output = u32;
+        type Error = String;
+
+        fn execute(context: &mut AuditedContext, parameters: &String) -> Result<u32, String> {
+            if !parameters.contains('@') {
+                context.details = Some(format!("rejected {}", parameters));
+                return Err("invalid email".to_string());
+            }
+            context.database.increment_transaction();
+            context.details = Some(format!("created {}", parameters));
+            Ok(context.database.transaction_count())
+        }
+
+        fn name() -> &'static str {
+            "create_user"
+        }
+    }
+
+    fn context() -> AuditedContext {
+        AuditedContext {
+            database: DatabaseContext::new("audit".to_string()),
+            details: None,
+        }
+    }
+
+    #[test]
+    fn test_execute_records_entries_when_enabled() {
+        let clock = MockClock::new();
+        let mut executor = ApiExecutor::new(context())
+            .with_clock(clock.clone())
+            .with_audit_log();
+        let start = executor.now();
+
+        executor
+            .execute(CreateUser, &"alice@example.com".to_string())
+            .unwrap();
+        clock.advance(Duration::from_secs(1));
+        executor
+            .execute(CreateUser, &"invalid".to_string())
+            .unwrap_err();
+
+        let entries = executor.audit_entries();
+        assert_eq!(entries.len(), 2);
+        assert!(entries[0].success);
+        assert!(!entries[1].success);
+        assert_eq!(entries[0].operation, "create_user");
+        assert_eq!(entries[1].timestamp - start, Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_collecting_audit_ignores_details_from_plain_execute() {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let mut executor = ApiExecutor::new(context()).with_notifier(sender);
+
+        executor
+            .execute(CreateUser, &"alice@example.com".to_string())
+            .unwrap();
+        executor
+            .execute_collecting_audit(CreateUser, &"bob@example.com".to_string())
+            .unwrap();
+
+        let entries = executor.take_audit_entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(
+            entries[0].details.as_deref(),
+            Some("created bob@example.com")
+        );
+        assert_eq!(receiver.try_iter().count(), 2);
+        assert!(executor.audit_entries().is_empty());
+    }
+
+    #[test]
+    fn test_collecting_audit_includes_context_details() {
+        let mut executor = ApiExecutor::new(context());
+
+        executor
+            .execute_collecting_audit(CreateUser, &"alice@example.com".to_string())
+            .unwrap();
+        executor
+            .execute_collecting_audit(CreateUser, &"invalid".to_string())
+            .unwrap_err();
+
+        let details: Vec<_> = executor
+            .audit_entries()
+            .iter()
+            .map(|entry| (entry.success, entry.details.as_deref()))
+            .collect();
+        assert_eq!(
+            details,
+            vec![
+                (true, Some("created alice@example.com")),
+                (false, Some("rejected invalid"))
+            ]
+        );
+    }
+}
@@ -0,0 +1,532 @@
+//! Running one operation over a batch of parameters.
+
+use crate::{ApiExecutor, ApiOperation, ApiQuery};
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+use std::thread;
+
+/// An operation that can process a whole batch of parameters at once.
+///
+/// The provided [`execute_bulk`](Self::execute_bulk) runs the operation once per item;
+/// backends with native bulk writes override it with a batched implementation.
+pub trait BulkOperation<C, P>: ApiOperation<C, P> {
+    /// Executes the operation for every parameter in `batch`, returning the outputs in
+    /// order or the first error.
+    fn execute_bulk(context: &mut C, batch: &[P]) -> Result<Vec<Self::Output>, Self::Error> {
+        batch
+            .iter()
+            .map(|parameters| Self::execute(context, parameters))
+            .collect()
+    }
+}
+
+/// Every error from a batch in which at least one item failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AggregateError<E> {
+    /// Each failed item's index in the batch with its error, in batch order.
+    pub errors: Vec<(usize, E)>,
+}
+
+impl<E: fmt::Display> fmt::Display for AggregateError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} batch item(s) failed", self.errors.len())?;
+        for (index, error) in &self.errors {
+            write!(f, "; item {}: {}", index, error)?;
+        }
+        Ok(())
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for AggregateError<E> {}
+
+impl<C> ApiExecutor<C> {
+    /// Executes an operation for a whole batch through [`BulkOperation::execute_bulk`],
+    /// using the operation's batched implementation when it provides one.
+    pub fn execute_batch<P, Op>(
+        &mut self,
+        _op: Op,
+        batch: &[P],
+    ) -> Result<Vec<Op::Output>, Op::Error>
+    where
+        Op: BulkOperation<C, P>,
+    {
+        let started = self.clock.now();
+        let result = Op::execute_bulk(&mut self.context, batch);
+        self.observe(Op::name(), started, result.is_ok());
+        result
+    }
+
+    /// Splits `batch` into groups by `key_fn` and executes each group through
+    /// [`BulkOperation::execute_bulk`] independently.
+    ///
+    /// Groups run in the order their keys first appear, each holding its parameters in
+    /// batch order. A failing group does not stop the others; each key maps to its own
+    /// group's outputs or error.
+    pub fn execute_batched_by_key<P, Op, K, F>(
+        &mut self,
+        _op: Op,
+        batch: &[P],
+        key_fn: F,
+    ) -> HashMap<K, Result<Vec<Op::Output>, Op::Error>>
+    where
+        Op: BulkOperation<C, P>,
+        P: Clone,
+        K: Hash + Eq + Clone,
+        F: Fn(&P) -> K,
+    {
+        let mut groups: Vec<(K, Vec<P>)> = Vec::new();
+        let mut positions: HashMap<K, usize> = HashMap::new();
+        for parameters in batch {
+            let key = key_fn(parameters);
+            match positions.get(&key) {
+                Some(&position) => groups[position].1.push(parameters.clone()),
+                None => {
+                    positions.insert(key.clone(), groups.len());
+                    groups.push((key, vec![parameters.clone()]));
+                }
+            }
+        }
+
+        groups
+            .into_iter()
+            .map(|(key, group)| {
+                let started = self.clock.now();
+                let result = Op::execute_bulk(&mut self.context, &group);
+                self.observe(Op::name(), started, result.is_ok());
+                (key, result)
+            })
+            .collect()
+    }
+
+    /// Executes an operation once per distinct parameter value in `batch`.
+    ///
+    /// Duplicate parameters share the result of their first occurrence, and the returned
+    /// vector is aligned with `batch`, so position `i` holds the result for `batch[i]`.
+    /// Unique parameters run in the order they first appear.
+    pub fn execute_with_input_hash_dedup<P, Op>(
+        &mut self,
+        _op: Op,
+        batch: &[P],
+    ) -> Vec<Result<Op::Output, Op::Error>>
+    where
+        Op: ApiOperation<C, P>,
+        Op::Output: Clone,
+        Op::Error: Clone,
+        P: Hash + Eq,
+    {
+        let mut unique: HashMap<&P, usize> = HashMap::new();
+        let mut results = Vec::new();
+        let positions: Vec<usize> = batch
+            .iter()
+            .map(|parameters| {
+                *unique.entry(parameters).or_insert_with(|| {
+                    results.push(self.execute_observed::<P, Op>(parameters));
+                    results.len() - 1
+                })
+            })
+            .collect();
+        positions
+            .into_iter()
+            .map(|position| results[position].clone())
+            .collect()
+    }
+
+    /// Executes an operation for each parameter in `batch`, mapping every successful
+    /// output through `f` in the same pass.
+    ///
+    /// The returned vector is aligned with `batch`; errors are passed through unchanged.
+    pub fn execute_batch_map<P, Op, U, F>(
+        &mut self,
+        _op: Op,
+        batch: &[P],
+        mut f: F,
+    ) -> Vec<Result<U, Op::Error>>
+    where
+        Op: ApiOperation<C, P>,
+        F: FnMut(Op::Output) -> U,
+    {
+        batch
+            .iter()
+            .map(|parameters| self.execute_observed::<P, Op>(parameters).map(&mut f))
+            .collect()
+    }
+
+    /// Executes an operation for each parameter in `batch`, calling `on_progress` with
+    /// `(completed, total)` after every item.
+    ///
+    /// Lets long-running imports drive progress bars or progress events without
+    /// coupling the operation to the UI. The returned vector is aligned with `batch`.
+    pub fn execute_batch_with_progress<P, Op, F>(
+        &mut self,
+        _op: Op,
+        batch: &[P],
+        mut on_progress: F,
+    ) -> Vec<Result<Op::Output, Op::Error>>
+    where
+        Op: ApiOperation<C, P>,
+        F: FnMut(usize, usize),
+    {
+        batch
+            .iter()
+            .enumerate()
+            .map(|(index, parameters)| {
+                let result = self.execute_observed::<P, Op>(parameters);
+                on_progress(index + 1, batch.len());
+                result
+            })
+            .collect()
+    }
+
+    /// Executes an operation for every parameter in `batch`, returning all outputs only
+    /// if every item succeeded.
+    ///
+    /// Every item runs even after a failure, so the [`AggregateError`] reports all
+    /// failures at once. Changes made by the items that succeeded are not undone; wrap
+    /// the call in a checkpoint to discard them.
+    pub fn execute_batch_aggregate_errors<P, Op>(
+        &mut self,
+        _op: Op,
+        batch: &[P],
+    ) -> Result<Vec<Op::Output>, AggregateError<Op::Error>>
+    where
+        Op: ApiOperation<C, P>,
+    {
+        let mut outputs = Vec::with_capacity(batch.len());
+        let mut errors = Vec::new();
+        for (index, parameters) in batch.iter().enumerate() {
+            match self.execute_observed::<P, Op>(parameters) {
+                Ok(output) => outputs.push(output),
+                Err(error) => errors.push((index, error)),
+            }
+        }
+        if errors.is_empty() {
+            Ok(outputs)
+        } else {
+            Err(AggregateError { errors })
+        }
+    }
+
+    /// Runs a read-only query over `batch` in parallel and folds the outputs into one result.
+    ///
+    /// The batch is split across worker threads that share the context immutably. Outputs
+    /// are folded with `combine` in batch order starting from `identity`, so the result
+    /// matches a sequential fold. The first error in batch order is returned.
+    pub fn par_map_reduce<P, Q, R, F>(
+        &self,
+        _query: Q,
+        batch: &[P],
+        identity: R,
+        combine: F,
+    ) -> Result<R, Q::Error>
+    where
+        C: Sync,
+        P: Sync,
+        Q: ApiQuery<C, P>,
+        Q::Output: Send,
+        Q::Error: Send,
+        F: Fn(R, Q::Output) -> R + Sync,
+    {
+        if batch.is_empty() {
+            return Ok(identity);
+        }
+        let workers = thread::available_parallelism().map_or(1, |count| count.get());
+        let chunk_size = (batch.len() + workers - 1) / workers;
+        let context = &self.context;
+
+        let chunks: Vec<Vec<Result<Q::Output, Q::Error>>> = thread::scope(|scope| {
+            let handles: Vec<_> = batch
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        chunk
+                            .iter()
+                            .map(|parameters| Q::query(context, parameters))
+                            .collect()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| {
+                    handle
+                        .join()
+                        .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+                })
+                .collect()
+        });
+
+        chunks
+            .into_iter()
+            .flatten()
+            .try_fold(identity, |accumulator, output| {
+                Ok(combine(accumulator, output?))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+
+    struct ImportRow;
+
+    impl ApiOperation<DatabaseContext, String> for ImportRow {
+        type Output = u32;
+        type Error = String;
+
+        fn execute(context: &mut DatabaseContext, parameters: &String) -> Result<u32, String> {
+            if parameters.is_empty() {
+                return Err("empty row".to_string());
+            }
+            context.increment_transaction();
+            Ok(context.transaction_count())
+        }
+    }
+
+    #[test]
+    fn test_duplicates_run_once_with_aligned_results() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("batch".to_string()));
+        let batch = ["alice", "bob", "alice", "carol"].map(String::from);
+
+        let results = executor.execute_with_input_hash_dedup(ImportRow, &batch);
+
+        assert_eq!(results, vec![Ok(1), Ok(2), Ok(1), Ok(3)]);
+        assert_eq!(executor.context().transaction_count(), 3);
+    }
+
+    #[test]
+    fn test_dedup_reports_each_unique_execution() {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let mut executor =
+            ApiExecutor::new(DatabaseContext::new("batch".to_string())).with_notifier(sender);
+        let batch = ["alice", "bob", "alice"].map(String::from);
+
+        executor.execute_with_input_hash_dedup(ImportRow, &batch);
+
+        assert_eq!(receiver.try_iter().count(), 2);
+    }
+
+    #[test]
+    fn test_duplicate_failures_share_the_error() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("batch".to_string()));
+        let batch = ["", "alice", ""].map(String::from);
+
+        let results = executor.execute_with_input_hash_dedup(ImportRow, &batch);
+
+        let empty = Err("empty row".to_string());
+        assert_eq!(results, vec![empty.clone(), Ok(1), empty]);
+    }
+
+    #[test]
+    fn test_progress_reported_after_each_item() {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let mut executor =
+            ApiExecutor::new(DatabaseContext::new("batch".to_string())).with_notifier(sender);
+        let batch = ["alice", "", "bob"].map(String::from);
+        let mut progress = Vec::new();
+
+        let results = executor.execute_batch_with_progress(ImportRow, &batch, |done, total| {
+            progress.push((done, total))
+        });
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(progress, vec![(1, 3), (2, 3), (3, 3)]);
+        assert_eq!(receiver.try_iter().count(), 3);
+    }
+
+    #[test]
+    fn test_aggregate_error_holds_every_failure() {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let mut executor =
+            ApiExecutor::new(DatabaseContext::new("batch".to_string())).with_notifier(sender);
+        let batch = ["alice", "", "bob", ""].map(String::from);
+
+        let error = executor
+            .execute_batch_aggregate_errors(ImportRow, &batch)
+            .unwrap_err();
+
+        assert_eq!(
+            error.errors,
+            vec![(1, "empty row".to_string()), (3, "empty row".to_string())]
+        );
+        assert_eq!(
+            error.to_string(),
+            "2 batch item(s) failed; item 1: empty row; item 3: empty row"
+        );
+        let outcomes: Vec<_> = receiver.try_iter().map(|outcome| outcome.success).collect();
+        assert_eq!(outcomes, vec![true, false, true, false]);
+    }
+
+    #[test]
+    fn test_aggregate_returns_outputs_when_all_succeed() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("batch".to_string()));
+        let batch = ["alice", "bob"].map(String::from);
+
+        let outputs = executor.execute_batch_aggregate_errors(ImportRow, &batch);
+
+        assert_eq!(outputs, Ok(vec![1, 2]));
+    }
+
+    #[derive(Debug)]
+    struct User {
+        id: u32,
+        name: String,
+    }
+
+    struct CreateUser;
+
+    impl ApiOperation<DatabaseContext, String> for CreateUser {
+        type Output = User;
+        type Error = String;
+
+        fn execute(context: &mut DatabaseContext, parameters: &String) -> Result<User, String> {
+            if parameters.is_empty() {
+                return Err("empty name".to_string());
+            }
+            context.increment_transaction();
+            Ok(User {
+                id: context.transaction_count(),
+                name: parameters.clone(),
+            })
+        }
+    }
+
+    #[test]
+    fn test_batch_map_transforms_successes() {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let mut executor =
+            ApiExecutor::new(DatabaseContext::new("batch".to_string())).with_notifier(sender);
+        let batch = ["alice", "", "bob"].map(String::from);
+
+        let ids = executor.execute_batch_map(CreateUser, &batch, |user| {
+            assert!(!user.name.is_empty());
+            user.id
+        });
+
+        assert_eq!(ids, vec![Ok(1), Err("empty name".to_string()), Ok(2)]);
+        let outcomes: Vec<_> = receiver.try_iter().map(|outcome| outcome.success).collect();
+        assert_eq!(outcomes, vec![true, false, true]);
+    }
+
+    impl BulkOperation<DatabaseContext, String> for ImportRow {}
+
+    /// Imports rows with a single round trip per batch.
+    struct BulkImport;
+
+    impl ApiOperation<DatabaseContext, String> for BulkImport {
+        type Output = u32;
+        type Error = String;
+
+        fn execute(context: &mut DatabaseContext, parameters: &String) -> Result<u32, String> {
+            Self::execute_bulk(context, std::slice::from_ref(parameters)).map(|ids| ids[0])
+        }
+    }
+
+    impl BulkOperation<DatabaseContext, String> for BulkImport {
+        fn execute_bulk(
+            context: &mut DatabaseContext,
+            batch: &[String],
+        ) -> Result<Vec<u32>, String> {
+            context.increment_transaction();
+            let first = context.cache().len() as u32 + 1;
+            for row in batch {
+                let id = context.cache().len() + 1;
+                context
+                    .cache_mut()
+                    .insert(format!("row_{}", id), row.clone());
+            }
+            Ok((first..first + batch.len() as u32).collect())
+        }
+    }
+
+    #[test]
+    fn test_bulk_operation_runs_once_per_batch() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("batch".to_string()));
+        let batch = ["a", "b", "c", "d", "e"].map(String::from);
+
+        let ids = executor.execute_batch(BulkImport, &batch).unwrap();
+
+        assert_eq!(ids, vec![1, 2, 3, 4, 5]);
+        assert_eq!(executor.context().transaction_count(), 1);
+    }
+
+    #[test]
+    fn test_batched_by_key_runs_each_group_independently() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("batch".to_string()));
+        let batch = ["tools:hammer", "books:atlas", "tools:saw", "books:map"].map(String::from);
+
+        let groups = executor.execute_batched_by_key(ImportRow, &batch, |row| {
+            row.split(':').next().unwrap_or_default().to_string()
+        });
+
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups["tools"], Ok(vec![1, 2]));
+        assert_eq!(groups["books"], Ok(vec![3, 4]));
+        assert_eq!(executor.context().transaction_count(), 4);
+    }
+
+    #[test]
+    fn test_default_bulk_loops_per_item() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("batch".to_string()));
+        let batch = ["a", "b", "c", "d", "e"].map(String::from);
+
+        let ids = executor.execute_batch(ImportRow, &batch).unwrap();
+
+        assert_eq!(ids, vec![1, 2, 3, 4, 5]);
+        assert_eq!(executor.context().transaction_count(), 5);
+    }
+
+    /// Looks up the price of a product in the cache.
+    struct Price;
+
+    impl ApiQuery<DatabaseContext, String> for Price {
+        type Output = u64;
+        type Error = String;
+
+        fn query(context: &DatabaseContext, parameters: &String) -> Result<u64, String> {
+            context
+                .cache()
+                .get(parameters)
+                .and_then(|price| price.parse().ok())
+                .ok_or_else(|| format!("no price for {}", parameters))
+        }
+    }
+
+    fn priced_executor() -> ApiExecutor<DatabaseContext> {
+        let mut context = DatabaseContext::new("batch".to_string());
+        for index in 0..100 {
+            context
+                .cache_mut()
+                .insert(format!("item_{}", index), (index * 3 + 1).to_string());
+        }
+        ApiExecutor::new(context)
+    }
+
+    #[test]
+    fn test_parallel_sum_matches_sequential_fold() {
+        let executor = priced_executor();
+        let batch: Vec<String> = (0..100).map(|index| format!("item_{}", index)).collect();
+
+        let total = executor
+            .par_map_reduce(Price, &batch, 0, |sum, price| sum + price)
+            .unwrap();
+
+        let expected = batch
+            .iter()
+            .map(|item| Price::query(executor.context(), item).unwrap())
+            .sum::<u64>();
+        assert_eq!(total, expected);
+    }
+
+    #[test]
+    fn test_parallel_reduce_returns_first_error() {
+        let executor = priced_executor();
+        let batch = ["item_1", "missing_a", "item_2", "missing_b"].map(String::from);
+
+        let result = executor.par_map_reduce(Price, &batch, 0, |sum, price| sum + price);
+
+        assert_eq!(result, Err("no price for missing_a".to_string()));
+    }
+}
@@ -0,0 +1,470 @@
+//! An executor-held cache of operation outputs with write-driven invalidation.
+
+use crate::{ApiExecutor, ApiOperation};
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt;
+use std::marker::PhantomData;
+
+/// Outputs cached by `execute_cached`, keyed by caller-chosen strings.
+///
+/// Cloning an executor does not clone its cached outputs; the clone starts empty.
+#[derive(Default)]
+pub(crate) struct ResultCache {
+    /// The cached outputs, each of the type produced by the operation that stored it.
+    entries: HashMap<String, Box<dyn Any + Send + Sync>>,
+}
+
+impl ResultCache {
+    /// Removes every entry matching `pattern`, returning how many were removed.
+    ///
+    /// A pattern ending in `*` matches every key starting with the rest of the pattern;
+    /// any other pattern matches only the identical key.
+    fn invalidate(&mut self, pattern: &str) -> usize {
+        let before = self.entries.len();
+        match pattern.strip_suffix('*') {
+            Some(prefix) => self.entries.retain(|key, _| !key.starts_with(prefix)),
+            None => {
+                self.entries.remove(pattern);
+            }
+        }
+        before - self.entries.len()
+    }
+}
+
+impl Clone for ResultCache {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl fmt::Debug for ResultCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResultCache")
+            .field("entries", &self.entries.len())
+            .finish()
+    }
+}
+
+/// A cache key namespaced by the entity type `T` it refers to.
+///
+/// Keys for different entity types never collide, even with the same id, and the
+/// typed helpers on [`ApiExecutor`] only store and return values of type `T` under them.
+pub struct CacheKey<T> {
+    /// The namespaced key, `"<type name>:<id>"`.
+    key: String,
+
+    /// The entity type the key refers to.
+    entity: PhantomData<fn() -> T>,
+}
+
+impl<T> CacheKey<T> {
+    /// Creates the key for the entity of type `T` identified by `id`.
+    pub fn new(id: impl fmt::Display) -> Self {
+        Self {
+            key: format!("{}:{}", std::any::type_name::<T>(), id),
+            entity: PhantomData,
+        }
+    }
+
+    /// Returns the pattern matching every key for entities of type `T`, for use with
+    /// [`ApiExecutor::invalidate_cached`].
+    pub fn pattern() -> String {
+        format!("{}:*", std::any::type_name::<T>())
+    }
+
+    /// Returns the namespaced key.
+    pub fn as_str(&self) -> &str {
+        &self.key
+    }
+}
+
+impl<T> Clone for CacheKey<T> {
+    fn clone(&self) -> Self {
+        Self {
+            key: self.key.clone(),
+            entity: PhantomData,
+        }
+    }
+}
+
+impl<T> PartialEq for CacheKey<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl<T> Eq for CacheKey<T> {}
+
+impl<T> fmt::Debug for CacheKey<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("CacheKey").field(&self.key).finish()
+    }
+}
+
+impl<T> fmt::Display for CacheKey<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.key)
+    }
+}
+
+/// An error from [`ApiExecutor::execute_content_addressed`].
+#[cfg(feature = "serde")]
+#[derive(Debug)]
+pub enum ContentCacheError<E> {
+    /// The parameters could not be serialized, so the operation did not run.
+    Encode(serde_json::Error),
+
+    /// The operation ran and failed.
+    Operation(E),
+}
+
+#[cfg(feature = "serde")]
+impl<E: fmt::Display> fmt::Display for ContentCacheError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ContentCacheError::Encode(error) => {
+                write!(f, "failed to encode parameters: {}", error)
+            }
+            ContentCacheError::Operation(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<E: fmt::Debug + fmt::Display> std::error::Error for ContentCacheError<E> {}
+
+/// A write operation that makes cached read results stale.
+pub trait Invalidates<C, P>: ApiOperation<C, P> {
+    /// Returns the cache keys or patterns to clear after a successful write.
+    ///
+    /// A pattern ending in `*` clears every key with that prefix.
+    fn invalidated_keys(parameters: &P) -> Vec<String>;
+}
+
+impl<C> ApiExecutor<C> {
+    /// Executes an operation, reusing the output cached under `key` if there is one.
+    ///
+    /// Successful outputs are cached until invalidated; errors are never cached. A key
+    /// holding an output of a different type is treated as a miss and overwritten.
+    pub fn execute_cached<P, Op>(
+        &mut self,
+        op: Op,
+        parameters: &P,
+        key: impl Into<String>,
+    ) -> Result<Op::Output, Op::Error>
+    where
+        Op: ApiOperation<C, P>,
+        Op::Output: Clone + Send + Sync + 'static,
+    {
+        let key = key.into();
+        if let Some(output) = self
+            .cache
+            .entries
+            .get(&key)
+            .and_then(|entry| entry.downcast_ref::<Op::Output>())
+        {
+            return Ok(output.clone());
+        }
+
+        let output = self.execute(op, parameters)?;
+        self.cache.entries.insert(key, Box::new(output.clone()));
+        Ok(output)
+    }
+
+    /// Executes a write operation and, if it succeeds, clears the cached results it
+    /// declares stale through [`Invalidates`].
+    pub fn execute_with_result_cache_invalidation<P, Op>(
+        &mut self,
+        op: Op,
+        parameters: &P,
+    ) -> Result<Op::Output, Op::Error>
+    where
+        Op: Invalidates<C, P>,
+    {
+        let output = self.execute(op, parameters)?;
+        for pattern in Op::invalidated_keys(parameters) {
+            self.cache.invalidate(&pattern);
+        }
+        Ok(output)
+    }
+
+    /// Clears cached results matching `pattern`, returning how many were removed.
+    ///
+    /// A pattern ending in `*` clears every key with that prefix.
+    pub fn invalidate_cached(&mut self, pattern: &str) -> usize {
+        self.cache.invalidate(pattern)
+    }
+
+    /// Returns true if an output is cached under `key`.
+    pub fn is_cached(&self, key: &str) -> bool {
+        self.cache.entries.contains_key(key)
+    }
+
+    /// Executes an operation producing a `T`, reusing the value cached under the typed
+    /// `key` if there is one.
+    ///
+    /// Behaves like [`execute_cached`](Self::execute_cached) with the key's namespaced
+    /// string.
+    pub fn execute_with_typed_cache_key<P, Op, T>(
+        &mut self,
+        op: Op,
+        parameters: &P,
+        key: &CacheKey<T>,
+    ) -> Result<T, Op::Error>
+    where
+        Op: ApiOperation<C, P, Output = T>,
+        T: Clone + Send + Sync + 'static,
+    {
+        self.execute_cached(op, parameters, key.as_str())
+    }
+
+    /// Executes a deterministic operation, reusing the output cached for the same
+    /// operation name and serialized parameters.
+    ///
+    /// The key is the JSON encoding of [`ApiOperation::name`] and the parameters, so
+    /// equal parameters share one entry however they were built, while different
+    /// operations or parameters never share entries. Entries live in the same cache as
+    /// [`execute_cached`](Self::execute_cached), under keys starting with `content:`.
+    #[cfg(feature = "serde")]
+    pub fn execute_content_addressed<P, Op>(
+        &mut self,
+        op: Op,
+        parameters: &P,
+    ) -> Result<Op::Output, ContentCacheError<Op::Error>>
+    where
+        P: serde::Serialize,
+        Op: ApiOperation<C, P>,
+        Op::Output: Clone + Send + Sync + 'static,
+    {
+        let content =
+            serde_json::to_string(&(Op::name(), parameters)).map_err(ContentCacheError::Encode)?;
+        let key = format!("content:{}", content);
+        self.execute_cached(op, parameters, key)
+            .map_err(ContentCacheError::Operation)
+    }
+
+    /// Returns the value cached under the typed `key`, if any.
+    pub fn typed_cached<T: 'static>(&self, key: &CacheKey<T>) -> Option<&T> {
+        self.cache
+            .entries
+            .get(key.as_str())
+            .and_then(|entry| entry.downcast_ref::<T>())
+    }
+
+    /// Caches `value` under the typed `key`, replacing any previous entry.
+    pub fn set_typed_cached<T: Send + Sync + 'static>(&mut self, key: &CacheKey<T>, value: T) {
+        self.cache
+            .entries
+            .insert(key.as_str().to_string(), Box::new(value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+
+    /// Lists users, counting each real execution as a transaction.
+    struct ListUsers;
+
+    impl ApiOperation<DatabaseContext, ()> for ListUsers {
+        type Output = Vec<String>;
+        type Error = ();
+
+        fn execute(context: &mut DatabaseContext, _parameters: &()) -> Result<Vec<String>, ()> {
+            context.increment_transaction();
+            let mut users: Vec<String> = context.cache().values().cloned().collect();
+            users.sort();
+            Ok(users)
+        }
+    }
+
+    struct CreateUser;
+
+    impl ApiOperation<DatabaseContext, (String, String)> for CreateUser {
+        type Output = ();
+        type Error = ();
+
+        fn execute(context: &mut DatabaseContext, parameters: &(String, String)) -> Result<(), ()> {
+            context
+                .cache_mut()
+                .insert(parameters.0.clone(), parameters.1.clone());
+            Ok(())
+        }
+    }
+
+    impl Invalidates<DatabaseContext, (String, String)> for CreateUser {
+        fn invalidated_keys(_parameters: &(String, String)) -> Vec<String> {
+            vec!["users:*".to_string()]
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct User {
+        name: String,
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Product {
+        title: String,
+    }
+
+    /// Loads a user by id, counting each real execution as a transaction.
+    struct LoadUser;
+
+    impl ApiOperation<DatabaseContext, u32> for LoadUser {
+        type Output = User;
+        type Error = ();
+
+        fn execute(context: &mut DatabaseContext, parameters: &u32) -> Result<User, ()> {
+            context.increment_transaction();
+            Ok(User {
+                name: format!("user {}", parameters),
+            })
+        }
+    }
+
+    #[test]
+    fn test_typed_keys_with_same_id_do_not_collide() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("cache".to_string()));
+        let user_key = CacheKey::<User>::new(7);
+        let product_key = CacheKey::<Product>::new(7);
+        assert_ne!(user_key.as_str(), product_key.as_str());
+
+        executor.set_typed_cached(
+            &user_key,
+            User {
+                name: "alice".to_string(),
+            },
+        );
+        executor.set_typed_cached(
+            &product_key,
+            Product {
+                title: "widget".to_string(),
+            },
+        );
+
+        assert_eq!(executor.typed_cached(&user_key).unwrap().name, "alice");
+        assert_eq!(executor.typed_cached(&product_key).unwrap().title, "widget");
+
+        executor.invalidate_cached(&CacheKey::<User>::pattern());
+        assert!(executor.typed_cached(&user_key).is_none());
+        assert!(executor.typed_cached(&product_key).is_some());
+    }
+
+    #[test]
+    fn test_typed_cache_key_skips_execution() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("cache".to_string()));
+        let key = CacheKey::<User>::new(1);
+
+        let first = executor
+            .execute_with_typed_cache_key(LoadUser, &1, &key)
+            .unwrap();
+        let second = executor
+            .execute_with_typed_cache_key(LoadUser, &1, &key)
+            .unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(executor.context().transaction_count(), 1);
+    }
+
+    #[test]
+    fn test_cached_read_skips_execution() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("cache".to_string()));
+
+        executor
+            .execute_cached(ListUsers, &(), "users:all")
+            .unwrap();
+        executor
+            .execute_cached(ListUsers, &(), "users:all")
+            .unwrap();
+
+        assert_eq!(executor.context().transaction_count(), 1);
+        assert!(executor.is_cached("users:all"));
+    }
+
+    #[test]
+    fn test_write_invalidates_cached_list() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("cache".to_string()));
+        assert_eq!(
+            executor.execute_cached(ListUsers, &(), "users:all"),
+            Ok(vec![])
+        );
+
+        executor
+            .execute_with_result_cache_invalidation(
+                CreateUser,
+                &("user_1".to_string(), "alice".to_string()),
+            )
+            .unwrap();
+
+        assert!(!executor.is_cached("users:all"));
+        assert_eq!(
+            executor.execute_cached(ListUsers, &(), "users:all"),
+            Ok(vec!["alice".to_string()])
+        );
+        assert_eq!(executor.context().transaction_count(), 2);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_content_addressed_entries_are_per_operation() {
+        /// Counts the users whose name starts with the prefix.
+        struct CountPrefix;
+
+        impl ApiOperation<DatabaseContext, String> for CountPrefix {
+            type Output = usize;
+            type Error = ();
+
+            fn execute(context: &mut DatabaseContext, parameters: &String) -> Result<usize, ()> {
+                context.increment_transaction();
+                Ok(context
+                    .cache()
+                    .values()
+                    .filter(|name| name.starts_with(parameters.as_str()))
+                    .count())
+            }
+        }
+
+        /// Counts the users whose name ends with the suffix.
+        struct CountSuffix;
+
+        impl ApiOperation<DatabaseContext, String> for CountSuffix {
+            type Output = usize;
+            type Error = ();
+
+            fn execute(context: &mut DatabaseContext, parameters: &String) -> Result<usize, ()> {
+                context.increment_transaction();
+                Ok(context
+                    .cache()
+                    .values()
+                    .filter(|name| name.ends_with(parameters.as_str()))
+                    .count())
+            }
+        }
+
+        let mut executor = ApiExecutor::new(DatabaseContext::new("cache".to_string()));
+        executor
+            .execute(CreateUser, &("1".to_string(), "anna".to_string()))
+            .unwrap();
+        let needle = "a".to_string();
+
+        let prefix = executor
+            .execute_content_addressed(CountPrefix, &needle)
+            .unwrap();
+        let suffix = executor
+            .execute_content_addressed(CountSuffix, &needle)
+            .unwrap();
+        executor
+            .execute_content_addressed(CountPrefix, &"a".to_string())
+            .unwrap();
+        executor
+            .execute_content_addressed(CountSuffix, &"a".to_string())
+            .unwrap();
+
+        assert_eq!((prefix, suffix), (1, 1));
+        assert_eq!(executor.context().transaction_count(), 2);
+        assert_eq!(executor.invalidate_cached("content:*"), 2);
+    }
+}
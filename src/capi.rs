@@ -0,0 +1,535 @@
+//! A C interface for hosts that drive operations with JSON strings.
+//!
+//! A Rust embedding crate makes a context type available with [`export_context`],
+//! giving it a name, a factory for new contexts and a catalog of operations registered
+//! with [`Registry::register_encoded`]. The host creates a handle with
+//! [`apithing_handle_new`], enables the operations it needs with [`apithing_register`],
+//! calls [`apithing_dispatch`], and releases everything with [`apithing_string_free`]
+//! and [`apithing_handle_free`]. Embedding crates that build the context themselves can
+//! instead pass the pointer from [`ApiHandle::into_raw`] to the host. The embedding
+//! crate is built as a `cdylib` or `staticlib` to export these symbols.
+
+// Crossing the C boundary needs raw pointers; this is the only module allowed to.
+#![allow(unsafe_code)]
+
+use crate::{ApiExecutor, DispatchError, EncodedDispatchError, JsonFormat, Registry};
+use std::collections::HashMap;
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// The status codes returned by the exported functions.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiStatus {
+    /// The call succeeded and the output holds the operation's JSON output.
+    Ok = 0,
+
+    /// A required pointer argument was null.
+    NullPointer = 1,
+
+    /// A string argument was not valid UTF-8.
+    InvalidUtf8 = 2,
+
+    /// No operation is registered under the requested name.
+    UnknownOperation = 3,
+
+    /// The operation was not registered with JSON support.
+    NotEncodable = 4,
+
+    /// The parameters were not valid JSON for the operation.
+    InvalidParameters = 5,
+
+    /// The operation ran and failed.
+    OperationFailed = 6,
+
+    /// The operation panicked; the handle should not be used again.
+    Panic = 7,
+
+    /// The operation is not allowed on this handle, so it was not run.
+    Forbidden = 8,
+
+    /// The parameters are larger than the configured input limit.
+    InputTooLarge = 9,
+
+    /// The library failed internally, such as when an output could not be encoded.
+    Internal = 10,
+
+    /// No context type was exported under the requested name.
+    UnknownContext = 11,
+
+    /// The output is larger than the configured output limit, so it was withheld.
+    OutputTooLarge = 12,
+}
+
+/// Builds handles for a context type exported with [`export_context`].
+type HandleFactory = dyn Fn() -> ApiHandle + Send + Sync;
+
+/// The context types exported to C hosts, by name.
+fn exported() -> &'static Mutex<HashMap<String, Arc<HandleFactory>>> {
+    static EXPORTED: OnceLock<Mutex<HashMap<String, Arc<HandleFactory>>>> = OnceLock::new();
+    EXPORTED.get_or_init(Default::default)
+}
+
+/// Makes a context type available to C hosts under `name`.
+///
+/// Each handle a host creates with [`apithing_handle_new`] owns a fresh context from
+/// `context` and starts with no operations; the host enables operations from `catalog`
+/// with [`apithing_register`]. Exporting another type under the same name replaces it.
+pub fn export_context<C, F>(name: &str, context: F, catalog: Registry<C>)
+where
+    C: 'static,
+    F: Fn() -> C + Send + Sync + 'static,
+{
+    let catalog = Arc::new(catalog);
+    let factory = move || ApiHandle {
+        dispatcher: Box::new(Dispatcher {
+            executor: ApiExecutor::new(context()),
+            registry: Registry::new(),
+            catalog: Some(Arc::clone(&catalog)),
+        }),
+    };
+    exported()
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .insert(name.to_string(), Arc::new(factory));
+}
+
+/// Dispatches JSON bodies against one context and registry.
+trait JsonDispatch {
+    /// Runs the operation registered under `name` with the JSON `body`.
+    fn dispatch_json(&mut self, name: &str, body: &[u8]) -> Result<Vec<u8>, EncodedDispatchError>;
+
+    /// Makes the catalog operation `name` available to `dispatch_json`, returning false
+    /// if there is no such operation.
+    fn register(&mut self, name: &str) -> bool;
+}
+
+/// An executor paired with the registry its operations are dispatched from.
+struct Dispatcher<C> {
+    /// The executor owning the context.
+    executor: ApiExecutor<C>,
+
+    /// The operations available to the host.
+    registry: Registry<C>,
+
+    /// The operations the host may add to `registry`, for exported context types.
+    catalog: Option<Arc<Registry<C>>>,
+}
+
+impl<C> JsonDispatch for Dispatcher<C> {
+    fn dispatch_json(&mut self, name: &str, body: &[u8]) -> Result<Vec<u8>, EncodedDispatchError> {
+        self.executor
+            .execute_with_input_size_guard(&self.registry, name, &JsonFormat, body)
+    }
+
+    fn register(&mut self, name: &str) -> bool {
+        self.registry.contains(name)
+            || self
+                .catalog
+                .as_ref()
+                .is_some_and(|catalog| self.registry.copy_from(catalog, name))
+    }
+}
+
+/// An opaque handle to a context and the operations a C host can dispatch against it.
+pub struct ApiHandle {
+    /// The type-erased executor and registry.
+    dispatcher: Box<dyn JsonDispatch>,
+}
+
+impl ApiHandle {
+    /// Creates a handle owning `context` with the operations in `registry`.
+    pub fn new<C: 'static>(context: C, registry: Registry<C>) -> Self {
+        Self {
+            dispatcher: Box::new(Dispatcher {
+                executor: ApiExecutor::new(context),
+                registry,
+                catalog: None,
+            }),
+        }
+    }
+
+    /// Moves the handle to the heap for a C host, which frees it with
+    /// [`apithing_handle_free`].
+    pub fn into_raw(self) -> *mut ApiHandle {
+        Box::into_raw(Box::new(self))
+    }
+}
+
+/// Maps a dispatch error to its status code and the JSON value reported under `error`.
+fn failure_of(error: EncodedDispatchError) -> (ApiStatus, serde_json::Value) {
+    let status = match &error {
+        EncodedDispatchError::Operation { error, .. } => {
+            let error = serde_json::from_slice(error)
+                .unwrap_or_else(|_| String::from_utf8_lossy(error).into_owned().into());
+            return (ApiStatus::OperationFailed, error);
+        }
+        EncodedDispatchError::Dispatch(DispatchError::UnknownOperation(_)) => {
+            ApiStatus::UnknownOperation
+        }
+        EncodedDispatchError::Dispatch(DispatchError::Operation(_)) => ApiStatus::OperationFailed,
+        EncodedDispatchError::Dispatch(DispatchError::OperationForbidden(_)) => {
+            ApiStatus::Forbidden
+        }
+        EncodedDispatchError::Dispatch(DispatchError::ParameterMismatch(_))
+        | EncodedDispatchError::EncodeOutput(_) => ApiStatus::Internal,
+        EncodedDispatchError::NotEncodable(_) => ApiStatus::NotEncodable,
+        EncodedDispatchError::Format(_) | EncodedDispatchError::InvalidParameters { .. } => {
+            ApiStatus::InvalidParameters
+        }
+        EncodedDispatchError::InputTooLarge { .. } => ApiStatus::InputTooLarge,
+        EncodedDispatchError::OutputTooLarge { .. } => ApiStatus::OutputTooLarge,
+    };
+    (status, error.to_string().into())
+}
+
+/// Writes `{"error": error}` to `output` for the host to read.
+///
+/// # Safety
+///
+/// `output` must be valid for writes.
+unsafe fn write_error(output: *mut *mut c_char, error: serde_json::Value) {
+    *output = into_c_string(serde_json::json!({ "error": error }).to_string());
+}
+
+/// Hands a string to the host, replacing interior nul bytes so it stays a valid C string.
+fn into_c_string(text: String) -> *mut c_char {
+    CString::new(text.replace('\0', "\u{FFFD}"))
+        .expect("nul bytes were replaced")
+        .into_raw()
+}
+
+/// Dispatches the operation registered under `name` with the JSON `parameters`.
+///
+/// On [`ApiStatus::Ok`], `*output` receives the operation's output as JSON. On any
+/// other status except [`ApiStatus::NullPointer`], it receives a JSON object with an
+/// `error` message. Strings written to `*output` must be released with
+/// [`apithing_string_free`]. Panics are caught and reported as [`ApiStatus::Panic`]
+/// rather than unwinding into the host.
+///
+/// # Safety
+///
+/// `handle` must be null or a pointer from [`apithing_handle_new`] or
+/// [`ApiHandle::into_raw`] that has not been freed and is not used concurrently. `name`
+/// and `parameters` must be null or valid
+/// nul-terminated strings, and `output` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn apithing_dispatch(
+    handle: *mut ApiHandle,
+    name: *const c_char,
+    parameters: *const c_char,
+    output: *mut *mut c_char,
+) -> ApiStatus {
+    if handle.is_null() || name.is_null() || parameters.is_null() || output.is_null() {
+        return ApiStatus::NullPointer;
+    }
+    *output = ptr::null_mut();
+    let (Ok(name), Ok(parameters)) = (
+        CStr::from_ptr(name).to_str(),
+        CStr::from_ptr(parameters).to_str(),
+    ) else {
+        write_error(output, "name or parameters are not valid UTF-8".into());
+        return ApiStatus::InvalidUtf8;
+    };
+    let dispatcher = &mut (*handle).dispatcher;
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        dispatcher.dispatch_json(name, parameters.as_bytes())
+    }));
+
+    let (status, error) = match result {
+        Ok(Ok(body)) => {
+            *output = into_c_string(String::from_utf8_lossy(&body).into_owned());
+            return ApiStatus::Ok;
+        }
+        Ok(Err(error)) => failure_of(error),
+        Err(_) => (ApiStatus::Panic, "operation panicked".into()),
+    };
+    write_error(output, error);
+    status
+}
+
+/// Creates a handle for the context type exported under `kind` with
+/// [`export_context`], writing it to `*handle`.
+///
+/// The handle starts with no operations; enable them with [`apithing_register`]. Free
+/// it with [`apithing_handle_free`]. On any status other than [`ApiStatus::Ok`],
+/// `*handle` is null.
+///
+/// # Safety
+///
+/// `kind` must be null or a valid nul-terminated string, and `handle` must be null or
+/// valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn apithing_handle_new(
+    kind: *const c_char,
+    handle: *mut *mut ApiHandle,
+) -> ApiStatus {
+    if kind.is_null() || handle.is_null() {
+        return ApiStatus::NullPointer;
+    }
+    *handle = ptr::null_mut();
+    let Ok(kind) = CStr::from_ptr(kind).to_str() else {
+        return ApiStatus::InvalidUtf8;
+    };
+    let factory = exported()
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .get(kind)
+        .cloned();
+    let Some(factory) = factory else {
+        return ApiStatus::UnknownContext;
+    };
+    match panic::catch_unwind(AssertUnwindSafe(|| factory())) {
+        Ok(created) => {
+            *handle = created.into_raw();
+            ApiStatus::Ok
+        }
+        Err(_) => ApiStatus::Panic,
+    }
+}
+
+/// Makes the operation `name` from the handle's catalog available to
+/// [`apithing_dispatch`].
+///
+/// Registering an operation that is already available succeeds. Returns
+/// [`ApiStatus::UnknownOperation`] if the catalog has no such operation.
+///
+/// # Safety
+///
+/// `handle` must be null or a pointer from [`apithing_handle_new`] or
+/// [`ApiHandle::into_raw`] that has not been freed and is not used concurrently. `name`
+/// must be null or a valid nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn apithing_register(
+    handle: *mut ApiHandle,
+    name: *const c_char,
+) -> ApiStatus {
+    if handle.is_null() || name.is_null() {
+        return ApiStatus::NullPointer;
+    }
+    let Ok(name) = CStr::from_ptr(name).to_str() else {
+        return ApiStatus::InvalidUtf8;
+    };
+    if (*handle).dispatcher.register(name) {
+        ApiStatus::Ok
+    } else {
+        ApiStatus::UnknownOperation
+    }
+}
+
+/// Releases a string returned through [`apithing_dispatch`]. Null is ignored.
+///
+/// # Safety
+///
+/// `string` must be null or a string returned by this library that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn apithing_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+/// Releases a handle and its context. Null is ignored.
+///
+/// # Safety
+///
+/// `handle` must be null or a pointer from [`apithing_handle_new`] or
+/// [`ApiHandle::into_raw`] that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn apithing_handle_free(handle: *mut ApiHandle) {
+    if !handle.is_null() {
+        drop(Box::from_raw(handle));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+    use crate::{ApiOperation, Identified, OperationId};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Deserialize)]
+    struct CreateUserProps {
+        email: String,
+    }
+
+    #[derive(Serialize)]
+    struct User {
+        id: u32,
+        email: String,
+    }
+
+    struct CreateUser;
+    struct Explode;
+
+    impl Identified for CreateUser {
+        const OP_ID: OperationId = OperationId::new("create_user");
+    }
+
+    impl Identified for Explode {
+        const OP_ID: OperationId = OperationId::new("explode");
+    }
+
+    impl ApiOperation<DatabaseContext, CreateUserProps> for CreateUser {
+        type Output = User;
+        type Error = String;
+
+        fn execute(
+            context: &mut DatabaseContext,
+            parameters: &CreateUserProps,
+        ) -> Result<User, String> {
+            if !parameters.email.contains('@') {
+                return Err("invalid email".to_string());
+            }
+            context.increment_transaction();
+            Ok(User {
+                id: context.transaction_count(),
+                email: parameters.email.clone(),
+            })
+        }
+    }
+
+    impl ApiOperation<DatabaseContext, ()> for Explode {
+        type Output = ();
+        type Error = ();
+
+        fn execute(_context: &mut DatabaseContext, _parameters: &()) -> Result<(), ()> {
+            panic!("boom");
+        }
+    }
+
+    fn handle() -> *mut ApiHandle {
+        let mut registry = Registry::new();
+        registry.register_encoded(CreateUser).unwrap();
+        registry.register_encoded(Explode).unwrap();
+        ApiHandle::new(DatabaseContext::new("capi".to_string()), registry).into_raw()
+    }
+
+    /// Calls `apithing_dispatch` the way a C host would, taking ownership of the output.
+    fn dispatch(handle: *mut ApiHandle, name: &str, parameters: &str) -> (ApiStatus, String) {
+        let name = CString::new(name).unwrap();
+        let parameters = CString::new(parameters).unwrap();
+        let mut output = ptr::null_mut();
+        unsafe {
+            let status = apithing_dispatch(handle, name.as_ptr(), parameters.as_ptr(), &mut output);
+            let text = CStr::from_ptr(output).to_str().unwrap().to_string();
+            apithing_string_free(output);
+            (status, text)
+        }
+    }
+
+    #[test]
+    fn test_dispatch_returns_json_output() {
+        let handle = handle();
+
+        let result = dispatch(handle, "create_user", r#"{"email":"alice@example.com"}"#);
+
+        assert_eq!(
+            result,
+            (
+                ApiStatus::Ok,
+                r#"{"email":"alice@example.com","id":1}"#.to_string()
+            )
+        );
+        unsafe { apithing_handle_free(handle) };
+    }
+
+    #[test]
+    fn test_failures_map_to_status_codes() {
+        let handle = handle();
+
+        let unknown = dispatch(handle, "delete_user", "{}");
+        let invalid = dispatch(handle, "create_user", "not json");
+        let failed = dispatch(handle, "create_user", r#"{"email":"alice"}"#);
+        let panicked = dispatch(handle, "explode", "null");
+
+        assert_eq!(unknown.0, ApiStatus::UnknownOperation);
+        assert_eq!(invalid.0, ApiStatus::InvalidParameters);
+        assert_eq!(
+            failed,
+            (
+                ApiStatus::OperationFailed,
+                r#"{"error":"invalid email"}"#.to_string()
+            )
+        );
+        assert_eq!(panicked.0, ApiStatus::Panic);
+        unsafe { apithing_handle_free(handle) };
+    }
+
+    #[test]
+    fn test_null_pointers_are_rejected() {
+        let name = CString::new("create_user").unwrap();
+        let mut output = ptr::null_mut();
+
+        let status = unsafe {
+            apithing_dispatch(ptr::null_mut(), name.as_ptr(), name.as_ptr(), &mut output)
+        };
+
+        assert_eq!(status, ApiStatus::NullPointer);
+        assert!(output.is_null());
+        unsafe {
+            apithing_string_free(ptr::null_mut());
+            apithing_handle_free(ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn test_invalid_utf8_writes_error() {
+        let handle = handle();
+        let name = CString::new(vec![0xff, 0xfe]).unwrap();
+        let mut output = ptr::null_mut();
+
+        let (status, text) = unsafe {
+            let status = apithing_dispatch(handle, name.as_ptr(), name.as_ptr(), &mut output);
+            let text = CStr::from_ptr(output).to_str().unwrap().to_string();
+            apithing_string_free(output);
+            apithing_handle_free(handle);
+            (status, text)
+        };
+
+        assert_eq!(status, ApiStatus::InvalidUtf8);
+        assert!(text.starts_with(r#"{"error":"#));
+    }
+
+    #[test]
+    fn test_host_creates_handle_and_registers_operations() {
+        let mut catalog = Registry::new();
+        catalog.register_encoded(CreateUser).unwrap();
+        export_context(
+            "users",
+            || DatabaseContext::new("capi".to_string()),
+            catalog,
+        );
+        let kind = CString::new("users").unwrap();
+        let operation = CString::new("create_user").unwrap();
+        let missing = CString::new("explode").unwrap();
+        let mut handle = ptr::null_mut();
+
+        let created = unsafe { apithing_handle_new(kind.as_ptr(), &mut handle) };
+        let before = dispatch(handle, "create_user", r#"{"email":"alice@example.com"}"#);
+        let registered = unsafe { apithing_register(handle, operation.as_ptr()) };
+        let unknown = unsafe { apithing_register(handle, missing.as_ptr()) };
+        let after = dispatch(handle, "create_user", r#"{"email":"alice@example.com"}"#);
+
+        assert_eq!(created, ApiStatus::Ok);
+        assert_eq!(before.0, ApiStatus::UnknownOperation);
+        assert_eq!(registered, ApiStatus::Ok);
+        assert_eq!(unknown, ApiStatus::UnknownOperation);
+        assert_eq!(after.0, ApiStatus::Ok);
+        unsafe { apithing_handle_free(handle) };
+    }
+
+    #[test]
+    fn test_unknown_context_is_rejected() {
+        let kind = CString::new("missing").unwrap();
+        let mut handle = ptr::null_mut();
+
+        let status = unsafe { apithing_handle_new(kind.as_ptr(), &mut handle) };
+
+        assert_eq!(status, ApiStatus::UnknownContext);
+        assert!(handle.is_null());
+    }
+}
@@ -0,0 +1,162 @@
+//! Multi-stage processing that refines the context into richer types.
+
+use crate::{ApiExecutor, IntoApiOperation};
+
+/// A builder running operations against a context that is transformed between stages.
+///
+/// Created by [`ApiExecutor::execute_chained_context_transform`]. Each stage runs
+/// operations with [`execute`](Self::execute), then [`transform`](Self::transform)
+/// turns the context into the next stage's type, so later operations can rely on
+/// fields that earlier stages populated.
+#[derive(Debug)]
+pub struct ContextChain<C> {
+    /// The executor for the current stage.
+    executor: ApiExecutor<C>,
+}
+
+impl<C> ContextChain<C> {
+    /// Runs an operation against the current stage's context, ending the chain with
+    /// the operation's error if it fails.
+    pub fn execute<P, Op, M>(mut self, op: Op, parameters: &P) -> Result<Self, Op::Error>
+    where
+        Op: IntoApiOperation<C, P, M>,
+    {
+        self.executor.execute(op, parameters)?;
+        Ok(self)
+    }
+
+    /// Turns the context into the next stage's type.
+    ///
+    /// Diagnostics such as the clock, logger, notifier and audit log carry over to the
+    /// next stage. State tied to the old context type, such as memoized or cached
+    /// outputs, checkpoints, transformers and overrides, does not.
+    pub fn transform<D, F>(self, transform: F) -> ContextChain<D>
+    where
+        F: FnOnce(C) -> D,
+    {
+        let executor = self.executor;
+        let mut next = ApiExecutor::new(transform(executor.context));
+        next.configs = executor.configs;
+        next.notifier = executor.notifier;
+        next.clock = executor.clock;
+        next.logger = executor.logger;
+        next.slow_threshold = executor.slow_threshold;
+        next.rng = executor.rng;
+        next.audit = executor.audit;
+        next.correlation = executor.correlation;
+        next.rate_limit = executor.rate_limit;
+        next.trace_sample_rate = executor.trace_sample_rate;
+        next.circuit_breakers = executor.circuit_breakers;
+        next.histograms = executor.histograms;
+        next.metrics = executor.metrics;
+        next.timeouts = executor.timeouts;
+        next.allowlist = executor.allowlist;
+        next.debug_context = executor.debug_context;
+        next.services = executor.services;
+        #[cfg(feature = "serde")]
+        {
+            next.output_limit = executor.output_limit;
+            next.input_limit = executor.input_limit;
+            next.wal = executor.wal;
+        }
+        ContextChain { executor: next }
+    }
+
+    /// Ends the chain, returning an executor over the final context.
+    pub fn finish(self) -> ApiExecutor<C> {
+        self.executor
+    }
+}
+
+impl<C> ApiExecutor<C> {
+    /// Starts a chain of stages that run operations and then transform the context
+    /// into a richer type for the next stage; see [`ContextChain`].
+    pub fn execute_chained_context_transform(self) -> ContextChain<C> {
+        ContextChain { executor: self }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+    use crate::ApiOperation;
+
+    /// The second stage's context, which knows the account being processed.
+    struct AccountContext {
+        database: DatabaseContext,
+        account_id: u32,
+    }
+
+    struct CreateAccount;
+    struct LoadProfile;
+
+    impl ApiOperation<DatabaseContext, String> for CreateAccount {
+        type Output = ();
+        type Error = String;
+
+        fn execute(context: &mut DatabaseContext, parameters: &String) -> Result<(), String> {
+            context.increment_transaction();
+            let id = context.transaction_count().to_string();
+            context
+                .cache_mut()
+                .insert("account".to_string(), id.clone());
+            context.cache_mut().insert(id, parameters.clone());
+            Ok(())
+        }
+    }
+
+    impl ApiOperation<AccountContext, ()> for LoadProfile {
+        type Output = ();
+        type Error = String;
+
+        fn execute(context: &mut AccountContext, _parameters: &()) -> Result<(), String> {
+            let id = context.account_id.to_string();
+            let name = context
+                .database
+                .cache()
+                .get(&id)
+                .cloned()
+                .ok_or("missing account")?;
+            context
+                .database
+                .cache_mut()
+                .insert("profile".to_string(), format!("profile of {}", name));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_second_stage_uses_field_populated_by_transform() {
+        let executor = ApiExecutor::new(DatabaseContext::new("chain".to_string()))
+            .execute_chained_context_transform()
+            .execute(CreateAccount, &"alice".to_string())
+            .unwrap()
+            .transform(|database: DatabaseContext| AccountContext {
+                account_id: database.cache()["account"].parse().unwrap(),
+                database,
+            })
+            .execute(LoadProfile, &())
+            .unwrap()
+            .finish();
+
+        assert_eq!(executor.context().account_id, 1);
+        assert_eq!(
+            executor.context().database.cache().get("profile"),
+            Some(&"profile of alice".to_string())
+        );
+    }
+
+    #[test]
+    fn test_failing_stage_ends_chain() {
+        let result = ApiExecutor::new(DatabaseContext::new("chain".to_string()))
+            .execute_chained_context_transform()
+            .transform(|database| AccountContext {
+                database,
+                account_id: 7,
+            })
+            .execute(LoadProfile, &());
+
+        assert_eq!(result.err(), Some("missing account".to_string()));
+    }
+}
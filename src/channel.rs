@@ -0,0 +1,101 @@
+//! Consuming parameters from channels with backpressure.
+
+use crate::{ApiExecutor, ApiOperation};
+use std::sync::mpsc::{Receiver, SyncSender};
+
+impl<C> ApiExecutor<C> {
+    /// Executes an operation for every parameter received until the channel closes,
+    /// returning the number of parameters processed.
+    ///
+    /// When `forward` is given, each result is sent on it. Sending blocks while the
+    /// downstream channel is full, which pauses consumption; with a bounded input
+    /// channel, producers then block in turn. Consumption also stops if the downstream
+    /// receiver is dropped.
+    pub fn run_from_channel<P, Op>(
+        &mut self,
+        _op: Op,
+        rx: Receiver<P>,
+        forward: Option<&SyncSender<Result<Op::Output, Op::Error>>>,
+    ) -> usize
+    where
+        Op: ApiOperation<C, P>,
+    {
+        let mut processed = 0;
+        for parameters in rx {
+            let started = self.clock.now();
+            let result = Op::execute(&mut self.context, &parameters);
+            self.observe(Op::name(), started, result.is_ok());
+            processed += 1;
+            if let Some(tx) = forward {
+                if tx.send(result).is_err() {
+                    break;
+                }
+            }
+        }
+        processed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+
+    struct Square;
+
+    impl ApiOperation<DatabaseContext, u32> for Square {
+        type Output = u32;
+        type Error = ();
+
+        fn execute(context: &mut DatabaseContext, parameters: &u32) -> Result<u32, ()> {
+            context.increment_transaction();
+            Ok(parameters * parameters)
+        }
+    }
+
+    #[test]
+    fn test_processes_until_channel_closes() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("channel".to_string()));
+        let (tx, rx) = mpsc::channel();
+        for value in 1..=3 {
+            tx.send(value).unwrap();
+        }
+        drop(tx);
+
+        assert_eq!(executor.run_from_channel(Square, rx, None), 3);
+        assert_eq!(executor.context().transaction_count(), 3);
+    }
+
+    #[test]
+    fn test_full_downstream_blocks_producers() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("channel".to_string()));
+        let (input_tx, input_rx) = mpsc::sync_channel(1);
+        let (output_tx, output_rx) = mpsc::sync_channel(1);
+        let sent = AtomicUsize::new(0);
+
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                for value in 1..=6 {
+                    input_tx.send(value).unwrap();
+                    sent.fetch_add(1, Ordering::SeqCst);
+                }
+                drop(input_tx);
+            });
+            let consumer =
+                scope.spawn(|| executor.run_from_channel(Square, input_rx, Some(&output_tx)));
+
+            thread::sleep(Duration::from_millis(100));
+            let sent_before_draining = sent.load(Ordering::SeqCst);
+
+            let outputs: Vec<_> = (0..6).map(|_| output_rx.recv().unwrap()).collect();
+            assert!(sent_before_draining < 6);
+            assert_eq!(outputs, vec![Ok(1), Ok(4), Ok(9), Ok(16), Ok(25), Ok(36)]);
+            assert_eq!(consumer.join().unwrap(), 6);
+        });
+        assert_eq!(sent.load(Ordering::SeqCst), 6);
+    }
+}
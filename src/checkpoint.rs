@@ -0,0 +1,135 @@
+//! Nested checkpoints over transactional contexts, similar to SQL savepoints.
+
+use crate::{ApiExecutor, Transactional};
+use std::any::Any;
+use std::fmt;
+
+/// Returned when committing or rolling back a checkpoint that was never pushed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NoCheckpointError;
+
+impl fmt::Display for NoCheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "no checkpoint to pop")
+    }
+}
+
+impl std::error::Error for NoCheckpointError {}
+
+/// Context snapshots pushed on an executor, innermost last.
+///
+/// Cloning an executor does not clone its checkpoints; the clone starts empty.
+#[derive(Default)]
+pub(crate) struct CheckpointStack {
+    /// The pushed snapshots, each a `C::Snapshot` for the executor's context type.
+    snapshots: Vec<Box<dyn Any + Send + Sync>>,
+}
+
+impl Clone for CheckpointStack {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl fmt::Debug for CheckpointStack {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CheckpointStack")
+            .field("depth", &self.snapshots.len())
+            .finish()
+    }
+}
+
+impl<C> ApiExecutor<C>
+where
+    C: Transactional,
+    C::Snapshot: Send + Sync + 'static,
+{
+    /// Snapshots the context onto the checkpoint stack.
+    pub fn push_checkpoint(&mut self) {
+        let snapshot = self.context.snapshot();
+        self.checkpoints.snapshots.push(Box::new(snapshot));
+    }
+
+    /// Pops the innermost checkpoint, keeping every change made since it was pushed.
+    ///
+    /// The changes still belong to any enclosing checkpoint, so rolling that back
+    /// discards them too.
+    pub fn commit_checkpoint(&mut self) -> Result<(), NoCheckpointError> {
+        self.pop_checkpoint().map(drop)
+    }
+
+    /// Pops the innermost checkpoint and restores the context to its state.
+    pub fn rollback_checkpoint(&mut self) -> Result<(), NoCheckpointError> {
+        let snapshot = self.pop_checkpoint()?;
+        self.context.restore(snapshot);
+        Ok(())
+    }
+
+    /// Returns the number of checkpoints currently pushed.
+    pub fn checkpoint_depth(&self) -> usize {
+        self.checkpoints.snapshots.len()
+    }
+
+    /// Pops the innermost snapshot.
+    fn pop_checkpoint(&mut self) -> Result<C::Snapshot, NoCheckpointError> {
+        let snapshot = self.checkpoints.snapshots.pop().ok_or(NoCheckpointError)?;
+        Ok(*snapshot
+            .downcast::<C::Snapshot>()
+            .expect("checkpoints are pushed with the context's snapshot type"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+    use crate::ApiOperation;
+
+    struct Store;
+
+    impl ApiOperation<DatabaseContext, (&'static str, &'static str)> for Store {
+        type Output = ();
+        type Error = ();
+
+        fn execute(
+            context: &mut DatabaseContext,
+            parameters: &(&'static str, &'static str),
+        ) -> Result<(), ()> {
+            context
+                .cache_mut()
+                .insert(parameters.0.to_string(), parameters.1.to_string());
+            context.increment_transaction();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_inner_rollback_keeps_outer_changes() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("checkpoint".to_string()));
+
+        executor.push_checkpoint();
+        executor.execute(Store, &("outer", "kept")).unwrap();
+        executor.push_checkpoint();
+        executor.execute(Store, &("inner", "discarded")).unwrap();
+        assert_eq!(executor.checkpoint_depth(), 2);
+
+        executor.rollback_checkpoint().unwrap();
+        executor.commit_checkpoint().unwrap();
+
+        assert_eq!(executor.checkpoint_depth(), 0);
+        assert_eq!(executor.context().transaction_count(), 1);
+        assert_eq!(
+            executor.context().cache().get("outer"),
+            Some(&"kept".to_string())
+        );
+        assert!(!executor.context().cache().contains_key("inner"));
+    }
+
+    #[test]
+    fn test_popping_empty_stack_is_an_error() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("checkpoint".to_string()));
+
+        assert_eq!(executor.commit_checkpoint(), Err(NoCheckpointError));
+        assert_eq!(executor.rollback_checkpoint(), Err(NoCheckpointError));
+    }
+}
@@ -0,0 +1,247 @@
+//! Circuit breakers that stop calling operations which keep failing.
+
+use crate::{ApiExecutor, ApiOperation};
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// The state of the circuit breaker guarding one operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Calls run normally while consecutive failures are counted.
+    Closed,
+
+    /// Calls fail fast until the cooldown has passed.
+    Open,
+
+    /// The cooldown has passed; the next call is a trial that closes or reopens the circuit.
+    HalfOpen,
+}
+
+/// The error returned by [`ApiExecutor::execute_with_circuit_breaker`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CircuitBreakerError<E> {
+    /// The circuit is open, so the operation was not run.
+    CircuitOpen {
+        /// How long until the circuit half-opens.
+        retry_after: Duration,
+    },
+
+    /// The operation ran and failed.
+    Operation(E),
+}
+
+impl<E: fmt::Display> fmt::Display for CircuitBreakerError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CircuitBreakerError::CircuitOpen { retry_after } => {
+                write!(f, "circuit open, retry after {:?}", retry_after)
+            }
+            CircuitBreakerError::Operation(error) => write!(f, "operation failed: {}", error),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for CircuitBreakerError<E> {}
+
+/// The breaker state tracked for one operation.
+#[derive(Debug, Clone, Default)]
+struct Breaker {
+    /// Failures since the last success.
+    consecutive_failures: u32,
+
+    /// When the circuit opened, if it is open or half-open.
+    opened_at: Option<Instant>,
+}
+
+/// Circuit breaker configuration and per-operation state held by an executor.
+#[derive(Debug, Clone)]
+pub(crate) struct CircuitBreakers {
+    /// Consecutive failures that open a circuit.
+    failure_threshold: u32,
+
+    /// How long an open circuit fails fast before half-opening.
+    cooldown: Duration,
+
+    /// The breaker of each operation, by operation name.
+    breakers: HashMap<&'static str, Breaker>,
+}
+
+impl CircuitBreakers {
+    /// Returns the state of `breaker` at `now`.
+    fn state(&self, breaker: &Breaker, now: Instant) -> CircuitState {
+        match breaker.opened_at {
+            None => CircuitState::Closed,
+            Some(opened) if now.saturating_duration_since(opened) < self.cooldown => {
+                CircuitState::Open
+            }
+            Some(_) => CircuitState::HalfOpen,
+        }
+    }
+}
+
+impl<C> ApiExecutor<C> {
+    /// Opens an operation's circuit after `failure_threshold` consecutive failures in
+    /// [`execute_with_circuit_breaker`](Self::execute_with_circuit_breaker), failing
+    /// fast for `cooldown` before letting a trial call through.
+    ///
+    /// The cooldown is measured with the executor's clock.
+    pub fn with_circuit_breaker(mut self, failure_threshold: u32, cooldown: Duration) -> Self {
+        self.circuit_breakers = Some(CircuitBreakers {
+            failure_threshold: failure_threshold.max(1),
+            cooldown,
+            breakers: HashMap::new(),
+        });
+        self
+    }
+
+    /// Returns the circuit state of the operation named `operation`.
+    ///
+    /// Operations that have never run, and every operation when no breaker is
+    /// configured, are [`CircuitState::Closed`].
+    pub fn circuit_state(&self, operation: &str) -> CircuitState {
+        let now = self.clock.now();
+        self.circuit_breakers
+            .as_ref()
+            .and_then(|breakers| {
+                breakers
+                    .breakers
+                    .get(operation)
+                    .map(|breaker| breakers.state(breaker, now))
+            })
+            .unwrap_or(CircuitState::Closed)
+    }
+
+    /// Executes an operation through its circuit breaker.
+    ///
+    /// While the circuit is open the operation is not run. In the half-open state a
+    /// success closes the circuit and a failure reopens it for another cooldown.
+    /// Without a configured breaker this behaves like `execute`.
+    pub fn execute_with_circuit_breaker<P, Op>(
+        &mut self,
+        op: Op,
+        parameters: &P,
+    ) -> Result<Op::Output, CircuitBreakerError<Op::Error>>
+    where
+        Op: ApiOperation<C, P>,
+    {
+        let now = self.clock.now();
+        if let Some(breakers) = &self.circuit_breakers {
+            if let Some(breaker) = breakers.breakers.get(Op::name()) {
+                if breakers.state(breaker, now) == CircuitState::Open {
+                    let opened = breaker.opened_at.unwrap_or(now);
+                    return Err(CircuitBreakerError::CircuitOpen {
+                        retry_after: breakers.cooldown - now.saturating_duration_since(opened),
+                    });
+                }
+            }
+        }
+
+        let result = self.execute(op, parameters);
+        let now = self.clock.now();
+        if let Some(breakers) = &mut self.circuit_breakers {
+            let threshold = breakers.failure_threshold;
+            let breaker = breakers.breakers.entry(Op::name()).or_default();
+            if result.is_ok() {
+                *breaker = Breaker::default();
+            } else {
+                breaker.consecutive_failures += 1;
+                if breaker.opened_at.is_some() || breaker.consecutive_failures >= threshold {
+                    breaker.opened_at = Some(now);
+                }
+            }
+        }
+        result.map_err(CircuitBreakerError::Operation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+    use crate::MockClock;
+
+    /// Fails while the cache marks the backend as down.
+    struct CallBackend;
+
+    impl ApiOperation<DatabaseContext, ()> for CallBackend {
+        type Output = ();
+        type Error = &'static str;
+
+        fn execute(context: &mut DatabaseContext, _parameters: &()) -> Result<(), &'static str> {
+            context.increment_transaction();
+            if context.cache().contains_key("down") {
+                return Err("backend down");
+            }
+            Ok(())
+        }
+
+        fn name() -> &'static str {
+            "call_backend"
+        }
+    }
+
+    #[test]
+    fn test_breaker_opens_fails_fast_and_recovers() {
+        let clock = MockClock::new();
+        let mut context = DatabaseContext::new("circuit".to_string());
+        context
+            .cache_mut()
+            .insert("down".to_string(), String::new());
+        let mut executor = ApiExecutor::new(context)
+            .with_clock(clock.clone())
+            .with_circuit_breaker(3, Duration::from_secs(10));
+
+        for _ in 0..3 {
+            assert_eq!(
+                executor.execute_with_circuit_breaker(CallBackend, &()),
+                Err(CircuitBreakerError::Operation("backend down"))
+            );
+        }
+        assert_eq!(executor.circuit_state("call_backend"), CircuitState::Open);
+
+        clock.advance(Duration::from_secs(4));
+        assert_eq!(
+            executor.execute_with_circuit_breaker(CallBackend, &()),
+            Err(CircuitBreakerError::CircuitOpen {
+                retry_after: Duration::from_secs(6)
+            })
+        );
+        assert_eq!(executor.context().transaction_count(), 3);
+
+        clock.advance(Duration::from_secs(6));
+        assert_eq!(
+            executor.circuit_state("call_backend"),
+            CircuitState::HalfOpen
+        );
+        executor.context_mut().cache_mut().remove("down");
+        assert_eq!(
+            executor.execute_with_circuit_breaker(CallBackend, &()),
+            Ok(())
+        );
+        assert_eq!(executor.circuit_state("call_backend"), CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_failed_trial_reopens_circuit() {
+        let clock = MockClock::new();
+        let mut context = DatabaseContext::new("circuit".to_string());
+        context
+            .cache_mut()
+            .insert("down".to_string(), String::new());
+        let mut executor = ApiExecutor::new(context)
+            .with_clock(clock.clone())
+            .with_circuit_breaker(1, Duration::from_secs(10));
+
+        executor
+            .execute_with_circuit_breaker(CallBackend, &())
+            .unwrap_err();
+        clock.advance(Duration::from_secs(10));
+        executor
+            .execute_with_circuit_breaker(CallBackend, &())
+            .unwrap_err();
+
+        assert_eq!(executor.circuit_state("call_backend"), CircuitState::Open);
+        assert_eq!(executor.context().transaction_count(), 2);
+    }
+}
@@ -0,0 +1,170 @@
+//! Injectable time sources for deterministic time-dependent behavior.
+
+use crate::ApiExecutor;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A source of the current time used by time-dependent executor features.
+pub trait Clock: Send + Sync {
+    /// Returns the current instant.
+    fn now(&self) -> Instant;
+
+    /// Waits for `duration` to pass on this clock.
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+}
+
+/// The real clock, backed by [`Instant::now`] and [`std::thread::sleep`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A manually advanced clock for tests.
+///
+/// Clones share the same time, so a test can keep a handle after installing the clock
+/// on an executor. Sleeping advances the clock instead of blocking.
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    /// The current time shared by every clone.
+    now: Arc<Mutex<Instant>>,
+}
+
+impl MockClock {
+    /// Creates a clock frozen at the current real time.
+    pub fn new() -> Self {
+        Self {
+            now: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    /// Moves the clock forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        let mut now = self
+            .now
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        *now += duration;
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        *self
+            .now
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+}
+
+/// The clock installed on an executor, shared by its clones.
+#[derive(Clone)]
+pub(crate) struct ClockHandle(Arc<dyn Clock>);
+
+impl ClockHandle {
+    /// Wraps `clock` for sharing.
+    pub(crate) fn new(clock: impl Clock + 'static) -> Self {
+        Self(Arc::new(clock))
+    }
+
+    /// Returns the current instant according to the installed clock.
+    pub(crate) fn now(&self) -> Instant {
+        self.0.now()
+    }
+
+    /// Waits for `duration` to pass on the installed clock.
+    pub(crate) fn sleep(&self, duration: Duration) {
+        self.0.sleep(duration);
+    }
+}
+
+impl Default for ClockHandle {
+    fn default() -> Self {
+        Self(Arc::new(SystemClock))
+    }
+}
+
+impl fmt::Debug for ClockHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ClockHandle").finish()
+    }
+}
+
+impl<C> ApiExecutor<C> {
+    /// Installs the clock consulted by time-dependent features such as TTL memoization
+    /// and retry backoff.
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = ClockHandle::new(clock);
+        self
+    }
+
+    /// Returns the current instant according to the executor's clock.
+    pub fn now(&self) -> Instant {
+        self.clock.now()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+    use crate::ApiOperation;
+
+    struct Lookup;
+
+    impl ApiOperation<DatabaseContext, String> for Lookup {
+        type Output = u32;
+        type Error = ();
+
+        fn execute(context: &mut DatabaseContext, _parameters: &String) -> Result<u32, ()> {
+            context.increment_transaction();
+            Ok(context.transaction_count())
+        }
+    }
+
+    #[test]
+    fn test_ttl_entry_expires_when_mock_clock_advances() {
+        let clock = MockClock::new();
+        let mut executor =
+            ApiExecutor::new(DatabaseContext::new("clock".to_string())).with_clock(clock.clone());
+        let ttl = Duration::from_secs(60);
+        let key = |name: &String| name.clone();
+
+        let first = executor.execute_memoized_for(Lookup, &"alice".to_string(), key, ttl);
+        clock.advance(Duration::from_secs(59));
+        let cached = executor.execute_memoized_for(Lookup, &"alice".to_string(), key, ttl);
+        clock.advance(Duration::from_secs(1));
+        let refreshed = executor.execute_memoized_for(Lookup, &"alice".to_string(), key, ttl);
+
+        assert_eq!(first, Ok(1));
+        assert_eq!(cached, Ok(1));
+        assert_eq!(refreshed, Ok(2));
+    }
+
+    #[test]
+    fn test_mock_clock_sleep_advances_time() {
+        let clock = MockClock::new();
+        let executor = ApiExecutor::new(()).with_clock(clock.clone());
+        let start = executor.now();
+
+        clock.sleep(Duration::from_millis(250));
+
+        assert_eq!(executor.now() - start, Duration::from_millis(250));
+    }
+}
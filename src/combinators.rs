@@ -0,0 +1,671 @@
+//! Adapters returned by the combinator methods of [`Execute`].
+//!
+//! The blanket `Execute` implementation covers every `ApiOperation`, so the adapters
+//! expose their own `execute_on` methods rather than implementing `Execute` themselves.
+
+use crate::clock::ClockHandle;
+use crate::{BackoffStrategy, Clock, Execute};
+use std::fmt;
+
+/// Runs a compensating operation when the wrapped operation fails.
+///
+/// Created by [`Execute::recover_with`].
+#[derive(Debug, Clone)]
+pub struct RecoverWith<Op, Comp> {
+    /// The operation to run.
+    operation: Op,
+
+    /// The operation that undoes partial effects on failure.
+    compensation: Comp,
+}
+
+impl<Op, Comp> RecoverWith<Op, Comp> {
+    /// Wraps `operation` so that `compensation` runs if it fails.
+    pub(crate) fn new(operation: Op, compensation: Comp) -> Self {
+        Self {
+            operation,
+            compensation,
+        }
+    }
+
+    /// Executes the wrapped operation, compensating and returning its error on failure.
+    ///
+    /// The outcome of the compensation itself is ignored.
+    pub fn execute_on<C, P>(self, context: &mut C, parameters: &P) -> Result<Op::Output, Op::Error>
+    where
+        Op: Execute<C, P>,
+        Comp: Execute<C, P>,
+    {
+        match self.operation.execute_on(context, parameters) {
+            Ok(output) => Ok(output),
+            Err(error) => {
+                let _ = self.compensation.execute_on(context, parameters);
+                Err(error)
+            }
+        }
+    }
+}
+
+/// Runs a context mutation after the wrapped operation succeeds.
+///
+/// Created by [`Execute::tap_context`].
+#[derive(Debug, Clone)]
+pub struct TapContext<Op, F> {
+    /// The operation to run.
+    operation: Op,
+
+    /// The mutation applied to the context on success.
+    tap: F,
+}
+
+impl<Op, F> TapContext<Op, F> {
+    /// Wraps `operation` so that `tap` runs after it succeeds.
+    pub(crate) fn new(operation: Op, tap: F) -> Self {
+        Self { operation, tap }
+    }
+
+    /// Executes the wrapped operation, applying the mutation only on success.
+    pub fn execute_on<C, P>(self, context: &mut C, parameters: &P) -> Result<Op::Output, Op::Error>
+    where
+        Op: Execute<C, P>,
+        F: FnOnce(&mut C),
+    {
+        let output = self.operation.execute_on(context, parameters)?;
+        (self.tap)(context);
+        Ok(output)
+    }
+}
+
+/// Runs a second operation after the wrapped one and pairs their outputs.
+///
+/// Created by [`Execute::zip`].
+#[derive(Debug, Clone)]
+pub struct Zip<Op, Op2, P2> {
+    /// The operation to run first.
+    operation: Op,
+
+    /// The operation to run second.
+    other: Op2,
+
+    /// The parameters for the second operation.
+    other_parameters: P2,
+}
+
+impl<Op, Op2, P2> Zip<Op, Op2, P2> {
+    /// Pairs `operation` with `other`, which runs with `other_parameters`.
+    pub(crate) fn new(operation: Op, other: Op2, other_parameters: P2) -> Self {
+        Self {
+            operation,
+            other,
+            other_parameters,
+        }
+    }
+
+    /// Executes both operations in order, stopping at the first error.
+    ///
+    /// The second operation's error is converted into the first operation's error type.
+    pub fn execute_on<C, P>(
+        self,
+        context: &mut C,
+        parameters: &P,
+    ) -> Result<(Op::Output, Op2::Output), Op::Error>
+    where
+        Op: Execute<C, P>,
+        Op2: Execute<C, P2>,
+        Op2::Error: Into<Op::Error>,
+    {
+        let first = self.operation.execute_on(context, parameters)?;
+        let second = self
+            .other
+            .execute_on(context, &self.other_parameters)
+            .map_err(Into::into)?;
+        Ok((first, second))
+    }
+}
+
+/// Reports a custom diagnostic name for the wrapped operation.
+///
+/// Created by [`Execute::with_name`].
+#[derive(Debug, Clone)]
+pub struct Named<Op> {
+    /// The operation to run.
+    operation: Op,
+
+    /// The name reported in diagnostics.
+    name: &'static str,
+}
+
+impl<Op> Named<Op> {
+    /// Wraps `operation` so that it is reported as `name`.
+    pub(crate) fn new(operation: Op, name: &'static str) -> Self {
+        Self { operation, name }
+    }
+
+    /// Returns the name reported in diagnostics.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Executes the wrapped operation.
+    pub fn execute_on<C, P>(self, context: &mut C, parameters: &P) -> Result<Op::Output, Op::Error>
+    where
+        Op: Execute<C, P>,
+    {
+        self.operation.execute_on(context, parameters)
+    }
+}
+
+/// Renders parameters for diagnostics with sensitive fields masked.
+pub trait Redact {
+    /// Returns a representation of `self` that is safe to log.
+    fn redacted(&self) -> String;
+}
+
+/// An operation error annotated with the parameters that caused it.
+///
+/// Returned by the `execute_on` methods of [`TraceParams`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TracedError<E> {
+    /// The error returned by the operation.
+    pub error: E,
+
+    /// The rendered parameters of the failed call.
+    pub parameters: String,
+}
+
+impl<E: fmt::Display> fmt::Display for TracedError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (parameters: {})", self.error, self.parameters)
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for TracedError<E> {}
+
+/// Attaches the failing call's parameters to the wrapped operation's error.
+///
+/// Created by [`Execute::trace_params`].
+#[derive(Debug, Clone)]
+pub struct TraceParams<Op> {
+    /// The operation to run.
+    operation: Op,
+}
+
+impl<Op> TraceParams<Op> {
+    /// Wraps `operation` so that its errors carry the parameters.
+    pub(crate) fn new(operation: Op) -> Self {
+        Self { operation }
+    }
+
+    /// Executes the wrapped operation, rendering the parameters with `Debug` on failure.
+    pub fn execute_on<C, P>(
+        self,
+        context: &mut C,
+        parameters: &P,
+    ) -> Result<Op::Output, TracedError<Op::Error>>
+    where
+        Op: Execute<C, P>,
+        P: fmt::Debug,
+    {
+        self.operation
+            .execute_on(context, parameters)
+            .map_err(|error| TracedError {
+                error,
+                parameters: format!("{:?}", parameters),
+            })
+    }
+
+    /// Executes the wrapped operation, rendering the parameters with [`Redact`] on
+    /// failure so sensitive fields stay out of the error.
+    pub fn execute_on_redacted<C, P>(
+        self,
+        context: &mut C,
+        parameters: &P,
+    ) -> Result<Op::Output, TracedError<Op::Error>>
+    where
+        Op: Execute<C, P>,
+        P: Redact,
+    {
+        self.operation
+            .execute_on(context, parameters)
+            .map_err(|error| TracedError {
+                error,
+                parameters: parameters.redacted(),
+            })
+    }
+}
+
+/// Replaces the wrapped operation's output with an error if it fails a predicate.
+///
+/// Created by [`Execute::ensure`].
+#[derive(Debug, Clone)]
+pub struct Ensure<Op, F, E> {
+    /// The operation to run.
+    operation: Op,
+
+    /// The invariant the output must satisfy.
+    predicate: F,
+
+    /// The error returned when the invariant does not hold.
+    error: E,
+}
+
+impl<Op, F, E> Ensure<Op, F, E> {
+    /// Wraps `operation` so that outputs failing `predicate` become `error`.
+    pub(crate) fn new(operation: Op, predicate: F, error: E) -> Self {
+        Self {
+            operation,
+            predicate,
+            error,
+        }
+    }
+
+    /// Executes the wrapped operation and checks its output against the predicate.
+    pub fn execute_on<C, P>(self, context: &mut C, parameters: &P) -> Result<Op::Output, E>
+    where
+        Op: Execute<C, P, Error = E>,
+        F: FnOnce(&Op::Output) -> bool,
+    {
+        let output = self.operation.execute_on(context, parameters)?;
+        if (self.predicate)(&output) {
+            Ok(output)
+        } else {
+            Err(self.error)
+        }
+    }
+}
+
+/// Retries the wrapped operation on a schedule chosen by a [`BackoffStrategy`].
+///
+/// Created by [`Execute::on_retry`].
+#[derive(Debug, Clone)]
+pub struct OnRetry<Op, S> {
+    /// The operation to run, cloned for each attempt.
+    operation: Op,
+
+    /// Decides whether and when to retry.
+    strategy: S,
+
+    /// The clock used to wait between attempts.
+    clock: ClockHandle,
+}
+
+impl<Op, S> OnRetry<Op, S> {
+    /// Wraps `operation` so that failures are retried according to `strategy`.
+    pub(crate) fn new(operation: Op, strategy: S) -> Self {
+        Self {
+            operation,
+            strategy,
+            clock: ClockHandle::default(),
+        }
+    }
+
+    /// Waits between attempts on `clock` instead of the system clock.
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = ClockHandle::new(clock);
+        self
+    }
+
+    /// Executes the wrapped operation, retrying failures until it succeeds or the
+    /// strategy gives up, in which case the last error is returned.
+    pub fn execute_on<C, P>(
+        mut self,
+        context: &mut C,
+        parameters: &P,
+    ) -> Result<Op::Output, Op::Error>
+    where
+        Op: Execute<C, P> + Clone,
+        S: BackoffStrategy,
+    {
+        let mut attempt = 1;
+        loop {
+            match self.operation.clone().execute_on(context, parameters) {
+                Ok(output) => return Ok(output),
+                Err(error) => match self.strategy.next_delay(attempt) {
+                    None => return Err(error),
+                    Some(delay) => {
+                        if !delay.is_zero() {
+                            self.clock.sleep(delay);
+                        }
+                        attempt += 1;
+                    }
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+    use crate::{ApiOperation, MockClock, RetryPolicy};
+    use std::time::Duration;
+
+    #[derive(Debug, PartialEq)]
+    enum TransferError {
+        InsufficientFunds,
+    }
+
+    /// Debits the account, then fails to credit the destination.
+    struct Transfer;
+
+    /// Marks the partial transfer as rolled back.
+    struct UndoTransfer;
+
+    impl ApiOperation<DatabaseContext, u32> for Transfer {
+        type Output = ();
+        type Error = TransferError;
+
+        fn execute(context: &mut DatabaseContext, parameters: &u32) -> Result<(), TransferError> {
+            context
+                .cache_mut()
+                .insert("debited".to_string(), parameters.to_string());
+            if *parameters > 100 {
+                return Err(TransferError::InsufficientFunds);
+            }
+            Ok(())
+        }
+    }
+
+    impl ApiOperation<DatabaseContext, u32> for UndoTransfer {
+        type Output = ();
+        type Error = ();
+
+        fn execute(context: &mut DatabaseContext, _parameters: &u32) -> Result<(), ()> {
+            context.cache_mut().remove("debited");
+            context
+                .cache_mut()
+                .insert("compensated".to_string(), "true".to_string());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_recover_with_runs_compensation_and_keeps_error() {
+        let mut context = DatabaseContext::new("saga".to_string());
+
+        let result = Transfer
+            .recover_with(UndoTransfer)
+            .execute_on(&mut context, &500);
+
+        assert_eq!(result, Err(TransferError::InsufficientFunds));
+        assert_eq!(
+            context.cache().get("compensated"),
+            Some(&"true".to_string())
+        );
+        assert!(!context.cache().contains_key("debited"));
+    }
+
+    #[test]
+    fn test_recover_with_skips_compensation_on_success() {
+        let mut context = DatabaseContext::new("saga".to_string());
+
+        let result = Transfer
+            .recover_with(UndoTransfer)
+            .execute_on(&mut context, &50);
+
+        assert_eq!(result, Ok(()));
+        assert!(!context.cache().contains_key("compensated"));
+    }
+
+    #[test]
+    fn test_tap_context_runs_only_on_success() {
+        let mut context = DatabaseContext::new("tap".to_string());
+
+        let result = Transfer
+            .tap_context(|context: &mut DatabaseContext| context.increment_transaction())
+            .execute_on(&mut context, &50);
+        assert_eq!(result, Ok(()));
+        assert_eq!(context.transaction_count(), 1);
+
+        let result = Transfer
+            .tap_context(|context: &mut DatabaseContext| context.increment_transaction())
+            .execute_on(&mut context, &500);
+        assert_eq!(result, Err(TransferError::InsufficientFunds));
+        assert_eq!(context.transaction_count(), 1);
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum LookupError {
+        NotFound(String),
+    }
+
+    /// Finds a user's name by id.
+    struct FindUser;
+
+    /// Finds the settings stored for a user name.
+    struct FindSettings;
+
+    impl ApiOperation<DatabaseContext, u32> for FindUser {
+        type Output = String;
+        type Error = LookupError;
+
+        fn execute(context: &mut DatabaseContext, parameters: &u32) -> Result<String, LookupError> {
+            let key = format!("user_{}", parameters);
+            context
+                .cache()
+                .get(&key)
+                .cloned()
+                .ok_or(LookupError::NotFound(key))
+        }
+    }
+
+    impl ApiOperation<DatabaseContext, String> for FindSettings {
+        type Output = String;
+        type Error = String;
+
+        fn execute(context: &mut DatabaseContext, parameters: &String) -> Result<String, String> {
+            let key = format!("settings_{}", parameters);
+            context.cache().get(&key).cloned().ok_or(key)
+        }
+    }
+
+    impl From<String> for LookupError {
+        fn from(key: String) -> Self {
+            LookupError::NotFound(key)
+        }
+    }
+
+    #[test]
+    fn test_zip_pairs_outputs_and_aborts_on_second_error() {
+        let mut context = DatabaseContext::new("zip".to_string());
+        context
+            .cache_mut()
+            .insert("user_1".to_string(), "alice".to_string());
+        context
+            .cache_mut()
+            .insert("settings_alice".to_string(), "dark".to_string());
+
+        let both = FindUser
+            .zip(FindSettings, "alice".to_string())
+            .execute_on(&mut context, &1);
+        assert_eq!(both, Ok(("alice".to_string(), "dark".to_string())));
+
+        let missing = FindUser
+            .zip(FindSettings, "bob".to_string())
+            .execute_on(&mut context, &1);
+        assert_eq!(
+            missing,
+            Err(LookupError::NotFound("settings_bob".to_string()))
+        );
+    }
+
+    /// A generic operation whose type name is unwieldy in logs.
+    struct Store<T>(std::marker::PhantomData<T>);
+
+    impl<T: ToString> ApiOperation<DatabaseContext, T> for Store<T> {
+        type Output = ();
+        type Error = ();
+
+        fn execute(context: &mut DatabaseContext, parameters: &T) -> Result<(), ()> {
+            context
+                .cache_mut()
+                .insert("stored".to_string(), parameters.to_string());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_with_name_overrides_reported_name() {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let mut executor = crate::ApiExecutor::new(DatabaseContext::new("named".to_string()))
+            .with_notifier(sender);
+
+        executor
+            .execute(Store::<u32>(std::marker::PhantomData), &7)
+            .unwrap();
+        executor
+            .execute_named(
+                Store::<u32>(std::marker::PhantomData).with_name("store_number"),
+                &8,
+            )
+            .unwrap();
+
+        let names: Vec<_> = receiver
+            .try_iter()
+            .map(|outcome| outcome.operation)
+            .collect();
+        assert!(names[0].contains("Store<u32>"));
+        assert_eq!(names[1], "store_number");
+        assert_eq!(
+            executor.context().cache().get("stored"),
+            Some(&"8".to_string())
+        );
+    }
+
+    #[derive(Debug)]
+    struct Login {
+        user: String,
+        password: String,
+    }
+
+    impl Redact for Login {
+        fn redacted(&self) -> String {
+            format!("Login {{ user: {:?}, password: \"***\" }}", self.user)
+        }
+    }
+
+    /// Accepts only the password `correct horse`.
+    struct Authenticate;
+
+    impl ApiOperation<DatabaseContext, Login> for Authenticate {
+        type Output = ();
+        type Error = String;
+
+        fn execute(_context: &mut DatabaseContext, parameters: &Login) -> Result<(), String> {
+            if parameters.password == "correct horse" {
+                return Ok(());
+            }
+            Err(format!("wrong password for {}", parameters.user))
+        }
+    }
+
+    #[test]
+    fn test_trace_params_attaches_parameters_to_error() {
+        let mut context = DatabaseContext::new("trace".to_string());
+
+        let error = FindUser
+            .trace_params()
+            .execute_on(&mut context, &42)
+            .unwrap_err();
+
+        assert_eq!(error.error, LookupError::NotFound("user_42".to_string()));
+        assert_eq!(error.parameters, "42");
+    }
+
+    #[test]
+    fn test_trace_params_respects_redaction() {
+        let mut context = DatabaseContext::new("trace".to_string());
+        let login = Login {
+            user: "alice".to_string(),
+            password: "hunter2".to_string(),
+        };
+
+        let debug = Authenticate
+            .trace_params()
+            .execute_on(&mut context, &login)
+            .unwrap_err();
+        let redacted = Authenticate
+            .trace_params()
+            .execute_on_redacted(&mut context, &login)
+            .unwrap_err();
+
+        assert!(debug.parameters.contains("hunter2"));
+        assert_eq!(
+            redacted.to_string(),
+            "wrong password for alice (parameters: Login { user: \"alice\", password: \"***\" })"
+        );
+    }
+
+    #[test]
+    fn test_ensure_replaces_output_failing_predicate() {
+        let mut context = DatabaseContext::new("ensure".to_string());
+        context
+            .cache_mut()
+            .insert("user_1".to_string(), "alice".to_string());
+        context
+            .cache_mut()
+            .insert("user_2".to_string(), String::new());
+        let named = |name: &String| !name.is_empty();
+
+        let passing = FindUser
+            .ensure(named, LookupError::NotFound("name".to_string()))
+            .execute_on(&mut context, &1);
+        let failing = FindUser
+            .ensure(named, LookupError::NotFound("name".to_string()))
+            .execute_on(&mut context, &2);
+
+        assert_eq!(passing, Ok("alice".to_string()));
+        assert_eq!(failing, Err(LookupError::NotFound("name".to_string())));
+    }
+
+    /// Fails until the context has recorded `parameters` transactions.
+    #[derive(Clone)]
+    struct Flaky;
+
+    impl ApiOperation<DatabaseContext, u32> for Flaky {
+        type Output = u32;
+        type Error = String;
+
+        fn execute(context: &mut DatabaseContext, parameters: &u32) -> Result<u32, String> {
+            context.increment_transaction();
+            if context.transaction_count() < *parameters {
+                return Err(format!("attempt {} failed", context.transaction_count()));
+            }
+            Ok(context.transaction_count())
+        }
+    }
+
+    #[test]
+    fn test_on_retry_consults_strategy_for_each_attempt_in_order() {
+        let clock = MockClock::new();
+        let start = clock.now();
+        let mut context = DatabaseContext::new("backoff".to_string());
+        let schedule = [10, 30, 70].map(Duration::from_millis);
+        let mut consulted = Vec::new();
+
+        let result = Flaky
+            .on_retry(|attempt: u32| {
+                consulted.push(attempt);
+                schedule.get(attempt as usize - 1).copied()
+            })
+            .with_clock(clock.clone())
+            .execute_on(&mut context, &4);
+
+        assert_eq!(result, Ok(4));
+        assert_eq!(consulted, vec![1, 2, 3]);
+        assert_eq!(clock.now() - start, Duration::from_millis(110));
+    }
+
+    #[test]
+    fn test_on_retry_returns_last_error_when_strategy_gives_up() {
+        let mut context = DatabaseContext::new("backoff".to_string());
+
+        let result = Flaky
+            .on_retry(RetryPolicy::new(2))
+            .execute_on(&mut context, &5);
+
+        assert_eq!(result, Err("attempt 2 failed".to_string()));
+        assert_eq!(context.transaction_count(), 2);
+    }
+}
@@ -0,0 +1,150 @@
+//! Logging compensating actions to undo multi-step workflows.
+
+use crate::{ApiExecutor, ApiOperation};
+use std::any::Any;
+use std::fmt;
+
+/// An action that undoes the effects of an operation that already succeeded.
+pub type Compensation<C> = Box<dyn FnOnce(&mut C) + Send + Sync>;
+
+/// An operation that can describe how to undo a successful execution.
+///
+/// Unlike the paired operations of a [`Saga`](crate::Saga), the compensation is a
+/// closure built from the actual parameters and output, so it can capture generated
+/// identifiers and other values known only after the operation ran.
+pub trait Compensable<C, P>: ApiOperation<C, P> {
+    /// Returns the action undoing this execution, or `None` if there is nothing to undo.
+    fn compensation(parameters: &P, output: &Self::Output) -> Option<Compensation<C>>;
+}
+
+/// The compensations registered by successful operations, oldest first.
+///
+/// Cloning an executor does not clone its compensations; the clone starts empty.
+#[derive(Default)]
+pub(crate) struct CompensationLog {
+    /// A [`Compensation`] for the executor's context type per registered action.
+    actions: Vec<Box<dyn Any + Send + Sync>>,
+}
+
+impl Clone for CompensationLog {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl fmt::Debug for CompensationLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CompensationLog")
+            .field("actions", &self.actions.len())
+            .finish()
+    }
+}
+
+impl<C: 'static> ApiExecutor<C> {
+    /// Executes an operation and, if it succeeds, records its compensation in the log.
+    pub fn execute_with_compensation_log<P, Op>(
+        &mut self,
+        op: Op,
+        parameters: &P,
+    ) -> Result<Op::Output, Op::Error>
+    where
+        Op: Compensable<C, P>,
+    {
+        let output = self.execute(op, parameters)?;
+        if let Some(compensation) = Op::compensation(parameters, &output) {
+            self.compensations.actions.push(Box::new(compensation));
+        }
+        Ok(output)
+    }
+
+    /// Runs every logged compensation in reverse registration order and clears the log,
+    /// returning how many ran.
+    pub fn compensate_all(&mut self) -> usize {
+        let actions = std::mem::take(&mut self.compensations.actions);
+        let count = actions.len();
+        for action in actions.into_iter().rev() {
+            let compensation = action
+                .downcast::<Compensation<C>>()
+                .expect("compensations are logged for the executor's context type");
+            compensation(&mut self.context);
+        }
+        count
+    }
+
+    /// Discards the logged compensations once a workflow has completed.
+    pub fn clear_compensations(&mut self) {
+        self.compensations.actions.clear();
+    }
+
+    /// Returns the number of logged compensations.
+    pub fn compensation_count(&self) -> usize {
+        self.compensations.actions.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+
+    /// Reserves a resource and logs how to release it.
+    struct Reserve;
+
+    impl ApiOperation<DatabaseContext, &'static str> for Reserve {
+        type Output = ();
+        type Error = ();
+
+        fn execute(context: &mut DatabaseContext, parameters: &&'static str) -> Result<(), ()> {
+            context
+                .cache_mut()
+                .insert(parameters.to_string(), "reserved".to_string());
+            Ok(())
+        }
+    }
+
+    impl Compensable<DatabaseContext, &'static str> for Reserve {
+        fn compensation(
+            parameters: &&'static str,
+            _output: &(),
+        ) -> Option<Compensation<DatabaseContext>> {
+            let resource = *parameters;
+            Some(Box::new(move |context: &mut DatabaseContext| {
+                context.cache_mut().remove(resource);
+                let log = context.cache_mut().entry("undone".to_string()).or_default();
+                log.push_str(resource);
+            }))
+        }
+    }
+
+    #[test]
+    fn test_compensations_run_in_reverse_order() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("compensation".to_string()));
+
+        for resource in ["a", "b", "c"] {
+            executor
+                .execute_with_compensation_log(Reserve, &resource)
+                .unwrap();
+        }
+        assert_eq!(executor.compensation_count(), 3);
+
+        assert_eq!(executor.compensate_all(), 3);
+
+        let cache = executor.context().cache();
+        assert_eq!(cache.get("undone"), Some(&"cba".to_string()));
+        assert_eq!(cache.len(), 1);
+        assert_eq!(executor.compensation_count(), 0);
+    }
+
+    #[test]
+    fn test_cleared_log_compensates_nothing() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("compensation".to_string()));
+        executor
+            .execute_with_compensation_log(Reserve, &"a")
+            .unwrap();
+
+        executor.clear_compensations();
+
+        assert_eq!(executor.compensate_all(), 0);
+        assert!(executor.context().cache().contains_key("a"));
+    }
+}
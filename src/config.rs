@@ -0,0 +1,155 @@
+//! Executor-held configuration used to fill in partial parameters.
+
+use crate::{ApiExecutor, ApiOperation};
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// Parameters that can be completed from an executor-held configuration object.
+///
+/// Unlike values provided by the context, the configuration belongs to the executor,
+/// which centralizes defaults such as timeouts in one place.
+pub trait Contextual<Cfg>: Sized {
+    /// Returns the parameters with any unset fields filled in from `config`.
+    fn resolve(partial: Self, config: &Cfg) -> Self;
+}
+
+/// Configuration objects installed on an executor, keyed by type.
+#[derive(Clone, Default)]
+pub(crate) struct ConfigStore {
+    /// One configuration object per type.
+    values: HashMap<TypeId, Arc<dyn Any + Send + Sync>>,
+}
+
+impl ConfigStore {
+    /// Stores `config`, replacing any configuration of the same type.
+    fn insert<Cfg: Send + Sync + 'static>(&mut self, config: Cfg) {
+        self.values.insert(TypeId::of::<Cfg>(), Arc::new(config));
+    }
+
+    /// Returns the configuration of type `Cfg`, if installed.
+    fn get<Cfg: 'static>(&self) -> Option<&Cfg> {
+        self.values
+            .get(&TypeId::of::<Cfg>())
+            .and_then(|config| config.downcast_ref())
+    }
+}
+
+impl fmt::Debug for ConfigStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConfigStore")
+            .field("values", &self.values.len())
+            .finish()
+    }
+}
+
+impl<C> ApiExecutor<C> {
+    /// Installs a configuration object, replacing any existing one of the same type.
+    pub fn with_config<Cfg: Send + Sync + 'static>(mut self, config: Cfg) -> Self {
+        self.configs.insert(config);
+        self
+    }
+
+    /// Returns the installed configuration object of type `Cfg`, if any.
+    pub fn config<Cfg: 'static>(&self) -> Option<&Cfg> {
+        self.configs.get()
+    }
+
+    /// Resolves partial parameters against the executor's configuration, then executes.
+    ///
+    /// If no configuration of type `Cfg` is installed, the parameters are used as given.
+    pub fn execute_contextual<P, Op, Cfg>(
+        &mut self,
+        _op: Op,
+        parameters: P,
+    ) -> Result<Op::Output, Op::Error>
+    where
+        Op: ApiOperation<C, P>,
+        P: Contextual<Cfg>,
+        Cfg: 'static,
+    {
+        let parameters = match self.configs.get::<Cfg>() {
+            Some(config) => P::resolve(parameters, config),
+            None => parameters,
+        };
+        self.execute_observed::<P, Op>(&parameters)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+    use std::time::Duration;
+
+    struct ServiceConfig {
+        default_timeout: Duration,
+    }
+
+    struct FetchProps {
+        url: String,
+        timeout: Option<Duration>,
+    }
+
+    impl Contextual<ServiceConfig> for FetchProps {
+        fn resolve(partial: Self, config: &ServiceConfig) -> Self {
+            Self {
+                timeout: partial.timeout.or(Some(config.default_timeout)),
+                ..partial
+            }
+        }
+    }
+
+    struct Fetch;
+
+    impl ApiOperation<DatabaseContext, FetchProps> for Fetch {
+        type Output = Option<Duration>;
+        type Error = ();
+
+        fn execute(
+            context: &mut DatabaseContext,
+            parameters: &FetchProps,
+        ) -> Result<Option<Duration>, ()> {
+            context
+                .cache_mut()
+                .insert("last_url".to_string(), parameters.url.clone());
+            Ok(parameters.timeout)
+        }
+    }
+
+    fn props(timeout: Option<Duration>) -> FetchProps {
+        FetchProps {
+            url: "https://example.com".to_string(),
+            timeout,
+        }
+    }
+
+    #[test]
+    fn test_unset_fields_are_filled_from_config() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("config".to_string()))
+            .with_config(ServiceConfig {
+                default_timeout: Duration::from_secs(30),
+            });
+
+        let resolved = executor.execute_contextual(Fetch, props(None)).unwrap();
+        assert_eq!(resolved, Some(Duration::from_secs(30)));
+
+        let explicit = executor
+            .execute_contextual(Fetch, props(Some(Duration::from_secs(5))))
+            .unwrap();
+        assert_eq!(explicit, Some(Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn test_parameters_pass_through_without_config() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("config".to_string()));
+
+        let resolved = executor
+            .execute_contextual::<_, _, ServiceConfig>(Fetch, props(None))
+            .unwrap();
+
+        assert_eq!(resolved, None);
+        assert!(executor.config::<ServiceConfig>().is_none());
+    }
+}
@@ -0,0 +1,141 @@
+//! Request-scoped correlation metadata threaded through operations.
+
+use crate::{ApiExecutor, ApiOperation};
+use std::collections::HashMap;
+
+/// Metadata identifying the request an operation runs on behalf of.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CorrelationContext {
+    /// The identifier shared by every operation serving the same request.
+    pub request_id: String,
+
+    /// The identifier of the current span within the request.
+    pub span_id: String,
+
+    /// Arbitrary key/value pairs propagated alongside the identifiers.
+    pub baggage: HashMap<String, String>,
+}
+
+impl CorrelationContext {
+    /// Creates a correlation context with empty baggage.
+    pub fn new(request_id: impl Into<String>, span_id: impl Into<String>) -> Self {
+        Self {
+            request_id: request_id.into(),
+            span_id: span_id.into(),
+            baggage: HashMap::new(),
+        }
+    }
+
+    /// Adds a baggage entry.
+    pub fn with_baggage(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.baggage.insert(key.into(), value.into());
+        self
+    }
+}
+
+/// A context that exposes the current [`CorrelationContext`] to operations.
+pub trait WithCorrelation {
+    /// Returns the correlation context of the current execution, if any.
+    fn correlation(&self) -> Option<&CorrelationContext>;
+
+    /// Installs or clears the correlation context.
+    fn set_correlation(&mut self, correlation: Option<CorrelationContext>);
+}
+
+impl<C> ApiExecutor<C> {
+    /// Sets the correlation context made available by
+    /// [`execute_with_correlation_context`](Self::execute_with_correlation_context).
+    pub fn with_correlation_context(mut self, correlation: CorrelationContext) -> Self {
+        self.correlation = Some(correlation);
+        self
+    }
+
+    /// Replaces the correlation context, for example when starting a new request.
+    pub fn set_correlation_context(&mut self, correlation: Option<CorrelationContext>) {
+        self.correlation = correlation;
+    }
+
+    /// Returns the correlation context carried by the executor.
+    pub fn correlation_context(&self) -> Option<&CorrelationContext> {
+        self.correlation.as_ref()
+    }
+
+    /// Executes an operation with the executor's correlation context installed on
+    /// the context for the duration of the call.
+    pub fn execute_with_correlation_context<P, Op>(
+        &mut self,
+        op: Op,
+        parameters: &P,
+    ) -> Result<Op::Output, Op::Error>
+    where
+        Op: ApiOperation<C, P>,
+        C: WithCorrelation,
+    {
+        self.context.set_correlation(self.correlation.clone());
+        let result = self.execute(op, parameters);
+        self.context.set_correlation(None);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct RequestContext {
+        correlation: Option<CorrelationContext>,
+    }
+
+    impl WithCorrelation for RequestContext {
+        fn correlation(&self) -> Option<&CorrelationContext> {
+            self.correlation.as_ref()
+        }
+
+        fn set_correlation(&mut self, correlation: Option<CorrelationContext>) {
+            self.correlation = correlation;
+        }
+    }
+
+    struct CurrentRequest;
+
+    impl ApiOperation<RequestContext, ()> for CurrentRequest {
+        type Output = String;
+        type Error = &'static str;
+
+        fn execute(context: &mut RequestContext, _parameters: &()) -> Result<String, &'static str> {
+            context
+                .correlation()
+                .map(|correlation| correlation.request_id.clone())
+                .ok_or("no correlation context")
+        }
+    }
+
+    #[test]
+    fn test_operation_reads_request_id() {
+        let correlation =
+            CorrelationContext::new("req-42", "span-1").with_baggage("tenant", "acme");
+        let mut executor =
+            ApiExecutor::new(RequestContext::default()).with_correlation_context(correlation);
+
+        let request_id = executor.execute_with_correlation_context(CurrentRequest, &());
+
+        assert_eq!(request_id, Ok("req-42".to_string()));
+        assert!(executor.context().correlation().is_none());
+        assert_eq!(
+            executor.correlation_context().unwrap().baggage["tenant"],
+            "acme"
+        );
+    }
+
+    #[test]
+    fn test_plain_execute_does_not_install_correlation() {
+        let mut executor = ApiExecutor::new(RequestContext::default())
+            .with_correlation_context(CorrelationContext::new("req-42", "span-1"));
+
+        assert_eq!(
+            executor.execute(CurrentRequest, &()),
+            Err("no correlation context")
+        );
+    }
+}
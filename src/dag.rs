@@ -0,0 +1,669 @@
+//! Workflows whose operations depend on each other's outputs.
+
+use crate::clock::ClockHandle;
+use crate::{ApiExecutor, ApiOperation, ApiQuery};
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt;
+use std::thread;
+use std::time::Duration;
+
+/// Runs a node against the context, given the outputs of the nodes before it.
+type NodeFn<C, E> = dyn Fn(&mut C, &DagOutputs) -> Result<Box<dyn Any>, E>;
+
+/// A query node's work, with its parameters already built from upstream outputs.
+type QueryJob<C, E> = Box<dyn FnOnce(&C) -> Result<Box<dyn Any + Send>, E> + Send>;
+
+/// Builds a query node's job from the outputs of the nodes before it.
+type PrepareFn<C, E> = dyn Fn(&DagOutputs) -> QueryJob<C, E>;
+
+/// How a node runs against the context.
+enum NodeRun<C, E> {
+    /// An operation needing exclusive access to the context.
+    Operation(Box<NodeFn<C, E>>),
+
+    /// A read-only query that may run concurrently with other queries.
+    Query(Box<PrepareFn<C, E>>),
+}
+
+/// A named operation and the nodes whose outputs it consumes.
+struct DagNode<C, E> {
+    /// The unique name of the node.
+    name: &'static str,
+
+    /// The diagnostic name of the node's operation or query.
+    operation: &'static str,
+
+    /// The names of the nodes that must run before this one.
+    dependencies: Vec<&'static str>,
+
+    /// Builds the node's parameters from upstream outputs and executes it.
+    run: NodeRun<C, E>,
+}
+
+/// Errors detected while building a [`Dag`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DagError {
+    /// Two nodes share the same name.
+    DuplicateNode(&'static str),
+
+    /// A node depends on a name that no node has.
+    UnknownDependency {
+        /// The node declaring the dependency.
+        node: &'static str,
+
+        /// The missing dependency.
+        dependency: &'static str,
+    },
+
+    /// The dependencies form a cycle through the listed nodes.
+    Cycle(Vec<&'static str>),
+}
+
+impl fmt::Display for DagError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DagError::DuplicateNode(name) => write!(f, "duplicate node `{}`", name),
+            DagError::UnknownDependency { node, dependency } => {
+                write!(
+                    f,
+                    "node `{}` depends on unknown node `{}`",
+                    node, dependency
+                )
+            }
+            DagError::Cycle(nodes) => write!(f, "dependency cycle among {:?}", nodes),
+        }
+    }
+}
+
+impl std::error::Error for DagError {}
+
+/// The error returned when a node fails while running a [`Dag`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DagRunError<E> {
+    /// The name of the node that failed.
+    pub node: &'static str,
+
+    /// The error returned by the node's operation.
+    pub error: E,
+}
+
+impl<E: fmt::Display> fmt::Display for DagRunError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "node `{}` failed: {}", self.node, self.error)
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for DagRunError<E> {}
+
+/// Outputs of the nodes that have run, looked up by node name.
+#[derive(Default)]
+pub struct DagOutputs {
+    /// The boxed output of each completed node.
+    values: HashMap<&'static str, Box<dyn Any>>,
+}
+
+impl DagOutputs {
+    /// Returns the output of `node`, or `None` if it has not run or has another type.
+    pub fn get<T: 'static>(&self, node: &str) -> Option<&T> {
+        self.values.get(node).and_then(|value| value.downcast_ref())
+    }
+
+    /// Removes and returns the output of `node`.
+    pub fn take<T: 'static>(&mut self, node: &str) -> Option<T> {
+        match self.values.remove(node)?.downcast() {
+            Ok(value) => Some(*value),
+            Err(_) => None,
+        }
+    }
+
+    /// Returns the number of stored outputs.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns `true` if no outputs are stored.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+impl fmt::Debug for DagOutputs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut nodes: Vec<_> = self.values.keys().collect();
+        nodes.sort();
+        f.debug_struct("DagOutputs").field("nodes", &nodes).finish()
+    }
+}
+
+/// Collects the nodes of a [`Dag`] before their dependencies are validated.
+pub struct DagBuilder<C, E> {
+    /// The nodes in the order they were added.
+    nodes: Vec<DagNode<C, E>>,
+}
+
+impl<C, E> DagBuilder<C, E> {
+    /// Creates an empty builder.
+    pub fn new() -> Self {
+        Self { nodes: Vec::new() }
+    }
+
+    /// Adds a node running `Op` with parameters built from its dependencies' outputs.
+    ///
+    /// `parameters` receives the outputs of every node that has already run, which
+    /// always includes everything listed in `dependencies`.
+    pub fn node<Op, P, F>(
+        mut self,
+        name: &'static str,
+        dependencies: &[&'static str],
+        _op: Op,
+        parameters: F,
+    ) -> Self
+    where
+        Op: ApiOperation<C, P, Error = E>,
+        Op::Output: 'static,
+        F: Fn(&DagOutputs) -> P + 'static,
+    {
+        self.nodes.push(DagNode {
+            name,
+            operation: Op::name(),
+            dependencies: dependencies.to_vec(),
+            run: NodeRun::Operation(Box::new(move |context, outputs| {
+                let parameters = parameters(outputs);
+                Op::execute(context, &parameters).map(|output| Box::new(output) as Box<dyn Any>)
+            })),
+        });
+        self
+    }
+
+    /// Adds a node running the read-only query `Q`, which
+    /// [`Dag::run_parallel`] may run concurrently with other queries.
+    ///
+    /// `parameters` is called on the calling thread; only the query itself runs on a
+    /// worker thread.
+    pub fn query_node<Q, P, F>(
+        mut self,
+        name: &'static str,
+        dependencies: &[&'static str],
+        _query: Q,
+        parameters: F,
+    ) -> Self
+    where
+        Q: ApiQuery<C, P, Error = E>,
+        Q::Output: Send + 'static,
+        P: Send + 'static,
+        F: Fn(&DagOutputs) -> P + 'static,
+    {
+        self.nodes.push(DagNode {
+            name,
+            operation: std::any::type_name::<Q>(),
+            dependencies: dependencies.to_vec(),
+            run: NodeRun::Query(Box::new(move |outputs| {
+                let parameters = parameters(outputs);
+                Box::new(move |context: &C| {
+                    Q::query(context, &parameters)
+                        .map(|output| Box::new(output) as Box<dyn Any + Send>)
+                })
+            })),
+        });
+        self
+    }
+
+    /// Validates the dependencies and computes a topological execution order.
+    ///
+    /// Among nodes that are ready at the same time, the one added first runs first.
+    pub fn build(self) -> Result<Dag<C, E>, DagError> {
+        let mut index = HashMap::new();
+        for (position, node) in self.nodes.iter().enumerate() {
+            if index.insert(node.name, position).is_some() {
+                return Err(DagError::DuplicateNode(node.name));
+            }
+        }
+
+        let mut pending = vec![0usize; self.nodes.len()];
+        let mut dependents = vec![Vec::new(); self.nodes.len()];
+        for (position, node) in self.nodes.iter().enumerate() {
+            for &dependency in &node.dependencies {
+                let upstream = *index.get(dependency).ok_or(DagError::UnknownDependency {
+                    node: node.name,
+                    dependency,
+                })?;
+                pending[position] += 1;
+                dependents[upstream].push(position);
+            }
+        }
+
+        let mut order = Vec::with_capacity(self.nodes.len());
+        let mut levels = vec![0usize; self.nodes.len()];
+        let mut ready: Vec<usize> = (0..self.nodes.len())
+            .filter(|&position| pending[position] == 0)
+            .collect();
+        while let Some(position) = ready.iter().copied().min() {
+            ready.retain(|&other| other != position);
+            order.push(position);
+            for &dependent in &dependents[position] {
+                levels[dependent] = levels[dependent].max(levels[position] + 1);
+                pending[dependent] -= 1;
+                if pending[dependent] == 0 {
+                    ready.push(dependent);
+                }
+            }
+        }
+
+        if order.len() < self.nodes.len() {
+            let cycle = (0..self.nodes.len())
+                .filter(|&position| pending[position] > 0)
+                .map(|position| self.nodes[position].name)
+                .collect();
+            return Err(DagError::Cycle(cycle));
+        }
+
+        let mut waves = vec![Vec::new(); levels.iter().max().map_or(0, |level| level + 1)];
+        for &position in &order {
+            waves[levels[position]].push(position);
+        }
+
+        Ok(Dag {
+            nodes: self.nodes,
+            order,
+            waves,
+        })
+    }
+}
+
+impl<C, E> Default for DagBuilder<C, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A validated graph of operations run in dependency order against one context.
+///
+/// Generalizes the linear steps of a `Pipeline` to arbitrary acyclic
+/// dependencies, with each node's parameters built from its upstream outputs.
+pub struct Dag<C, E> {
+    /// The nodes in the order they were added.
+    nodes: Vec<DagNode<C, E>>,
+
+    /// Indices into `nodes` in execution order.
+    order: Vec<usize>,
+
+    /// Indices into `nodes` grouped by depth; each group depends only on earlier ones.
+    waves: Vec<Vec<usize>>,
+}
+
+impl<C, E> Dag<C, E> {
+    /// Returns a builder for a new graph.
+    pub fn builder() -> DagBuilder<C, E> {
+        DagBuilder::new()
+    }
+
+    /// Returns the node names in the order they will run.
+    pub fn execution_order(&self) -> Vec<&'static str> {
+        self.order
+            .iter()
+            .map(|&position| self.nodes[position].name)
+            .collect()
+    }
+
+    /// Runs every node in topological order, stopping at the first failure.
+    pub fn run(&self, context: &mut C) -> Result<DagOutputs, DagRunError<E>> {
+        self.run_on(&mut Unobserved::new(context))
+    }
+
+    /// Runs the graph wave by wave, running the query nodes of each wave concurrently.
+    ///
+    /// A wave holds the nodes whose dependencies all ran in earlier waves. Its query
+    /// nodes share the context immutably on worker threads; its operation nodes then run
+    /// one at a time on the calling thread, so mutation never overlaps a query. The first
+    /// failure in execution order among a wave's queries stops the graph.
+    pub fn run_parallel(&self, context: &mut C) -> Result<DagOutputs, DagRunError<E>>
+    where
+        C: Sync,
+        E: Send,
+    {
+        self.run_parallel_on(&mut Unobserved::new(context))
+    }
+
+    /// Runs every node in topological order on `runner`.
+    fn run_on(&self, runner: &mut impl DagRunner<C>) -> Result<DagOutputs, DagRunError<E>> {
+        let mut outputs = DagOutputs::default();
+        for &position in &self.order {
+            self.run_node(position, runner, &mut outputs)?;
+        }
+        Ok(outputs)
+    }
+
+    /// Runs the graph wave by wave on `runner`, as [`run_parallel`](Self::run_parallel).
+    fn run_parallel_on(&self, runner: &mut impl DagRunner<C>) -> Result<DagOutputs, DagRunError<E>>
+    where
+        C: Sync,
+        E: Send,
+    {
+        let clock = runner.clock();
+        let mut outputs = DagOutputs::default();
+        for wave in &self.waves {
+            let mut jobs = Vec::new();
+            let mut operations = Vec::new();
+            for &position in wave {
+                match &self.nodes[position].run {
+                    NodeRun::Query(prepare) => jobs.push((position, prepare(&outputs))),
+                    NodeRun::Operation(_) => operations.push(position),
+                }
+            }
+
+            let shared: &C = runner.context();
+            let timed = |job: QueryJob<C, E>| {
+                let started = clock.now();
+                let result = job(shared);
+                (clock.now() - started, result)
+            };
+            let results: Vec<_> = if jobs.len() == 1 {
+                jobs.into_iter()
+                    .map(|(position, job)| (position, timed(job)))
+                    .collect()
+            } else {
+                thread::scope(|scope| {
+                    let handles: Vec<_> = jobs
+                        .into_iter()
+                        .map(|(position, job)| (position, scope.spawn(move || timed(job))))
+                        .collect();
+                    handles
+                        .into_iter()
+                        .map(|(position, handle)| {
+                            let result = handle
+                                .join()
+                                .unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+                            (position, result)
+                        })
+                        .collect()
+                })
+            };
+            for (position, (elapsed, result)) in &results {
+                runner.report(self.nodes[*position].operation, *elapsed, result.is_ok());
+            }
+            for (position, (_, result)) in results {
+                let name = self.nodes[position].name;
+                let output = result.map_err(|error| DagRunError { node: name, error })?;
+                outputs.values.insert(name, output);
+            }
+
+            for position in operations {
+                self.run_node(position, runner, &mut outputs)?;
+            }
+        }
+        Ok(outputs)
+    }
+
+    /// Runs one node on the calling thread, reports it and stores its output.
+    fn run_node(
+        &self,
+        position: usize,
+        runner: &mut impl DagRunner<C>,
+        outputs: &mut DagOutputs,
+    ) -> Result<(), DagRunError<E>> {
+        let node = &self.nodes[position];
+        let clock = runner.clock();
+        let started = clock.now();
+        let result = match &node.run {
+            NodeRun::Operation(run) => run(runner.context(), outputs),
+            NodeRun::Query(prepare) => {
+                prepare(outputs)(runner.context()).map(|output| output as Box<dyn Any>)
+            }
+        };
+        runner.report(node.operation, clock.now() - started, result.is_ok());
+        let output = result.map_err(|error| DagRunError {
+            node: node.name,
+            error,
+        })?;
+        outputs.values.insert(node.name, output);
+        Ok(())
+    }
+}
+
+impl<C, E> fmt::Debug for Dag<C, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Dag")
+            .field("execution_order", &self.execution_order())
+            .finish()
+    }
+}
+
+/// The context a graph runs against and where the outcome of each node is reported.
+trait DagRunner<C> {
+    /// Returns the context the nodes run against.
+    fn context(&mut self) -> &mut C;
+
+    /// Returns the clock used to time nodes.
+    fn clock(&self) -> ClockHandle;
+
+    /// Reports that the node running `operation` finished after `elapsed`.
+    fn report(&mut self, operation: &'static str, elapsed: Duration, success: bool);
+}
+
+/// Runs a graph against a bare context without reporting its nodes.
+struct Unobserved<'a, C> {
+    /// The context the nodes run against.
+    context: &'a mut C,
+}
+
+impl<'a, C> Unobserved<'a, C> {
+    /// Wraps `context`.
+    fn new(context: &'a mut C) -> Self {
+        Self { context }
+    }
+}
+
+impl<C> DagRunner<C> for Unobserved<'_, C> {
+    fn context(&mut self) -> &mut C {
+        self.context
+    }
+
+    fn clock(&self) -> ClockHandle {
+        ClockHandle::default()
+    }
+
+    fn report(&mut self, _operation: &'static str, _elapsed: Duration, _success: bool) {}
+}
+
+impl<C> DagRunner<C> for ApiExecutor<C> {
+    fn context(&mut self) -> &mut C {
+        &mut self.context
+    }
+
+    fn clock(&self) -> ClockHandle {
+        self.clock.clone()
+    }
+
+    fn report(&mut self, operation: &'static str, elapsed: Duration, success: bool) {
+        self.observe_elapsed(operation, elapsed, success);
+    }
+}
+
+impl<C> ApiExecutor<C> {
+    /// Runs a dependency graph against this executor's context, reporting each node
+    /// like `execute`.
+    pub fn execute_dag<E>(&mut self, dag: &Dag<C, E>) -> Result<DagOutputs, DagRunError<E>> {
+        dag.run_on(self)
+    }
+
+    /// Runs a dependency graph against this executor's context, running independent
+    /// query nodes concurrently; see [`Dag::run_parallel`]. Each node is reported like
+    /// `execute`.
+    pub fn execute_graph_parallel<E>(
+        &mut self,
+        dag: &Dag<C, E>,
+    ) -> Result<DagOutputs, DagRunError<E>>
+    where
+        C: Sync,
+        E: Send,
+    {
+        dag.run_parallel_on(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Barrier;
+
+    /// Appends a label to the context's execution log and returns it.
+    struct Record;
+
+    impl ApiOperation<DatabaseContext, String> for Record {
+        type Output = String;
+        type Error = String;
+
+        fn execute(context: &mut DatabaseContext, parameters: &String) -> Result<String, String> {
+            context.increment_transaction();
+            let log = context.cache_mut().entry("log".to_string()).or_default();
+            log.push_str(parameters.split(':').next().unwrap_or_default());
+            Ok(parameters.clone())
+        }
+    }
+
+    fn upstream(outputs: &DagOutputs, node: &str) -> String {
+        outputs.get::<String>(node).unwrap().clone()
+    }
+
+    #[test]
+    fn test_diamond_runs_in_dependency_order() {
+        let dag = Dag::builder()
+            .node("d", &["b", "c"], Record, |outputs| {
+                format!("d:{}+{}", upstream(outputs, "b"), upstream(outputs, "c"))
+            })
+            .node("b", &["a"], Record, |outputs| {
+                format!("b:{}", upstream(outputs, "a"))
+            })
+            .node("c", &["a"], Record, |outputs| {
+                format!("c:{}", upstream(outputs, "a"))
+            })
+            .node("a", &[], Record, |_| "a:root".to_string())
+            .build()
+            .unwrap();
+        let mut executor =
+            ApiExecutor::new(DatabaseContext::new("dag".to_string())).with_audit_log();
+
+        let mut outputs = executor.execute_dag(&dag).unwrap();
+
+        assert_eq!(dag.execution_order(), vec!["a", "b", "c", "d"]);
+        assert_eq!(
+            executor.context().cache().get("log"),
+            Some(&"abcd".to_string())
+        );
+        assert_eq!(
+            outputs.take::<String>("d"),
+            Some("d:b:a:root+c:a:root".to_string())
+        );
+        assert_eq!(outputs.len(), 3);
+        assert_eq!(executor.audit_entries().len(), 4);
+    }
+
+    #[test]
+    fn test_build_detects_cycles_and_unknown_dependencies() {
+        let cyclic = Dag::<DatabaseContext, String>::builder()
+            .node("a", &[], Record, |_| "a".to_string())
+            .node("b", &["a", "c"], Record, |_| "b".to_string())
+            .node("c", &["b"], Record, |_| "c".to_string())
+            .build();
+        assert!(matches!(cyclic, Err(DagError::Cycle(nodes)) if nodes == vec!["b", "c"]));
+
+        let unknown = Dag::<DatabaseContext, String>::builder()
+            .node("a", &["missing"], Record, |_| "a".to_string())
+            .build();
+        assert!(matches!(
+            unknown,
+            Err(DagError::UnknownDependency {
+                node: "a",
+                dependency: "missing"
+            })
+        ));
+    }
+
+    /// Counts how many report queries are running at once.
+    #[derive(Default)]
+    struct ReportContext {
+        /// Makes both branches wait for each other, when they are expected to overlap.
+        barrier: Option<Barrier>,
+        running: AtomicUsize,
+        peak: AtomicUsize,
+        reports: Vec<String>,
+    }
+
+    /// Sums a branch of the report while recording how many branches overlap.
+    struct SumBranch;
+
+    impl ApiQuery<ReportContext, Vec<u32>> for SumBranch {
+        type Output = u32;
+        type Error = String;
+
+        fn query(context: &ReportContext, parameters: &Vec<u32>) -> Result<u32, String> {
+            let running = context.running.fetch_add(1, Ordering::SeqCst) + 1;
+            context.peak.fetch_max(running, Ordering::SeqCst);
+            if let Some(barrier) = &context.barrier {
+                barrier.wait();
+            }
+            context.running.fetch_sub(1, Ordering::SeqCst);
+            Ok(parameters.iter().sum())
+        }
+    }
+
+    /// Stores the report built from both branches.
+    struct SaveReport;
+
+    impl ApiOperation<ReportContext, String> for SaveReport {
+        type Output = String;
+        type Error = String;
+
+        fn execute(context: &mut ReportContext, parameters: &String) -> Result<String, String> {
+            context.reports.push(parameters.clone());
+            Ok(parameters.clone())
+        }
+    }
+
+    #[test]
+    fn test_independent_branches_run_concurrently() {
+        let dag = Dag::builder()
+            .query_node("revenue", &[], SumBranch, |_| vec![100, 250])
+            .query_node("costs", &[], SumBranch, |_| vec![40, 60])
+            .node("report", &["revenue", "costs"], SaveReport, |outputs| {
+                let revenue = outputs.get::<u32>("revenue").unwrap();
+                let costs = outputs.get::<u32>("costs").unwrap();
+                format!("profit {}", revenue - costs)
+            })
+            .build()
+            .unwrap();
+        let mut executor = ApiExecutor::new(ReportContext {
+            barrier: Some(Barrier::new(2)),
+            ..ReportContext::default()
+        })
+        .with_audit_log();
+
+        let mut outputs = executor.execute_graph_parallel(&dag).unwrap();
+
+        assert_eq!(
+            outputs.take::<String>("report"),
+            Some("profit 250".to_string())
+        );
+        assert_eq!(executor.context().peak.load(Ordering::SeqCst), 2);
+        assert_eq!(executor.context().reports, vec!["profit 250".to_string()]);
+        assert_eq!(executor.audit_entries().len(), 3);
+    }
+
+    #[test]
+    fn test_query_nodes_also_run_sequentially() {
+        let dag = Dag::builder()
+            .query_node("revenue", &[], SumBranch, |_| vec![1, 2])
+            .query_node("costs", &[], SumBranch, |_| vec![3])
+            .build()
+            .unwrap();
+        let mut executor = ApiExecutor::new(ReportContext::default());
+
+        let outputs = executor.execute_dag(&dag).unwrap();
+
+        assert_eq!(outputs.get::<u32>("revenue"), Some(&3));
+        assert_eq!(executor.context().peak.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,262 @@
+//! Describing exactly how an operation changed its context.
+
+use crate::{ApiExecutor, ApiOperation, LogLevel};
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+
+/// A context that can describe the changes between two of its states.
+pub trait Diff {
+    /// A description of the changes.
+    type Diff;
+
+    /// Describes the changes that turn `self` into `after`.
+    fn diff(&self, after: &Self) -> Self::Diff;
+}
+
+/// The changes between two maps, for building [`Diff`] implementations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MapDiff<K, V> {
+    /// Entries present only in the later map.
+    pub added: Vec<(K, V)>,
+
+    /// Entries present only in the earlier map.
+    pub removed: Vec<(K, V)>,
+
+    /// Keys present in both maps with their earlier and later values, where those differ.
+    pub changed: Vec<(K, V, V)>,
+}
+
+impl<K, V> MapDiff<K, V>
+where
+    K: Hash + Eq + Ord + Clone,
+    V: PartialEq + Clone,
+{
+    /// Compares two maps, listing each kind of change in key order.
+    pub fn between(before: &HashMap<K, V>, after: &HashMap<K, V>) -> Self {
+        let mut diff = Self {
+            added: Vec::new(),
+            removed: Vec::new(),
+            changed: Vec::new(),
+        };
+        for (key, value) in after {
+            match before.get(key) {
+                None => diff.added.push((key.clone(), value.clone())),
+                Some(old) if old != value => {
+                    diff.changed.push((key.clone(), old.clone(), value.clone()))
+                }
+                Some(_) => {}
+            }
+        }
+        for (key, value) in before {
+            if !after.contains_key(key) {
+                diff.removed.push((key.clone(), value.clone()));
+            }
+        }
+        diff.added.sort_by(|a, b| a.0.cmp(&b.0));
+        diff.removed.sort_by(|a, b| a.0.cmp(&b.0));
+        diff.changed.sort_by(|a, b| a.0.cmp(&b.0));
+        diff
+    }
+
+    /// Returns true if the maps were equal.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// An operation's output together with the changes it made to the context.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffedOutput<O, D> {
+    /// The output the operation produced.
+    pub output: O,
+
+    /// The changes the operation made to the context.
+    pub diff: D,
+}
+
+impl<C> ApiExecutor<C> {
+    /// Enables or disables the context logging of `execute_with_structured_context_logging`.
+    ///
+    /// Off by default, since every logged call clones the context.
+    pub fn with_debug_context(mut self, enabled: bool) -> Self {
+        self.debug_context = enabled;
+        self
+    }
+}
+
+impl<C: Clone + Diff> ApiExecutor<C> {
+    /// Executes an operation and describes how it changed the context, for detailed
+    /// audit logs.
+    ///
+    /// The context is cloned before the operation runs. When the operation fails, its
+    /// error is returned without a diff.
+    pub fn execute_returning_effects_diff<P, Op>(
+        &mut self,
+        op: Op,
+        parameters: &P,
+    ) -> Result<DiffedOutput<Op::Output, C::Diff>, Op::Error>
+    where
+        Op: ApiOperation<C, P>,
+    {
+        let before = self.context.clone();
+        let output = self.execute(op, parameters)?;
+        Ok(DiffedOutput {
+            output,
+            diff: before.diff(&self.context),
+        })
+    }
+
+    /// Executes an operation and, when enabled with
+    /// [`with_debug_context`](Self::with_debug_context), logs how it changed the context.
+    ///
+    /// The change is logged at [`LogLevel::Debug`] whether or not the operation
+    /// succeeds, rendered with the diff's `Debug` implementation. Only writes show up;
+    /// reads leave no trace in the diff. When disabled, this is a plain `execute`.
+    pub fn execute_with_structured_context_logging<P, Op>(
+        &mut self,
+        op: Op,
+        parameters: &P,
+    ) -> Result<Op::Output, Op::Error>
+    where
+        Op: ApiOperation<C, P>,
+        C::Diff: fmt::Debug,
+    {
+        if !self.debug_context {
+            return self.execute(op, parameters);
+        }
+        let before = self.context.clone();
+        let result = self.execute(op, parameters);
+        let outcome = if result.is_ok() {
+            "succeeded"
+        } else {
+            "failed"
+        };
+        self.logger.log(
+            LogLevel::Debug,
+            Op::name(),
+            format!(
+                "{}; context changes: {:?}",
+                outcome,
+                before.diff(&self.context)
+            ),
+        );
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+    use crate::MemoryLogger;
+
+    #[derive(Debug, PartialEq)]
+    pub struct DatabaseDiff {
+        pub cache: MapDiff<String, String>,
+        pub transactions: i64,
+    }
+
+    impl Diff for DatabaseContext {
+        type Diff = DatabaseDiff;
+
+        fn diff(&self, after: &Self) -> DatabaseDiff {
+            DatabaseDiff {
+                cache: MapDiff::between(self.cache(), after.cache()),
+                transactions: i64::from(after.transaction_count())
+                    - i64::from(self.transaction_count()),
+            }
+        }
+    }
+
+    struct CreateUser;
+
+    impl ApiOperation<DatabaseContext, String> for CreateUser {
+        type Output = u32;
+        type Error = ();
+
+        fn execute(context: &mut DatabaseContext, parameters: &String) -> Result<u32, ()> {
+            context.increment_transaction();
+            let id = context.transaction_count();
+            context
+                .cache_mut()
+                .insert(format!("user_{}", id), parameters.clone());
+            Ok(id)
+        }
+    }
+
+    #[test]
+    fn test_create_user_reports_added_key_and_transaction() {
+        let mut context = DatabaseContext::new("diff".to_string());
+        context
+            .cache_mut()
+            .insert("settings".to_string(), "dark".to_string());
+        let mut executor = ApiExecutor::new(context);
+
+        let result = executor
+            .execute_returning_effects_diff(CreateUser, &"alice".to_string())
+            .unwrap();
+
+        assert_eq!(result.output, 1);
+        assert_eq!(
+            result.diff,
+            DatabaseDiff {
+                cache: MapDiff {
+                    added: vec![("user_1".to_string(), "alice".to_string())],
+                    removed: Vec::new(),
+                    changed: Vec::new(),
+                },
+                transactions: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_debug_context_logs_touched_cache_keys() {
+        let logger = MemoryLogger::new();
+        let mut executor = ApiExecutor::new(DatabaseContext::new("diff".to_string()))
+            .with_logger(logger.clone())
+            .with_debug_context(true);
+
+        executor
+            .execute_with_structured_context_logging(CreateUser, &"alice".to_string())
+            .unwrap();
+
+        let records = logger.records();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].level, LogLevel::Debug);
+        assert!(records[0]
+            .message
+            .starts_with("succeeded; context changes:"));
+        assert!(records[0]
+            .message
+            .contains(r#"added: [("user_1", "alice")]"#));
+        assert!(records[0].message.contains("transactions: 1"));
+    }
+
+    #[test]
+    fn test_context_logging_is_off_by_default() {
+        let logger = MemoryLogger::new();
+        let mut executor =
+            ApiExecutor::new(DatabaseContext::new("diff".to_string())).with_logger(logger.clone());
+
+        executor
+            .execute_with_structured_context_logging(CreateUser, &"alice".to_string())
+            .unwrap();
+
+        assert!(logger.records().is_empty());
+    }
+
+    #[test]
+    fn test_map_diff_lists_removed_and_changed_entries() {
+        let before = HashMap::from([("a", 1), ("b", 2)]);
+        let after = HashMap::from([("b", 3), ("c", 4)]);
+
+        let diff = MapDiff::between(&before, &after);
+
+        assert_eq!(diff.added, vec![("c", 4)]);
+        assert_eq!(diff.removed, vec![("a", 1)]);
+        assert_eq!(diff.changed, vec![("b", 2, 3)]);
+        assert!(MapDiff::between(&before, &before).is_empty());
+    }
+}
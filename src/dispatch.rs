@@ -0,0 +1,124 @@
+//! Type-safe routing of request enums to operations.
+
+use crate::ApiExecutor;
+
+/// A request that selects an operation and its parameters for an executor.
+///
+/// Implemented on request enums, usually by matching each variant to an operation.
+/// Unlike [`Registry`](crate::Registry) dispatch, outputs and errors stay statically
+/// typed and no downcasting is involved.
+pub trait Dispatch<C> {
+    /// The type returned by a successful dispatch.
+    type Output;
+
+    /// The error type returned when the selected operation fails.
+    type Error;
+
+    /// Runs the operation selected by this request on `executor`.
+    fn dispatch(self, executor: &mut ApiExecutor<C>) -> Result<Self::Output, Self::Error>;
+}
+
+impl<C> ApiExecutor<C> {
+    /// Executes the operation selected by `request`.
+    pub fn execute_matching<R>(&mut self, request: R) -> Result<R::Output, R::Error>
+    where
+        R: Dispatch<C>,
+    {
+        request.dispatch(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+    use crate::ApiOperation;
+
+    struct Insert;
+    struct Remove;
+    struct Count;
+
+    impl ApiOperation<DatabaseContext, (String, String)> for Insert {
+        type Output = Option<String>;
+        type Error = String;
+
+        fn execute(
+            context: &mut DatabaseContext,
+            parameters: &(String, String),
+        ) -> Result<Option<String>, String> {
+            Ok(context
+                .cache_mut()
+                .insert(parameters.0.clone(), parameters.1.clone()))
+        }
+    }
+
+    impl ApiOperation<DatabaseContext, String> for Remove {
+        type Output = Option<String>;
+        type Error = String;
+
+        fn execute(
+            context: &mut DatabaseContext,
+            parameters: &String,
+        ) -> Result<Option<String>, String> {
+            context
+                .cache_mut()
+                .remove(parameters)
+                .map(Some)
+                .ok_or_else(|| format!("no such key: {}", parameters))
+        }
+    }
+
+    impl ApiOperation<DatabaseContext, ()> for Count {
+        type Output = usize;
+        type Error = String;
+
+        fn execute(context: &mut DatabaseContext, _parameters: &()) -> Result<usize, String> {
+            Ok(context.cache().len())
+        }
+    }
+
+    enum Command {
+        Set { key: String, value: String },
+        Delete(String),
+        Count,
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Reply {
+        Previous(Option<String>),
+        Size(usize),
+    }
+
+    impl Dispatch<DatabaseContext> for Command {
+        type Output = Reply;
+        type Error = String;
+
+        fn dispatch(self, executor: &mut ApiExecutor<DatabaseContext>) -> Result<Reply, String> {
+            match self {
+                Command::Set { key, value } => {
+                    executor.execute(Insert, &(key, value)).map(Reply::Previous)
+                }
+                Command::Delete(key) => executor.execute(Remove, &key).map(Reply::Previous),
+                Command::Count => executor.execute(Count, &()).map(Reply::Size),
+            }
+        }
+    }
+
+    #[test]
+    fn test_each_variant_routes_to_its_operation() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("dispatch".to_string()));
+
+        let set = executor.execute_matching(Command::Set {
+            key: "user_1".to_string(),
+            value: "Alice".to_string(),
+        });
+        let count = executor.execute_matching(Command::Count);
+        let delete = executor.execute_matching(Command::Delete("user_1".to_string()));
+        let missing = executor.execute_matching(Command::Delete("user_1".to_string()));
+
+        assert_eq!(set, Ok(Reply::Previous(None)));
+        assert_eq!(count, Ok(Reply::Size(1)));
+        assert_eq!(delete, Ok(Reply::Previous(Some("Alice".to_string()))));
+        assert_eq!(missing, Err("no such key: user_1".to_string()));
+    }
+}
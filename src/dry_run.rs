@@ -0,0 +1,174 @@
+//! Running operations without letting them change the context.
+
+use crate::{ApiExecutor, ApiOperation};
+use std::ops::Deref;
+
+/// A context wrapper that can silently discard mutations.
+///
+/// Operations written against `DryRunContext<C>` read the inner context through
+/// `Deref` and funnel every change through [`mutate`](Self::mutate). In dry-run mode
+/// mutations are skipped, so the operation's logic runs in full but leaves no trace.
+#[derive(Debug, Clone, Default)]
+pub struct DryRunContext<C> {
+    /// The wrapped context.
+    inner: C,
+
+    /// Whether mutations are currently being discarded.
+    dry_run: bool,
+}
+
+impl<C> DryRunContext<C> {
+    /// Wraps `inner` with mutations enabled.
+    pub fn new(inner: C) -> Self {
+        Self {
+            inner,
+            dry_run: false,
+        }
+    }
+
+    /// Applies `change` to the inner context unless in dry-run mode.
+    pub fn mutate(&mut self, change: impl FnOnce(&mut C)) {
+        if !self.dry_run {
+            change(&mut self.inner);
+        }
+    }
+
+    /// Returns true while mutations are being discarded.
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// Consumes the wrapper and returns the inner context.
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+}
+
+impl<C> Deref for DryRunContext<C> {
+    type Target = C;
+
+    fn deref(&self) -> &C {
+        &self.inner
+    }
+}
+
+/// Keeps an executor in dry-run mode until dropped, even if the operation panics.
+struct DryRunGuard<'a, C> {
+    /// The executor whose context is in dry-run mode.
+    executor: &'a mut ApiExecutor<DryRunContext<C>>,
+}
+
+impl<'a, C> DryRunGuard<'a, C> {
+    /// Switches the executor's context into dry-run mode.
+    fn new(executor: &'a mut ApiExecutor<DryRunContext<C>>) -> Self {
+        executor.context.dry_run = true;
+        Self { executor }
+    }
+}
+
+impl<C> Drop for DryRunGuard<'_, C> {
+    fn drop(&mut self) {
+        self.executor.context.dry_run = false;
+    }
+}
+
+impl<C> ApiExecutor<DryRunContext<C>> {
+    /// Runs an operation with mutations disabled and reports whether it would succeed.
+    ///
+    /// The operation sees the current state of the context, but any changes it
+    /// requests through [`DryRunContext::mutate`] are discarded.
+    pub fn dry_run<P, Op>(&mut self, _op: Op, parameters: &P) -> bool
+    where
+        Op: ApiOperation<DryRunContext<C>, P>,
+    {
+        let guard = DryRunGuard::new(self);
+        guard.executor.execute_observed::<P, Op>(parameters).is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+
+    struct CreateUser;
+
+    impl ApiOperation<DryRunContext<DatabaseContext>, String> for CreateUser {
+        type Output = u32;
+        type Error = String;
+
+        fn execute(
+            context: &mut DryRunContext<DatabaseContext>,
+            parameters: &String,
+        ) -> Result<u32, String> {
+            if !parameters.contains('@') {
+                return Err(format!("invalid email: {}", parameters));
+            }
+            if context.cache().contains_key(parameters) {
+                return Err(format!("duplicate email: {}", parameters));
+            }
+            context.mutate(|database| {
+                database
+                    .cache_mut()
+                    .insert(parameters.clone(), "user".to_string());
+                database.increment_transaction();
+            });
+            Ok(context.transaction_count())
+        }
+    }
+
+    struct Crash;
+
+    impl ApiOperation<DryRunContext<DatabaseContext>, String> for Crash {
+        type Output = ();
+        type Error = String;
+
+        fn execute(
+            _context: &mut DryRunContext<DatabaseContext>,
+            _parameters: &String,
+        ) -> Result<(), String> {
+            panic!("crashed during dry run");
+        }
+    }
+
+    fn executor() -> ApiExecutor<DryRunContext<DatabaseContext>> {
+        ApiExecutor::new(DryRunContext::new(DatabaseContext::new(
+            "dry_run".to_string(),
+        )))
+    }
+
+    #[test]
+    fn test_dry_run_validates_without_mutating() {
+        let mut executor = executor();
+
+        assert!(executor.dry_run(CreateUser, &"alice@example.com".to_string()));
+        assert!(!executor.dry_run(CreateUser, &"invalid".to_string()));
+
+        assert_eq!(executor.context().transaction_count(), 0);
+        assert!(executor.context().cache().is_empty());
+        assert!(!executor.context().is_dry_run());
+    }
+
+    #[test]
+    fn test_panic_leaves_mutations_enabled() {
+        let mut executor = executor();
+
+        let outcome = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            executor.dry_run(Crash, &"alice@example.com".to_string())
+        }));
+
+        assert!(outcome.is_err());
+        assert!(!executor.context().is_dry_run());
+    }
+
+    #[test]
+    fn test_dry_run_sees_real_state() {
+        let mut executor = executor();
+        executor
+            .execute(CreateUser, &"alice@example.com".to_string())
+            .unwrap();
+
+        assert!(!executor.dry_run(CreateUser, &"alice@example.com".to_string()));
+        assert_eq!(executor.context().transaction_count(), 1);
+    }
+}
@@ -0,0 +1,201 @@
+//! Operations that declare their side effects instead of applying them directly.
+
+use crate::ApiExecutor;
+
+/// Collects the side effects an operation intends to apply to its context.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SideEffectRecorder<E> {
+    /// The effects recorded so far, in declaration order.
+    effects: Vec<E>,
+}
+
+impl<E> SideEffectRecorder<E> {
+    /// Creates an empty recorder.
+    pub fn new() -> Self {
+        Self {
+            effects: Vec::new(),
+        }
+    }
+
+    /// Records an intended side effect.
+    pub fn record(&mut self, effect: E) {
+        self.effects.push(effect);
+    }
+
+    /// Returns the effects recorded so far.
+    pub fn effects(&self) -> &[E] {
+        &self.effects
+    }
+
+    /// Consumes the recorder and returns the recorded effects.
+    pub fn into_effects(self) -> Vec<E> {
+        self.effects
+    }
+}
+
+impl<E> Default for SideEffectRecorder<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The outcome of previewing an [`EffectfulOperation`] without applying its effects.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SideEffectPreview<O, E> {
+    /// The output the operation produced.
+    pub output: O,
+
+    /// The side effects the operation would have applied, in declaration order.
+    pub effects: Vec<E>,
+}
+
+/// An operation that routes every context mutation through a [`SideEffectRecorder`].
+///
+/// The operation reads the context immutably while planning, then the executor decides
+/// whether the recorded effects are applied or merely reported.
+pub trait EffectfulOperation<C, P> {
+    /// The type returned by a successful operation execution.
+    type Output;
+
+    /// The error type returned when an operation fails.
+    type Error;
+
+    /// The side effect type this operation records.
+    type Effect;
+
+    /// Runs the operation logic, recording intended mutations instead of applying them.
+    fn plan(
+        context: &C,
+        parameters: &P,
+        recorder: &mut SideEffectRecorder<Self::Effect>,
+    ) -> Result<Self::Output, Self::Error>;
+
+    /// Applies a single recorded side effect to the context.
+    fn apply(context: &mut C, effect: Self::Effect);
+
+    /// Returns the diagnostic name of the operation, defaulting to its type name.
+    fn name() -> &'static str {
+        std::any::type_name::<Self>()
+    }
+}
+
+impl<C> ApiExecutor<C> {
+    /// Executes an effectful operation and applies its recorded side effects to the context.
+    ///
+    /// The execution is reported like `execute`.
+    pub fn execute_with_side_effects<P, Op>(
+        &mut self,
+        _op: Op,
+        parameters: &P,
+    ) -> Result<Op::Output, Op::Error>
+    where
+        Op: EffectfulOperation<C, P>,
+    {
+        let started = self.clock.now();
+        let mut recorder = SideEffectRecorder::new();
+        let result = Op::plan(&self.context, parameters, &mut recorder);
+        if result.is_ok() {
+            for effect in recorder.into_effects() {
+                Op::apply(&mut self.context, effect);
+            }
+        }
+        self.observe(Op::name(), started, result.is_ok());
+        result
+    }
+
+    /// Executes an effectful operation without mutating the context.
+    ///
+    /// Returns the operation's output together with the side effects it would have
+    /// applied, enabling "what would this do?" previews and dry-run tests.
+    pub fn execute_collecting_side_effects<P, Op>(
+        &self,
+        _op: Op,
+        parameters: &P,
+    ) -> Result<SideEffectPreview<Op::Output, Op::Effect>, Op::Error>
+    where
+        Op: EffectfulOperation<C, P>,
+    {
+        let mut recorder = SideEffectRecorder::new();
+        let output = Op::plan(&self.context, parameters, &mut recorder)?;
+        Ok(SideEffectPreview {
+            output,
+            effects: recorder.into_effects(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+
+    #[derive(Debug, PartialEq)]
+    enum CacheEffect {
+        Insert { key: String, value: String },
+    }
+
+    struct CacheUser;
+
+    impl EffectfulOperation<DatabaseContext, String> for CacheUser {
+        type Output = String;
+        type Error = ();
+        type Effect = CacheEffect;
+
+        fn plan(
+            context: &DatabaseContext,
+            parameters: &String,
+            recorder: &mut SideEffectRecorder<CacheEffect>,
+        ) -> Result<String, ()> {
+            let key = format!("user_{}", context.cache().len() + 1);
+            recorder.record(CacheEffect::Insert {
+                key: key.clone(),
+                value: parameters.clone(),
+            });
+            Ok(key)
+        }
+
+        fn apply(context: &mut DatabaseContext, effect: CacheEffect) {
+            match effect {
+                CacheEffect::Insert { key, value } => {
+                    context.cache_mut().insert(key, value);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_collecting_side_effects_does_not_mutate() {
+        let executor = ApiExecutor::new(DatabaseContext::new("preview".to_string()));
+
+        let preview = executor
+            .execute_collecting_side_effects(CacheUser, &"Alice".to_string())
+            .unwrap();
+
+        assert_eq!(preview.output, "user_1");
+        assert_eq!(
+            preview.effects,
+            vec![CacheEffect::Insert {
+                key: "user_1".to_string(),
+                value: "Alice".to_string(),
+            }]
+        );
+        assert!(executor.context().cache().is_empty());
+    }
+
+    #[test]
+    fn test_side_effects_are_applied_on_real_run() {
+        let mut executor =
+            ApiExecutor::new(DatabaseContext::new("apply".to_string())).with_audit_log();
+
+        executor
+            .execute_with_side_effects(CacheUser, &"Alice".to_string())
+            .unwrap();
+
+        assert_eq!(
+            executor.context().cache().get("user_1"),
+            Some(&"Alice".to_string())
+        );
+        assert_eq!(executor.audit_entries().len(), 1);
+        assert!(executor.audit_entries()[0].success);
+    }
+}
@@ -0,0 +1,147 @@
+//! Mapping operation errors to HTTP status codes.
+
+use crate::{ApiExecutor, ApiOperation};
+use std::fmt;
+
+/// An error type with a stable HTTP status and machine-readable code.
+///
+/// Implemented on operation error types so every family maps its domain errors to
+/// HTTP responses the same way.
+pub trait ErrorCode {
+    /// Returns the HTTP status code for this error, such as 404 or 400.
+    fn status(&self) -> u16;
+
+    /// Returns a stable, machine-readable identifier for this error, such as
+    /// `"not_found"`.
+    fn code(&self) -> &'static str;
+}
+
+/// A failed operation rendered as the parts of an HTTP error response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorResponse {
+    /// The HTTP status code.
+    pub status: u16,
+
+    /// The machine-readable error code.
+    pub code: &'static str,
+
+    /// The human-readable message, taken from the error's `Display` implementation.
+    pub message: String,
+}
+
+impl ErrorResponse {
+    /// Builds the response describing `error`.
+    pub fn from_error<E: ErrorCode + fmt::Display>(error: &E) -> Self {
+        Self {
+            status: error.status(),
+            code: error.code(),
+            message: error.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ErrorResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}: {}", self.status, self.code, self.message)
+    }
+}
+
+impl std::error::Error for ErrorResponse {}
+
+impl<C> ApiExecutor<C> {
+    /// Executes an operation, converting a failure into an [`ErrorResponse`] through
+    /// the error's [`ErrorCode`] implementation.
+    pub fn execute_with_typed_error_codes<P, Op>(
+        &mut self,
+        op: Op,
+        parameters: &P,
+    ) -> Result<Op::Output, ErrorResponse>
+    where
+        Op: ApiOperation<C, P>,
+        Op::Error: ErrorCode + fmt::Display,
+    {
+        self.execute(op, parameters)
+            .map_err(|error| ErrorResponse::from_error(&error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+
+    #[derive(Debug)]
+    enum UserError {
+        NotFound(String),
+        InvalidEmail(String),
+    }
+
+    impl fmt::Display for UserError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                UserError::NotFound(key) => write!(f, "user '{}' not found", key),
+                UserError::InvalidEmail(email) => write!(f, "'{}' is not an email", email),
+            }
+        }
+    }
+
+    impl ErrorCode for UserError {
+        fn status(&self) -> u16 {
+            match self {
+                UserError::NotFound(_) => 404,
+                UserError::InvalidEmail(_) => 400,
+            }
+        }
+
+        fn code(&self) -> &'static str {
+            match self {
+                UserError::NotFound(_) => "not_found",
+                UserError::InvalidEmail(_) => "invalid_email",
+            }
+        }
+    }
+
+    struct FindUser;
+
+    impl ApiOperation<DatabaseContext, String> for FindUser {
+        type Output = String;
+        type Error = UserError;
+
+        fn execute(
+            context: &mut DatabaseContext,
+            parameters: &String,
+        ) -> Result<String, UserError> {
+            if !parameters.contains('@') {
+                return Err(UserError::InvalidEmail(parameters.clone()));
+            }
+            context
+                .cache()
+                .get(parameters)
+                .cloned()
+                .ok_or_else(|| UserError::NotFound(parameters.clone()))
+        }
+    }
+
+    #[test]
+    fn test_errors_map_to_http_statuses() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("http".to_string()));
+
+        let missing = executor
+            .execute_with_typed_error_codes(FindUser, &"bob@example.com".to_string())
+            .unwrap_err();
+        let invalid = executor
+            .execute_with_typed_error_codes(FindUser, &"bob".to_string())
+            .unwrap_err();
+
+        assert_eq!(
+            missing,
+            ErrorResponse {
+                status: 404,
+                code: "not_found",
+                message: "user 'bob@example.com' not found".to_string(),
+            }
+        );
+        assert_eq!(invalid.status, 400);
+        assert_eq!(invalid.code, "invalid_email");
+    }
+}
@@ -0,0 +1,140 @@
+//! Operations that emit domain events for event-sourced contexts.
+
+use crate::ApiExecutor;
+
+/// A context that keeps an append-only log of domain events.
+pub trait EventLog<Ev> {
+    /// Appends events to the log, in the order they were emitted.
+    fn append_events(&mut self, events: &[Ev]);
+}
+
+/// An operation that emits domain events alongside its output.
+pub trait EventProducing<C, P> {
+    /// The type returned by a successful operation execution.
+    type Output;
+
+    /// The error type returned when an operation fails.
+    type Error;
+
+    /// The domain event type this operation emits.
+    type Event;
+
+    /// Runs the operation, pushing the events it emits onto `events`.
+    fn execute(
+        context: &mut C,
+        parameters: &P,
+        events: &mut Vec<Self::Event>,
+    ) -> Result<Self::Output, Self::Error>;
+
+    /// Returns the diagnostic name of the operation, defaulting to its type name.
+    fn name() -> &'static str {
+        std::any::type_name::<Self>()
+    }
+}
+
+/// The output of an [`EventProducing`] operation together with the events it emitted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventOutcome<O, Ev> {
+    /// The output the operation produced.
+    pub output: O,
+
+    /// The events the operation emitted, in order.
+    pub events: Vec<Ev>,
+}
+
+impl<C> ApiExecutor<C> {
+    /// Executes an event-producing operation and appends its events to the context's log.
+    ///
+    /// Events emitted by a failed operation are discarded and never reach the log.
+    pub fn execute_events<P, Op>(
+        &mut self,
+        _op: Op,
+        parameters: &P,
+    ) -> Result<EventOutcome<Op::Output, Op::Event>, Op::Error>
+    where
+        Op: EventProducing<C, P>,
+        C: EventLog<Op::Event>,
+    {
+        let mut events = Vec::new();
+        let started = self.clock.now();
+        let result = Op::execute(&mut self.context, parameters, &mut events);
+        self.observe(Op::name(), started, result.is_ok());
+        let output = result?;
+        self.context.append_events(&events);
+        Ok(EventOutcome { output, events })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum DomainEvent {
+        UserCreated { id: u32, email: String },
+    }
+
+    #[derive(Default)]
+    struct UserStore {
+        users: Vec<String>,
+        log: Vec<DomainEvent>,
+    }
+
+    impl EventLog<DomainEvent> for UserStore {
+        fn append_events(&mut self, events: &[DomainEvent]) {
+            self.log.extend_from_slice(events);
+        }
+    }
+
+    struct CreateUser;
+
+    impl EventProducing<UserStore, String> for CreateUser {
+        type Output = u32;
+        type Error = String;
+        type Event = DomainEvent;
+
+        fn execute(
+            context: &mut UserStore,
+            parameters: &String,
+            events: &mut Vec<DomainEvent>,
+        ) -> Result<u32, String> {
+            let id = context.users.len() as u32 + 1;
+            events.push(DomainEvent::UserCreated {
+                id,
+                email: parameters.clone(),
+            });
+            if !parameters.contains('@') {
+                return Err(format!("invalid email: {}", parameters));
+            }
+            context.users.push(parameters.clone());
+            Ok(id)
+        }
+    }
+
+    #[test]
+    fn test_create_user_emits_user_created() {
+        let mut executor = ApiExecutor::new(UserStore::default());
+
+        let outcome = executor
+            .execute_events(CreateUser, &"alice@example.com".to_string())
+            .unwrap();
+
+        let expected = DomainEvent::UserCreated {
+            id: 1,
+            email: "alice@example.com".to_string(),
+        };
+        assert_eq!(outcome.output, 1);
+        assert_eq!(outcome.events, vec![expected.clone()]);
+        assert_eq!(executor.context().log, vec![expected]);
+    }
+
+    #[test]
+    fn test_failed_operation_does_not_log_events() {
+        let mut executor = ApiExecutor::new(UserStore::default());
+
+        let result = executor.execute_events(CreateUser, &"invalid".to_string());
+
+        assert!(result.is_err());
+        assert!(executor.context().log.is_empty());
+    }
+}
@@ -0,0 +1,212 @@
+//! Prioritized fallback across tiers of interchangeable operations.
+
+use crate::{ApiExecutor, ApiOperation};
+use std::fmt;
+
+/// Runs one tier, converting its error into the chain's error type.
+type TierFn<C, P, O, E> = fn(&mut C, &P) -> Result<O, E>;
+
+/// A tier's diagnostic name with the function running it.
+type Tier<C, P, O, E> = (&'static str, TierFn<C, P, O, E>);
+
+/// Runs `Op` and converts its error into `E`.
+fn run_tier<C, P, Op, E>(context: &mut C, parameters: &P) -> Result<Op::Output, E>
+where
+    Op: ApiOperation<C, P>,
+    Op::Error: Into<E>,
+{
+    Op::execute(context, parameters).map_err(Into::into)
+}
+
+/// An ordered list of operations sharing parameter and output types.
+///
+/// Tiers are tried from first to last, so the cheapest source (such as an in-memory
+/// cache) should be added first and the most authoritative (such as a remote store) last.
+pub struct FallbackChain<C, P, O, E> {
+    /// The tiers in priority order.
+    tiers: Vec<Tier<C, P, O, E>>,
+}
+
+impl<C, P, O, E> FallbackChain<C, P, O, E> {
+    /// Creates an empty chain.
+    pub fn new() -> Self {
+        Self { tiers: Vec::new() }
+    }
+
+    /// Appends a tier that runs `Op`.
+    pub fn tier<Op>(mut self, _op: Op) -> Self
+    where
+        Op: ApiOperation<C, P, Output = O>,
+        Op::Error: Into<E>,
+    {
+        self.tiers.push((Op::name(), run_tier::<C, P, Op, E>));
+        self
+    }
+
+    /// Returns the number of tiers in the chain.
+    pub fn len(&self) -> usize {
+        self.tiers.len()
+    }
+
+    /// Returns true if the chain has no tiers.
+    pub fn is_empty(&self) -> bool {
+        self.tiers.is_empty()
+    }
+}
+
+impl<C, P, O, E> Default for FallbackChain<C, P, O, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C, P, O, E> fmt::Debug for FallbackChain<C, P, O, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FallbackChain")
+            .field("tiers", &self.tiers.len())
+            .finish()
+    }
+}
+
+/// The output of a fallback chain and the tier that produced it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FallbackOutcome<O> {
+    /// The index of the tier that succeeded.
+    pub tier: usize,
+
+    /// The output of the successful tier.
+    pub output: O,
+}
+
+impl<C> ApiExecutor<C> {
+    /// Runs the tiers of `chain` in order until one succeeds.
+    ///
+    /// Returns the first successful output along with its tier index. If every tier
+    /// fails, the errors of all tiers are returned in tier order. Each tier that runs is
+    /// reported like `execute`.
+    pub fn execute_prioritized_fallback_chain<P, O, E>(
+        &mut self,
+        chain: &FallbackChain<C, P, O, E>,
+        parameters: &P,
+    ) -> Result<FallbackOutcome<O>, Vec<E>> {
+        let mut errors = Vec::with_capacity(chain.tiers.len());
+        for (tier, (name, run)) in chain.tiers.iter().enumerate() {
+            let started = self.clock.now();
+            let result = run(&mut self.context, parameters);
+            self.observe(name, started, result.is_ok());
+            match result {
+                Ok(output) => return Ok(FallbackOutcome { tier, output }),
+                Err(error) => errors.push(error),
+            }
+        }
+        Err(errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+
+    struct MemoryCache;
+    struct LocalDisk;
+    struct RemoteStore;
+
+    impl ApiOperation<DatabaseContext, String> for MemoryCache {
+        type Output = String;
+        type Error = String;
+
+        fn execute(context: &mut DatabaseContext, parameters: &String) -> Result<String, String> {
+            context
+                .cache()
+                .get(parameters)
+                .cloned()
+                .ok_or_else(|| "memory miss".to_string())
+        }
+    }
+
+    impl ApiOperation<DatabaseContext, String> for LocalDisk {
+        type Output = String;
+        type Error = &'static str;
+
+        fn execute(
+            _context: &mut DatabaseContext,
+            _parameters: &String,
+        ) -> Result<String, &'static str> {
+            Err("disk miss")
+        }
+    }
+
+    impl ApiOperation<DatabaseContext, String> for RemoteStore {
+        type Output = String;
+        type Error = String;
+
+        fn execute(context: &mut DatabaseContext, parameters: &String) -> Result<String, String> {
+            context.increment_transaction();
+            Ok(format!("remote:{}", parameters))
+        }
+    }
+
+    fn chain() -> FallbackChain<DatabaseContext, String, String, String> {
+        FallbackChain::new()
+            .tier(MemoryCache)
+            .tier(LocalDisk)
+            .tier(RemoteStore)
+    }
+
+    #[test]
+    fn test_last_tier_serves_after_earlier_misses() {
+        let mut executor =
+            ApiExecutor::new(DatabaseContext::new("fallback".to_string())).with_audit_log();
+
+        let outcome = executor
+            .execute_prioritized_fallback_chain(&chain(), &"user_1".to_string())
+            .unwrap();
+
+        assert_eq!(
+            outcome,
+            FallbackOutcome {
+                tier: 2,
+                output: "remote:user_1".to_string()
+            }
+        );
+        assert_eq!(executor.context().transaction_count(), 1);
+        let audited: Vec<_> = executor
+            .audit_entries()
+            .iter()
+            .map(|entry| entry.success)
+            .collect();
+        assert_eq!(audited, vec![false, false, true]);
+    }
+
+    #[test]
+    fn test_first_successful_tier_short_circuits() {
+        let mut context = DatabaseContext::new("fallback".to_string());
+        context
+            .cache_mut()
+            .insert("user_1".to_string(), "cached".to_string());
+        let mut executor = ApiExecutor::new(context);
+
+        let outcome = executor
+            .execute_prioritized_fallback_chain(&chain(), &"user_1".to_string())
+            .unwrap();
+
+        assert_eq!(outcome.tier, 0);
+        assert_eq!(outcome.output, "cached");
+        assert_eq!(executor.context().transaction_count(), 0);
+    }
+
+    #[test]
+    fn test_exhausted_chain_returns_every_error() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("fallback".to_string()));
+        let chain = FallbackChain::<_, _, _, String>::new()
+            .tier(MemoryCache)
+            .tier(LocalDisk);
+
+        let errors = executor
+            .execute_prioritized_fallback_chain(&chain, &"user_1".to_string())
+            .unwrap_err();
+
+        assert_eq!(errors, vec!["memory miss", "disk miss"]);
+    }
+}
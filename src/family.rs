@@ -0,0 +1,261 @@
+//! Organizing operations into families that share a single context.
+
+use crate::{ApiExecutor, DispatchError, OperationId, RegisterError, Registry};
+use std::any::Any;
+
+/// A group of related operations over context `C`, such as a user or product API.
+///
+/// Families register into a [`Registry<C>`], whose registration methods only accept
+/// [`Identified`](crate::Identified) operations implementing
+/// [`ApiOperation`](crate::ApiOperation) over that same `C`. Registering an operation
+/// written for a different context is therefore a compile error rather than a
+/// runtime mismatch:
+///
+/// ```compile_fail
+/// use apithing::{ApiFamily, ApiOperation, Identified, OperationId, RegisterError, Registry};
+///
+/// struct UserContext;
+/// struct ProductContext;
+///
+/// struct CreateProduct;
+///
+/// impl Identified for CreateProduct {
+///     const OP_ID: OperationId = OperationId::new("product.create");
+/// }
+///
+/// impl ApiOperation<ProductContext, String> for CreateProduct {
+///     type Output = ();
+///     type Error = ();
+///
+///     fn execute(_context: &mut ProductContext, _parameters: &String) -> Result<(), ()> {
+///         Ok(())
+///     }
+/// }
+///
+/// struct UserFamily;
+///
+/// impl ApiFamily<UserContext> for UserFamily {
+///     fn name(&self) -> &'static str {
+///         "user"
+///     }
+///
+///     fn register(&self, registry: &mut Registry<UserContext>) -> Result<(), RegisterError> {
+///         // `CreateProduct` runs against `ProductContext`, not the family's `UserContext`.
+///         registry.register::<String, _>(CreateProduct)
+///     }
+/// }
+/// ```
+pub trait ApiFamily<C> {
+    /// Returns the name of this family.
+    fn name(&self) -> &'static str;
+
+    /// Registers every operation of this family into `registry`.
+    fn register(&self, registry: &mut Registry<C>) -> Result<(), RegisterError>;
+}
+
+/// Collects operations from multiple [`ApiFamily`] implementations into one registry.
+#[derive(Debug)]
+pub struct FamilyRegistry<C> {
+    /// The operations of every registered family.
+    registry: Registry<C>,
+
+    /// The registered family names with the operations each contributed.
+    families: Vec<(&'static str, Vec<OperationId>)>,
+}
+
+impl<C> FamilyRegistry<C> {
+    /// Creates an empty family registry.
+    pub fn new() -> Self {
+        Self {
+            registry: Registry::new(),
+            families: Vec::new(),
+        }
+    }
+
+    /// Registers all operations contributed by `family`.
+    ///
+    /// Fails if the family name or any of its operation identifiers is already registered,
+    /// in which case none of the family's operations are kept.
+    pub fn register_family<F>(&mut self, family: F) -> Result<&mut Self, RegisterError>
+    where
+        F: ApiFamily<C>,
+    {
+        let name = family.name();
+        if self.families.iter().any(|(existing, _)| *existing == name) {
+            return Err(RegisterError::DuplicateFamily(name));
+        }
+
+        let mut staged = Registry::new();
+        family.register(&mut staged)?;
+        let ids = staged.ids();
+        if let Some(id) = ids.iter().find(|id| self.registry.contains(id.name())) {
+            return Err(RegisterError::DuplicateId(*id));
+        }
+
+        self.registry.absorb(staged);
+        self.families.push((name, ids));
+        Ok(self)
+    }
+
+    /// Returns the names of the registered families in registration order.
+    pub fn families(&self) -> Vec<&'static str> {
+        self.families.iter().map(|(name, _)| *name).collect()
+    }
+
+    /// Returns the operations contributed by the named family.
+    pub fn operations(&self, family: &str) -> Option<&[OperationId]> {
+        self.families
+            .iter()
+            .find(|(name, _)| *name == family)
+            .map(|(_, ids)| ids.as_slice())
+    }
+
+    /// Builds an executor that runs the registered operations against `context`.
+    pub fn into_executor(self, context: C) -> FamilyExecutor<C> {
+        FamilyExecutor {
+            executor: ApiExecutor::new(context),
+            families: self,
+        }
+    }
+}
+
+impl<C> Default for FamilyRegistry<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An executor running operations from multiple families against one shared context.
+#[derive(Debug)]
+pub struct FamilyExecutor<C> {
+    /// The executor owning the shared context.
+    executor: ApiExecutor<C>,
+
+    /// The operations available to this executor.
+    families: FamilyRegistry<C>,
+}
+
+impl<C> FamilyExecutor<C> {
+    /// Runs the operation registered under `name` with type-erased parameters.
+    pub fn run(
+        &mut self,
+        name: &str,
+        parameters: &dyn Any,
+    ) -> Result<Box<dyn Any + Send>, DispatchError> {
+        self.executor
+            .execute_dynamic(&self.families.registry, name, parameters)
+    }
+
+    /// Returns the registered families.
+    pub fn families(&self) -> &FamilyRegistry<C> {
+        &self.families
+    }
+
+    /// Returns an immutable reference to the shared context.
+    pub fn context(&self) -> &C {
+        self.executor.context()
+    }
+
+    /// Returns a mutable reference to the shared context.
+    pub fn context_mut(&mut self) -> &mut C {
+        self.executor.context_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+    use crate::{ApiOperation, Identified};
+
+    struct CreateUser;
+    struct CreateProduct;
+    struct UserFamily;
+    struct ProductFamily;
+
+    impl Identified for CreateUser {
+        const OP_ID: OperationId = OperationId::new("user.create");
+    }
+
+    impl Identified for CreateProduct {
+        const OP_ID: OperationId = OperationId::new("product.create");
+    }
+
+    impl ApiOperation<DatabaseContext, String> for CreateUser {
+        type Output = String;
+        type Error = ();
+
+        fn execute(context: &mut DatabaseContext, parameters: &String) -> Result<String, ()> {
+            context.increment_transaction();
+            let key = format!("user_{}", context.transaction_count());
+            context.cache_mut().insert(key.clone(), parameters.clone());
+            Ok(key)
+        }
+    }
+
+    impl ApiOperation<DatabaseContext, f64> for CreateProduct {
+        type Output = String;
+        type Error = ();
+
+        fn execute(context: &mut DatabaseContext, parameters: &f64) -> Result<String, ()> {
+            context.increment_transaction();
+            let key = format!("product_{}", context.transaction_count());
+            context
+                .cache_mut()
+                .insert(key.clone(), parameters.to_string());
+            Ok(key)
+        }
+    }
+
+    impl ApiFamily<DatabaseContext> for UserFamily {
+        fn name(&self) -> &'static str {
+            "user"
+        }
+
+        fn register(&self, registry: &mut Registry<DatabaseContext>) -> Result<(), RegisterError> {
+            registry.register(CreateUser)
+        }
+    }
+
+    impl ApiFamily<DatabaseContext> for ProductFamily {
+        fn name(&self) -> &'static str {
+            "product"
+        }
+
+        fn register(&self, registry: &mut Registry<DatabaseContext>) -> Result<(), RegisterError> {
+            registry.register(CreateProduct)
+        }
+    }
+
+    #[test]
+    fn test_multi_family_executor() {
+        let mut families = FamilyRegistry::new();
+        families
+            .register_family(UserFamily)
+            .unwrap()
+            .register_family(ProductFamily)
+            .unwrap();
+        assert_eq!(families.families(), vec!["user", "product"]);
+
+        let mut executor = families.into_executor(DatabaseContext::new("shared".to_string()));
+
+        let user = executor.run("user.create", &"Alice".to_string()).unwrap();
+        let product = executor.run("product.create", &9.99f64).unwrap();
+
+        assert_eq!(user.downcast_ref::<String>(), Some(&"user_1".to_string()));
+        assert_eq!(
+            product.downcast_ref::<String>(),
+            Some(&"product_2".to_string())
+        );
+        assert_eq!(executor.context().transaction_count(), 2);
+    }
+
+    #[test]
+    fn test_duplicate_family_is_rejected() {
+        let mut families = FamilyRegistry::<DatabaseContext>::new();
+        families.register_family(UserFamily).unwrap();
+
+        let result = families.register_family(UserFamily).map(|_| ());
+        assert_eq!(result, Err(RegisterError::DuplicateFamily("user")));
+    }
+}
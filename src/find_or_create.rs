@@ -0,0 +1,137 @@
+//! Looking up an entity and creating it when it does not exist.
+
+use crate::{ApiExecutor, ApiOperation};
+
+impl<C> ApiExecutor<C> {
+    /// Runs `find_op`, and if it fails with an error matching `is_not_found`, runs
+    /// `create_op` instead, returning the entity either way.
+    ///
+    /// Any other error from `find_op` is returned without attempting the create.
+    pub fn find_or_create<PFind, OpFind, PCreate, OpCreate, F>(
+        &mut self,
+        find_op: OpFind,
+        find_parameters: &PFind,
+        create_op: OpCreate,
+        create_parameters: &PCreate,
+        is_not_found: F,
+    ) -> Result<OpFind::Output, OpFind::Error>
+    where
+        OpFind: ApiOperation<C, PFind>,
+        OpCreate: ApiOperation<C, PCreate, Output = OpFind::Output>,
+        OpCreate::Error: Into<OpFind::Error>,
+        F: FnOnce(&OpFind::Error) -> bool,
+    {
+        match self.execute(find_op, find_parameters) {
+            Err(error) if is_not_found(&error) => self
+                .execute(create_op, create_parameters)
+                .map_err(Into::into),
+            result => result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+
+    #[derive(Debug, PartialEq)]
+    enum UserError {
+        NotFound,
+        Unavailable,
+    }
+
+    struct FindUser;
+    struct CreateUser;
+
+    impl ApiOperation<DatabaseContext, String> for FindUser {
+        type Output = String;
+        type Error = UserError;
+
+        fn execute(
+            context: &mut DatabaseContext,
+            parameters: &String,
+        ) -> Result<String, UserError> {
+            if context.connection_pool() == "offline" {
+                return Err(UserError::Unavailable);
+            }
+            context
+                .cache()
+                .get(parameters)
+                .cloned()
+                .ok_or(UserError::NotFound)
+        }
+    }
+
+    impl ApiOperation<DatabaseContext, (String, String)> for CreateUser {
+        type Output = String;
+        type Error = UserError;
+
+        fn execute(
+            context: &mut DatabaseContext,
+            parameters: &(String, String),
+        ) -> Result<String, UserError> {
+            context
+                .cache_mut()
+                .insert(parameters.0.clone(), parameters.1.clone());
+            context.increment_transaction();
+            Ok(parameters.1.clone())
+        }
+    }
+
+    fn is_not_found(error: &UserError) -> bool {
+        *error == UserError::NotFound
+    }
+
+    #[test]
+    fn test_found_entity_skips_create() {
+        let mut context = DatabaseContext::new("users".to_string());
+        context
+            .cache_mut()
+            .insert("user_1".to_string(), "alice".to_string());
+        let mut executor = ApiExecutor::new(context);
+
+        let user = executor.find_or_create(
+            FindUser,
+            &"user_1".to_string(),
+            CreateUser,
+            &("user_1".to_string(), "bob".to_string()),
+            is_not_found,
+        );
+
+        assert_eq!(user, Ok("alice".to_string()));
+        assert_eq!(executor.context().transaction_count(), 0);
+    }
+
+    #[test]
+    fn test_missing_entity_is_created() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("users".to_string()));
+
+        let user = executor.find_or_create(
+            FindUser,
+            &"user_1".to_string(),
+            CreateUser,
+            &("user_1".to_string(), "bob".to_string()),
+            is_not_found,
+        );
+
+        assert_eq!(user, Ok("bob".to_string()));
+        assert_eq!(executor.context().transaction_count(), 1);
+    }
+
+    #[test]
+    fn test_other_errors_are_returned() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("offline".to_string()));
+
+        let user = executor.find_or_create(
+            FindUser,
+            &"user_1".to_string(),
+            CreateUser,
+            &("user_1".to_string(), "bob".to_string()),
+            is_not_found,
+        );
+
+        assert_eq!(user, Err(UserError::Unavailable));
+        assert_eq!(executor.context().transaction_count(), 0);
+    }
+}
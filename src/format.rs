@@ -0,0 +1,539 @@
+//! Pluggable wire formats for dispatching operations with encoded bodies.
+
+use crate::{ApiExecutor, DispatchError, OperationId, Registry};
+use std::fmt;
+
+/// A serialization format for request and response bodies.
+///
+/// Formats transcode through [`serde_json::Value`], so any self-describing format can
+/// be plugged in by converting between its bytes and a JSON value.
+pub trait Format: Send + Sync {
+    /// Returns the MIME content type handled by this format.
+    fn content_type(&self) -> &'static str;
+
+    /// Decodes a body into a JSON value.
+    fn decode(&self, body: &[u8]) -> Result<serde_json::Value, FormatError>;
+
+    /// Encodes a JSON value into a body.
+    fn encode(&self, value: &serde_json::Value) -> Result<Vec<u8>, FormatError>;
+}
+
+/// An error raised while decoding or encoding a body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatError {
+    /// A description of what went wrong.
+    message: String,
+}
+
+impl FormatError {
+    /// Creates an error from any displayable cause.
+    pub fn new(cause: impl fmt::Display) -> Self {
+        Self {
+            message: cause.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+/// JSON bodies.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonFormat;
+
+impl Format for JsonFormat {
+    fn content_type(&self) -> &'static str {
+        "application/json"
+    }
+
+    fn decode(&self, body: &[u8]) -> Result<serde_json::Value, FormatError> {
+        serde_json::from_slice(body).map_err(FormatError::new)
+    }
+
+    fn encode(&self, value: &serde_json::Value) -> Result<Vec<u8>, FormatError> {
+        serde_json::to_vec(value).map_err(FormatError::new)
+    }
+}
+
+/// MessagePack bodies.
+#[cfg(feature = "msgpack")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MessagePackFormat;
+
+#[cfg(feature = "msgpack")]
+impl Format for MessagePackFormat {
+    fn content_type(&self) -> &'static str {
+        "application/msgpack"
+    }
+
+    fn decode(&self, body: &[u8]) -> Result<serde_json::Value, FormatError> {
+        rmp_serde::from_slice(body).map_err(FormatError::new)
+    }
+
+    fn encode(&self, value: &serde_json::Value) -> Result<Vec<u8>, FormatError> {
+        rmp_serde::to_vec_named(value).map_err(FormatError::new)
+    }
+}
+
+/// CBOR bodies.
+#[cfg(feature = "cbor")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CborFormat;
+
+#[cfg(feature = "cbor")]
+impl Format for CborFormat {
+    fn content_type(&self) -> &'static str {
+        "application/cbor"
+    }
+
+    fn decode(&self, body: &[u8]) -> Result<serde_json::Value, FormatError> {
+        ciborium::from_reader(body).map_err(FormatError::new)
+    }
+
+    fn encode(&self, value: &serde_json::Value) -> Result<Vec<u8>, FormatError> {
+        let mut body = Vec::new();
+        ciborium::into_writer(value, &mut body).map_err(FormatError::new)?;
+        Ok(body)
+    }
+}
+
+/// Returns the built-in format handling `content_type`, if it is enabled.
+pub fn format_for(content_type: &str) -> Option<&'static dyn Format> {
+    match content_type {
+        "application/json" => Some(&JsonFormat),
+        #[cfg(feature = "msgpack")]
+        "application/msgpack" | "application/x-msgpack" => Some(&MessagePackFormat),
+        #[cfg(feature = "cbor")]
+        "application/cbor" => Some(&CborFormat),
+        _ => None,
+    }
+}
+
+/// Errors returned by [`Registry::dispatch_encoded`].
+#[derive(Debug)]
+pub enum EncodedDispatchError {
+    /// The request body could not be decoded.
+    Format(FormatError),
+
+    /// The operation's output or error could not be encoded.
+    EncodeOutput(FormatError),
+
+    /// The operation was not registered with `register_encoded`.
+    NotEncodable(OperationId),
+
+    /// The decoded body does not match the operation's parameter type.
+    InvalidParameters {
+        /// The operation being dispatched.
+        operation: OperationId,
+
+        /// The underlying deserialization error.
+        source: serde_json::Error,
+    },
+
+    /// Dispatching the operation failed before it ran.
+    Dispatch(DispatchError),
+
+    /// The operation ran and failed.
+    Operation {
+        /// The operation that failed.
+        operation: OperationId,
+
+        /// The operation's error, encoded in the request's format.
+        error: Vec<u8>,
+    },
+
+    /// The body is larger than the executor's input limit, so it was not decoded.
+    InputTooLarge {
+        /// The size of the body, in bytes.
+        size: usize,
+
+        /// The configured limit, in bytes.
+        limit: usize,
+    },
+
+    /// The encoded output is larger than the executor's output limit, so it was
+    /// withheld. The operation has already run.
+    OutputTooLarge {
+        /// The operation that produced the output.
+        operation: OperationId,
+
+        /// The size of the encoded output, in bytes.
+        size: usize,
+
+        /// The configured limit, in bytes.
+        limit: usize,
+    },
+}
+
+impl fmt::Display for EncodedDispatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncodedDispatchError::Format(error) => write!(f, "format error: {}", error),
+            EncodedDispatchError::EncodeOutput(error) => {
+                write!(f, "failed to encode output: {}", error)
+            }
+            EncodedDispatchError::NotEncodable(id) => write!(
+                f,
+                "operation `{}` was not registered with encoding support",
+                id
+            ),
+            EncodedDispatchError::InvalidParameters { operation, source } => {
+                write!(f, "invalid parameters for `{}`: {}", operation, source)
+            }
+            EncodedDispatchError::Dispatch(error) => error.fmt(f),
+            EncodedDispatchError::Operation { operation, .. } => {
+                write!(f, "operation `{}` failed", operation)
+            }
+            EncodedDispatchError::InputTooLarge { size, limit } => write!(
+                f,
+                "request body is {} bytes, over the limit of {} bytes",
+                size, limit
+            ),
+            EncodedDispatchError::OutputTooLarge {
+                operation,
+                size,
+                limit,
+            } => write!(
+                f,
+                "output of operation `{}` is {} bytes, over the limit of {} bytes",
+                operation, size, limit
+            ),
+        }
+    }
+}
+
+impl std::error::Error for EncodedDispatchError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            EncodedDispatchError::Format(error) | EncodedDispatchError::EncodeOutput(error) => {
+                Some(error)
+            }
+            EncodedDispatchError::InvalidParameters { source, .. } => Some(source),
+            EncodedDispatchError::Dispatch(error) => Some(error),
+            EncodedDispatchError::NotEncodable(_)
+            | EncodedDispatchError::Operation { .. }
+            | EncodedDispatchError::InputTooLarge { .. }
+            | EncodedDispatchError::OutputTooLarge { .. } => None,
+        }
+    }
+}
+
+impl<C> Registry<C> {
+    /// Executes the operation registered under `name` with a body encoded in `format`.
+    ///
+    /// The parameters are decoded from `body` and the output is encoded in the same
+    /// format, so binary protocols can be served without extra conversion code.
+    pub fn dispatch_encoded(
+        &self,
+        context: &mut C,
+        name: &str,
+        format: &dyn Format,
+        body: &[u8],
+    ) -> Result<Vec<u8>, EncodedDispatchError> {
+        let (operation, registered) = self.get_entry(name).ok_or_else(|| {
+            EncodedDispatchError::Dispatch(DispatchError::UnknownOperation(name.to_string()))
+        })?;
+        let (Some(decode), Some(encode), Some(encode_error)) = (
+            &registered.decode,
+            &registered.encode,
+            &registered.encode_error,
+        ) else {
+            return Err(EncodedDispatchError::NotEncodable(operation));
+        };
+
+        let value = format.decode(body).map_err(EncodedDispatchError::Format)?;
+        let parameters = decode(value)
+            .map_err(|source| EncodedDispatchError::InvalidParameters { operation, source })?;
+        let output = match (registered.execute)(context, parameters.as_ref()) {
+            Ok(output) => output,
+            Err(DispatchError::Operation(error)) => {
+                let error = encode_error(error.as_ref())
+                    .map_err(FormatError::new)
+                    .and_then(|value| format.encode(&value))
+                    .map_err(EncodedDispatchError::EncodeOutput)?;
+                return Err(EncodedDispatchError::Operation { operation, error });
+            }
+            Err(error) => return Err(EncodedDispatchError::Dispatch(error)),
+        };
+        let value = encode(output.as_ref())
+            .map_err(|error| EncodedDispatchError::EncodeOutput(FormatError::new(error)))?;
+        format
+            .encode(&value)
+            .map_err(EncodedDispatchError::EncodeOutput)
+    }
+}
+
+impl<C> ApiExecutor<C> {
+    /// Rejects bodies passed to `execute_with_input_size_guard` that are larger than
+    /// `bytes`.
+    pub fn with_input_limit(mut self, bytes: usize) -> Self {
+        self.input_limit = Some(bytes);
+        self
+    }
+
+    /// Dispatches an encoded body through [`Registry::dispatch_encoded`] after checking
+    /// its size against the limit set with [`with_input_limit`](Self::with_input_limit).
+    ///
+    /// Oversized bodies are rejected before any decoding, so untrusted callers cannot
+    /// exhaust memory by sending huge payloads. Without a limit, every body passes.
+    /// Encoded outputs are likewise checked against the limit set with
+    /// [`with_output_limit`](Self::with_output_limit).
+    pub fn execute_with_input_size_guard(
+        &mut self,
+        registry: &Registry<C>,
+        name: &str,
+        format: &dyn Format,
+        body: &[u8],
+    ) -> Result<Vec<u8>, EncodedDispatchError> {
+        self.check_allowlist(name)
+            .map_err(EncodedDispatchError::Dispatch)?;
+        if let Some(limit) = self.input_limit {
+            if body.len() > limit {
+                return Err(EncodedDispatchError::InputTooLarge {
+                    size: body.len(),
+                    limit,
+                });
+            }
+        }
+        let output = registry.dispatch_encoded(&mut self.context, name, format, body)?;
+        if let Some(limit) = self.output_limit {
+            if output.len() > limit {
+                let operation = registry
+                    .get_entry(name)
+                    .map(|(operation, _)| operation)
+                    .expect("dispatched operations are registered");
+                return Err(EncodedDispatchError::OutputTooLarge {
+                    operation,
+                    size: output.len(),
+                    limit,
+                });
+            }
+        }
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+    use crate::{ApiOperation, Identified};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize)]
+    struct CreateUserProps {
+        email: String,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct User {
+        id: u32,
+        email: String,
+    }
+
+    struct CreateUser;
+
+    impl Identified for CreateUser {
+        const OP_ID: OperationId = OperationId::new("create_user");
+    }
+
+    impl ApiOperation<DatabaseContext, CreateUserProps> for CreateUser {
+        type Output = User;
+        type Error = ();
+
+        fn execute(
+            context: &mut DatabaseContext,
+            parameters: &CreateUserProps,
+        ) -> Result<User, ()> {
+            context.increment_transaction();
+            Ok(User {
+                id: context.transaction_count(),
+                email: parameters.email.clone(),
+            })
+        }
+    }
+
+    fn registry() -> Registry<DatabaseContext> {
+        let mut registry = Registry::new();
+        registry.register_encoded(CreateUser).unwrap();
+        registry
+    }
+
+    fn props() -> CreateUserProps {
+        CreateUserProps {
+            email: "alice@example.com".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_dispatch_json_body() {
+        let registry = registry();
+        let mut context = DatabaseContext::new("format".to_string());
+        let format = format_for("application/json").unwrap();
+
+        let body = serde_json::to_vec(&props()).unwrap();
+        let response = registry
+            .dispatch_encoded(&mut context, "create_user", format, &body)
+            .unwrap();
+
+        let user: User = serde_json::from_slice(&response).unwrap();
+        assert_eq!(
+            user,
+            User {
+                id: 1,
+                email: "alice@example.com".to_string()
+            }
+        );
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn test_json_and_msgpack_produce_identical_results() {
+        let registry = registry();
+        let mut json_context = DatabaseContext::new("json".to_string());
+        let mut msgpack_context = DatabaseContext::new("msgpack".to_string());
+
+        let json = registry
+            .dispatch_encoded(
+                &mut json_context,
+                "create_user",
+                &JsonFormat,
+                &serde_json::to_vec(&props()).unwrap(),
+            )
+            .unwrap();
+        let msgpack = registry
+            .dispatch_encoded(
+                &mut msgpack_context,
+                "create_user",
+                format_for("application/msgpack").unwrap(),
+                &rmp_serde::to_vec(&props()).unwrap(),
+            )
+            .unwrap();
+
+        let from_json: User = serde_json::from_slice(&json).unwrap();
+        let from_msgpack: User = rmp_serde::from_slice(&msgpack).unwrap();
+        assert_eq!(from_json, from_msgpack);
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn test_dispatch_cbor_body() {
+        let registry = registry();
+        let mut context = DatabaseContext::new("cbor".to_string());
+        let mut body = Vec::new();
+        ciborium::into_writer(&props(), &mut body).unwrap();
+
+        let response = registry
+            .dispatch_encoded(&mut context, "create_user", &CborFormat, &body)
+            .unwrap();
+
+        let user: User = ciborium::from_reader(response.as_slice()).unwrap();
+        assert_eq!(user.email, "alice@example.com");
+    }
+
+    #[test]
+    fn test_input_size_guard_rejects_oversized_body_before_execution() {
+        let registry = registry();
+        let mut executor =
+            ApiExecutor::new(DatabaseContext::new("format".to_string())).with_input_limit(64);
+        let oversized = serde_json::to_vec(&CreateUserProps {
+            email: format!("{}@example.com", "a".repeat(100)),
+        })
+        .unwrap();
+
+        let result = executor.execute_with_input_size_guard(
+            &registry,
+            "create_user",
+            &JsonFormat,
+            &oversized,
+        );
+
+        assert!(matches!(
+            result,
+            Err(EncodedDispatchError::InputTooLarge { limit: 64, size }) if size == oversized.len()
+        ));
+        assert_eq!(executor.context().transaction_count(), 0);
+    }
+
+    #[test]
+    fn test_input_size_guard_passes_body_under_limit() {
+        let registry = registry();
+        let mut executor =
+            ApiExecutor::new(DatabaseContext::new("format".to_string())).with_input_limit(64);
+        let body = serde_json::to_vec(&props()).unwrap();
+
+        let response = executor
+            .execute_with_input_size_guard(&registry, "create_user", &JsonFormat, &body)
+            .unwrap();
+
+        let user: User = serde_json::from_slice(&response).unwrap();
+        assert_eq!(user.id, 1);
+    }
+
+    #[test]
+    fn test_input_size_guard_withholds_oversized_output() {
+        let registry = registry();
+        let mut executor =
+            ApiExecutor::new(DatabaseContext::new("format".to_string())).with_output_limit(8);
+        let body = serde_json::to_vec(&props()).unwrap();
+
+        let result =
+            executor.execute_with_input_size_guard(&registry, "create_user", &JsonFormat, &body);
+
+        assert!(matches!(
+            result,
+            Err(EncodedDispatchError::OutputTooLarge { operation, limit: 8, .. })
+                if operation == CreateUser::OP_ID
+        ));
+    }
+
+    #[test]
+    fn test_input_size_guard_enforces_configured_allowlist() {
+        let registry = registry();
+        let mut executor = ApiExecutor::new(DatabaseContext::new("format".to_string()))
+            .with_allowlist(["read_user"]);
+        let body = serde_json::to_vec(&props()).unwrap();
+
+        let result =
+            executor.execute_with_input_size_guard(&registry, "create_user", &JsonFormat, &body);
+
+        assert!(matches!(
+            result,
+            Err(EncodedDispatchError::Dispatch(
+                DispatchError::OperationForbidden(_)
+            ))
+        ));
+        assert_eq!(executor.context().transaction_count(), 0);
+    }
+
+    #[test]
+    fn test_operations_without_encoding_support_are_rejected() {
+        struct Count;
+
+        impl Identified for Count {
+            const OP_ID: OperationId = OperationId::new("count");
+        }
+
+        impl ApiOperation<DatabaseContext, ()> for Count {
+            type Output = u32;
+            type Error = ();
+
+            fn execute(context: &mut DatabaseContext, _parameters: &()) -> Result<u32, ()> {
+                Ok(context.transaction_count())
+            }
+        }
+
+        let mut registry = Registry::new();
+        registry.register_serde(Count).unwrap();
+        let mut context = DatabaseContext::new("format".to_string());
+
+        let result = registry.dispatch_encoded(&mut context, "count", &JsonFormat, b"null");
+
+        assert!(matches!(result, Err(EncodedDispatchError::NotEncodable(_))));
+    }
+}
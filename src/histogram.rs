@@ -0,0 +1,227 @@
+//! Per-operation latency histograms with percentile queries.
+
+use crate::{ApiExecutor, ApiOperation};
+use std::time::Duration;
+
+/// The number of power-of-two microsecond buckets, covering up to about 36 minutes.
+const BUCKETS: usize = 32;
+
+/// Execution latencies bucketed by powers of two microseconds.
+///
+/// Bucket `i` counts latencies up to `2^i` microseconds; latencies beyond the last
+/// bucket are counted separately and only appear in [`count`](Self::count).
+/// Percentiles are reported as bucket upper bounds, so they are accurate to within a
+/// factor of two.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LatencyHistogram {
+    /// The number of recorded latencies in each bucket.
+    counts: [u64; BUCKETS],
+
+    /// The number of recorded latencies beyond the last bucket.
+    overflow: u64,
+
+    /// The longest recorded latency.
+    max: Duration,
+
+    /// The total number of recorded latencies.
+    total: u64,
+
+    /// The sum of every recorded latency.
+    sum: Duration,
+}
+
+impl LatencyHistogram {
+    /// Creates an empty histogram.
+    pub fn new() -> Self {
+        Self {
+            counts: [0; BUCKETS],
+            overflow: 0,
+            max: Duration::ZERO,
+            total: 0,
+            sum: Duration::ZERO,
+        }
+    }
+
+    /// Records one latency.
+    pub fn record(&mut self, latency: Duration) {
+        let micros = latency.as_micros().max(1);
+        let bucket = (u128::BITS - (micros - 1).leading_zeros()) as usize;
+        match self.counts.get_mut(bucket) {
+            Some(count) => *count += 1,
+            None => self.overflow += 1,
+        }
+        self.max = self.max.max(latency);
+        self.total += 1;
+        self.sum = self.sum.saturating_add(latency);
+    }
+
+    /// Returns the number of recorded latencies.
+    pub fn count(&self) -> u64 {
+        self.total
+    }
+
+    /// Returns the sum of every recorded latency.
+    pub fn sum(&self) -> Duration {
+        self.sum
+    }
+
+    /// Returns the number of recorded latencies beyond the last bucket.
+    pub fn overflow(&self) -> u64 {
+        self.overflow
+    }
+
+    /// Returns each bucket's upper bound with the number of latencies up to it,
+    /// smallest first, as needed for cumulative exposition formats.
+    ///
+    /// Latencies beyond the last bucket are not included; they belong only to the
+    /// unbounded `+Inf` bucket, whose count is [`count`](Self::count).
+    pub fn cumulative_buckets(&self) -> impl Iterator<Item = (Duration, u64)> + '_ {
+        let mut seen = 0;
+        self.counts.iter().enumerate().map(move |(bucket, count)| {
+            seen += count;
+            (Duration::from_micros(1 << bucket), seen)
+        })
+    }
+
+    /// Returns the upper bound of the bucket holding the `percentile`th latency, or
+    /// `None` if nothing has been recorded.
+    ///
+    /// A percentile beyond the last bucket is reported as the longest recorded latency.
+    ///
+    /// `percentile` is clamped to `[0, 100]`.
+    pub fn percentile(&self, percentile: f64) -> Option<Duration> {
+        if self.total == 0 {
+            return None;
+        }
+        let rank =
+            ((percentile.clamp(0.0, 100.0) / 100.0 * self.total as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        let bucketed = self.counts.iter().enumerate().find_map(|(bucket, count)| {
+            seen += count;
+            (seen >= rank).then(|| Duration::from_micros(1 << bucket))
+        });
+        Some(bucketed.unwrap_or(self.max))
+    }
+
+    /// Returns the median latency bucket.
+    pub fn p50(&self) -> Option<Duration> {
+        self.percentile(50.0)
+    }
+
+    /// Returns the 95th percentile latency bucket.
+    pub fn p95(&self) -> Option<Duration> {
+        self.percentile(95.0)
+    }
+
+    /// Returns the 99th percentile latency bucket.
+    pub fn p99(&self) -> Option<Duration> {
+        self.percentile(99.0)
+    }
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C> ApiExecutor<C> {
+    /// Executes an operation and records its latency, measured with the executor's
+    /// clock, in the histogram for its name.
+    pub fn execute_collecting_latency_histogram<P, Op>(
+        &mut self,
+        op: Op,
+        parameters: &P,
+    ) -> Result<Op::Output, Op::Error>
+    where
+        Op: ApiOperation<C, P>,
+    {
+        let started = self.clock.now();
+        let result = self.execute(op, parameters);
+        let elapsed = self.clock.now() - started;
+        self.histograms
+            .entry(Op::name())
+            .or_default()
+            .record(elapsed);
+        result
+    }
+
+    /// Returns the latency histogram recorded for the operation named `operation`.
+    pub fn latency_histogram(&self, operation: &str) -> Option<&LatencyHistogram> {
+        self.histograms.get(operation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+    use crate::MockClock;
+
+    /// Pretends to take the requested number of milliseconds on a mock clock.
+    struct Work;
+
+    impl ApiOperation<(DatabaseContext, MockClock), u64> for Work {
+        type Output = ();
+        type Error = ();
+
+        fn execute(context: &mut (DatabaseContext, MockClock), parameters: &u64) -> Result<(), ()> {
+            context.0.increment_transaction();
+            context.1.advance(Duration::from_millis(*parameters));
+            Ok(())
+        }
+
+        fn name() -> &'static str {
+            "work"
+        }
+    }
+
+    #[test]
+    fn test_percentiles_fall_in_expected_buckets() {
+        let clock = MockClock::new();
+        let mut executor =
+            ApiExecutor::new((DatabaseContext::new("histogram".to_string()), clock.clone()))
+                .with_clock(clock);
+        let durations = std::iter::repeat(5)
+            .take(90)
+            .chain(std::iter::repeat(50).take(9))
+            .chain(std::iter::once(500));
+
+        for millis in durations {
+            executor
+                .execute_collecting_latency_histogram(Work, &millis)
+                .unwrap();
+        }
+
+        let histogram = executor.latency_histogram("work").unwrap();
+        assert_eq!(histogram.count(), 100);
+        assert_eq!(histogram.p50(), Some(Duration::from_micros(8192)));
+        assert_eq!(histogram.p95(), Some(Duration::from_micros(65536)));
+        assert_eq!(histogram.p99(), Some(Duration::from_micros(65536)));
+        assert_eq!(
+            histogram.percentile(100.0),
+            Some(Duration::from_micros(524288))
+        );
+        assert!(executor.latency_histogram("other").is_none());
+    }
+
+    #[test]
+    fn test_latencies_beyond_last_bucket_overflow() {
+        let mut histogram = LatencyHistogram::new();
+        histogram.record(Duration::from_millis(1));
+        histogram.record(Duration::from_secs(3 * 3600));
+
+        assert_eq!(histogram.count(), 2);
+        assert_eq!(histogram.overflow(), 1);
+        let (last_bound, last_count) = histogram.cumulative_buckets().last().unwrap();
+        assert_eq!(last_bound, Duration::from_micros(1 << (BUCKETS - 1)));
+        assert_eq!(last_count, 1);
+        assert_eq!(histogram.p50(), Some(Duration::from_micros(1024)));
+        assert_eq!(histogram.p99(), Some(Duration::from_secs(3 * 3600)));
+    }
+
+    #[test]
+    fn test_empty_histogram_has_no_percentiles() {
+        assert_eq!(LatencyHistogram::new().p50(), None);
+    }
+}
@@ -0,0 +1,220 @@
+//! Deduplicating retried requests through a pluggable idempotency store.
+
+use crate::{ApiExecutor, ApiOperation, Clock, SystemClock};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Storage for the outputs of requests already handled, keyed by idempotency key.
+///
+/// Methods take `&self` so one store can be shared across executors, and an
+/// implementation backed by an external service can share state across instances.
+pub trait IdempotencyStore<V> {
+    /// Returns the output stored under `key`, unless it is missing or expired.
+    fn get(&self, key: &str) -> Option<V>;
+
+    /// Stores `value` under `key` for `ttl`.
+    ///
+    /// A `ttl` too large to add to the current instant, such as [`Duration::MAX`],
+    /// should never expire.
+    fn put(&self, key: &str, value: V, ttl: Duration);
+}
+
+/// Stored outputs by key with their expiry times, or `None` for entries that never
+/// expire.
+type Entries<V> = HashMap<String, (V, Option<Instant>)>;
+
+/// An [`IdempotencyStore`] held in process memory.
+///
+/// Clones share the same entries.
+pub struct InMemoryIdempotencyStore<V> {
+    /// The stored outputs with their expiry times.
+    entries: Arc<Mutex<Entries<V>>>,
+
+    /// The time source used for expiry.
+    clock: Arc<dyn Clock>,
+}
+
+impl<V> InMemoryIdempotencyStore<V> {
+    /// Creates an empty store using the system clock.
+    pub fn new() -> Self {
+        Self::with_clock(SystemClock)
+    }
+
+    /// Creates an empty store that measures expiry with `clock`.
+    pub fn with_clock(clock: impl Clock + 'static) -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(HashMap::new())),
+            clock: Arc::new(clock),
+        }
+    }
+
+    /// Returns the number of stored entries, including expired ones not yet evicted.
+    pub fn len(&self) -> usize {
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .len()
+    }
+
+    /// Returns true if the store holds no entries.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<V> Default for InMemoryIdempotencyStore<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> Clone for InMemoryIdempotencyStore<V> {
+    fn clone(&self) -> Self {
+        Self {
+            entries: Arc::clone(&self.entries),
+            clock: Arc::clone(&self.clock),
+        }
+    }
+}
+
+impl<V> fmt::Debug for InMemoryIdempotencyStore<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InMemoryIdempotencyStore")
+            .field("entries", &self.len())
+            .finish()
+    }
+}
+
+impl<V: Clone> IdempotencyStore<V> for InMemoryIdempotencyStore<V> {
+    fn get(&self, key: &str) -> Option<V> {
+        let mut entries = self
+            .entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let now = self.clock.now();
+        match entries.get(key) {
+            Some((value, expires)) if expires.map_or(true, |expires| now < expires) => {
+                Some(value.clone())
+            }
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn put(&self, key: &str, value: V, ttl: Duration) {
+        let expires = self.clock.now().checked_add(ttl);
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(key.to_string(), (value, expires));
+    }
+}
+
+impl<C> ApiExecutor<C> {
+    /// Executes an operation at most once per idempotency `key` while its stored output
+    /// is fresh.
+    ///
+    /// A key found in `store` returns the stored output without running the operation.
+    /// Otherwise a successful output is stored for `ttl`; errors are not stored, so a
+    /// failed request can be retried under the same key.
+    pub fn execute_idempotent<P, Op, S>(
+        &mut self,
+        key: &str,
+        op: Op,
+        parameters: &P,
+        store: &S,
+        ttl: Duration,
+    ) -> Result<Op::Output, Op::Error>
+    where
+        Op: ApiOperation<C, P>,
+        Op::Output: Clone,
+        S: IdempotencyStore<Op::Output> + ?Sized,
+    {
+        if let Some(output) = store.get(key) {
+            return Ok(output);
+        }
+        let output = self.execute(op, parameters)?;
+        store.put(key, output.clone(), ttl);
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+    use crate::MockClock;
+
+    struct ChargeCard;
+
+    impl ApiOperation<DatabaseContext, u32> for ChargeCard {
+        type Output = u32;
+        type Error = String;
+
+        fn execute(context: &mut DatabaseContext, parameters: &u32) -> Result<u32, String> {
+            if *parameters == 0 {
+                return Err("nothing to charge".to_string());
+            }
+            context.increment_transaction();
+            Ok(context.transaction_count())
+        }
+    }
+
+    #[test]
+    fn test_repeated_key_returns_stored_output() {
+        let clock = MockClock::new();
+        let store = InMemoryIdempotencyStore::with_clock(clock.clone());
+        let mut executor = ApiExecutor::new(DatabaseContext::new("idempotent".to_string()));
+        let ttl = Duration::from_secs(60);
+
+        let first = executor.execute_idempotent("req-1", ChargeCard, &100, &store, ttl);
+        let retry = executor.execute_idempotent("req-1", ChargeCard, &100, &store, ttl);
+        let other = executor.execute_idempotent("req-2", ChargeCard, &100, &store, ttl);
+
+        assert_eq!(first, Ok(1));
+        assert_eq!(retry, Ok(1));
+        assert_eq!(other, Ok(2));
+        assert_eq!(executor.context().transaction_count(), 2);
+
+        clock.advance(ttl);
+        let expired = executor.execute_idempotent("req-1", ChargeCard, &100, &store, ttl);
+        assert_eq!(expired, Ok(3));
+    }
+
+    #[test]
+    fn test_unbounded_ttl_never_expires() {
+        let clock = MockClock::new();
+        let store = InMemoryIdempotencyStore::with_clock(clock.clone());
+        let mut executor = ApiExecutor::new(DatabaseContext::new("idempotent".to_string()));
+
+        let first = executor.execute_idempotent("req-1", ChargeCard, &100, &store, Duration::MAX);
+        clock.advance(Duration::from_secs(365 * 24 * 60 * 60));
+        let retry = executor.execute_idempotent("req-1", ChargeCard, &100, &store, Duration::MAX);
+
+        assert_eq!(first, Ok(1));
+        assert_eq!(retry, Ok(1));
+        assert_eq!(executor.context().transaction_count(), 1);
+    }
+
+    #[test]
+    fn test_errors_are_not_stored() {
+        let store = InMemoryIdempotencyStore::new();
+        let mut executor = ApiExecutor::new(DatabaseContext::new("idempotent".to_string()));
+        let ttl = Duration::from_secs(60);
+
+        executor
+            .execute_idempotent("req-1", ChargeCard, &0, &store, ttl)
+            .unwrap_err();
+
+        assert!(store.is_empty());
+        assert_eq!(
+            executor.execute_idempotent("req-1", ChargeCard, &5, &store, ttl),
+            Ok(1)
+        );
+    }
+}
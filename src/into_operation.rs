@@ -0,0 +1,153 @@
+//! Normalizing operation types, closures and function pointers into one form.
+
+use crate::ApiOperation;
+use std::fmt;
+use std::marker::PhantomData;
+
+/// Anything the executor can run as an operation: [`ApiOperation`] types, closures,
+/// function pointers and [`FnOperation`] wrappers.
+///
+/// The `M` parameter only keeps the implementations apart and is inferred from the
+/// operation; turbofish calls such as
+/// [`ApiExecutor::execute`](crate::ApiExecutor::execute) spell it as `_`.
+/// Closures must annotate their argument types, for example
+/// `|context: &mut MyContext, parameters: &MyParameters| ...`.
+pub trait IntoApiOperation<C, P, M> {
+    /// The type returned by a successful execution.
+    type Output;
+
+    /// The error type returned when execution fails.
+    type Error;
+
+    /// Runs the operation against the context.
+    fn run(self, context: &mut C, parameters: &P) -> Result<Self::Output, Self::Error>;
+
+    /// Returns the diagnostic name of the operation.
+    fn name() -> &'static str {
+        std::any::type_name::<Self>()
+    }
+}
+
+/// Selects the [`IntoApiOperation`] implementation for [`ApiOperation`] types.
+pub struct OperationMarker;
+
+/// Selects the [`IntoApiOperation`] implementation for closures and function pointers.
+pub struct ClosureMarker<O, E>(PhantomData<fn() -> (O, E)>);
+
+/// Selects the [`IntoApiOperation`] implementation for [`FnOperation`].
+pub struct FnOperationMarker<O, E>(PhantomData<fn() -> (O, E)>);
+
+impl<C, P, Op> IntoApiOperation<C, P, OperationMarker> for Op
+where
+    Op: ApiOperation<C, P>,
+{
+    type Output = Op::Output;
+    type Error = Op::Error;
+
+    fn run(self, context: &mut C, parameters: &P) -> Result<Op::Output, Op::Error> {
+        Op::execute(context, parameters)
+    }
+
+    fn name() -> &'static str {
+        Op::name()
+    }
+}
+
+impl<C, P, O, E, F> IntoApiOperation<C, P, ClosureMarker<O, E>> for F
+where
+    F: FnOnce(&mut C, &P) -> Result<O, E>,
+{
+    type Output = O;
+    type Error = E;
+
+    fn run(self, context: &mut C, parameters: &P) -> Result<O, E> {
+        self(context, parameters)
+    }
+}
+
+/// Wraps a closure or function pointer as an explicit operation value.
+///
+/// Bare closures can be passed to the executor directly; the wrapper remains useful
+/// for storing an operation in a named field or making the intent explicit.
+pub struct FnOperation<F> {
+    /// The wrapped function.
+    f: F,
+}
+
+impl<F> FnOperation<F> {
+    /// Wraps `f` as an operation.
+    pub fn new(f: F) -> Self {
+        Self { f }
+    }
+}
+
+impl<F> fmt::Debug for FnOperation<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FnOperation").finish_non_exhaustive()
+    }
+}
+
+impl<C, P, O, E, F> IntoApiOperation<C, P, FnOperationMarker<O, E>> for FnOperation<F>
+where
+    F: FnOnce(&mut C, &P) -> Result<O, E>,
+{
+    type Output = O;
+    type Error = E;
+
+    fn run(self, context: &mut C, parameters: &P) -> Result<O, E> {
+        (self.f)(context, parameters)
+    }
+
+    fn name() -> &'static str {
+        std::any::type_name::<F>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+    use crate::ApiExecutor;
+
+    struct Increment;
+
+    impl ApiOperation<DatabaseContext, u32> for Increment {
+        type Output = u32;
+        type Error = String;
+
+        fn execute(context: &mut DatabaseContext, parameters: &u32) -> Result<u32, String> {
+            for _ in 0..*parameters {
+                context.increment_transaction();
+            }
+            Ok(context.transaction_count())
+        }
+    }
+
+    fn double(context: &mut DatabaseContext, _parameters: &u32) -> Result<u32, String> {
+        Ok(context.transaction_count() * 2)
+    }
+
+    #[test]
+    fn test_execute_accepts_every_operation_form() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("into".to_string()));
+
+        let from_marker = executor.execute(Increment, &2);
+        let from_closure = executor.execute(
+            |context: &mut DatabaseContext, parameters: &u32| -> Result<u32, String> {
+                context.increment_transaction();
+                Ok(context.transaction_count() + parameters)
+            },
+            &10,
+        );
+        let from_wrapper = executor.execute(FnOperation::new(double), &0);
+        let from_fn_pointer = executor.execute(double, &0);
+        let from_turbofish = executor.execute::<u32, Increment, _>(Increment, &0);
+
+        assert_eq!(from_marker, Ok(2));
+        assert_eq!(from_closure, Ok(13));
+        assert_eq!(from_wrapper, Ok(6));
+        assert_eq!(from_fn_pointer, Ok(6));
+        assert_eq!(from_turbofish, Ok(3));
+        assert_eq!(executor.context().transaction_count(), 3);
+    }
+}
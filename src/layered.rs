@@ -0,0 +1,303 @@
+//! Executors composed into layers, such as an in-process cache over a remote store.
+
+use crate::{ApiExecutor, ApiOperation};
+
+/// A layer that can serve read operations, reporting a miss as `Ok(None)`.
+///
+/// Implemented for [`ApiExecutor`] when the operation returns an `Option`, and for
+/// [`LayeredExecutor`] when both of its layers can serve the operation.
+pub trait ReadLayer<Op, P> {
+    /// The value returned on a hit.
+    type Value;
+
+    /// The error returned by the operation.
+    type Error;
+
+    /// Reads through the layer, returning the first hit.
+    fn read_layer(&mut self, parameters: &P) -> Result<Option<Self::Value>, Self::Error>;
+}
+
+/// A layer that can apply write operations.
+///
+/// Implemented for [`ApiExecutor`] and for [`LayeredExecutor`] when both of its layers
+/// can apply the operation.
+pub trait WriteLayer<Op, P> {
+    /// The output of the operation.
+    type Output;
+
+    /// The error returned by the operation.
+    type Error;
+
+    /// Applies the write to every executor in the layer, primary first.
+    fn write_through_layer(&mut self, parameters: &P) -> Result<Self::Output, Self::Error>;
+
+    /// Applies the write to the primary executor only.
+    fn write_primary_layer(&mut self, parameters: &P) -> Result<Self::Output, Self::Error>;
+}
+
+/// The result of reading through layer `L`.
+type ReadResult<L, Op, P> =
+    Result<Option<<L as ReadLayer<Op, P>>::Value>, <L as ReadLayer<Op, P>>::Error>;
+
+/// The result of writing to layer `L`.
+type WriteResult<L, Op, P> =
+    Result<<L as WriteLayer<Op, P>>::Output, <L as WriteLayer<Op, P>>::Error>;
+
+impl<C, P, Op, V> ReadLayer<Op, P> for ApiExecutor<C>
+where
+    Op: ApiOperation<C, P, Output = Option<V>>,
+{
+    type Value = V;
+    type Error = Op::Error;
+
+    fn read_layer(&mut self, parameters: &P) -> Result<Option<V>, Op::Error> {
+        let started = self.clock.now();
+        let result = Op::execute(&mut self.context, parameters);
+        self.observe(Op::name(), started, result.is_ok());
+        result
+    }
+}
+
+impl<C, P, Op> WriteLayer<Op, P> for ApiExecutor<C>
+where
+    Op: ApiOperation<C, P>,
+{
+    type Output = Op::Output;
+    type Error = Op::Error;
+
+    fn write_through_layer(&mut self, parameters: &P) -> Result<Op::Output, Op::Error> {
+        let started = self.clock.now();
+        let result = Op::execute(&mut self.context, parameters);
+        self.observe(Op::name(), started, result.is_ok());
+        result
+    }
+
+    fn write_primary_layer(&mut self, parameters: &P) -> Result<Op::Output, Op::Error> {
+        <Self as WriteLayer<Op, P>>::write_through_layer(self, parameters)
+    }
+}
+
+/// Two executors layered for cache-aside access, each over its own context type.
+///
+/// Reads try the front layer and fall back to the back layer on a miss. The back layer
+/// is the primary: writes go to it first, and through to the front only once it
+/// succeeds. Layers nest, so a `LayeredExecutor` can itself be the back layer of
+/// another to build deeper hierarchies. The same operation type implements
+/// [`ApiOperation`] for every layer's context.
+#[derive(Debug, Clone)]
+pub struct LayeredExecutor<F, B> {
+    /// The layer consulted first, typically an in-process cache.
+    front: F,
+
+    /// The primary layer, consulted on a miss in the front layer.
+    back: B,
+}
+
+impl<F, B> LayeredExecutor<F, B> {
+    /// Layers `front` over the primary layer `back`.
+    pub fn new(front: F, back: B) -> Self {
+        Self { front, back }
+    }
+
+    /// Executes a read operation, returning the value from the first layer that has it.
+    ///
+    /// Values found in the back layer are not copied into the front layer; follow up
+    /// with a write to the front layer to populate it.
+    pub fn read<P, Op>(&mut self, _op: Op, parameters: &P) -> ReadResult<Self, Op, P>
+    where
+        Self: ReadLayer<Op, P>,
+    {
+        self.read_layer(parameters)
+    }
+
+    /// Executes a write operation on the primary layer and then on the front layer,
+    /// returning the primary's output.
+    ///
+    /// The front layer is left untouched if the primary write fails.
+    pub fn write_through<P, Op>(&mut self, _op: Op, parameters: &P) -> WriteResult<Self, Op, P>
+    where
+        Self: WriteLayer<Op, P>,
+    {
+        self.write_through_layer(parameters)
+    }
+
+    /// Executes a write operation on the primary layer only.
+    pub fn write_primary<P, Op>(&mut self, _op: Op, parameters: &P) -> WriteResult<Self, Op, P>
+    where
+        Self: WriteLayer<Op, P>,
+    {
+        self.write_primary_layer(parameters)
+    }
+
+    /// Returns the front layer.
+    pub fn front(&self) -> &F {
+        &self.front
+    }
+
+    /// Returns the front layer mutably.
+    pub fn front_mut(&mut self) -> &mut F {
+        &mut self.front
+    }
+
+    /// Returns the primary layer.
+    pub fn back(&self) -> &B {
+        &self.back
+    }
+
+    /// Returns the primary layer mutably.
+    pub fn back_mut(&mut self) -> &mut B {
+        &mut self.back
+    }
+}
+
+impl<F, B, P, Op> ReadLayer<Op, P> for LayeredExecutor<F, B>
+where
+    F: ReadLayer<Op, P>,
+    B: ReadLayer<Op, P, Value = F::Value, Error = F::Error>,
+{
+    type Value = F::Value;
+    type Error = F::Error;
+
+    fn read_layer(&mut self, parameters: &P) -> Result<Option<F::Value>, F::Error> {
+        match self.front.read_layer(parameters)? {
+            Some(value) => Ok(Some(value)),
+            None => self.back.read_layer(parameters),
+        }
+    }
+}
+
+impl<F, B, P, Op> WriteLayer<Op, P> for LayeredExecutor<F, B>
+where
+    F: WriteLayer<Op, P>,
+    B: WriteLayer<Op, P, Output = F::Output, Error = F::Error>,
+{
+    type Output = F::Output;
+    type Error = F::Error;
+
+    fn write_through_layer(&mut self, parameters: &P) -> Result<F::Output, F::Error> {
+        let output = self.back.write_through_layer(parameters)?;
+        self.front.write_through_layer(parameters)?;
+        Ok(output)
+    }
+
+    fn write_primary_layer(&mut self, parameters: &P) -> Result<F::Output, F::Error> {
+        self.back.write_primary_layer(parameters)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+    use std::collections::HashMap;
+
+    /// The primary store, standing in for a remote service.
+    #[derive(Default)]
+    struct RemoteStore {
+        rows: HashMap<u32, String>,
+        writes: u32,
+    }
+
+    struct GetUser;
+    struct PutUser;
+
+    impl ApiOperation<DatabaseContext, u32> for GetUser {
+        type Output = Option<String>;
+        type Error = String;
+
+        fn execute(
+            context: &mut DatabaseContext,
+            parameters: &u32,
+        ) -> Result<Option<String>, String> {
+            Ok(context.cache().get(&parameters.to_string()).cloned())
+        }
+    }
+
+    impl ApiOperation<RemoteStore, u32> for GetUser {
+        type Output = Option<String>;
+        type Error = String;
+
+        fn execute(context: &mut RemoteStore, parameters: &u32) -> Result<Option<String>, String> {
+            Ok(context.rows.get(parameters).cloned())
+        }
+    }
+
+    impl ApiOperation<DatabaseContext, (u32, String)> for PutUser {
+        type Output = ();
+        type Error = String;
+
+        fn execute(
+            context: &mut DatabaseContext,
+            parameters: &(u32, String),
+        ) -> Result<(), String> {
+            context
+                .cache_mut()
+                .insert(parameters.0.to_string(), parameters.1.clone());
+            Ok(())
+        }
+    }
+
+    impl ApiOperation<RemoteStore, (u32, String)> for PutUser {
+        type Output = ();
+        type Error = String;
+
+        fn execute(context: &mut RemoteStore, parameters: &(u32, String)) -> Result<(), String> {
+            if parameters.1.is_empty() {
+                return Err("empty name".to_string());
+            }
+            context.rows.insert(parameters.0, parameters.1.clone());
+            context.writes += 1;
+            Ok(())
+        }
+    }
+
+    fn layered() -> LayeredExecutor<ApiExecutor<DatabaseContext>, ApiExecutor<RemoteStore>> {
+        LayeredExecutor::new(
+            ApiExecutor::new(DatabaseContext::new("local".to_string())),
+            ApiExecutor::new(RemoteStore::default()),
+        )
+    }
+
+    #[test]
+    fn test_read_misses_front_and_hits_back() {
+        let mut executor = layered();
+        executor
+            .back_mut()
+            .context_mut()
+            .rows
+            .insert(1, "alice".to_string());
+
+        assert_eq!(executor.read(GetUser, &1), Ok(Some("alice".to_string())));
+        assert_eq!(executor.read(GetUser, &2), Ok(None));
+    }
+
+    #[test]
+    fn test_write_propagates_to_primary() {
+        let mut executor = layered();
+
+        executor
+            .write_through(PutUser, &(1, "alice".to_string()))
+            .unwrap();
+        executor
+            .write_primary(PutUser, &(2, "bob".to_string()))
+            .unwrap();
+
+        assert_eq!(executor.back().context().writes, 2);
+        assert_eq!(
+            executor.front().context().cache().get("1"),
+            Some(&"alice".to_string())
+        );
+        assert!(!executor.front().context().cache().contains_key("2"));
+        assert_eq!(executor.read(GetUser, &2), Ok(Some("bob".to_string())));
+    }
+
+    #[test]
+    fn test_failed_primary_write_skips_front() {
+        let mut executor = layered();
+
+        let result = executor.write_through(PutUser, &(1, String::new()));
+
+        assert_eq!(result, Err("empty name".to_string()));
+        assert!(executor.front().context().cache().is_empty());
+    }
+}
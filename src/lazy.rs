@@ -0,0 +1,120 @@
+//! Contexts constructed on first use.
+
+use crate::{ApiExecutor, ApiOperation};
+use std::fmt;
+
+/// The initializer of a [`LazyContext`].
+type Initializer<C> = Box<dyn FnOnce() -> C + Send>;
+
+/// A context that is not constructed until an operation first needs it.
+///
+/// Useful for expensive contexts such as connection pools, so requests that never run
+/// an operation do not pay their setup cost. Run operations against it with
+/// [`ApiExecutor::execute_lazy_context_init`].
+pub struct LazyContext<C> {
+    /// The initializer, until it has run.
+    init: Option<Initializer<C>>,
+
+    /// The context, once initialized.
+    value: Option<C>,
+}
+
+impl<C> LazyContext<C> {
+    /// Creates a lazy context built by `init` on first use.
+    pub fn new(init: impl FnOnce() -> C + Send + 'static) -> Self {
+        Self {
+            init: Some(Box::new(init)),
+            value: None,
+        }
+    }
+
+    /// Returns true once the context has been constructed.
+    pub fn is_initialized(&self) -> bool {
+        self.value.is_some()
+    }
+
+    /// Returns the context if it has been constructed, without constructing it.
+    pub fn get(&self) -> Option<&C> {
+        self.value.as_ref()
+    }
+
+    /// Returns the context, constructing it first if needed.
+    pub fn force(&mut self) -> &mut C {
+        if self.value.is_none() {
+            let init = self
+                .init
+                .take()
+                .expect("a lazy context keeps its initializer until initialized");
+            self.value = Some(init());
+        }
+        self.value
+            .as_mut()
+            .expect("the lazy context was just initialized")
+    }
+}
+
+impl<C: fmt::Debug> fmt::Debug for LazyContext<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.value {
+            Some(value) => f.debug_tuple("LazyContext").field(value).finish(),
+            None => f.write_str("LazyContext(<uninitialized>)"),
+        }
+    }
+}
+
+impl<C> ApiExecutor<LazyContext<C>> {
+    /// Executes an operation over the lazily constructed context, constructing it if
+    /// this is the first operation to run.
+    pub fn execute_lazy_context_init<P, Op>(
+        &mut self,
+        _op: Op,
+        parameters: &P,
+    ) -> Result<Op::Output, Op::Error>
+    where
+        Op: ApiOperation<C, P>,
+    {
+        let started = self.clock.now();
+        let result = Op::execute(self.context.force(), parameters);
+        self.observe(Op::name(), started, result.is_ok());
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    struct Connect;
+
+    impl ApiOperation<DatabaseContext, ()> for Connect {
+        type Output = u32;
+        type Error = ();
+
+        fn execute(context: &mut DatabaseContext, _parameters: &()) -> Result<u32, ()> {
+            context.increment_transaction();
+            Ok(context.transaction_count())
+        }
+    }
+
+    #[test]
+    fn test_initializer_runs_once_on_first_execution() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let mut executor = ApiExecutor::new(LazyContext::new(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            DatabaseContext::new("lazy".to_string())
+        }));
+
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+        assert!(!executor.context().is_initialized());
+
+        assert_eq!(executor.execute_lazy_context_init(Connect, &()), Ok(1));
+        assert_eq!(executor.execute_lazy_context_init(Connect, &()), Ok(2));
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(executor.context().get().unwrap().connection_pool(), "lazy");
+    }
+}
@@ -133,6 +133,176 @@
 #![warn(missing_docs)]
 #![deny(unsafe_code)]
 
+mod allowlist;
+#[cfg(feature = "tokio")]
+mod async_executor;
+mod audit;
+mod batch;
+mod cache;
+#[cfg(feature = "capi")]
+mod capi;
+mod chain;
+mod channel;
+mod checkpoint;
+mod circuit;
+mod clock;
+mod combinators;
+mod compensation;
+mod config;
+mod correlation;
+mod dag;
+mod diff;
+mod dispatch;
+mod dry_run;
+mod effects;
+mod error_code;
+mod events;
+mod fallback;
+mod family;
+mod find_or_create;
+#[cfg(feature = "serde")]
+mod format;
+mod histogram;
+mod idempotency;
+mod into_operation;
+mod layered;
+mod lazy;
+mod log;
+mod lru;
+mod memo;
+mod metrics;
+mod middleware;
+mod normalize;
+mod notify;
+#[cfg(feature = "serde")]
+mod output_limit;
+mod overrides;
+#[cfg(feature = "serde")]
+mod persist;
+#[cfg(feature = "serde")]
+mod pipeline;
+mod pool;
+mod postcondition;
+mod priority;
+mod rate_limit;
+mod read_only;
+mod registry;
+mod replicated;
+mod retry;
+mod rng;
+mod saga;
+mod sampling;
+mod scan;
+#[cfg(feature = "schemars")]
+mod schema;
+#[cfg(feature = "tower")]
+mod service;
+mod services;
+mod shadow;
+mod sharded;
+mod shared;
+mod sink;
+#[cfg(feature = "serde")]
+mod snapshot;
+mod soft_delete;
+mod span;
+mod tenant;
+mod timeout;
+mod transaction;
+mod transform;
+mod tuple;
+mod versioned;
+#[cfg(feature = "serde")]
+mod wal;
+mod warmup;
+
+#[cfg(feature = "tokio")]
+pub use async_executor::{AsyncApiExecutor, Scope, ScopedOutput};
+pub use audit::{AuditEntry, AuditHook};
+pub use batch::{AggregateError, BulkOperation};
+#[cfg(feature = "serde")]
+pub use cache::ContentCacheError;
+pub use cache::{CacheKey, Invalidates};
+#[cfg(feature = "capi")]
+pub use capi::{
+    apithing_dispatch, apithing_handle_free, apithing_handle_new, apithing_register,
+    apithing_string_free, export_context, ApiHandle, ApiStatus,
+};
+pub use chain::ContextChain;
+pub use checkpoint::NoCheckpointError;
+pub use circuit::{CircuitBreakerError, CircuitState};
+pub use clock::{Clock, MockClock, SystemClock};
+pub use combinators::{
+    Ensure, Named, OnRetry, RecoverWith, Redact, TapContext, TraceParams, TracedError, Zip,
+};
+pub use compensation::{Compensable, Compensation};
+pub use config::Contextual;
+pub use correlation::{CorrelationContext, WithCorrelation};
+pub use dag::{Dag, DagBuilder, DagError, DagOutputs, DagRunError};
+pub use diff::{Diff, DiffedOutput, MapDiff};
+pub use dispatch::Dispatch;
+pub use dry_run::DryRunContext;
+pub use effects::{EffectfulOperation, SideEffectPreview, SideEffectRecorder};
+pub use error_code::{ErrorCode, ErrorResponse};
+pub use events::{EventLog, EventOutcome, EventProducing};
+pub use fallback::{FallbackChain, FallbackOutcome};
+pub use family::{ApiFamily, FamilyExecutor, FamilyRegistry};
+#[cfg(feature = "cbor")]
+pub use format::CborFormat;
+#[cfg(feature = "msgpack")]
+pub use format::MessagePackFormat;
+#[cfg(feature = "serde")]
+pub use format::{format_for, EncodedDispatchError, Format, FormatError, JsonFormat};
+pub use histogram::LatencyHistogram;
+pub use idempotency::{IdempotencyStore, InMemoryIdempotencyStore};
+pub use into_operation::{
+    ClosureMarker, FnOperation, FnOperationMarker, IntoApiOperation, OperationMarker,
+};
+pub use layered::{LayeredExecutor, ReadLayer, WriteLayer};
+pub use lazy::LazyContext;
+pub use log::{LogLevel, LogRecord, Logger, MemoryLogger};
+pub use lru::LruCache;
+pub use metrics::OperationMetrics;
+pub use middleware::{Middleware, MiddlewareStack, Next};
+pub use normalize::Normalize;
+pub use notify::OperationOutcome;
+#[cfg(feature = "serde")]
+pub use output_limit::OutputLimitError;
+#[cfg(feature = "serde")]
+pub use pipeline::{Pipeline, PipelineConfig, PipelineError, PipelineRunError, PipelineStepConfig};
+pub use pool::{ContextPool, PooledExecutor, Reset};
+pub use postcondition::{Postcondition, PostconditionCheckError, PostconditionError};
+pub use priority::{JobId, Priority, PriorityQueue, QueueOutputs, QueueRunError};
+pub use rate_limit::{RateLimitError, RateLimitMode};
+pub use read_only::ReadOnlyExecutor;
+pub use registry::{DispatchError, Identified, OperationId, RegisterError, Registry};
+pub use replicated::{ConsistencyMode, Replicated, ReplicatedExecutor, ReplicationError};
+pub use retry::{BackoffStrategy, Jitter, RetryPolicy};
+pub use rng::{Rng, SeededRng};
+pub use saga::{Saga, SagaError};
+pub use scan::{ScanOperation, ScanOutcome};
+#[cfg(feature = "schemars")]
+pub use schema::{OutputValidationError, SchemaViolation};
+#[cfg(feature = "tower")]
+pub use service::ServiceAdapter;
+pub use services::{ApiOperationWithServices, Services};
+pub use shadow::ShadowMismatch;
+pub use sharded::{ShardStats, ShardedError, ShardedExecutor};
+pub use shared::{ApiQuery, ReentrancyError, SharedApiExecutor};
+pub use sink::ApiOperationSink;
+#[cfg(feature = "serde")]
+pub use snapshot::ContextSnapshot;
+pub use soft_delete::SoftDeletable;
+pub use span::{execute_in_child_span, SpanRecord, SpanRecorder};
+pub use tenant::Tenanted;
+pub use timeout::{CancellationFlag, CooperativeOperation, TimeoutError};
+pub use transaction::{CommitGuard, TransactionScope, Transactional};
+pub use tuple::{AllReport, OperationTuple, ResultTuple};
+pub use versioned::{ConflictRetryError, VersionConflict, Versioned};
+#[cfg(feature = "serde")]
+pub use wal::{MemoryWal, WalEntry, WalError, WalReplayError, WalSink, WalSinkError, WalStatus};
+pub use warmup::Warmable;
+
 /// Core trait that all API operations implement.
 pub trait ApiOperation<C, P> {
     /// The type returned by a successful operation execution.
@@ -143,6 +313,11 @@ pub trait ApiOperation<C, P> {
 
     /// Execute the API operation with the given context and properties.
     fn execute(context: &mut C, parameters: &P) -> Result<Self::Output, Self::Error>;
+
+    /// Returns the diagnostic name of the operation, defaulting to its type name.
+    fn name() -> &'static str {
+        std::any::type_name::<Self>()
+    }
 }
 
 /// A trait providing ergonomic method-style execution for API operations.
@@ -155,6 +330,86 @@ pub trait Execute<C, P> {
 
     /// Execute the API operation on the given context with the specified properties.
     fn execute_on(self, context: &mut C, parameters: &P) -> Result<Self::Output, Self::Error>;
+
+    /// Runs `compensation` against the same context if this operation fails.
+    ///
+    /// Unlike a fallback, the compensation only undoes partial effects: the original
+    /// error is still returned after it runs.
+    fn recover_with<Comp>(self, compensation: Comp) -> RecoverWith<Self, Comp>
+    where
+        Self: Sized,
+        Comp: Execute<C, P>,
+    {
+        RecoverWith::new(self, compensation)
+    }
+
+    /// Runs `f` against the context after this operation succeeds, before returning.
+    ///
+    /// Useful for lightweight side effects between chain steps, such as bumping a
+    /// counter, that do not warrant a full operation.
+    fn tap_context<F>(self, f: F) -> TapContext<Self, F>
+    where
+        Self: Sized,
+        F: FnOnce(&mut C),
+    {
+        TapContext::new(self, f)
+    }
+
+    /// Runs `other` with `parameters` after this operation and pairs both outputs.
+    ///
+    /// Both operations share the same context and run in order; the first error aborts
+    /// the pair. `other`'s error must convert into this operation's error type.
+    fn zip<Op2, P2>(self, other: Op2, parameters: P2) -> Zip<Self, Op2, P2>
+    where
+        Self: Sized,
+        Op2: Execute<C, P2>,
+        Op2::Error: Into<Self::Error>,
+    {
+        Zip::new(self, other, parameters)
+    }
+
+    /// Returns `error` instead of the output when the output fails `predicate`.
+    ///
+    /// A lightweight inline postcondition that does not require implementing
+    /// [`Postcondition`].
+    fn ensure<F>(self, predicate: F, error: Self::Error) -> Ensure<Self, F, Self::Error>
+    where
+        Self: Sized,
+        F: FnOnce(&Self::Output) -> bool,
+    {
+        Ensure::new(self, predicate, error)
+    }
+
+    /// Retries this operation when it fails, waiting between attempts as decided by
+    /// `strategy`.
+    ///
+    /// Lets each step of a workflow use its own schedule, such as decorrelated jitter
+    /// for a contended resource. The operation is cloned for every attempt.
+    fn on_retry<S>(self, strategy: S) -> OnRetry<Self, S>
+    where
+        Self: Sized + Clone,
+        S: BackoffStrategy,
+    {
+        OnRetry::new(self, strategy)
+    }
+
+    /// Reports this operation as `name` instead of its type name when run through
+    /// [`ApiExecutor::execute_named`].
+    fn with_name(self, name: &'static str) -> Named<Self>
+    where
+        Self: Sized,
+    {
+        Named::new(self, name)
+    }
+
+    /// Attaches the parameters of a failed call to the error, rendered with `Debug` or,
+    /// through [`TraceParams::execute_on_redacted`], with [`Redact`].
+    fn trace_params(self) -> TraceParams<Self>
+    where
+        Self: Sized,
+    {
+        TraceParams::new(self)
+    }
 }
 
 /// Blanket implementation of `Execute` for all `ApiOperation` implementors.
@@ -175,20 +430,242 @@ where
 pub struct ApiExecutor<C> {
     /// The context instance owned by this executor.
     context: C,
+
+    /// Outputs memoized by `execute_memoized`, owned by the executor rather than the context.
+    memo: memo::MemoStore,
+
+    /// Configuration objects used to resolve partial parameters.
+    configs: config::ConfigStore,
+
+    /// Receives an outcome message after each `execute` call, when installed.
+    notifier: Option<std::sync::mpsc::Sender<OperationOutcome>>,
+
+    /// The time source consulted by time-dependent features.
+    clock: clock::ClockHandle,
+
+    /// The destination for diagnostics such as slow-operation warnings.
+    logger: log::LoggerHandle,
+
+    /// Calls to `execute` taking longer than this are logged as warnings.
+    slow_threshold: Option<std::time::Duration>,
+
+    /// The random number generator used for retry jitter.
+    rng: rng::RngHandle,
+
+    /// The audit trail, recorded by `execute` once enabled.
+    audit: Option<Vec<AuditEntry>>,
+
+    /// Context snapshots pushed by `push_checkpoint`, innermost last.
+    checkpoints: checkpoint::CheckpointStack,
+
+    /// The request metadata installed by `execute_with_correlation_context`.
+    correlation: Option<CorrelationContext>,
+
+    /// The token bucket consulted by `execute_rate_limited`, when configured.
+    rate_limit: Option<rate_limit::TokenBucket>,
+
+    /// Outputs cached by `execute_cached` until invalidated.
+    cache: cache::ResultCache,
+
+    /// Transformers applied by `execute_transformed`, grouped by output type.
+    transformers: transform::TransformerChain,
+
+    /// The fraction of `execute_with_sampling` calls that are traced, when configured.
+    trace_sample_rate: Option<f64>,
+
+    /// Per-operation circuit breakers used by `execute_with_circuit_breaker`.
+    circuit_breakers: Option<circuit::CircuitBreakers>,
+
+    /// Implementations run in place of logical operations by `execute_with_local_override`.
+    overrides: overrides::OverrideMap,
+
+    /// Latency histograms recorded by `execute_collecting_latency_histogram`, by operation name.
+    histograms: std::collections::HashMap<&'static str, LatencyHistogram>,
+
+    /// Outcome counters recorded by `execute_with_metrics_export`, by operation name.
+    metrics: std::collections::HashMap<&'static str, OperationMetrics>,
+
+    /// Per-operation time budgets applied by `execute_bounded`.
+    timeouts: timeout::TimeoutMap,
+
+    /// Shadow implementations run by `execute_weighted_sample_for_shadow_traffic`.
+    shadows: shadow::ShadowMap,
+
+    /// Compensations recorded by `execute_with_compensation_log`, oldest first.
+    compensations: compensation::CompensationLog,
+
+    /// The largest serialized output accepted by `execute_with_output_size_limit` and
+    /// `execute_with_input_size_guard`, in bytes.
+    #[cfg(feature = "serde")]
+    output_limit: Option<usize>,
+
+    /// The operation names `execute_with_operation_allowlist` may dispatch.
+    allowlist: std::collections::HashSet<String>,
+
+    /// The operations whose context priming has run, by type.
+    warmed: std::collections::HashSet<std::any::TypeId>,
+
+    /// Whether `execute_with_structured_context_logging` logs context changes.
+    debug_context: bool,
+
+    /// Services injected into operations by `execute_with_dependency_injection`.
+    services: Services,
+
+    /// The largest encoded body accepted by `execute_with_input_size_guard`, in bytes.
+    #[cfg(feature = "serde")]
+    input_limit: Option<usize>,
+
+    /// The write-ahead log used by `execute_with_write_ahead_log`, when installed.
+    #[cfg(feature = "serde")]
+    wal: Option<wal::WalHandle>,
 }
 
 impl<C> ApiExecutor<C> {
     /// Creates a new `ApiExecutor` that owns the provided context.
     pub fn new(context: C) -> Self {
-        Self { context }
+        Self {
+            context,
+            memo: memo::MemoStore::default(),
+            configs: config::ConfigStore::default(),
+            notifier: None,
+            clock: clock::ClockHandle::default(),
+            logger: log::LoggerHandle::default(),
+            slow_threshold: None,
+            rng: rng::RngHandle::default(),
+            audit: None,
+            checkpoints: checkpoint::CheckpointStack::default(),
+            correlation: None,
+            rate_limit: None,
+            cache: cache::ResultCache::default(),
+            transformers: transform::TransformerChain::default(),
+            trace_sample_rate: None,
+            circuit_breakers: None,
+            overrides: overrides::OverrideMap::default(),
+            histograms: std::collections::HashMap::new(),
+            metrics: std::collections::HashMap::new(),
+            timeouts: timeout::TimeoutMap::default(),
+            shadows: shadow::ShadowMap::default(),
+            compensations: compensation::CompensationLog::default(),
+            #[cfg(feature = "serde")]
+            output_limit: None,
+            allowlist: std::collections::HashSet::new(),
+            warmed: std::collections::HashSet::new(),
+            debug_context: false,
+            services: Services::default(),
+            #[cfg(feature = "serde")]
+            input_limit: None,
+            #[cfg(feature = "serde")]
+            wal: None,
+        }
     }
 
     /// Executes an API operation using this executor's context.
-    pub fn execute<P, Op>(&mut self, _op: Op, parameters: &P) -> Result<Op::Output, Op::Error>
+    ///
+    /// Besides [`ApiOperation`] types, `op` can be a closure or function pointer taking
+    /// the context and parameters; see [`IntoApiOperation`].
+    ///
+    /// The third type parameter is the [`IntoApiOperation`] marker. Callers that name
+    /// the type parameters explicitly must now pass three of them, leaving the marker
+    /// to inference: `execute::<P, Op, _>(op, &parameters)` instead of
+    /// `execute::<P, Op>(op, &parameters)`.
+    pub fn execute<P, Op, M>(&mut self, op: Op, parameters: &P) -> Result<Op::Output, Op::Error>
+    where
+        Op: IntoApiOperation<C, P, M>,
+    {
+        let started = self.clock.now();
+        let result = op.run(&mut self.context, parameters);
+        self.observe(Op::name(), started, result.is_ok());
+        result
+    }
+
+    /// Executes an operation renamed with [`Execute::with_name`], reporting the given
+    /// name to the logger, notifier and audit log.
+    pub fn execute_named<P, Op>(
+        &mut self,
+        op: Named<Op>,
+        parameters: &P,
+    ) -> Result<Op::Output, Op::Error>
+    where
+        Op: Execute<C, P>,
+    {
+        let name = op.name();
+        let started = self.clock.now();
+        let result = op.execute_on(&mut self.context, parameters);
+        self.observe(name, started, result.is_ok());
+        result
+    }
+
+    /// Reports a finished execution to the executor's diagnostics.
+    pub(crate) fn observe(
+        &mut self,
+        operation: &'static str,
+        started: std::time::Instant,
+        success: bool,
+    ) {
+        self.observe_elapsed(operation, self.clock.now() - started, success);
+    }
+
+    /// Reports an execution that took `elapsed`, for work timed off the calling thread.
+    pub(crate) fn observe_elapsed(
+        &mut self,
+        operation: &'static str,
+        elapsed: std::time::Duration,
+        success: bool,
+    ) {
+        self.check_slow(operation, elapsed);
+        self.notify(operation, success);
+        self.record_audit(operation, success);
+    }
+
+    /// Runs `Op` against the context and reports it like `execute`, for methods that
+    /// take the operation by type rather than by value.
+    pub(crate) fn execute_observed<P, Op>(
+        &mut self,
+        parameters: &P,
+    ) -> Result<Op::Output, Op::Error>
     where
         Op: ApiOperation<C, P>,
     {
-        Op::execute(&mut self.context, parameters)
+        let started = self.clock.now();
+        let result = Op::execute(&mut self.context, parameters);
+        self.observe(Op::name(), started, result.is_ok());
+        result
+    }
+
+    /// Executes an operation defined over a pair of parameter objects.
+    ///
+    /// Saves defining a wrapper struct for each parameter combination: implement the
+    /// operation for `(P1, P2)` and pass the two objects separately.
+    pub fn execute2<P1, P2, Op>(
+        &mut self,
+        op: Op,
+        first: &P1,
+        second: &P2,
+    ) -> Result<Op::Output, Op::Error>
+    where
+        Op: ApiOperation<C, (P1, P2)>,
+        P1: Clone,
+        P2: Clone,
+    {
+        self.execute(op, &(first.clone(), second.clone()))
+    }
+
+    /// Executes an operation and returns only the part of its output selected by
+    /// `project`, dropping the rest.
+    ///
+    /// Suits callers that need a single field of a large output, such as the id of a
+    /// created entity.
+    pub fn execute_with_result_projection<P, Op, V, F>(
+        &mut self,
+        op: Op,
+        parameters: &P,
+        project: F,
+    ) -> Result<V, Op::Error>
+    where
+        Op: ApiOperation<C, P>,
+        F: FnOnce(Op::Output) -> V,
+    {
+        self.execute(op, parameters).map(project)
     }
 
     /// Returns an immutable reference to the executor's context.
@@ -248,6 +725,11 @@ mod tests {
             self.transaction_count
         }
 
+        /// Resets the transaction counter to zero.
+        pub fn reset_transactions(&mut self) {
+            self.transaction_count = 0;
+        }
+
         /// Returns an immutable reference to the connection pool identifier.
         pub fn connection_pool(&self) -> &str {
             &self.connection_pool
@@ -264,6 +746,18 @@ mod tests {
         }
     }
 
+    impl Transactional for DatabaseContext {
+        type Snapshot = DatabaseContext;
+
+        fn snapshot(&self) -> DatabaseContext {
+            self.clone()
+        }
+
+        fn restore(&mut self, snapshot: DatabaseContext) {
+            *self = snapshot;
+        }
+    }
+
     #[test]
     fn test_crate_compiles() {
         // Basic test to verify the crate compiles and runs
@@ -690,4 +1184,90 @@ mod tests {
         assert_eq!(retrieved, Some("test_value".to_string()));
         assert_eq!(executor.context().transaction_count(), 1);
     }
+
+    #[test]
+    fn test_execute2_tuples_parameter_objects() {
+        #[derive(Clone)]
+        struct CreateUserProps {
+            email: String,
+        }
+
+        #[derive(Clone)]
+        struct CreateOptions {
+            admin: bool,
+        }
+
+        struct CreateUser;
+
+        impl ApiOperation<DatabaseContext, (CreateUserProps, CreateOptions)> for CreateUser {
+            type Output = String;
+            type Error = ();
+
+            fn execute(
+                context: &mut DatabaseContext,
+                parameters: &(CreateUserProps, CreateOptions),
+            ) -> Result<String, ()> {
+                let (props, options) = parameters;
+                let role = if options.admin { "admin" } else { "user" };
+                context
+                    .cache_mut()
+                    .insert(props.email.clone(), role.to_string());
+                Ok(format!("{}:{}", props.email, role))
+            }
+        }
+
+        let mut executor = ApiExecutor::new(DatabaseContext::new("test".to_string()));
+        let props = CreateUserProps {
+            email: "alice@example.com".to_string(),
+        };
+
+        let created = executor
+            .execute2(CreateUser, &props, &CreateOptions { admin: true })
+            .unwrap();
+
+        assert_eq!(created, "alice@example.com:admin");
+        assert_eq!(
+            executor.context().cache().get("alice@example.com"),
+            Some(&"admin".to_string())
+        );
+    }
+
+    #[test]
+    fn test_result_projection_keeps_only_the_id() {
+        struct User {
+            id: u32,
+            email: String,
+        }
+
+        struct CreateUser;
+
+        impl ApiOperation<DatabaseContext, String> for CreateUser {
+            type Output = User;
+            type Error = ();
+
+            fn execute(context: &mut DatabaseContext, parameters: &String) -> Result<User, ()> {
+                context.increment_transaction();
+                Ok(User {
+                    id: context.transaction_count(),
+                    email: parameters.clone(),
+                })
+            }
+        }
+
+        let mut executor = ApiExecutor::new(DatabaseContext::new("test".to_string()));
+
+        let id = executor
+            .execute_with_result_projection(CreateUser, &"alice@example.com".to_string(), |user| {
+                user.id
+            })
+            .unwrap();
+
+        assert_eq!(id, 1);
+        let email = executor
+            .execute_with_result_projection(CreateUser, &"bob@example.com".to_string(), |user| {
+                user.email
+            })
+            .unwrap();
+        assert_eq!(email, "bob@example.com");
+    }
 }
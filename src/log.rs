@@ -0,0 +1,198 @@
+//! Log sinks for executor diagnostics.
+
+use crate::ApiExecutor;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// The severity of a [`LogRecord`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LogLevel {
+    /// Detailed diagnostics.
+    Debug,
+
+    /// Routine events.
+    Info,
+
+    /// Conditions worth attention that did not cause a failure.
+    Warn,
+
+    /// Failures.
+    Error,
+}
+
+/// A diagnostic message emitted by an executor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogRecord {
+    /// The severity of the message.
+    pub level: LogLevel,
+
+    /// The name of the operation the message concerns.
+    pub operation: &'static str,
+
+    /// The human-readable message.
+    pub message: String,
+}
+
+/// A destination for executor log records.
+///
+/// Implemented for any `Fn(LogRecord)` closure, so forwarding to a logging framework
+/// only takes a closure.
+pub trait Logger: Send + Sync {
+    /// Handles a single record.
+    fn log(&self, record: LogRecord);
+}
+
+impl<F: Fn(LogRecord) + Send + Sync> Logger for F {
+    fn log(&self, record: LogRecord) {
+        self(record)
+    }
+}
+
+/// A logger that keeps records in memory, mainly for tests.
+///
+/// Clones share the same records, so a handle can be kept after installing the logger.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryLogger {
+    /// The records logged so far, shared by every clone.
+    records: Arc<Mutex<Vec<LogRecord>>>,
+}
+
+impl MemoryLogger {
+    /// Creates an empty logger.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a copy of the records logged so far.
+    pub fn records(&self) -> Vec<LogRecord> {
+        self.records
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+}
+
+impl Logger for MemoryLogger {
+    fn log(&self, record: LogRecord) {
+        self.records
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .push(record);
+    }
+}
+
+/// The logger installed on an executor, shared by its clones.
+#[derive(Clone, Default)]
+pub(crate) struct LoggerHandle(Option<Arc<dyn Logger>>);
+
+impl LoggerHandle {
+    /// Sends a record to the installed logger, if any.
+    pub(crate) fn log(&self, level: LogLevel, operation: &'static str, message: String) {
+        if let Some(logger) = &self.0 {
+            logger.log(LogRecord {
+                level,
+                operation,
+                message,
+            });
+        }
+    }
+}
+
+impl fmt::Debug for LoggerHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("LoggerHandle")
+            .field(&self.0.is_some())
+            .finish()
+    }
+}
+
+impl<C> ApiExecutor<C> {
+    /// Installs the logger that receives the executor's diagnostics.
+    pub fn with_logger(mut self, logger: impl Logger + 'static) -> Self {
+        self.logger = LoggerHandle(Some(Arc::new(logger)));
+        self
+    }
+
+    /// Logs a warning whenever an `execute` call takes longer than `threshold`.
+    ///
+    /// Elapsed time is measured with the executor's clock. Only outliers are reported,
+    /// which is cheaper than recording full metrics.
+    pub fn with_slow_threshold(mut self, threshold: Duration) -> Self {
+        self.slow_threshold = Some(threshold);
+        self
+    }
+
+    /// Warns through the logger if `elapsed` exceeds the slow-operation threshold.
+    pub(crate) fn check_slow(&self, operation: &'static str, elapsed: Duration) {
+        if self
+            .slow_threshold
+            .is_some_and(|threshold| elapsed > threshold)
+        {
+            self.logger.log(
+                LogLevel::Warn,
+                operation,
+                format!("slow operation took {:?}", elapsed),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+    use crate::{ApiOperation, MockClock};
+
+    /// Pretends to take the requested number of milliseconds on a mock clock.
+    struct Work;
+
+    impl ApiOperation<(DatabaseContext, MockClock), u64> for Work {
+        type Output = ();
+        type Error = ();
+
+        fn execute(context: &mut (DatabaseContext, MockClock), parameters: &u64) -> Result<(), ()> {
+            context.0.increment_transaction();
+            context.1.advance(Duration::from_millis(*parameters));
+            Ok(())
+        }
+
+        fn name() -> &'static str {
+            "work"
+        }
+    }
+
+    #[test]
+    fn test_only_operations_over_threshold_are_logged() {
+        let clock = MockClock::new();
+        let logger = MemoryLogger::new();
+        let mut executor =
+            ApiExecutor::new((DatabaseContext::new("slow".to_string()), clock.clone()))
+                .with_clock(clock)
+                .with_logger(logger.clone())
+                .with_slow_threshold(Duration::from_millis(100));
+
+        executor.execute(Work, &5).unwrap();
+        assert!(logger.records().is_empty());
+
+        executor.execute(Work, &250).unwrap();
+        let records = logger.records();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].level, LogLevel::Warn);
+        assert_eq!(records[0].operation, "work");
+        assert_eq!(records[0].message, "slow operation took 250ms");
+    }
+
+    #[test]
+    fn test_closures_are_loggers() {
+        let logger = MemoryLogger::new();
+        let forward = logger.clone();
+        let executor = ApiExecutor::new(()).with_logger(move |record| forward.log(record));
+
+        executor
+            .logger
+            .log(LogLevel::Info, "noop", "forwarded".to_string());
+
+        assert_eq!(logger.records()[0].message, "forwarded");
+    }
+}
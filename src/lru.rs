@@ -0,0 +1,191 @@
+//! Bounded least-recently-used caches.
+
+use crate::{ApiExecutor, ApiOperation};
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+
+/// A fixed-capacity map that evicts its least recently used entry when full.
+///
+/// Useful as a context field in long-running services where an unbounded `HashMap`
+/// cache would grow without limit. Both [`get`](Self::get) and
+/// [`insert`](Self::insert) count as a use.
+#[derive(Debug, Clone)]
+pub struct LruCache<K, V> {
+    /// The maximum number of entries.
+    capacity: usize,
+
+    /// Each entry with the tick of its last use.
+    entries: HashMap<K, (V, u64)>,
+
+    /// Keys by the tick of their last use, oldest first.
+    recency: BTreeMap<u64, K>,
+
+    /// Incremented on every use.
+    tick: u64,
+}
+
+impl<K: Hash + Eq + Clone, V> LruCache<K, V> {
+    /// Creates an empty cache holding at most `capacity` entries, and at least one.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            tick: 0,
+        }
+    }
+
+    /// Returns the value for `key`, marking it as the most recently used.
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        let (value, used) = self.entries.get_mut(key)?;
+        self.tick += 1;
+        let key = self
+            .recency
+            .remove(used)
+            .expect("every entry has a recency");
+        *used = self.tick;
+        self.recency.insert(self.tick, key);
+        Some(value)
+    }
+
+    /// Inserts a value as the most recently used, returning the entry evicted to make
+    /// room, if any.
+    pub fn insert(&mut self, key: K, value: V) -> Option<(K, V)> {
+        self.tick += 1;
+        if let Some((_, used)) = self.entries.insert(key.clone(), (value, self.tick)) {
+            self.recency.remove(&used);
+            self.recency.insert(self.tick, key);
+            return None;
+        }
+        self.recency.insert(self.tick, key);
+        if self.entries.len() <= self.capacity {
+            return None;
+        }
+        let (_, oldest) = self
+            .recency
+            .pop_first()
+            .expect("the cache is over capacity");
+        self.entries
+            .remove(&oldest)
+            .map(|(value, _)| (oldest, value))
+    }
+
+    /// Removes and returns the value for `key`.
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let (value, used) = self.entries.remove(key)?;
+        self.recency.remove(&used);
+        Some(value)
+    }
+
+    /// Returns true if `key` is cached, without marking it as used.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    /// Returns the number of cached entries.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if nothing is cached.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the maximum number of entries.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+impl<C> ApiExecutor<C> {
+    /// Executes an operation, reusing outputs cached for equal parameters in a bounded
+    /// LRU cache held by the executor.
+    ///
+    /// Each operation and parameter type gets its own cache, sized by the `capacity`
+    /// passed on first use. Errors are not cached.
+    pub fn execute_with_lru_cache<P, Op>(
+        &mut self,
+        _op: Op,
+        parameters: &P,
+        capacity: usize,
+    ) -> Result<Op::Output, Op::Error>
+    where
+        Op: ApiOperation<C, P> + 'static,
+        Op::Output: Clone + Send + Sync + 'static,
+        P: Hash + Eq + Clone + Send + Sync + 'static,
+    {
+        let cache = self
+            .memo
+            .store::<Op, P, LruCache<P, Op::Output>>(|| LruCache::new(capacity));
+        if let Some(output) = cache.get(parameters) {
+            return Ok(output.clone());
+        }
+
+        let output = self.execute_observed::<P, Op>(parameters)?;
+        self.memo
+            .store::<Op, P, LruCache<P, Op::Output>>(|| LruCache::new(capacity))
+            .insert(parameters.clone(), output.clone());
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+
+    struct LoadProfile;
+
+    impl ApiOperation<DatabaseContext, String> for LoadProfile {
+        type Output = String;
+        type Error = ();
+
+        fn execute(context: &mut DatabaseContext, parameters: &String) -> Result<String, ()> {
+            context.increment_transaction();
+            Ok(format!("profile of {}", parameters))
+        }
+    }
+
+    #[test]
+    fn test_insert_beyond_capacity_evicts_oldest() {
+        let mut cache = LruCache::new(2);
+
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+        let evicted = cache.insert("c", 3);
+
+        assert_eq!(evicted, Some(("a", 1)));
+        assert!(!cache.contains_key(&"a"));
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn test_access_refreshes_recency() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("lru".to_string()));
+        let [alice, bob, carol] = ["alice", "bob", "carol"].map(String::from);
+
+        executor
+            .execute_with_lru_cache(LoadProfile, &alice, 2)
+            .unwrap();
+        executor
+            .execute_with_lru_cache(LoadProfile, &bob, 2)
+            .unwrap();
+        executor
+            .execute_with_lru_cache(LoadProfile, &alice, 2)
+            .unwrap();
+        executor
+            .execute_with_lru_cache(LoadProfile, &carol, 2)
+            .unwrap();
+        assert_eq!(executor.context().transaction_count(), 3);
+
+        let profile = executor.execute_with_lru_cache(LoadProfile, &alice, 2);
+        assert_eq!(profile, Ok("profile of alice".to_string()));
+        assert_eq!(executor.context().transaction_count(), 3);
+
+        executor
+            .execute_with_lru_cache(LoadProfile, &bob, 2)
+            .unwrap();
+        assert_eq!(executor.context().transaction_count(), 4);
+    }
+}
@@ -0,0 +1,393 @@
+//! Memoizing pure operation results on the executor.
+
+use crate::{ApiExecutor, ApiOperation};
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+/// Memoized outputs owned by an executor, grouped by operation and key type.
+///
+/// Cloning an executor does not clone its memoized outputs; the clone starts empty.
+#[derive(Default)]
+pub(crate) struct MemoStore {
+    /// A `HashMap<K, O>` per `(Op, P, K, O)` combination, or another store per
+    /// `(Op, P, T)` combination.
+    tables: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+}
+
+impl MemoStore {
+    /// Returns the table for the given operation, parameter, key and value types.
+    fn table<Op, P, K, O>(&mut self) -> &mut HashMap<K, O>
+    where
+        Op: 'static,
+        P: 'static,
+        K: Send + Sync + 'static,
+        O: Send + Sync + 'static,
+    {
+        self.tables
+            .entry(TypeId::of::<(Op, P, K, O)>())
+            .or_insert_with(|| Box::new(HashMap::<K, O>::new()))
+            .downcast_mut::<HashMap<K, O>>()
+            .expect("memo table type is determined by its key")
+    }
+
+    /// Returns the store for the given operation and parameter types, creating it with
+    /// `init` on first use.
+    pub(crate) fn store<Op, P, T>(&mut self, init: impl FnOnce() -> T) -> &mut T
+    where
+        Op: 'static,
+        P: 'static,
+        T: Send + Sync + 'static,
+    {
+        self.tables
+            .entry(TypeId::of::<(Op, P, T)>())
+            .or_insert_with(|| Box::new(init()))
+            .downcast_mut::<T>()
+            .expect("memo store type is determined by its key")
+    }
+
+    /// Returns the number of memo tables.
+    fn len(&self) -> usize {
+        self.tables.len()
+    }
+}
+
+/// Errors remembered by `execute_negative_cached`, kept apart from memoized outputs
+/// even when the error and output types coincide.
+struct NegativeEntries<P, E>(HashMap<P, (E, Option<Instant>)>);
+
+/// Returns whether an entry expiring at `expires` is still fresh at `now`.
+///
+/// `None` means the expiry lies beyond what [`Instant`] can represent, so the entry
+/// never expires.
+fn is_fresh(now: Instant, expires: Option<Instant>) -> bool {
+    match expires {
+        Some(expires) => now < expires,
+        None => true,
+    }
+}
+
+impl Clone for MemoStore {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl fmt::Debug for MemoStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoStore")
+            .field("tables", &self.len())
+            .finish()
+    }
+}
+
+impl<C> ApiExecutor<C> {
+    /// Executes an operation, reusing a memoized output for parameters with the same key.
+    ///
+    /// `key` must be a pure function of the parameters: any two parameters mapping to the
+    /// same key are assumed to produce the same output regardless of the context. Errors
+    /// are not memoized. Memoized outputs are held by the executor, not the context.
+    pub fn execute_memoized<P, Op, K, F>(
+        &mut self,
+        _op: Op,
+        parameters: &P,
+        key: F,
+    ) -> Result<Op::Output, Op::Error>
+    where
+        Op: ApiOperation<C, P> + 'static,
+        Op::Output: Clone + Send + Sync + 'static,
+        P: 'static,
+        K: Hash + Eq + Send + Sync + 'static,
+        F: FnOnce(&P) -> K,
+    {
+        let key = key(parameters);
+        if let Some(output) = self.memo.table::<Op, P, K, Op::Output>().get(&key) {
+            return Ok(output.clone());
+        }
+
+        let output = self.execute_observed::<P, Op>(parameters)?;
+        self.memo
+            .table::<Op, P, K, Op::Output>()
+            .insert(key, output.clone());
+        Ok(output)
+    }
+
+    /// Like [`execute_memoized`](Self::execute_memoized), but memoized outputs expire
+    /// after `ttl` as measured by the executor's clock.
+    ///
+    /// A `ttl` too large to add to the current instant, such as [`Duration::MAX`], never
+    /// expires.
+    pub fn execute_memoized_for<P, Op, K, F>(
+        &mut self,
+        _op: Op,
+        parameters: &P,
+        key: F,
+        ttl: Duration,
+    ) -> Result<Op::Output, Op::Error>
+    where
+        Op: ApiOperation<C, P> + 'static,
+        Op::Output: Clone + Send + Sync + 'static,
+        P: 'static,
+        K: Hash + Eq + Send + Sync + 'static,
+        F: FnOnce(&P) -> K,
+    {
+        let key = key(parameters);
+        let now = self.clock.now();
+        let table = self.memo.table::<Op, P, K, (Op::Output, Option<Instant>)>();
+        if let Some((output, expires)) = table.get(&key) {
+            if is_fresh(now, *expires) {
+                return Ok(output.clone());
+            }
+            table.remove(&key);
+        }
+
+        let output = self.execute_observed::<P, Op>(parameters)?;
+        self.memo
+            .table::<Op, P, K, (Op::Output, Option<Instant>)>()
+            .insert(key, (output.clone(), now.checked_add(ttl)));
+        Ok(output)
+    }
+
+    /// Executes an operation, remembering errors matching `is_cached` for `ttl` as
+    /// measured by the executor's clock.
+    ///
+    /// While a remembered error is fresh, calls with equal parameters return it without
+    /// running the operation. This spares the backend repeated lookups of entities known
+    /// to be missing. Successes and other errors are never cached. As with
+    /// [`execute_memoized_for`](Self::execute_memoized_for), a `ttl` too large to add to
+    /// the current instant never expires.
+    pub fn execute_negative_cached<P, Op, F>(
+        &mut self,
+        _op: Op,
+        parameters: &P,
+        ttl: Duration,
+        is_cached: F,
+    ) -> Result<Op::Output, Op::Error>
+    where
+        Op: ApiOperation<C, P> + 'static,
+        Op::Error: Clone + Send + Sync + 'static,
+        P: Hash + Eq + Clone + Send + Sync + 'static,
+        F: FnOnce(&Op::Error) -> bool,
+    {
+        let now = self.clock.now();
+        let table = &mut self
+            .memo
+            .store::<Op, P, NegativeEntries<P, Op::Error>>(|| NegativeEntries(HashMap::new()))
+            .0;
+        if let Some((error, expires)) = table.get(parameters) {
+            if is_fresh(now, *expires) {
+                return Err(error.clone());
+            }
+            table.remove(parameters);
+        }
+
+        let result = self.execute_observed::<P, Op>(parameters);
+        if let Err(error) = &result {
+            if is_cached(error) {
+                self.memo
+                    .store::<Op, P, NegativeEntries<P, Op::Error>>(|| {
+                        NegativeEntries(HashMap::new())
+                    })
+                    .0
+                    .insert(parameters.clone(), (error.clone(), now.checked_add(ttl)));
+            }
+        }
+        result
+    }
+
+    /// Discards every memoized output held by this executor.
+    pub fn clear_memo(&mut self) {
+        self.memo = MemoStore::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+
+    /// Normalizes a name, counting each computation as a transaction.
+    struct Normalize;
+
+    impl ApiOperation<DatabaseContext, String> for Normalize {
+        type Output = String;
+        type Error = ();
+
+        fn execute(context: &mut DatabaseContext, parameters: &String) -> Result<String, ()> {
+            context.increment_transaction();
+            Ok(parameters.trim().to_lowercase())
+        }
+    }
+
+    #[test]
+    fn test_memoized_output_is_reused_for_colliding_keys() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("memo".to_string()));
+        let key = |name: &String| name.trim().to_lowercase();
+
+        let first = executor
+            .execute_memoized(Normalize, &"  Alice ".to_string(), key)
+            .unwrap();
+        let second = executor
+            .execute_memoized(Normalize, &"ALICE".to_string(), key)
+            .unwrap();
+
+        assert_eq!(first, "alice");
+        assert_eq!(second, "alice");
+        assert_eq!(executor.context().transaction_count(), 1);
+
+        executor
+            .execute_memoized(Normalize, &"Bob".to_string(), key)
+            .unwrap();
+        assert_eq!(executor.context().transaction_count(), 2);
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum FindError {
+        NotFound,
+        Unavailable,
+    }
+
+    /// Looks up a user, counting each attempt as a transaction.
+    struct FindUser;
+
+    impl ApiOperation<DatabaseContext, String> for FindUser {
+        type Output = String;
+        type Error = FindError;
+
+        fn execute(
+            context: &mut DatabaseContext,
+            parameters: &String,
+        ) -> Result<String, FindError> {
+            context.increment_transaction();
+            if parameters.is_empty() {
+                return Err(FindError::Unavailable);
+            }
+            context
+                .cache()
+                .get(parameters)
+                .cloned()
+                .ok_or(FindError::NotFound)
+        }
+    }
+
+    #[test]
+    fn test_not_found_is_cached_until_ttl_expires() {
+        let clock = crate::MockClock::new();
+        let mut executor =
+            ApiExecutor::new(DatabaseContext::new("memo".to_string())).with_clock(clock.clone());
+        let ttl = Duration::from_secs(30);
+        let not_found = |error: &FindError| *error == FindError::NotFound;
+
+        for _ in 0..2 {
+            let result =
+                executor.execute_negative_cached(FindUser, &"ghost".to_string(), ttl, not_found);
+            assert_eq!(result, Err(FindError::NotFound));
+        }
+        assert_eq!(executor.context().transaction_count(), 1);
+
+        clock.advance(ttl);
+        executor
+            .execute_negative_cached(FindUser, &"ghost".to_string(), ttl, not_found)
+            .unwrap_err();
+        assert_eq!(executor.context().transaction_count(), 2);
+
+        for _ in 0..2 {
+            executor
+                .execute_negative_cached(FindUser, &String::new(), ttl, not_found)
+                .unwrap_err();
+        }
+        assert_eq!(executor.context().transaction_count(), 4);
+    }
+
+    #[test]
+    fn test_memoized_output_with_unbounded_ttl_never_expires() {
+        let clock = crate::MockClock::new();
+        let mut executor =
+            ApiExecutor::new(DatabaseContext::new("memo".to_string())).with_clock(clock.clone());
+
+        for _ in 0..2 {
+            let output = executor
+                .execute_memoized_for(
+                    Normalize,
+                    &"Alice".to_string(),
+                    String::clone,
+                    Duration::MAX,
+                )
+                .unwrap();
+            assert_eq!(output, "alice");
+            clock.advance(Duration::from_secs(365 * 24 * 60 * 60));
+        }
+
+        assert_eq!(executor.context().transaction_count(), 1);
+    }
+
+    #[test]
+    fn test_not_found_with_unbounded_ttl_stays_cached() {
+        let clock = crate::MockClock::new();
+        let mut executor =
+            ApiExecutor::new(DatabaseContext::new("memo".to_string())).with_clock(clock.clone());
+        let not_found = |error: &FindError| *error == FindError::NotFound;
+
+        for _ in 0..2 {
+            let result = executor.execute_negative_cached(
+                FindUser,
+                &"ghost".to_string(),
+                Duration::MAX,
+                not_found,
+            );
+            assert_eq!(result, Err(FindError::NotFound));
+            clock.advance(Duration::from_secs(365 * 24 * 60 * 60));
+        }
+
+        assert_eq!(executor.context().transaction_count(), 1);
+    }
+
+    #[test]
+    fn test_clear_memo_forces_recomputation() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("memo".to_string()));
+
+        executor
+            .execute_memoized(Normalize, &"Alice".to_string(), String::clone)
+            .unwrap();
+        executor.clear_memo();
+        executor
+            .execute_memoized(Normalize, &"Alice".to_string(), String::clone)
+            .unwrap();
+
+        assert_eq!(executor.context().transaction_count(), 2);
+    }
+
+    /// Echoes a lookup as either output or error, both strings.
+    struct Lookup;
+
+    impl ApiOperation<DatabaseContext, String> for Lookup {
+        type Output = String;
+        type Error = String;
+
+        fn execute(context: &mut DatabaseContext, parameters: &String) -> Result<String, String> {
+            context.increment_transaction();
+            if parameters.is_empty() {
+                Err("missing".to_string())
+            } else {
+                Ok(format!("found {}", parameters))
+            }
+        }
+    }
+
+    #[test]
+    fn test_negative_cache_ignores_memoized_outputs() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("memo".to_string()));
+        let ttl = Duration::from_secs(30);
+        let alice = "alice".to_string();
+
+        executor
+            .execute_memoized_for(Lookup, &alice, String::clone, ttl)
+            .unwrap();
+        let result = executor.execute_negative_cached(Lookup, &alice, ttl, |_| true);
+
+        assert_eq!(result, Ok("found alice".to_string()));
+        assert_eq!(executor.context().transaction_count(), 2);
+    }
+}
@@ -0,0 +1,198 @@
+//! Per-operation success and failure counters with Prometheus export.
+
+use crate::{ApiExecutor, ApiOperation};
+use std::fmt::Write;
+
+/// Execution counts recorded for one operation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OperationMetrics {
+    /// The number of executions that succeeded.
+    pub successes: u64,
+
+    /// The number of executions that failed.
+    pub failures: u64,
+}
+
+/// Escapes a Prometheus label value.
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+impl<C> ApiExecutor<C> {
+    /// Executes an operation, counting its outcome and recording its latency histogram
+    /// for [`metrics_prometheus`](Self::metrics_prometheus).
+    pub fn execute_with_metrics_export<P, Op>(
+        &mut self,
+        op: Op,
+        parameters: &P,
+    ) -> Result<Op::Output, Op::Error>
+    where
+        Op: ApiOperation<C, P>,
+    {
+        let result = self.execute_collecting_latency_histogram(op, parameters);
+        let metrics = self.metrics.entry(Op::name()).or_default();
+        if result.is_ok() {
+            metrics.successes += 1;
+        } else {
+            metrics.failures += 1;
+        }
+        result
+    }
+
+    /// Returns the execution counts recorded for the operation named `operation`.
+    pub fn operation_metrics(&self, operation: &str) -> Option<OperationMetrics> {
+        self.metrics.get(operation).copied()
+    }
+
+    /// Renders the recorded counters and latency histograms in the Prometheus text
+    /// exposition format, ready to serve from a scrape endpoint.
+    ///
+    /// Counters come from `execute_with_metrics_export`; histograms cover every
+    /// operation with a latency histogram, however it was recorded. Operations are
+    /// listed by name.
+    pub fn metrics_prometheus(&self) -> String {
+        let mut output = String::new();
+
+        let mut counters: Vec<_> = self.metrics.iter().collect();
+        counters.sort_by_key(|(name, _)| **name);
+        output.push_str("# HELP apithing_operations_total Operations executed, by outcome.\n");
+        output.push_str("# TYPE apithing_operations_total counter\n");
+        for (name, metrics) in counters {
+            let name = escape_label(name);
+            for (outcome, count) in [
+                ("success", metrics.successes),
+                ("failure", metrics.failures),
+            ] {
+                let _ = writeln!(
+                    output,
+                    "apithing_operations_total{{operation=\"{}\",outcome=\"{}\"}} {}",
+                    name, outcome, count
+                );
+            }
+        }
+
+        let mut histograms: Vec<_> = self.histograms.iter().collect();
+        histograms.sort_by_key(|(name, _)| **name);
+        output
+            .push_str("# HELP apithing_operation_duration_seconds Operation latency in seconds.\n");
+        output.push_str("# TYPE apithing_operation_duration_seconds histogram\n");
+        for (name, histogram) in histograms {
+            let name = escape_label(name);
+            for (bound, count) in histogram.cumulative_buckets() {
+                let _ = writeln!(
+                    output,
+                    "apithing_operation_duration_seconds_bucket{{operation=\"{}\",le=\"{}\"}} {}",
+                    name,
+                    bound.as_secs_f64(),
+                    count
+                );
+            }
+            let _ = writeln!(
+                output,
+                "apithing_operation_duration_seconds_bucket{{operation=\"{}\",le=\"+Inf\"}} {}",
+                name,
+                histogram.count()
+            );
+            let _ = writeln!(
+                output,
+                "apithing_operation_duration_seconds_sum{{operation=\"{}\"}} {}",
+                name,
+                histogram.sum().as_secs_f64()
+            );
+            let _ = writeln!(
+                output,
+                "apithing_operation_duration_seconds_count{{operation=\"{}\"}} {}",
+                name,
+                histogram.count()
+            );
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+
+    struct CreateUser;
+    struct DeleteUser;
+
+    impl ApiOperation<DatabaseContext, String> for CreateUser {
+        type Output = ();
+        type Error = ();
+
+        fn execute(context: &mut DatabaseContext, parameters: &String) -> Result<(), ()> {
+            context
+                .cache_mut()
+                .insert(parameters.clone(), "active".to_string());
+            Ok(())
+        }
+
+        fn name() -> &'static str {
+            "create_user"
+        }
+    }
+
+    impl ApiOperation<DatabaseContext, String> for DeleteUser {
+        type Output = ();
+        type Error = ();
+
+        fn execute(context: &mut DatabaseContext, parameters: &String) -> Result<(), ()> {
+            context.cache_mut().remove(parameters).map(drop).ok_or(())
+        }
+
+        fn name() -> &'static str {
+            "delete_user"
+        }
+    }
+
+    #[test]
+    fn test_prometheus_export_lists_counters_and_histograms() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("metrics".to_string()));
+        let alice = "alice".to_string();
+
+        executor
+            .execute_with_metrics_export(CreateUser, &alice)
+            .unwrap();
+        executor
+            .execute_with_metrics_export(DeleteUser, &alice)
+            .unwrap();
+        executor
+            .execute_with_metrics_export(DeleteUser, &alice)
+            .unwrap_err();
+
+        assert_eq!(
+            executor.operation_metrics("delete_user"),
+            Some(OperationMetrics {
+                successes: 1,
+                failures: 1
+            })
+        );
+
+        let exported = executor.metrics_prometheus();
+        for line in [
+            "# TYPE apithing_operations_total counter",
+            "apithing_operations_total{operation=\"create_user\",outcome=\"success\"} 1",
+            "apithing_operations_total{operation=\"create_user\",outcome=\"failure\"} 0",
+            "apithing_operations_total{operation=\"delete_user\",outcome=\"success\"} 1",
+            "apithing_operations_total{operation=\"delete_user\",outcome=\"failure\"} 1",
+            "# TYPE apithing_operation_duration_seconds histogram",
+            "apithing_operation_duration_seconds_bucket{operation=\"create_user\",le=\"+Inf\"} 1",
+            "apithing_operation_duration_seconds_count{operation=\"delete_user\"} 2",
+        ] {
+            assert!(
+                exported.lines().any(|exported| exported == line),
+                "missing `{}` in:\n{}",
+                line,
+                exported
+            );
+        }
+        assert!(exported.contains(
+            "apithing_operation_duration_seconds_bucket{operation=\"delete_user\",le=\"0.000001\"}"
+        ));
+    }
+}
@@ -0,0 +1,216 @@
+//! Middleware that wraps operation execution and can rewrite results.
+
+use crate::{ApiExecutor, ApiOperation};
+use std::fmt;
+
+/// Wraps the execution of operations sharing parameter, output and error types.
+///
+/// A middleware decides whether and how to call the rest of the chain through
+/// [`Next::run`], and can inspect or transform the `Result` it returns. This enables
+/// response caching, output sanitization and error enrichment.
+pub trait Middleware<C, P, O, E> {
+    /// Runs around the remaining middleware and the operation.
+    fn around(&self, context: &mut C, parameters: &P, next: Next<'_, C, P, O, E>) -> Result<O, E>;
+}
+
+impl<C, P, O, E, F> Middleware<C, P, O, E> for F
+where
+    F: Fn(&mut C, &P, Next<'_, C, P, O, E>) -> Result<O, E>,
+{
+    fn around(&self, context: &mut C, parameters: &P, next: Next<'_, C, P, O, E>) -> Result<O, E> {
+        self(context, parameters, next)
+    }
+}
+
+/// The remainder of a middleware chain, ending in the operation itself.
+pub struct Next<'a, C, P, O, E> {
+    /// The middleware still to run, outermost first.
+    layers: &'a [Box<dyn Middleware<C, P, O, E>>],
+
+    /// The operation at the end of the chain.
+    operation: fn(&mut C, &P) -> Result<O, E>,
+}
+
+impl<C, P, O, E> Next<'_, C, P, O, E> {
+    /// Runs the rest of the chain and returns its result.
+    pub fn run(self, context: &mut C, parameters: &P) -> Result<O, E> {
+        match self.layers.split_first() {
+            Some((layer, layers)) => layer.around(
+                context,
+                parameters,
+                Next {
+                    layers,
+                    operation: self.operation,
+                },
+            ),
+            None => (self.operation)(context, parameters),
+        }
+    }
+}
+
+/// An ordered stack of middleware applied around operations.
+///
+/// The first layer added is the outermost: it runs first and sees the result last,
+/// after every inner layer has transformed it.
+pub struct MiddlewareStack<C, P, O, E> {
+    /// The layers, outermost first.
+    layers: Vec<Box<dyn Middleware<C, P, O, E>>>,
+}
+
+impl<C, P, O, E> MiddlewareStack<C, P, O, E> {
+    /// Creates an empty stack.
+    pub fn new() -> Self {
+        Self { layers: Vec::new() }
+    }
+
+    /// Adds a layer inside every layer added so far.
+    pub fn layer(mut self, middleware: impl Middleware<C, P, O, E> + 'static) -> Self {
+        self.layers.push(Box::new(middleware));
+        self
+    }
+
+    /// Returns the number of layers in the stack.
+    pub fn len(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// Returns true if the stack has no layers.
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    /// Runs `Op` through every layer of the stack.
+    pub(crate) fn run<Op>(&self, context: &mut C, parameters: &P) -> Result<O, E>
+    where
+        Op: ApiOperation<C, P, Output = O, Error = E>,
+    {
+        Next {
+            layers: &self.layers,
+            operation: Op::execute,
+        }
+        .run(context, parameters)
+    }
+}
+
+impl<C, P, O, E> Default for MiddlewareStack<C, P, O, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C, P, O, E> fmt::Debug for MiddlewareStack<C, P, O, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MiddlewareStack")
+            .field("layers", &self.layers.len())
+            .finish()
+    }
+}
+
+impl<C> ApiExecutor<C> {
+    /// Executes an operation wrapped by every layer of `stack`.
+    pub fn execute_with_middleware<P, Op>(
+        &mut self,
+        _op: Op,
+        parameters: &P,
+        stack: &MiddlewareStack<C, P, Op::Output, Op::Error>,
+    ) -> Result<Op::Output, Op::Error>
+    where
+        Op: ApiOperation<C, P>,
+    {
+        let started = self.clock.now();
+        let result = stack.run::<Op>(&mut self.context, parameters);
+        self.observe(Op::name(), started, result.is_ok());
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+
+    #[derive(Debug, PartialEq)]
+    enum LookupError {
+        NotFound,
+        Enriched(String),
+    }
+
+    struct Lookup;
+
+    impl ApiOperation<DatabaseContext, String> for Lookup {
+        type Output = String;
+        type Error = LookupError;
+
+        fn execute(
+            context: &mut DatabaseContext,
+            parameters: &String,
+        ) -> Result<String, LookupError> {
+            context
+                .cache()
+                .get(parameters)
+                .cloned()
+                .ok_or(LookupError::NotFound)
+        }
+    }
+
+    type LookupStack = MiddlewareStack<DatabaseContext, String, String, LookupError>;
+
+    fn suffix(tag: &'static str) -> impl Middleware<DatabaseContext, String, String, LookupError> {
+        move |context: &mut DatabaseContext,
+              parameters: &String,
+              next: Next<'_, DatabaseContext, String, String, LookupError>| {
+            next.run(context, parameters)
+                .map(|output| format!("{}[{}]", output, tag))
+        }
+    }
+
+    fn enrich_not_found(
+        context: &mut DatabaseContext,
+        parameters: &String,
+        next: Next<'_, DatabaseContext, String, String, LookupError>,
+    ) -> Result<String, LookupError> {
+        next.run(context, parameters).map_err(|error| match error {
+            LookupError::NotFound => LookupError::Enriched(format!("missing key {}", parameters)),
+            other => other,
+        })
+    }
+
+    fn executor() -> ApiExecutor<DatabaseContext> {
+        let mut context = DatabaseContext::new("middleware".to_string());
+        context
+            .cache_mut()
+            .insert("user_1".to_string(), "alice".to_string());
+        ApiExecutor::new(context)
+    }
+
+    #[test]
+    fn test_outer_layer_sees_inner_transformations() {
+        let stack = LookupStack::new()
+            .layer(suffix("outer"))
+            .layer(suffix("inner"));
+        let mut executor = executor();
+
+        let output = executor
+            .execute_with_middleware(Lookup, &"user_1".to_string(), &stack)
+            .unwrap();
+
+        assert_eq!(output, "alice[inner][outer]");
+    }
+
+    #[test]
+    fn test_error_mapping_layer_rewrites_specific_variant() {
+        let stack = LookupStack::new()
+            .layer(suffix("outer"))
+            .layer(enrich_not_found);
+        let mut executor = executor();
+
+        let found = executor.execute_with_middleware(Lookup, &"user_1".to_string(), &stack);
+        let missing = executor.execute_with_middleware(Lookup, &"user_2".to_string(), &stack);
+
+        assert_eq!(found, Ok("alice[outer]".to_string()));
+        assert_eq!(
+            missing,
+            Err(LookupError::Enriched("missing key user_2".to_string()))
+        );
+    }
+}
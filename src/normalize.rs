@@ -0,0 +1,86 @@
+//! Cleaning up parameters before operations see them.
+
+use crate::{ApiExecutor, ApiOperation};
+
+/// Parameters that can be put into a canonical form, such as trimmed strings or
+/// lowercased email addresses.
+pub trait Normalize {
+    /// Rewrites the parameters in place into their canonical form.
+    fn normalize(&mut self);
+}
+
+impl Normalize for String {
+    /// Trims leading and trailing whitespace.
+    fn normalize(&mut self) {
+        let trimmed = self.trim();
+        if trimmed.len() != self.len() {
+            *self = trimmed.to_string();
+        }
+    }
+}
+
+impl<C> ApiExecutor<C> {
+    /// Executes an operation with a normalized copy of `parameters`.
+    ///
+    /// Centralizes input cleanup so operation bodies can assume canonical input; the
+    /// caller's parameters are left unchanged.
+    pub fn execute_with_input_normalization<P, Op>(
+        &mut self,
+        op: Op,
+        parameters: &P,
+    ) -> Result<Op::Output, Op::Error>
+    where
+        Op: ApiOperation<C, P>,
+        P: Normalize + Clone,
+    {
+        let mut normalized = parameters.clone();
+        normalized.normalize();
+        self.execute(op, &normalized)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+
+    #[derive(Clone)]
+    struct SignUp {
+        email: String,
+    }
+
+    impl Normalize for SignUp {
+        fn normalize(&mut self) {
+            self.email.normalize();
+            self.email.make_ascii_lowercase();
+        }
+    }
+
+    struct RegisterEmail;
+
+    impl ApiOperation<DatabaseContext, SignUp> for RegisterEmail {
+        type Output = String;
+        type Error = ();
+
+        fn execute(context: &mut DatabaseContext, parameters: &SignUp) -> Result<String, ()> {
+            context
+                .cache_mut()
+                .insert(parameters.email.clone(), "registered".to_string());
+            Ok(parameters.email.clone())
+        }
+    }
+
+    #[test]
+    fn test_email_is_normalized_before_operation_runs() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("normalize".to_string()));
+        let parameters = SignUp {
+            email: "  Alice@Example.COM\n".to_string(),
+        };
+
+        let email = executor.execute_with_input_normalization(RegisterEmail, &parameters);
+
+        assert_eq!(email, Ok("alice@example.com".to_string()));
+        assert!(executor.context().cache().contains_key("alice@example.com"));
+        assert_eq!(parameters.email, "  Alice@Example.COM\n");
+    }
+}
@@ -0,0 +1,102 @@
+//! Notifying downstream workers about executed operations.
+
+use crate::ApiExecutor;
+use std::sync::mpsc::Sender;
+
+/// A message describing the outcome of a single `execute` call.
+///
+/// Only the operation name and success flag are sent, so operations need not have
+/// `Send` or `Clone` outputs to be observed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OperationOutcome {
+    /// The name of the executed operation.
+    pub operation: &'static str,
+
+    /// Whether the operation succeeded.
+    pub success: bool,
+}
+
+impl<C> ApiExecutor<C> {
+    /// Sends an [`OperationOutcome`] to `sender` after every `execute` call.
+    ///
+    /// Messages are dropped silently once the receiving end has hung up.
+    pub fn with_notifier(mut self, sender: Sender<OperationOutcome>) -> Self {
+        self.notifier = Some(sender);
+        self
+    }
+
+    /// Sends an outcome to the installed notifier, if any.
+    pub(crate) fn notify(&self, operation: &'static str, success: bool) {
+        if let Some(notifier) = &self.notifier {
+            let _ = notifier.send(OperationOutcome { operation, success });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+    use crate::ApiOperation;
+    use std::sync::mpsc;
+
+    struct CreateUser;
+
+    impl ApiOperation<DatabaseContext, String> for CreateUser {
+        type Output = u32;
+        type Error = ();
+
+        fn execute(context: &mut DatabaseContext, parameters: &String) -> Result<u32, ()> {
+            if !parameters.contains('@') {
+                return Err(());
+            }
+            context.increment_transaction();
+            Ok(context.transaction_count())
+        }
+
+        fn name() -> &'static str {
+            "create_user"
+        }
+    }
+
+    #[test]
+    fn test_each_execution_sends_an_outcome() {
+        let (sender, receiver) = mpsc::channel();
+        let mut executor =
+            ApiExecutor::new(DatabaseContext::new("notify".to_string())).with_notifier(sender);
+
+        executor
+            .execute(CreateUser, &"alice@example.com".to_string())
+            .unwrap();
+        executor
+            .execute(CreateUser, &"invalid".to_string())
+            .unwrap_err();
+
+        let outcomes: Vec<_> = receiver.try_iter().collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                OperationOutcome {
+                    operation: "create_user",
+                    success: true
+                },
+                OperationOutcome {
+                    operation: "create_user",
+                    success: false
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dropped_receiver_does_not_fail_execution() {
+        let (sender, receiver) = mpsc::channel();
+        let mut executor =
+            ApiExecutor::new(DatabaseContext::new("notify".to_string())).with_notifier(sender);
+        drop(receiver);
+
+        let result = executor.execute(CreateUser, &"bob@example.com".to_string());
+
+        assert_eq!(result, Ok(1));
+    }
+}
@@ -0,0 +1,164 @@
+//! Rejecting operations whose serialized output exceeds a byte limit.
+
+use crate::{ApiExecutor, ApiOperation};
+use serde::Serialize;
+use std::fmt;
+use std::io;
+
+/// An error from [`ApiExecutor::execute_with_output_size_limit`].
+#[derive(Debug)]
+pub enum OutputLimitError<E> {
+    /// The output serialized to more bytes than the configured limit.
+    OutputTooLarge {
+        /// The name of the operation that produced the output.
+        operation: &'static str,
+
+        /// The serialized size of the output, in bytes.
+        size: usize,
+
+        /// The configured limit, in bytes.
+        limit: usize,
+    },
+
+    /// The output could not be serialized to measure its size.
+    Encode(serde_json::Error),
+
+    /// The operation itself failed.
+    Operation(E),
+}
+
+impl<E: fmt::Display> fmt::Display for OutputLimitError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputLimitError::OutputTooLarge {
+                operation,
+                size,
+                limit,
+            } => write!(
+                f,
+                "output of operation '{}' is {} bytes, over the limit of {} bytes",
+                operation, size, limit
+            ),
+            OutputLimitError::Encode(error) => write!(f, "failed to encode output: {}", error),
+            OutputLimitError::Operation(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for OutputLimitError<E> {}
+
+/// A writer that discards its input, counting the bytes written.
+#[derive(Default)]
+struct ByteCounter {
+    /// The number of bytes written so far.
+    count: usize,
+}
+
+impl io::Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.count += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<C> ApiExecutor<C> {
+    /// Rejects outputs of `execute_with_output_size_limit` whose JSON encoding is
+    /// larger than `bytes`, and responses of `execute_with_input_size_guard` whose
+    /// encoded body is.
+    pub fn with_output_limit(mut self, bytes: usize) -> Self {
+        self.output_limit = Some(bytes);
+        self
+    }
+
+    /// Executes an operation and checks the size of its output as JSON against the
+    /// limit set with [`with_output_limit`](Self::with_output_limit).
+    ///
+    /// The size is measured without buffering the encoded output. Without a limit,
+    /// every output passes.
+    pub fn execute_with_output_size_limit<P, Op>(
+        &mut self,
+        op: Op,
+        parameters: &P,
+    ) -> Result<Op::Output, OutputLimitError<Op::Error>>
+    where
+        Op: ApiOperation<C, P>,
+        Op::Output: Serialize,
+    {
+        let output = self
+            .execute(op, parameters)
+            .map_err(OutputLimitError::Operation)?;
+        if let Some(limit) = self.output_limit {
+            let mut counter = ByteCounter::default();
+            serde_json::to_writer(&mut counter, &output).map_err(OutputLimitError::Encode)?;
+            if counter.count > limit {
+                return Err(OutputLimitError::OutputTooLarge {
+                    operation: Op::name(),
+                    size: counter.count,
+                    limit,
+                });
+            }
+        }
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+
+    /// Returns a string of the requested length.
+    struct Generate;
+
+    impl ApiOperation<DatabaseContext, usize> for Generate {
+        type Output = String;
+        type Error = ();
+
+        fn execute(_context: &mut DatabaseContext, parameters: &usize) -> Result<String, ()> {
+            Ok("x".repeat(*parameters))
+        }
+
+        fn name() -> &'static str {
+            "generate"
+        }
+    }
+
+    #[test]
+    fn test_output_over_limit_is_rejected() {
+        let mut executor =
+            ApiExecutor::new(DatabaseContext::new("limit".to_string())).with_output_limit(16);
+
+        let error = executor
+            .execute_with_output_size_limit(Generate, &100)
+            .unwrap_err();
+
+        match error {
+            OutputLimitError::OutputTooLarge {
+                operation,
+                size,
+                limit,
+            } => {
+                assert_eq!(operation, "generate");
+                assert_eq!(size, 102);
+                assert_eq!(limit, 16);
+            }
+            other => panic!("expected OutputTooLarge, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_output_under_limit_passes() {
+        let mut executor =
+            ApiExecutor::new(DatabaseContext::new("limit".to_string())).with_output_limit(16);
+
+        let output = executor
+            .execute_with_output_size_limit(Generate, &10)
+            .unwrap();
+
+        assert_eq!(output.len(), 10);
+    }
+}
@@ -0,0 +1,147 @@
+//! Swapping the implementation behind a logical operation at runtime.
+//!
+//! Overrides are looked up by `TypeId`, which needs `'static` operation, parameter,
+//! output and error types. [`ApiExecutor::execute`] accepts borrowed types and
+//! closures, so it does not consult overrides; call sites that should be switchable
+//! use [`ApiExecutor::execute_with_local_override`] instead.
+
+use crate::{ApiExecutor, ApiOperation};
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// The entry point of an operation implementation.
+type Implementation<C, P, O, E> = fn(&mut C, &P) -> Result<O, E>;
+
+/// The implementations registered in place of logical operations.
+///
+/// Clones of an executor share the overrides registered before cloning.
+#[derive(Clone, Default)]
+pub(crate) struct OverrideMap {
+    /// An [`Implementation`] per `(LogicalOp, P)` combination.
+    overrides: HashMap<TypeId, Arc<dyn Any + Send + Sync>>,
+}
+
+impl fmt::Debug for OverrideMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OverrideMap")
+            .field("overrides", &self.overrides.len())
+            .finish()
+    }
+}
+
+impl<C: 'static> ApiExecutor<C> {
+    /// Runs `Impl` whenever `Logical` is executed through
+    /// [`execute_with_local_override`](Self::execute_with_local_override) with
+    /// parameters of type `P`.
+    ///
+    /// Both operations must share parameter, output and error types, so call sites are
+    /// unaffected. Registering again replaces the previous override. Plain
+    /// [`execute`](Self::execute) always runs `Logical` itself.
+    pub fn register_override<P, Logical, Impl>(&mut self)
+    where
+        P: 'static,
+        Logical: ApiOperation<C, P> + 'static,
+        Impl: ApiOperation<C, P, Output = Logical::Output, Error = Logical::Error>,
+        Logical::Output: 'static,
+        Logical::Error: 'static,
+    {
+        let run: Implementation<C, P, Logical::Output, Logical::Error> = Impl::execute;
+        self.overrides
+            .overrides
+            .insert(TypeId::of::<(Logical, P)>(), Arc::new(run));
+    }
+
+    /// Removes the override of `Logical` for parameters of type `P`, returning whether
+    /// one was registered.
+    pub fn remove_override<P, Logical>(&mut self) -> bool
+    where
+        P: 'static,
+        Logical: 'static,
+    {
+        self.overrides
+            .overrides
+            .remove(&TypeId::of::<(Logical, P)>())
+            .is_some()
+    }
+
+    /// Executes the implementation registered for `op`, or `op` itself if it has no
+    /// override.
+    pub fn execute_with_local_override<P, Op>(
+        &mut self,
+        op: Op,
+        parameters: &P,
+    ) -> Result<Op::Output, Op::Error>
+    where
+        P: 'static,
+        Op: ApiOperation<C, P> + 'static,
+        Op::Output: 'static,
+        Op::Error: 'static,
+    {
+        let run = self
+            .overrides
+            .overrides
+            .get(&TypeId::of::<(Op, P)>())
+            .and_then(|run| run.downcast_ref::<Implementation<C, P, Op::Output, Op::Error>>())
+            .copied();
+        match run {
+            Some(run) => {
+                let started = self.clock.now();
+                let result = run(&mut self.context, parameters);
+                self.observe(Op::name(), started, result.is_ok());
+                result
+            }
+            None => self.execute(op, parameters),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+
+    struct RankResults;
+    struct RankResultsV2;
+
+    impl ApiOperation<DatabaseContext, Vec<u32>> for RankResults {
+        type Output = Vec<u32>;
+        type Error = ();
+
+        fn execute(_context: &mut DatabaseContext, parameters: &Vec<u32>) -> Result<Vec<u32>, ()> {
+            let mut ranked = parameters.clone();
+            ranked.sort_unstable();
+            Ok(ranked)
+        }
+    }
+
+    impl ApiOperation<DatabaseContext, Vec<u32>> for RankResultsV2 {
+        type Output = Vec<u32>;
+        type Error = ();
+
+        fn execute(context: &mut DatabaseContext, parameters: &Vec<u32>) -> Result<Vec<u32>, ()> {
+            context.increment_transaction();
+            let mut ranked = parameters.clone();
+            ranked.sort_unstable_by(|a, b| b.cmp(a));
+            Ok(ranked)
+        }
+    }
+
+    #[test]
+    fn test_override_replaces_logical_operation() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("override".to_string()));
+        let scores = vec![2, 9, 4];
+
+        let before = executor.execute_with_local_override(RankResults, &scores);
+        executor.register_override::<Vec<u32>, RankResults, RankResultsV2>();
+        let during = executor.execute_with_local_override(RankResults, &scores);
+        assert!(executor.remove_override::<Vec<u32>, RankResults>());
+        let after = executor.execute_with_local_override(RankResults, &scores);
+
+        assert_eq!(before, Ok(vec![2, 4, 9]));
+        assert_eq!(during, Ok(vec![9, 4, 2]));
+        assert_eq!(after, Ok(vec![2, 4, 9]));
+        assert_eq!(executor.context().transaction_count(), 1);
+    }
+}
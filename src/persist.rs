@@ -0,0 +1,122 @@
+//! Saving and restoring an executor's state for long-running jobs.
+
+use crate::ApiExecutor;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// The persisted form of an executor.
+#[derive(Serialize, Deserialize)]
+struct SavedState<C> {
+    /// The executor's context.
+    context: C,
+
+    /// The slow-operation warning threshold.
+    slow_threshold: Option<Duration>,
+
+    /// The fraction of sampled executions that are traced.
+    trace_sample_rate: Option<f64>,
+}
+
+impl<C: Serialize> ApiExecutor<C> {
+    /// Serializes the context and the executor's plain settings so a crashed job can
+    /// resume with [`restore_state`](Self::restore_state).
+    ///
+    /// Installed components that cannot be serialized, such as the clock, logger,
+    /// random number generator, notifier, transformers, rate limiter and circuit
+    /// breakers, are not saved. Caches, memoized outputs, checkpoints and the audit log
+    /// are not saved either, nor are the outcome counters from
+    /// [`execute_with_metrics_export`](Self::execute_with_metrics_export) and the
+    /// latency histograms from
+    /// [`execute_collecting_latency_histogram`](Self::execute_collecting_latency_histogram):
+    /// a restored executor starts its diagnostics from zero.
+    pub fn save_state(&self) -> Result<Vec<u8>, serde_json::Error> {
+        serde_json::to_vec(&SavedState {
+            context: &self.context,
+            slow_threshold: self.slow_threshold,
+            trace_sample_rate: self.trace_sample_rate,
+        })
+    }
+}
+
+impl<C: DeserializeOwned> ApiExecutor<C> {
+    /// Rebuilds an executor from bytes produced by [`save_state`](Self::save_state).
+    ///
+    /// Components that were not saved start at their defaults; reinstall them with the
+    /// usual `with_*` builder methods on the returned executor.
+    pub fn restore_state(bytes: &[u8]) -> Result<Self, serde_json::Error> {
+        let saved: SavedState<C> = serde_json::from_slice(bytes)?;
+        let mut executor = ApiExecutor::new(saved.context);
+        executor.slow_threshold = saved.slow_threshold;
+        executor.trace_sample_rate = saved.trace_sample_rate;
+        Ok(executor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ApiOperation;
+
+    #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+    struct JobContext {
+        processed: Vec<u32>,
+        total: u64,
+    }
+
+    struct ProcessItem;
+
+    impl ApiOperation<JobContext, u32> for ProcessItem {
+        type Output = u64;
+        type Error = ();
+
+        fn execute(context: &mut JobContext, parameters: &u32) -> Result<u64, ()> {
+            context.processed.push(*parameters);
+            context.total += u64::from(*parameters);
+            Ok(context.total)
+        }
+    }
+
+    #[test]
+    fn test_restored_executor_resumes_from_saved_context() {
+        let mut executor =
+            ApiExecutor::new(JobContext::default()).with_slow_threshold(Duration::from_secs(2));
+        for item in [3, 5, 8] {
+            executor.execute(ProcessItem, &item).unwrap();
+        }
+
+        let bytes = executor.save_state().unwrap();
+        let mut restored = ApiExecutor::<JobContext>::restore_state(&bytes).unwrap();
+
+        assert_eq!(restored.context(), executor.context());
+        assert_eq!(restored.slow_threshold, Some(Duration::from_secs(2)));
+        assert_eq!(restored.execute(ProcessItem, &13), Ok(29));
+    }
+
+    #[test]
+    fn test_diagnostics_are_not_restored() {
+        let mut executor = ApiExecutor::new(JobContext::default()).with_trace_sampling(0.5);
+        executor
+            .execute_with_metrics_export(ProcessItem, &3)
+            .unwrap();
+        executor
+            .execute_collecting_latency_histogram(ProcessItem, &5)
+            .unwrap();
+        let name = <ProcessItem as ApiOperation<JobContext, u32>>::name();
+        assert!(executor.operation_metrics(name).is_some());
+        assert!(executor.latency_histogram(name).is_some());
+
+        let bytes = executor.save_state().unwrap();
+        let restored = ApiExecutor::<JobContext>::restore_state(&bytes).unwrap();
+
+        assert_eq!(restored.context().processed, vec![3, 5]);
+        assert_eq!(restored.trace_sample_rate, Some(0.5));
+        assert!(restored.operation_metrics(name).is_none());
+        assert!(restored.latency_histogram(name).is_none());
+    }
+
+    #[test]
+    fn test_corrupt_bytes_are_rejected() {
+        assert!(ApiExecutor::<JobContext>::restore_state(b"not json").is_err());
+    }
+}
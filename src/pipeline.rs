@@ -0,0 +1,388 @@
+//! Declarative pipelines built from serialized configuration.
+
+use crate::registry::ErasedOperation;
+use crate::{ApiExecutor, DispatchError, OperationId, Registry};
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+use std::fmt;
+use std::sync::Arc;
+
+/// A pipeline definition listing operations by name with their parameters.
+///
+/// The configuration can be deserialized from any self-describing format such as
+/// JSON or TOML, letting workflows be defined without writing Rust.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PipelineConfig {
+    /// The steps to run, in order.
+    pub steps: Vec<PipelineStepConfig>,
+}
+
+/// A single step of a [`PipelineConfig`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PipelineStepConfig {
+    /// The name of the registered operation to run.
+    pub operation: String,
+
+    /// The operation's parameters; `null` when omitted.
+    #[serde(default)]
+    pub parameters: serde_json::Value,
+}
+
+/// Errors detected while building a pipeline from its configuration.
+#[derive(Debug)]
+pub enum PipelineError {
+    /// The step names an operation that is not registered.
+    UnknownOperation {
+        /// The index of the offending step.
+        step: usize,
+
+        /// The operation name used by the step.
+        operation: String,
+    },
+
+    /// The operation was registered without serialization support.
+    NotDeserializable {
+        /// The index of the offending step.
+        step: usize,
+
+        /// The operation used by the step.
+        operation: OperationId,
+    },
+
+    /// The step's parameters do not match the operation's parameter type.
+    InvalidParameters {
+        /// The index of the offending step.
+        step: usize,
+
+        /// The operation used by the step.
+        operation: OperationId,
+
+        /// The underlying deserialization error.
+        source: serde_json::Error,
+    },
+}
+
+impl fmt::Display for PipelineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PipelineError::UnknownOperation { step, operation } => {
+                write!(f, "step {}: unknown operation `{}`", step, operation)
+            }
+            PipelineError::NotDeserializable { step, operation } => write!(
+                f,
+                "step {}: operation `{}` was not registered with serde support",
+                step, operation
+            ),
+            PipelineError::InvalidParameters {
+                step,
+                operation,
+                source,
+            } => write!(
+                f,
+                "step {}: invalid parameters for `{}`: {}",
+                step, operation, source
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PipelineError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PipelineError::InvalidParameters { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// The error returned when a step fails while running a pipeline.
+#[derive(Debug)]
+pub struct PipelineRunError {
+    /// The index of the step that failed.
+    pub step: usize,
+
+    /// The operation used by the failed step.
+    pub operation: OperationId,
+
+    /// The error returned by the step.
+    pub error: DispatchError,
+}
+
+impl fmt::Display for PipelineRunError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "step {} (`{}`) failed: {}",
+            self.step, self.operation, self.error
+        )
+    }
+}
+
+impl std::error::Error for PipelineRunError {}
+
+/// A single validated pipeline step.
+struct PipelineStep<C> {
+    /// The operation run by the step.
+    operation: OperationId,
+
+    /// Executes the operation.
+    execute: Arc<ErasedOperation<C>>,
+
+    /// The decoded parameters for the operation.
+    parameters: Box<dyn Any + Send + Sync>,
+}
+
+/// A validated sequence of operations ready to run against a context.
+///
+/// Built by [`Registry::build_pipeline`]; every step's operation and parameters have
+/// been checked, so running it can only fail because an operation fails.
+pub struct Pipeline<C> {
+    /// The steps in execution order.
+    steps: Vec<PipelineStep<C>>,
+}
+
+impl<C> Pipeline<C> {
+    /// Returns the operations run by this pipeline, in order.
+    pub fn operations(&self) -> Vec<OperationId> {
+        self.steps.iter().map(|step| step.operation).collect()
+    }
+
+    /// Runs every step in order against `context`, stopping at the first failure.
+    ///
+    /// Returns the boxed output of each step.
+    pub fn run(&self, context: &mut C) -> Result<Vec<Box<dyn Any + Send>>, PipelineRunError> {
+        self.steps
+            .iter()
+            .enumerate()
+            .map(|(index, step)| {
+                (step.execute)(context, step.parameters.as_ref()).map_err(|error| {
+                    PipelineRunError {
+                        step: index,
+                        operation: step.operation,
+                        error,
+                    }
+                })
+            })
+            .collect()
+    }
+}
+
+impl<C> fmt::Debug for Pipeline<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pipeline")
+            .field("operations", &self.operations())
+            .finish()
+    }
+}
+
+impl<C> Registry<C> {
+    /// Validates `config` against this registry and builds a runnable pipeline.
+    ///
+    /// Unknown operation names and parameters that do not match an operation's
+    /// parameter type are reported here rather than when the pipeline runs.
+    pub fn build_pipeline(&self, config: &PipelineConfig) -> Result<Pipeline<C>, PipelineError> {
+        let steps = config
+            .steps
+            .iter()
+            .enumerate()
+            .map(|(index, step)| {
+                let (operation, registered) = self.get_entry(&step.operation).ok_or_else(|| {
+                    PipelineError::UnknownOperation {
+                        step: index,
+                        operation: step.operation.clone(),
+                    }
+                })?;
+                let decode =
+                    registered
+                        .decode
+                        .as_ref()
+                        .ok_or(PipelineError::NotDeserializable {
+                            step: index,
+                            operation,
+                        })?;
+                let parameters = decode(step.parameters.clone()).map_err(|source| {
+                    PipelineError::InvalidParameters {
+                        step: index,
+                        operation,
+                        source,
+                    }
+                })?;
+                Ok(PipelineStep {
+                    operation,
+                    execute: Arc::clone(&registered.execute),
+                    parameters,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Pipeline { steps })
+    }
+}
+
+impl<C> ApiExecutor<C> {
+    /// Runs a pipeline against this executor's context, reporting each step like
+    /// `execute`.
+    ///
+    /// If an allowlist is configured, every step is checked against it before any step
+    /// runs, so a pipeline naming a forbidden operation fails with
+    /// [`DispatchError::OperationForbidden`] and leaves the context untouched.
+    pub fn execute_pipeline(
+        &mut self,
+        pipeline: &Pipeline<C>,
+    ) -> Result<Vec<Box<dyn Any + Send>>, PipelineRunError> {
+        for (index, step) in pipeline.steps.iter().enumerate() {
+            self.check_allowlist(step.operation.name())
+                .map_err(|error| PipelineRunError {
+                    step: index,
+                    operation: step.operation,
+                    error,
+                })?;
+        }
+        let mut outputs = Vec::with_capacity(pipeline.steps.len());
+        for (index, step) in pipeline.steps.iter().enumerate() {
+            let started = self.clock.now();
+            let result = (step.execute)(&mut self.context, step.parameters.as_ref());
+            self.observe(step.operation.name(), started, result.is_ok());
+            outputs.push(result.map_err(|error| PipelineRunError {
+                step: index,
+                operation: step.operation,
+                error,
+            })?);
+        }
+        Ok(outputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+    use crate::{ApiOperation, Identified};
+
+    #[derive(Deserialize)]
+    struct StoreProps {
+        key: String,
+        value: String,
+    }
+
+    #[derive(Deserialize)]
+    struct CountProps {
+        prefix: String,
+    }
+
+    struct Store;
+    struct CountKeys;
+
+    impl Identified for Store {
+        const OP_ID: OperationId = OperationId::new("store");
+    }
+
+    impl Identified for CountKeys {
+        const OP_ID: OperationId = OperationId::new("count_keys");
+    }
+
+    impl ApiOperation<DatabaseContext, StoreProps> for Store {
+        type Output = ();
+        type Error = ();
+
+        fn execute(context: &mut DatabaseContext, parameters: &StoreProps) -> Result<(), ()> {
+            context
+                .cache_mut()
+                .insert(parameters.key.clone(), parameters.value.clone());
+            Ok(())
+        }
+    }
+
+    impl ApiOperation<DatabaseContext, CountProps> for CountKeys {
+        type Output = usize;
+        type Error = ();
+
+        fn execute(context: &mut DatabaseContext, parameters: &CountProps) -> Result<usize, ()> {
+            Ok(context
+                .cache()
+                .keys()
+                .filter(|key| key.starts_with(&parameters.prefix))
+                .count())
+        }
+    }
+
+    fn registry() -> Registry<DatabaseContext> {
+        let mut registry = Registry::new();
+        registry.register_serde(Store).unwrap();
+        registry.register_serde(CountKeys).unwrap();
+        registry
+    }
+
+    fn config(json: &str) -> PipelineConfig {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_build_and_run_two_step_pipeline() {
+        let pipeline = registry()
+            .build_pipeline(&config(
+                r#"{"steps": [
+                    {"operation": "store", "parameters": {"key": "user_1", "value": "Alice"}},
+                    {"operation": "count_keys", "parameters": {"prefix": "user_"}}
+                ]}"#,
+            ))
+            .unwrap();
+        let mut executor =
+            ApiExecutor::new(DatabaseContext::new("pipeline".to_string())).with_audit_log();
+
+        let outputs = executor.execute_pipeline(&pipeline).unwrap();
+
+        assert_eq!(outputs.len(), 2);
+        assert_eq!(outputs[1].downcast_ref::<usize>(), Some(&1));
+        assert_eq!(executor.audit_entries().len(), 2);
+        assert_eq!(executor.audit_entries()[0].operation, "store");
+        assert_eq!(
+            executor.context().cache().get("user_1"),
+            Some(&"Alice".to_string())
+        );
+    }
+
+    #[test]
+    fn test_pipeline_with_forbidden_step_does_not_run() {
+        let pipeline = registry()
+            .build_pipeline(&config(
+                r#"{"steps": [
+                    {"operation": "count_keys", "parameters": {"prefix": "user_"}},
+                    {"operation": "store", "parameters": {"key": "user_1", "value": "Alice"}}
+                ]}"#,
+            ))
+            .unwrap();
+        let mut executor = ApiExecutor::new(DatabaseContext::new("pipeline".to_string()))
+            .with_allowlist(["count_keys"]);
+
+        let error = executor.execute_pipeline(&pipeline).unwrap_err();
+
+        assert_eq!(error.step, 1);
+        assert_eq!(error.operation, Store::OP_ID);
+        assert!(matches!(error.error, DispatchError::OperationForbidden(_)));
+        assert!(executor.context().cache().is_empty());
+    }
+
+    #[test]
+    fn test_build_rejects_unknown_operations_and_bad_parameters() {
+        let registry = registry();
+
+        let unknown = registry.build_pipeline(&config(
+            r#"{"steps": [{"operation": "delete", "parameters": {}}]}"#,
+        ));
+        assert!(matches!(
+            unknown,
+            Err(PipelineError::UnknownOperation { step: 0, .. })
+        ));
+
+        let mismatched = registry.build_pipeline(&config(
+            r#"{"steps": [
+                {"operation": "count_keys", "parameters": {"prefix": "user_"}},
+                {"operation": "store", "parameters": {"key": 42}}
+            ]}"#,
+        ));
+        assert!(matches!(
+            mismatched,
+            Err(PipelineError::InvalidParameters { step: 1, .. })
+        ));
+    }
+}
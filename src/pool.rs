@@ -0,0 +1,395 @@
+//! Recycling contexts across request-scoped executors.
+
+use crate::{ApiExecutor, ApiOperation, LruCache};
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// The number of sessions a pool keeps contexts for unless configured otherwise.
+const DEFAULT_MAX_SESSIONS: usize = 1024;
+
+/// A context that can clear its per-request state while keeping expensive resources.
+pub trait Reset {
+    /// Clears per-request state such as caches and counters.
+    fn reset(&mut self);
+}
+
+/// Shared state behind every clone of a [`ContextPool`].
+struct PoolInner<C> {
+    /// Builds a new context when no idle one is available.
+    factory: Box<dyn Fn() -> C + Send + Sync>,
+
+    /// Contexts waiting to be reused.
+    idle: Mutex<Vec<C>>,
+
+    /// The maximum number of idle contexts retained.
+    max_idle: usize,
+
+    /// Contexts kept unreset for the sessions that last used them, evicting the least
+    /// recently used session when full.
+    sessions: Mutex<LruCache<String, C>>,
+}
+
+/// A pool of reusable contexts handed out as request-scoped executors.
+///
+/// Cloning the pool is cheap and every clone shares the same idle contexts.
+pub struct ContextPool<C> {
+    /// The state shared by every clone of this pool.
+    inner: Arc<PoolInner<C>>,
+}
+
+impl<C: Reset> ContextPool<C> {
+    /// Creates a pool that builds new contexts with `factory` when none are idle.
+    pub fn new<F>(factory: F) -> Self
+    where
+        F: Fn() -> C + Send + Sync + 'static,
+    {
+        Self::with_max_idle(factory, usize::MAX)
+    }
+
+    /// Creates a pool that retains at most `max_idle` contexts between uses.
+    pub fn with_max_idle<F>(factory: F, max_idle: usize) -> Self
+    where
+        F: Fn() -> C + Send + Sync + 'static,
+    {
+        Self {
+            inner: Arc::new(PoolInner {
+                factory: Box::new(factory),
+                idle: Mutex::new(Vec::new()),
+                max_idle,
+                sessions: Mutex::new(LruCache::new(DEFAULT_MAX_SESSIONS)),
+            }),
+        }
+    }
+
+    /// Keeps contexts for at most `max_sessions` sessions, and at least one.
+    ///
+    /// When a new session would exceed the limit, the least recently used session is
+    /// ended and its context returned to the idle pool. The default is 1024 sessions.
+    /// Contexts already kept for sessions are discarded.
+    pub fn with_max_sessions(self, max_sessions: usize) -> Self {
+        *self.sessions() = LruCache::new(max_sessions);
+        self
+    }
+
+    /// Returns an executor over an idle context, or over a newly built one.
+    ///
+    /// The context returns to the pool, reset, when the executor is dropped.
+    pub fn acquire(&self) -> PooledExecutor<C> {
+        let context = self.idle().pop().unwrap_or_else(|| (self.inner.factory)());
+        PooledExecutor {
+            executor: Some(ApiExecutor::new(context)),
+            pool: self.clone(),
+            session: None,
+        }
+    }
+
+    /// Returns an executor over the context affine to `session_id`, or over an idle or
+    /// newly built context if the session has none.
+    ///
+    /// When the executor is dropped, the context is kept for the session without being
+    /// reset, so the session's next request benefits from its warm caches. A session
+    /// holds at most one context: if the same session acquires again while its context
+    /// is checked out, it gets a different one; whichever is released last is kept and
+    /// the other is reset and returned to the idle pool. Session contexts do not count
+    /// towards the idle limit; release them with [`end_session`](Self::end_session), or
+    /// let the pool evict them once [`with_max_sessions`](Self::with_max_sessions) is
+    /// reached.
+    pub fn acquire_for_session(&self, session_id: impl Into<String>) -> PooledExecutor<C> {
+        let session_id = session_id.into();
+        let affine = self.sessions().remove(&session_id);
+        let context = affine
+            .or_else(|| self.idle().pop())
+            .unwrap_or_else(|| (self.inner.factory)());
+        PooledExecutor {
+            executor: Some(ApiExecutor::new(context)),
+            pool: self.clone(),
+            session: Some(session_id),
+        }
+    }
+
+    /// Executes an operation on the context affine to `session_id`, acquiring and
+    /// releasing it around the call as [`acquire_for_session`](Self::acquire_for_session)
+    /// does.
+    pub fn execute_with_context_pool_and_affinity<P, Op>(
+        &self,
+        session_id: impl Into<String>,
+        op: Op,
+        parameters: &P,
+    ) -> Result<Op::Output, Op::Error>
+    where
+        Op: ApiOperation<C, P>,
+    {
+        self.acquire_for_session(session_id).execute(op, parameters)
+    }
+
+    /// Ends `session_id`, resetting its context and returning it to the idle pool.
+    ///
+    /// Returns false if the session held no context.
+    pub fn end_session(&self, session_id: &str) -> bool {
+        let context = self.sessions().remove(&session_id.to_string());
+        match context {
+            Some(context) => {
+                self.release(context);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns the number of sessions holding an affine context.
+    pub fn session_count(&self) -> usize {
+        self.sessions().len()
+    }
+
+    /// Returns the number of contexts currently waiting to be reused.
+    pub fn idle_count(&self) -> usize {
+        self.idle().len()
+    }
+
+    /// Resets `context` and keeps it for reuse if the pool has room.
+    fn release(&self, mut context: C) {
+        context.reset();
+        let mut idle = self.idle();
+        if idle.len() < self.inner.max_idle {
+            idle.push(context);
+        }
+    }
+
+    /// Keeps `context` unreset for `session_id`, releasing any context it replaces or
+    /// evicts.
+    fn release_to_session(&self, session_id: String, context: C) {
+        let (replaced, evicted) = {
+            let mut sessions = self.sessions();
+            let replaced = sessions.remove(&session_id);
+            (replaced, sessions.insert(session_id, context))
+        };
+        if let Some(context) = replaced {
+            self.release(context);
+        }
+        if let Some((_, context)) = evicted {
+            self.release(context);
+        }
+    }
+
+    /// Locks the session contexts, recovering from a poisoned lock.
+    fn sessions(&self) -> MutexGuard<'_, LruCache<String, C>> {
+        self.inner
+            .sessions
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Locks the idle list, recovering from a poisoned lock.
+    fn idle(&self) -> MutexGuard<'_, Vec<C>> {
+        self.inner
+            .idle
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl<C> Clone for ContextPool<C> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<C> fmt::Debug for ContextPool<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ContextPool")
+            .field("max_idle", &self.inner.max_idle)
+            .finish_non_exhaustive()
+    }
+}
+
+/// An executor over a pooled context that returns the context to its pool on drop.
+///
+/// Dereferences to [`ApiExecutor`], so operations are executed as usual.
+pub struct PooledExecutor<C: Reset> {
+    /// The executor owning the borrowed context until it is released.
+    executor: Option<ApiExecutor<C>>,
+
+    /// The pool the context returns to.
+    pool: ContextPool<C>,
+
+    /// The session the context is kept for on release, if acquired for one.
+    session: Option<String>,
+}
+
+impl<C: Reset> Deref for PooledExecutor<C> {
+    type Target = ApiExecutor<C>;
+
+    fn deref(&self) -> &ApiExecutor<C> {
+        self.executor
+            .as_ref()
+            .expect("pooled executor already released")
+    }
+}
+
+impl<C: Reset> DerefMut for PooledExecutor<C> {
+    fn deref_mut(&mut self) -> &mut ApiExecutor<C> {
+        self.executor
+            .as_mut()
+            .expect("pooled executor already released")
+    }
+}
+
+impl<C: Reset> Drop for PooledExecutor<C> {
+    fn drop(&mut self) {
+        if let Some(executor) = self.executor.take() {
+            match self.session.take() {
+                Some(session_id) => self.pool.release_to_session(session_id, executor.context),
+                None => self.pool.release(executor.context),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+    use crate::ApiOperation;
+
+    impl Reset for DatabaseContext {
+        fn reset(&mut self) {
+            self.cache_mut().clear();
+            self.reset_transactions();
+        }
+    }
+
+    struct CacheRequest;
+
+    impl ApiOperation<DatabaseContext, String> for CacheRequest {
+        type Output = u32;
+        type Error = ();
+
+        fn execute(context: &mut DatabaseContext, parameters: &String) -> Result<u32, ()> {
+            context.increment_transaction();
+            context
+                .cache_mut()
+                .insert(parameters.clone(), "cached".to_string());
+            Ok(context.transaction_count())
+        }
+    }
+
+    #[test]
+    fn test_pool_reuses_and_resets_contexts() {
+        let pool = ContextPool::new(|| DatabaseContext::new("pooled".to_string()));
+
+        let first_allocation = {
+            let mut executor = pool.acquire();
+            executor
+                .execute(CacheRequest, &"request_1".to_string())
+                .unwrap();
+            assert_eq!(executor.context().transaction_count(), 1);
+            executor.context().connection_pool().as_ptr()
+        };
+        assert_eq!(pool.idle_count(), 1);
+
+        let mut executor = pool.acquire();
+        assert_eq!(
+            executor.context().connection_pool().as_ptr(),
+            first_allocation
+        );
+        assert_eq!(executor.context().transaction_count(), 0);
+        assert!(executor.context().cache().is_empty());
+
+        let count = executor
+            .execute(CacheRequest, &"request_2".to_string())
+            .unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(pool.idle_count(), 0);
+    }
+
+    #[test]
+    fn test_pool_respects_max_idle() {
+        let pool = ContextPool::with_max_idle(|| DatabaseContext::new("pooled".to_string()), 1);
+
+        let first = pool.acquire();
+        let second = pool.acquire();
+        drop(first);
+        drop(second);
+
+        assert_eq!(pool.idle_count(), 1);
+    }
+
+    #[test]
+    fn test_sessions_keep_their_own_contexts() {
+        let pool = ContextPool::new(|| DatabaseContext::new("pooled".to_string()));
+
+        let alice = {
+            let mut executor = pool.acquire_for_session("alice");
+            executor
+                .execute(CacheRequest, &"profile".to_string())
+                .unwrap();
+            executor.context().connection_pool().as_ptr()
+        };
+        let bob = {
+            let executor = pool.acquire_for_session("bob");
+            executor.context().connection_pool().as_ptr()
+        };
+        assert_ne!(alice, bob);
+        assert_eq!(pool.session_count(), 2);
+
+        let executor = pool.acquire_for_session("alice");
+        assert_eq!(executor.context().connection_pool().as_ptr(), alice);
+        assert!(executor.context().cache().contains_key("profile"));
+        drop(executor);
+
+        assert_eq!(
+            pool.execute_with_context_pool_and_affinity("alice", CacheRequest, &"feed".to_string()),
+            Ok(2)
+        );
+    }
+
+    #[test]
+    fn test_ending_session_returns_reset_context_to_pool() {
+        let pool = ContextPool::new(|| DatabaseContext::new("pooled".to_string()));
+        pool.execute_with_context_pool_and_affinity("alice", CacheRequest, &"feed".to_string())
+            .unwrap();
+
+        assert!(pool.end_session("alice"));
+        assert!(!pool.end_session("alice"));
+
+        assert_eq!(pool.session_count(), 0);
+        assert_eq!(pool.idle_count(), 1);
+        assert!(pool.acquire().context().cache().is_empty());
+    }
+
+    #[test]
+    fn test_second_release_for_a_session_returns_the_first_to_idle() {
+        let pool = ContextPool::new(|| DatabaseContext::new("pooled".to_string()));
+
+        let mut first = pool.acquire_for_session("alice");
+        first.execute(CacheRequest, &"first".to_string()).unwrap();
+        let mut second = pool.acquire_for_session("alice");
+        second.execute(CacheRequest, &"second".to_string()).unwrap();
+        drop(first);
+        drop(second);
+
+        assert_eq!(pool.session_count(), 1);
+        assert_eq!(pool.idle_count(), 1);
+        let executor = pool.acquire_for_session("alice");
+        assert!(executor.context().cache().contains_key("second"));
+    }
+
+    #[test]
+    fn test_least_recently_used_session_is_evicted() {
+        let pool =
+            ContextPool::new(|| DatabaseContext::new("pooled".to_string())).with_max_sessions(2);
+
+        for session in ["alice", "bob", "carol"] {
+            pool.execute_with_context_pool_and_affinity(session, CacheRequest, &"feed".to_string())
+                .unwrap();
+        }
+
+        assert_eq!(pool.session_count(), 2);
+        assert_eq!(pool.idle_count(), 1);
+        assert!(!pool.end_session("alice"));
+        assert!(pool.end_session("carol"));
+    }
+}
@@ -0,0 +1,210 @@
+//! Invariants checked against an operation's output before it is returned.
+
+use crate::{ApiExecutor, ApiOperation, Transactional};
+use std::fmt;
+
+/// Describes why a postcondition rejected an operation's output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PostconditionError {
+    /// A human-readable description of the violated invariant.
+    message: String,
+}
+
+impl PostconditionError {
+    /// Creates an error describing the violated invariant.
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+        }
+    }
+
+    /// Returns the description of the violated invariant.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for PostconditionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "postcondition violated: {}", self.message)
+    }
+}
+
+impl std::error::Error for PostconditionError {}
+
+/// An invariant that must hold after an operation succeeds.
+pub trait Postcondition<C, P, O> {
+    /// Checks the output against the parameters and the context the operation left behind.
+    fn check(context: &C, parameters: &P, output: &O) -> Result<(), PostconditionError>;
+}
+
+/// The error returned by [`ApiExecutor::execute_with_postcondition`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PostconditionCheckError<E> {
+    /// The operation succeeded but its output violated the postcondition.
+    Violated(PostconditionError),
+
+    /// The operation itself failed.
+    Operation(E),
+}
+
+impl<E: fmt::Display> fmt::Display for PostconditionCheckError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PostconditionCheckError::Violated(error) => write!(f, "{}", error),
+            PostconditionCheckError::Operation(error) => write!(f, "operation failed: {}", error),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for PostconditionCheckError<E> {}
+
+impl<C> ApiExecutor<C> {
+    /// Executes an operation and checks its output against the postcondition `Pc`.
+    ///
+    /// A violation is reported as an error, but whatever the operation changed in the
+    /// context is kept; use
+    /// [`execute_with_postcondition_rollback`](Self::execute_with_postcondition_rollback)
+    /// to discard those changes.
+    pub fn execute_with_postcondition<P, Op, Pc>(
+        &mut self,
+        op: Op,
+        parameters: &P,
+        _postcondition: Pc,
+    ) -> Result<Op::Output, PostconditionCheckError<Op::Error>>
+    where
+        Op: ApiOperation<C, P>,
+        Pc: Postcondition<C, P, Op::Output>,
+    {
+        let output = self
+            .execute(op, parameters)
+            .map_err(PostconditionCheckError::Operation)?;
+        Pc::check(&self.context, parameters, &output).map_err(PostconditionCheckError::Violated)?;
+        Ok(output)
+    }
+}
+
+impl<C: Transactional> ApiExecutor<C> {
+    /// Like [`execute_with_postcondition`](Self::execute_with_postcondition), but
+    /// restores the context to its prior state when the operation fails or its output
+    /// violates the postcondition.
+    pub fn execute_with_postcondition_rollback<P, Op, Pc>(
+        &mut self,
+        op: Op,
+        parameters: &P,
+        postcondition: Pc,
+    ) -> Result<Op::Output, PostconditionCheckError<Op::Error>>
+    where
+        Op: ApiOperation<C, P>,
+        Pc: Postcondition<C, P, Op::Output>,
+    {
+        let snapshot = self.context.snapshot();
+        let result = self.execute_with_postcondition(op, parameters, postcondition);
+        if result.is_err() {
+            self.context.restore(snapshot);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+
+    #[derive(Debug, PartialEq)]
+    struct User {
+        id: u32,
+        email: String,
+    }
+
+    /// Creates a user whose id is taken from the parameters, for testing invariants.
+    struct CreateUser;
+
+    impl ApiOperation<DatabaseContext, (u32, String)> for CreateUser {
+        type Output = User;
+        type Error = String;
+
+        fn execute(
+            context: &mut DatabaseContext,
+            parameters: &(u32, String),
+        ) -> Result<User, String> {
+            context.increment_transaction();
+            Ok(User {
+                id: parameters.0,
+                email: parameters.1.clone(),
+            })
+        }
+    }
+
+    struct NonZeroId;
+
+    impl Postcondition<DatabaseContext, (u32, String), User> for NonZeroId {
+        fn check(
+            _context: &DatabaseContext,
+            _parameters: &(u32, String),
+            output: &User,
+        ) -> Result<(), PostconditionError> {
+            if output.id == 0 {
+                return Err(PostconditionError::new("user id must not be zero"));
+            }
+            Ok(())
+        }
+    }
+
+    fn executor() -> ApiExecutor<DatabaseContext> {
+        ApiExecutor::new(DatabaseContext::new("postcondition".to_string()))
+    }
+
+    #[test]
+    fn test_valid_output_passes() {
+        let mut executor = executor();
+
+        let user = executor.execute_with_postcondition(
+            CreateUser,
+            &(7, "alice@example.com".to_string()),
+            NonZeroId,
+        );
+
+        assert_eq!(
+            user,
+            Ok(User {
+                id: 7,
+                email: "alice@example.com".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_zero_id_is_rejected() {
+        let mut executor = executor();
+
+        let result = executor.execute_with_postcondition(
+            CreateUser,
+            &(0, "alice@example.com".to_string()),
+            NonZeroId,
+        );
+
+        assert_eq!(
+            result,
+            Err(PostconditionCheckError::Violated(PostconditionError::new(
+                "user id must not be zero"
+            )))
+        );
+        assert_eq!(executor.context().transaction_count(), 1);
+    }
+
+    #[test]
+    fn test_rollback_discards_changes_on_violation() {
+        let mut executor = executor();
+
+        let result = executor.execute_with_postcondition_rollback(
+            CreateUser,
+            &(0, "alice@example.com".to_string()),
+            NonZeroId,
+        );
+
+        assert!(matches!(result, Err(PostconditionCheckError::Violated(_))));
+        assert_eq!(executor.context().transaction_count(), 0);
+    }
+}
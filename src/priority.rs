@@ -0,0 +1,326 @@
+//! Priority-ordered queues of operations with priority inheritance across dependencies.
+
+use crate::{ApiExecutor, ApiOperation};
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt;
+
+/// How urgently a queued operation should run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum Priority {
+    /// Background work that runs once nothing more urgent is ready.
+    Low,
+
+    /// The default priority.
+    #[default]
+    Normal,
+
+    /// Work that should run as soon as its dependencies allow.
+    High,
+}
+
+/// Identifies an operation queued on a [`PriorityQueue`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct JobId(usize);
+
+/// Runs a queued operation against the context, boxing its output.
+type JobFn<C, E> = Box<dyn FnOnce(&mut C) -> Result<Box<dyn Any>, E>>;
+
+/// An operation waiting in a [`PriorityQueue`].
+struct QueuedJob<C, E> {
+    /// The diagnostic name of the queued operation.
+    name: &'static str,
+
+    /// The priority the job was queued with.
+    priority: Priority,
+
+    /// Jobs that must run before this one.
+    dependencies: Vec<JobId>,
+
+    /// Executes the operation with its parameters.
+    run: JobFn<C, E>,
+}
+
+/// The error returned when a queued operation fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueueRunError<E> {
+    /// The job that failed.
+    pub job: JobId,
+
+    /// The error returned by the job's operation.
+    pub error: E,
+}
+
+impl<E: fmt::Display> fmt::Display for QueueRunError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "job {} failed: {}", self.job.0, self.error)
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for QueueRunError<E> {}
+
+/// Operations queued with a priority and the jobs they depend on.
+///
+/// Dependencies can only name jobs queued earlier, so a queue never has cycles.
+pub struct PriorityQueue<C, E> {
+    /// The queued jobs, indexed by [`JobId`].
+    jobs: Vec<QueuedJob<C, E>>,
+}
+
+impl<C, E> PriorityQueue<C, E> {
+    /// Creates an empty queue.
+    pub fn new() -> Self {
+        Self { jobs: Vec::new() }
+    }
+
+    /// Queues an operation with no dependencies.
+    pub fn push<P, Op>(&mut self, op: Op, parameters: P, priority: Priority) -> JobId
+    where
+        P: 'static,
+        Op: ApiOperation<C, P>,
+        Op::Output: 'static,
+        Op::Error: Into<E>,
+    {
+        self.push_after(op, parameters, priority, &[])
+    }
+
+    /// Queues an operation that may only run once every job in `dependencies` has.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a dependency was not queued on this queue.
+    pub fn push_after<P, Op>(
+        &mut self,
+        _op: Op,
+        parameters: P,
+        priority: Priority,
+        dependencies: &[JobId],
+    ) -> JobId
+    where
+        P: 'static,
+        Op: ApiOperation<C, P>,
+        Op::Output: 'static,
+        Op::Error: Into<E>,
+    {
+        let id = JobId(self.jobs.len());
+        assert!(
+            dependencies.iter().all(|dependency| dependency.0 < id.0),
+            "dependencies must be queued on this queue first"
+        );
+        self.jobs.push(QueuedJob {
+            name: Op::name(),
+            priority,
+            dependencies: dependencies.to_vec(),
+            run: Box::new(move |context| {
+                Op::execute(context, &parameters)
+                    .map(|output| Box::new(output) as Box<dyn Any>)
+                    .map_err(Into::into)
+            }),
+        });
+        id
+    }
+
+    /// Returns the number of queued jobs.
+    pub fn len(&self) -> usize {
+        self.jobs.len()
+    }
+
+    /// Returns `true` if no jobs are queued.
+    pub fn is_empty(&self) -> bool {
+        self.jobs.is_empty()
+    }
+
+    /// Returns each job's priority raised to that of the most urgent job waiting on
+    /// it, directly or transitively.
+    fn effective_priorities(&self) -> Vec<Priority> {
+        let mut effective: Vec<_> = self.jobs.iter().map(|job| job.priority).collect();
+        // Dependents always come after their dependencies, so one backwards pass
+        // carries every priority down the whole chain.
+        for (index, job) in self.jobs.iter().enumerate().rev() {
+            for dependency in &job.dependencies {
+                effective[dependency.0] = effective[dependency.0].max(effective[index]);
+            }
+        }
+        effective
+    }
+}
+
+impl<C, E> Default for PriorityQueue<C, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C, E> fmt::Debug for PriorityQueue<C, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PriorityQueue")
+            .field("jobs", &self.jobs.len())
+            .finish()
+    }
+}
+
+/// The outputs of a drained [`PriorityQueue`] and the order its jobs ran in.
+#[derive(Default)]
+pub struct QueueOutputs {
+    /// The jobs in the order they ran.
+    order: Vec<JobId>,
+
+    /// The boxed output of each job.
+    values: HashMap<JobId, Box<dyn Any>>,
+}
+
+impl QueueOutputs {
+    /// Returns the jobs in the order they ran.
+    pub fn order(&self) -> &[JobId] {
+        &self.order
+    }
+
+    /// Returns the output of `job`, or `None` if it has another type.
+    pub fn get<T: 'static>(&self, job: JobId) -> Option<&T> {
+        self.values.get(&job).and_then(|value| value.downcast_ref())
+    }
+
+    /// Removes and returns the output of `job`.
+    pub fn take<T: 'static>(&mut self, job: JobId) -> Option<T> {
+        match self.values.remove(&job)?.downcast() {
+            Ok(value) => Some(*value),
+            Err(_) => None,
+        }
+    }
+}
+
+impl fmt::Debug for QueueOutputs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QueueOutputs")
+            .field("order", &self.order)
+            .finish()
+    }
+}
+
+impl<C> ApiExecutor<C> {
+    /// Drains a priority queue, always running the most urgent job whose dependencies
+    /// have finished.
+    ///
+    /// A job inherits the priority of the most urgent job depending on it, so a
+    /// high-priority job is never held back by a low-priority dependency waiting
+    /// behind other work. Among jobs of the same priority, inherited ones run first,
+    /// then jobs in the order they were queued. Each job is reported like `execute`.
+    /// Stops at the first failing job.
+    pub fn execute_with_priority_inheritance<E>(
+        &mut self,
+        queue: PriorityQueue<C, E>,
+    ) -> Result<QueueOutputs, QueueRunError<E>> {
+        let effective = queue.effective_priorities();
+        let mut pending: Vec<_> = queue.jobs.into_iter().map(Some).collect();
+        let mut done = vec![false; pending.len()];
+        let mut outputs = QueueOutputs::default();
+
+        while let Some(index) = (0..pending.len())
+            .filter(|&index| {
+                pending[index]
+                    .as_ref()
+                    .is_some_and(|job| job.dependencies.iter().all(|dependency| done[dependency.0]))
+            })
+            .max_by_key(|&index| {
+                let inherited = pending[index]
+                    .as_ref()
+                    .is_some_and(|job| effective[index] > job.priority);
+                (effective[index], inherited, std::cmp::Reverse(index))
+            })
+        {
+            let job = pending[index]
+                .take()
+                .expect("only pending jobs are selected");
+            let started = self.clock.now();
+            let result = (job.run)(&mut self.context);
+            self.observe(job.name, started, result.is_ok());
+            let output = result.map_err(|error| QueueRunError {
+                job: JobId(index),
+                error,
+            })?;
+            done[index] = true;
+            outputs.order.push(JobId(index));
+            outputs.values.insert(JobId(index), output);
+        }
+        Ok(outputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+
+    /// Records its label in the cache under the next transaction number.
+    struct Record;
+
+    impl ApiOperation<DatabaseContext, &'static str> for Record {
+        type Output = u32;
+        type Error = String;
+
+        fn execute(
+            context: &mut DatabaseContext,
+            parameters: &&'static str,
+        ) -> Result<u32, String> {
+            context.increment_transaction();
+            let position = context.transaction_count();
+            context
+                .cache_mut()
+                .insert(parameters.to_string(), position.to_string());
+            Ok(position)
+        }
+    }
+
+    /// Fails unless the named entry has been recorded.
+    struct Require;
+
+    impl ApiOperation<DatabaseContext, &'static str> for Require {
+        type Output = ();
+        type Error = String;
+
+        fn execute(context: &mut DatabaseContext, parameters: &&'static str) -> Result<(), String> {
+            if context.cache().contains_key(*parameters) {
+                Ok(())
+            } else {
+                Err(format!("{} has not run", parameters))
+            }
+        }
+    }
+
+    #[test]
+    fn test_dependency_inherits_dependent_priority() {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let mut executor =
+            ApiExecutor::new(DatabaseContext::new("priority".to_string())).with_notifier(sender);
+        let mut queue = PriorityQueue::<DatabaseContext, String>::new();
+
+        let report = queue.push(Record, "report", Priority::High);
+        let cleanup = queue.push(Record, "cleanup", Priority::Low);
+        let index = queue.push(Record, "index", Priority::Low);
+        let search = queue.push_after(Require, "index", Priority::High, &[index]);
+
+        let mut outputs = executor.execute_with_priority_inheritance(queue).unwrap();
+
+        assert_eq!(outputs.order(), &[index, report, search, cleanup]);
+        assert_eq!(outputs.take::<u32>(index), Some(1));
+        assert_eq!(outputs.get::<u32>(cleanup), Some(&3));
+        assert_eq!(receiver.try_iter().count(), 4);
+    }
+
+    #[test]
+    fn test_failure_stops_the_queue() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("priority".to_string()));
+        let mut queue = PriorityQueue::<DatabaseContext, String>::new();
+
+        let missing = queue.push(Require, "missing", Priority::High);
+        queue.push(Record, "later", Priority::Low);
+
+        let error = executor
+            .execute_with_priority_inheritance(queue)
+            .unwrap_err();
+
+        assert_eq!(error.job, missing);
+        assert_eq!(error.error, "missing has not run");
+        assert_eq!(executor.context().transaction_count(), 0);
+    }
+}
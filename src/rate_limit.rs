@@ -0,0 +1,259 @@
+//! Token-bucket rate limiting of operation executions.
+
+use crate::{ApiExecutor, ApiOperation};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// What [`ApiExecutor::execute_rate_limited`] does when the bucket is empty.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RateLimitMode {
+    /// Fail immediately with [`RateLimitError::RateLimited`].
+    #[default]
+    Reject,
+
+    /// Sleep on the executor's clock until a token is available.
+    ///
+    /// A bucket that never refills still rejects once empty.
+    Block,
+}
+
+/// The error returned by [`ApiExecutor::execute_rate_limited`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RateLimitError<E> {
+    /// No token was available and the limiter is in [`RateLimitMode::Reject`] mode.
+    RateLimited {
+        /// How long until the next token becomes available.
+        retry_after: Duration,
+    },
+
+    /// The operation itself failed.
+    Operation(E),
+}
+
+impl<E: fmt::Display> fmt::Display for RateLimitError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RateLimitError::RateLimited { retry_after } => {
+                write!(f, "rate limited, retry after {:?}", retry_after)
+            }
+            RateLimitError::Operation(error) => write!(f, "operation failed: {}", error),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for RateLimitError<E> {}
+
+/// A bucket of tokens refilled continuously at a fixed rate.
+#[derive(Debug, Clone)]
+pub(crate) struct TokenBucket {
+    /// The maximum number of tokens the bucket holds.
+    capacity: f64,
+
+    /// Tokens added per second.
+    refill_per_sec: f64,
+
+    /// Tokens currently available.
+    tokens: f64,
+
+    /// When tokens were last added, unset until the bucket is first used.
+    last_refill: Option<Instant>,
+
+    /// The behavior when no token is available.
+    mode: RateLimitMode,
+}
+
+impl TokenBucket {
+    /// Adds the tokens accrued since the last refill.
+    fn refill(&mut self, now: Instant) {
+        if let Some(last) = self.last_refill {
+            let elapsed = now.saturating_duration_since(last).as_secs_f64();
+            self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        }
+        self.last_refill = Some(now);
+    }
+
+    /// Takes a token, or returns how long until one is available.
+    fn try_acquire(&mut self, now: Instant) -> Result<(), Duration> {
+        self.refill(now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return Ok(());
+        }
+        if self.refill_per_sec <= 0.0 {
+            return Err(Duration::MAX);
+        }
+        Err(
+            Duration::try_from_secs_f64((1.0 - self.tokens) / self.refill_per_sec)
+                .unwrap_or(Duration::MAX),
+        )
+    }
+}
+
+impl<C> ApiExecutor<C> {
+    /// Limits [`execute_rate_limited`](Self::execute_rate_limited) to bursts of
+    /// `capacity` calls, refilled at `refill_per_sec` calls per second.
+    ///
+    /// The bucket starts full and holds at least one token. A rate of zero or less
+    /// never refills. Calls over the limit are rejected; use
+    /// [`with_rate_limit_mode`](Self::with_rate_limit_mode) to block instead.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `refill_per_sec` is NaN or infinite.
+    pub fn with_rate_limit(mut self, capacity: u32, refill_per_sec: f64) -> Self {
+        assert!(
+            refill_per_sec.is_finite(),
+            "rate limit refill rate must be finite"
+        );
+        let capacity = f64::from(capacity.max(1));
+        let mode = self
+            .rate_limit
+            .as_ref()
+            .map_or_else(Default::default, |bucket| bucket.mode);
+        self.rate_limit = Some(TokenBucket {
+            capacity,
+            refill_per_sec,
+            tokens: capacity,
+            last_refill: None,
+            mode,
+        });
+        self
+    }
+
+    /// Chooses whether calls over the rate limit are rejected or wait for a token.
+    ///
+    /// Has no effect unless a limit is installed with
+    /// [`with_rate_limit`](Self::with_rate_limit).
+    pub fn with_rate_limit_mode(mut self, mode: RateLimitMode) -> Self {
+        if let Some(bucket) = &mut self.rate_limit {
+            bucket.mode = mode;
+        }
+        self
+    }
+
+    /// Executes an operation once a rate-limit token is available.
+    ///
+    /// Without a configured limit this behaves like `execute`. Refill timing follows
+    /// the executor's clock, so a [`MockClock`](crate::MockClock) makes it deterministic.
+    pub fn execute_rate_limited<P, Op>(
+        &mut self,
+        op: Op,
+        parameters: &P,
+    ) -> Result<Op::Output, RateLimitError<Op::Error>>
+    where
+        Op: ApiOperation<C, P>,
+    {
+        if let Some(bucket) = &mut self.rate_limit {
+            loop {
+                match bucket.try_acquire(self.clock.now()) {
+                    Ok(()) => break,
+                    Err(retry_after)
+                        if bucket.mode == RateLimitMode::Reject || retry_after == Duration::MAX =>
+                    {
+                        return Err(RateLimitError::RateLimited { retry_after });
+                    }
+                    Err(retry_after) => self.clock.sleep(retry_after),
+                }
+            }
+        }
+        self.execute(op, parameters)
+            .map_err(RateLimitError::Operation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+    use crate::MockClock;
+
+    struct Increment;
+
+    impl ApiOperation<DatabaseContext, ()> for Increment {
+        type Output = u32;
+        type Error = ();
+
+        fn execute(context: &mut DatabaseContext, _parameters: &()) -> Result<u32, ()> {
+            context.increment_transaction();
+            Ok(context.transaction_count())
+        }
+    }
+
+    fn executor(clock: &MockClock) -> ApiExecutor<DatabaseContext> {
+        ApiExecutor::new(DatabaseContext::new("rate_limit".to_string()))
+            .with_clock(clock.clone())
+            .with_rate_limit(2, 1.0)
+    }
+
+    #[test]
+    fn test_exhausted_bucket_rejects_until_refilled() {
+        let clock = MockClock::new();
+        let mut executor = executor(&clock);
+
+        assert_eq!(executor.execute_rate_limited(Increment, &()), Ok(1));
+        assert_eq!(executor.execute_rate_limited(Increment, &()), Ok(2));
+        assert_eq!(
+            executor.execute_rate_limited(Increment, &()),
+            Err(RateLimitError::RateLimited {
+                retry_after: Duration::from_secs(1)
+            })
+        );
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(executor.execute_rate_limited(Increment, &()), Ok(3));
+        assert_eq!(executor.context().transaction_count(), 3);
+    }
+
+    #[test]
+    fn test_block_mode_waits_for_token() {
+        let clock = MockClock::new();
+        let mut executor = executor(&clock).with_rate_limit_mode(RateLimitMode::Block);
+        let start = executor.now();
+
+        for _ in 0..3 {
+            executor.execute_rate_limited(Increment, &()).unwrap();
+        }
+
+        assert_eq!(executor.now() - start, Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_zero_capacity_allows_one_call_per_refill() {
+        let clock = MockClock::new();
+        let mut executor = ApiExecutor::new(DatabaseContext::new("rate_limit".to_string()))
+            .with_clock(clock.clone())
+            .with_rate_limit(0, 1.0)
+            .with_rate_limit_mode(RateLimitMode::Block);
+        let start = executor.now();
+
+        for _ in 0..2 {
+            executor.execute_rate_limited(Increment, &()).unwrap();
+        }
+
+        assert_eq!(executor.now() - start, Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_tiny_refill_rate_reports_unbounded_wait() {
+        let clock = MockClock::new();
+        let mut executor = ApiExecutor::new(DatabaseContext::new("rate_limit".to_string()))
+            .with_clock(clock)
+            .with_rate_limit(1, f64::MIN_POSITIVE);
+
+        executor.execute_rate_limited(Increment, &()).unwrap();
+
+        assert_eq!(
+            executor.execute_rate_limited(Increment, &()),
+            Err(RateLimitError::RateLimited {
+                retry_after: Duration::MAX
+            })
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "must be finite")]
+    fn test_nan_refill_rate_is_rejected() {
+        let _ = ApiExecutor::new(DatabaseContext::new("rate_limit".to_string()))
+            .with_rate_limit(1, f64::NAN);
+    }
+}
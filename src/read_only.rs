@@ -0,0 +1,125 @@
+//! Lock-free sharing of a context that no longer changes.
+
+use crate::{ApiExecutor, ApiQuery};
+use std::sync::Arc;
+
+/// A cheaply cloneable executor over an immutable context that only runs queries.
+///
+/// This is the immutable counterpart to [`SharedApiExecutor`](crate::SharedApiExecutor):
+/// since nothing can write to the context, clones share it through an `Arc` and run
+/// [`ApiQuery`] implementations concurrently without any locking.
+#[derive(Debug)]
+pub struct ReadOnlyExecutor<C> {
+    /// The context shared by every clone of this executor.
+    context: Arc<C>,
+}
+
+impl<C> ReadOnlyExecutor<C> {
+    /// Creates a read-only executor over `context`.
+    pub fn new(context: C) -> Self {
+        Self {
+            context: Arc::new(context),
+        }
+    }
+
+    /// Runs a read-only query against the context.
+    pub fn query<P, Q>(&self, _query: Q, parameters: &P) -> Result<Q::Output, Q::Error>
+    where
+        Q: ApiQuery<C, P>,
+    {
+        Q::query(&self.context, parameters)
+    }
+
+    /// Returns the shared context.
+    pub fn context(&self) -> &C {
+        &self.context
+    }
+}
+
+impl<C> Clone for ReadOnlyExecutor<C> {
+    fn clone(&self) -> Self {
+        Self {
+            context: Arc::clone(&self.context),
+        }
+    }
+}
+
+impl<C> ApiExecutor<C> {
+    /// Ends the write phase, turning the executor into a [`ReadOnlyExecutor`] over its
+    /// context.
+    pub fn freeze(self) -> ReadOnlyExecutor<C> {
+        ReadOnlyExecutor::new(self.context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+    use crate::ApiOperation;
+    use std::sync::Barrier;
+    use std::thread;
+
+    struct Store;
+
+    impl ApiOperation<DatabaseContext, (String, String)> for Store {
+        type Output = ();
+        type Error = ();
+
+        fn execute(context: &mut DatabaseContext, parameters: &(String, String)) -> Result<(), ()> {
+            context
+                .cache_mut()
+                .insert(parameters.0.clone(), parameters.1.clone());
+            Ok(())
+        }
+    }
+
+    struct Lookup;
+
+    impl ApiQuery<DatabaseContext, String> for Lookup {
+        type Output = Option<String>;
+        type Error = ();
+
+        fn query(context: &DatabaseContext, parameters: &String) -> Result<Option<String>, ()> {
+            Ok(context.cache().get(parameters).cloned())
+        }
+    }
+
+    #[test]
+    fn test_frozen_context_is_queried_concurrently() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("frozen".to_string()));
+        for index in 0..4 {
+            executor
+                .execute(
+                    Store,
+                    &(format!("user_{}", index), format!("name_{}", index)),
+                )
+                .unwrap();
+        }
+        let frozen = executor.freeze();
+        let barrier = Arc::new(Barrier::new(4));
+
+        let handles: Vec<_> = (0..4)
+            .map(|index| {
+                let frozen = frozen.clone();
+                let barrier = Arc::clone(&barrier);
+                thread::spawn(move || {
+                    barrier.wait();
+                    frozen.query(Lookup, &format!("user_{}", index)).unwrap()
+                })
+            })
+            .collect();
+
+        let names: Vec<_> = handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            (0..4)
+                .map(|index| Some(format!("name_{}", index)))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(frozen.context().cache().len(), 4);
+    }
+}
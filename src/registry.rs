@@ -0,0 +1,499 @@
+//! Dynamic registration and dispatch of operations by identifier.
+
+use crate::{ApiExecutor, ApiOperation};
+use std::any::Any;
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// A unique, human-readable identifier for a registered operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct OperationId(&'static str);
+
+impl OperationId {
+    /// Creates a new identifier from a static name.
+    pub const fn new(name: &'static str) -> Self {
+        Self(name)
+    }
+
+    /// Returns the name of this identifier.
+    pub const fn name(&self) -> &'static str {
+        self.0
+    }
+}
+
+impl fmt::Display for OperationId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+impl Borrow<str> for OperationId {
+    fn borrow(&self) -> &str {
+        self.0
+    }
+}
+
+/// An operation with a stable identifier used for dynamic registration.
+pub trait Identified {
+    /// The identifier the operation is registered under.
+    const OP_ID: OperationId;
+}
+
+/// Errors returned when registering an operation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegisterError {
+    /// Another operation is already registered under the same identifier.
+    DuplicateId(OperationId),
+
+    /// Another family is already registered under the same name.
+    DuplicateFamily(&'static str),
+
+    /// The declared output schema is not a valid JSON Schema.
+    #[cfg(feature = "schemars")]
+    InvalidSchema(OperationId),
+}
+
+impl fmt::Display for RegisterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegisterError::DuplicateId(id) => {
+                write!(f, "an operation is already registered as `{}`", id)
+            }
+            RegisterError::DuplicateFamily(name) => {
+                write!(f, "a family is already registered as `{}`", name)
+            }
+            #[cfg(feature = "schemars")]
+            RegisterError::InvalidSchema(id) => {
+                write!(f, "the output schema declared for `{}` is invalid", id)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RegisterError {}
+
+/// Errors returned when dispatching an operation through a [`Registry`].
+#[derive(Debug)]
+pub enum DispatchError {
+    /// No operation is registered under the requested name.
+    UnknownOperation(String),
+
+    /// The supplied parameters do not match the operation's parameter type.
+    ParameterMismatch(OperationId),
+
+    /// The operation ran and failed; holds the operation's boxed error.
+    Operation(Box<dyn Any + Send>),
+
+    /// The operation is not on the executor's allowlist, so it was not run.
+    OperationForbidden(String),
+}
+
+impl fmt::Display for DispatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DispatchError::UnknownOperation(name) => write!(f, "unknown operation `{}`", name),
+            DispatchError::ParameterMismatch(id) => {
+                write!(f, "parameters do not match operation `{}`", id)
+            }
+            DispatchError::Operation(_) => f.write_str("operation failed"),
+            DispatchError::OperationForbidden(name) => {
+                write!(f, "operation `{}` is not allowed", name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DispatchError {}
+
+/// A type-erased operation stored in a [`Registry`].
+pub(crate) type ErasedOperation<C> =
+    dyn Fn(&mut C, &dyn Any) -> Result<Box<dyn Any + Send>, DispatchError> + Send + Sync;
+
+/// Decodes JSON parameters into the boxed parameter type of an operation.
+#[cfg(feature = "serde")]
+pub(crate) type ParameterDecoder = dyn Fn(serde_json::Value) -> Result<Box<dyn Any + Send + Sync>, serde_json::Error>
+    + Send
+    + Sync;
+
+/// Encodes the boxed output of an operation as JSON.
+#[cfg(feature = "serde")]
+pub(crate) type OutputEncoder =
+    dyn Fn(&(dyn Any + Send)) -> Result<serde_json::Value, serde_json::Error> + Send + Sync;
+
+/// An operation stored in a [`Registry`] with its optional serialization support.
+pub(crate) struct RegisteredOperation<C> {
+    /// Executes the operation with type-erased parameters.
+    pub(crate) execute: Arc<ErasedOperation<C>>,
+
+    /// Decodes parameters for operations registered with `register_serde`.
+    #[cfg(feature = "serde")]
+    pub(crate) decode: Option<Arc<ParameterDecoder>>,
+
+    /// Encodes outputs for operations registered with `register_encoded`.
+    #[cfg(feature = "serde")]
+    pub(crate) encode: Option<Arc<OutputEncoder>>,
+
+    /// Encodes errors for operations registered with `register_encoded`.
+    #[cfg(feature = "serde")]
+    pub(crate) encode_error: Option<Arc<OutputEncoder>>,
+
+    /// The declared output schema of operations registered with `register_with_output_schema`.
+    #[cfg(feature = "schemars")]
+    pub(crate) schema: Option<Arc<jsonschema::JSONSchema>>,
+}
+
+impl<C> Clone for RegisteredOperation<C> {
+    fn clone(&self) -> Self {
+        Self {
+            execute: Arc::clone(&self.execute),
+            #[cfg(feature = "serde")]
+            decode: self.decode.clone(),
+            #[cfg(feature = "serde")]
+            encode: self.encode.clone(),
+            #[cfg(feature = "serde")]
+            encode_error: self.encode_error.clone(),
+            #[cfg(feature = "schemars")]
+            schema: self.schema.clone(),
+        }
+    }
+}
+
+/// A collection of operations over context `C`, addressable by [`OperationId`].
+pub struct Registry<C> {
+    /// The registered operations keyed by identifier.
+    operations: HashMap<OperationId, RegisteredOperation<C>>,
+}
+
+impl<C> Registry<C> {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self {
+            operations: HashMap::new(),
+        }
+    }
+
+    /// Registers an operation under its [`Identified::OP_ID`].
+    ///
+    /// Returns [`RegisterError::DuplicateId`] rather than overwriting an operation
+    /// that is already registered under the same identifier.
+    pub fn register<P, Op>(&mut self, _op: Op) -> Result<(), RegisterError>
+    where
+        Op: ApiOperation<C, P> + Identified,
+        P: 'static,
+        Op::Output: Send + 'static,
+        Op::Error: Send + 'static,
+    {
+        self.insert::<P, Op>(RegisteredOperation {
+            execute: Self::erase::<P, Op>(),
+            #[cfg(feature = "serde")]
+            decode: None,
+            #[cfg(feature = "serde")]
+            encode: None,
+            #[cfg(feature = "serde")]
+            encode_error: None,
+            #[cfg(feature = "schemars")]
+            schema: None,
+        })
+    }
+
+    /// Registers an operation whose parameters can be decoded from serialized data.
+    ///
+    /// Operations registered this way can also be used in config-defined pipelines.
+    #[cfg(feature = "serde")]
+    pub fn register_serde<P, Op>(&mut self, _op: Op) -> Result<(), RegisterError>
+    where
+        Op: ApiOperation<C, P> + Identified,
+        P: serde::de::DeserializeOwned + Send + Sync + 'static,
+        Op::Output: Send + 'static,
+        Op::Error: Send + 'static,
+    {
+        self.insert::<P, Op>(RegisteredOperation {
+            execute: Self::erase::<P, Op>(),
+            decode: Some(Self::decoder::<P>()),
+            encode: None,
+            encode_error: None,
+            #[cfg(feature = "schemars")]
+            schema: None,
+        })
+    }
+
+    /// Registers an operation whose parameters and output can both be serialized.
+    ///
+    /// Operations registered this way can be dispatched with encoded request bodies
+    /// through [`Registry::dispatch_encoded`], as well as used in pipelines. Their errors
+    /// are encoded too, so remote callers can see why an operation failed.
+    #[cfg(feature = "serde")]
+    pub fn register_encoded<P, Op>(&mut self, _op: Op) -> Result<(), RegisterError>
+    where
+        Op: ApiOperation<C, P> + Identified,
+        P: serde::de::DeserializeOwned + Send + Sync + 'static,
+        Op::Output: serde::Serialize + Send + 'static,
+        Op::Error: serde::Serialize + Send + 'static,
+    {
+        self.insert::<P, Op>(RegisteredOperation {
+            execute: Self::erase::<P, Op>(),
+            decode: Some(Self::decoder::<P>()),
+            encode: Some(Self::encoder::<Op::Output>()),
+            encode_error: Some(Self::encoder::<Op::Error>()),
+            #[cfg(feature = "schemars")]
+            schema: None,
+        })
+    }
+
+    /// Registers an operation like [`register_encoded`](Self::register_encoded), declaring
+    /// the JSON Schema its output must conform to.
+    ///
+    /// The schema is the published contract, which can be generated from a Rust type
+    /// with `schemars::schema_for!` or written by hand. Outputs are checked against it by
+    /// [`ApiExecutor::execute_with_output_validation_schema`].
+    #[cfg(feature = "schemars")]
+    pub fn register_with_output_schema<P, Op>(
+        &mut self,
+        _op: Op,
+        schema: schemars::schema::RootSchema,
+    ) -> Result<(), RegisterError>
+    where
+        Op: ApiOperation<C, P> + Identified,
+        P: serde::de::DeserializeOwned + Send + Sync + 'static,
+        Op::Output: serde::Serialize + Send + 'static,
+        Op::Error: serde::Serialize + Send + 'static,
+    {
+        let schema = serde_json::to_value(schema)
+            .ok()
+            .and_then(|schema| jsonschema::JSONSchema::compile(&schema).ok())
+            .ok_or(RegisterError::InvalidSchema(Op::OP_ID))?;
+        self.insert::<P, Op>(RegisteredOperation {
+            execute: Self::erase::<P, Op>(),
+            decode: Some(Self::decoder::<P>()),
+            encode: Some(Self::encoder::<Op::Output>()),
+            encode_error: Some(Self::encoder::<Op::Error>()),
+            schema: Some(Arc::new(schema)),
+        })
+    }
+
+    /// Builds the JSON encoder for outputs or errors of type `O`.
+    #[cfg(feature = "serde")]
+    fn encoder<O>() -> Arc<OutputEncoder>
+    where
+        O: serde::Serialize + 'static,
+    {
+        Arc::new(|output| {
+            let output = output
+                .downcast_ref::<O>()
+                .expect("encoder is registered with its operation's output or error type");
+            serde_json::to_value(output)
+        })
+    }
+
+    /// Builds the JSON decoder for parameters of type `P`.
+    #[cfg(feature = "serde")]
+    fn decoder<P>() -> Arc<ParameterDecoder>
+    where
+        P: serde::de::DeserializeOwned + Send + Sync + 'static,
+    {
+        Arc::new(|value| {
+            let parameters: P = serde_json::from_value(value)?;
+            Ok(Box::new(parameters))
+        })
+    }
+
+    /// Returns the registered operation with the given name.
+    pub(crate) fn get(&self, name: &str) -> Option<&RegisteredOperation<C>> {
+        self.operations.get(name)
+    }
+
+    /// Returns the identifier and registered operation with the given name.
+    pub(crate) fn get_entry(&self, name: &str) -> Option<(OperationId, &RegisteredOperation<C>)> {
+        self.operations
+            .get_key_value(name)
+            .map(|(id, operation)| (*id, operation))
+    }
+
+    /// Stores `operation` under `Op::OP_ID` unless that identifier is taken.
+    fn insert<P, Op>(&mut self, operation: RegisteredOperation<C>) -> Result<(), RegisterError>
+    where
+        Op: ApiOperation<C, P> + Identified,
+    {
+        if self.operations.contains_key(&Op::OP_ID) {
+            return Err(RegisterError::DuplicateId(Op::OP_ID));
+        }
+        self.operations.insert(Op::OP_ID, operation);
+        Ok(())
+    }
+
+    /// Builds the type-erased executor for `Op`.
+    fn erase<P, Op>() -> Arc<ErasedOperation<C>>
+    where
+        Op: ApiOperation<C, P> + Identified,
+        P: 'static,
+        Op::Output: Send + 'static,
+        Op::Error: Send + 'static,
+    {
+        Arc::new(|context, parameters| {
+            let parameters = parameters
+                .downcast_ref::<P>()
+                .ok_or(DispatchError::ParameterMismatch(Op::OP_ID))?;
+            match Op::execute(context, parameters) {
+                Ok(output) => Ok(Box::new(output)),
+                Err(error) => Err(DispatchError::Operation(Box::new(error))),
+            }
+        })
+    }
+
+    /// Copies the operation registered under `name` in `other` into this registry,
+    /// returning false if `other` has no such operation.
+    #[cfg(feature = "capi")]
+    pub(crate) fn copy_from(&mut self, other: &Registry<C>, name: &str) -> bool {
+        match other.operations.get_key_value(name) {
+            Some((id, operation)) => {
+                self.operations.insert(*id, operation.clone());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Moves every operation from `other` into this registry.
+    pub(crate) fn absorb(&mut self, other: Registry<C>) {
+        self.operations.extend(other.operations);
+    }
+
+    /// Returns true if an operation is registered under `name`.
+    pub fn contains(&self, name: &str) -> bool {
+        self.operations.contains_key(name)
+    }
+
+    /// Returns the identifiers of all registered operations in sorted order.
+    pub fn ids(&self) -> Vec<OperationId> {
+        let mut ids: Vec<_> = self.operations.keys().copied().collect();
+        ids.sort();
+        ids
+    }
+
+    /// Executes the operation registered under `name` with type-erased parameters.
+    ///
+    /// On success the boxed output can be downcast to the operation's output type.
+    pub fn dispatch(
+        &self,
+        context: &mut C,
+        name: &str,
+        parameters: &dyn Any,
+    ) -> Result<Box<dyn Any + Send>, DispatchError> {
+        let operation = self
+            .get(name)
+            .ok_or_else(|| DispatchError::UnknownOperation(name.to_string()))?;
+        (operation.execute)(context, parameters)
+    }
+}
+
+impl<C> Default for Registry<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C> fmt::Debug for Registry<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Registry")
+            .field("operations", &self.ids())
+            .finish()
+    }
+}
+
+impl<C> ApiExecutor<C> {
+    /// Executes an operation from `registry` by name using this executor's context.
+    pub fn execute_dynamic(
+        &mut self,
+        registry: &Registry<C>,
+        name: &str,
+        parameters: &dyn Any,
+    ) -> Result<Box<dyn Any + Send>, DispatchError> {
+        self.check_allowlist(name)?;
+        let (id, operation) = registry
+            .get_entry(name)
+            .ok_or_else(|| DispatchError::UnknownOperation(name.to_string()))?;
+        let started = self.clock.now();
+        let result = (operation.execute)(&mut self.context, parameters);
+        if !matches!(result, Err(DispatchError::ParameterMismatch(_))) {
+            self.observe(id.name(), started, result.is_ok());
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+
+    struct CreateUser;
+    struct CreateAccount;
+
+    impl Identified for CreateUser {
+        const OP_ID: OperationId = OperationId::new("create");
+    }
+
+    impl Identified for CreateAccount {
+        const OP_ID: OperationId = OperationId::new("create");
+    }
+
+    impl ApiOperation<DatabaseContext, String> for CreateUser {
+        type Output = u32;
+        type Error = ();
+
+        fn execute(context: &mut DatabaseContext, _parameters: &String) -> Result<u32, ()> {
+            context.increment_transaction();
+            Ok(context.transaction_count())
+        }
+    }
+
+    impl ApiOperation<DatabaseContext, u64> for CreateAccount {
+        type Output = u64;
+        type Error = ();
+
+        fn execute(_context: &mut DatabaseContext, parameters: &u64) -> Result<u64, ()> {
+            Ok(*parameters)
+        }
+    }
+
+    #[test]
+    fn test_duplicate_registration_fails() {
+        let mut registry = Registry::<DatabaseContext>::new();
+        registry.register(CreateUser).unwrap();
+
+        let result = registry.register(CreateAccount);
+        assert_eq!(
+            result,
+            Err(RegisterError::DuplicateId(OperationId::new("create")))
+        );
+        assert_eq!(registry.ids(), vec![OperationId::new("create")]);
+    }
+
+    #[test]
+    fn test_dispatch_by_name() {
+        let mut registry = Registry::new();
+        registry.register(CreateUser).unwrap();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let mut executor =
+            ApiExecutor::new(DatabaseContext::new("registry".to_string())).with_notifier(sender);
+
+        let output = executor
+            .execute_dynamic(&registry, "create", &"Alice".to_string())
+            .unwrap();
+        assert_eq!(output.downcast_ref::<u32>(), Some(&1));
+
+        let mismatch = executor.execute_dynamic(&registry, "create", &42u64);
+        assert!(matches!(mismatch, Err(DispatchError::ParameterMismatch(_))));
+
+        let unknown = executor.execute_dynamic(&registry, "delete", &());
+        assert!(matches!(unknown, Err(DispatchError::UnknownOperation(_))));
+
+        let reported: Vec<_> = receiver
+            .try_iter()
+            .map(|outcome| outcome.operation)
+            .collect();
+        assert_eq!(reported, vec!["create"]);
+    }
+}
@@ -0,0 +1,246 @@
+//! Applying write operations to a primary context and its replicas.
+
+use crate::{ApiExecutor, ApiOperation, LogLevel, Logger};
+use std::fmt;
+
+/// How replica failures affect the result of [`ReplicatedExecutor::execute_replicated`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConsistencyMode {
+    /// The write fails if the primary or any replica fails.
+    AllMustSucceed,
+
+    /// The write succeeds once the primary succeeds; replica failures are only logged.
+    #[default]
+    BestEffort,
+}
+
+/// The output of a replicated write along with the outcome on each replica.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Replicated<O> {
+    /// The output of the operation on the primary.
+    pub output: O,
+
+    /// Whether the operation succeeded on each replica, in the order they were added.
+    pub replicas: Vec<bool>,
+}
+
+impl<O> Replicated<O> {
+    /// Returns true if the operation succeeded on every replica.
+    pub fn fully_replicated(&self) -> bool {
+        self.replicas.iter().all(|&success| success)
+    }
+}
+
+/// Errors returned by [`ReplicatedExecutor::execute_replicated`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplicationError<E> {
+    /// The operation failed on the primary, so no replica was written.
+    Primary(E),
+
+    /// The operation failed on a replica under [`ConsistencyMode::AllMustSucceed`].
+    Replica {
+        /// The index of the first replica that failed.
+        replica: usize,
+
+        /// The error returned by that replica.
+        error: E,
+    },
+}
+
+impl<E: fmt::Display> fmt::Display for ReplicationError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplicationError::Primary(error) => write!(f, "write failed on primary: {}", error),
+            ReplicationError::Replica { replica, error } => {
+                write!(f, "write failed on replica {}: {}", replica, error)
+            }
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for ReplicationError<E> {}
+
+/// An executor that applies each write to a primary context and then to every replica.
+///
+/// Replicas are written synchronously, in the order they were added, after the
+/// primary succeeds. Writes already applied are not undone when a later replica fails.
+#[derive(Debug)]
+pub struct ReplicatedExecutor<C> {
+    /// The executor owning the primary context, whose logger reports replica failures.
+    primary: ApiExecutor<C>,
+
+    /// The executors owning the replica contexts.
+    replicas: Vec<ApiExecutor<C>>,
+
+    /// How replica failures affect the result.
+    mode: ConsistencyMode,
+}
+
+impl<C> ReplicatedExecutor<C> {
+    /// Creates an executor over `primary` without replicas, in best-effort mode.
+    pub fn new(primary: C) -> Self {
+        Self {
+            primary: ApiExecutor::new(primary),
+            replicas: Vec::new(),
+            mode: ConsistencyMode::default(),
+        }
+    }
+
+    /// Adds a replica context that receives every subsequent write.
+    pub fn add_replica(&mut self, context: C) {
+        self.replicas.push(ApiExecutor::new(context));
+    }
+
+    /// Sets how replica failures affect the result.
+    pub fn with_consistency(mut self, mode: ConsistencyMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Sends replica failures to `logger`.
+    pub fn with_logger(mut self, logger: impl Logger + 'static) -> Self {
+        self.primary = self.primary.with_logger(logger);
+        self
+    }
+
+    /// Executes a write on the primary and, if it succeeds, on every replica.
+    ///
+    /// In best-effort mode, each replica failure is logged as a warning and reported
+    /// in [`Replicated::replicas`]. Under [`ConsistencyMode::AllMustSucceed`], the
+    /// first replica failure is returned instead and later replicas are not written.
+    pub fn execute_replicated<P, Op>(
+        &mut self,
+        _op: Op,
+        parameters: &P,
+    ) -> Result<Replicated<Op::Output>, ReplicationError<Op::Error>>
+    where
+        Op: ApiOperation<C, P>,
+    {
+        let output = self
+            .primary
+            .execute_observed::<P, Op>(parameters)
+            .map_err(ReplicationError::Primary)?;
+
+        let mut replicas = Vec::with_capacity(self.replicas.len());
+        for (index, replica) in self.replicas.iter_mut().enumerate() {
+            match replica.execute_observed::<P, Op>(parameters) {
+                Ok(_) => replicas.push(true),
+                Err(error) => {
+                    if self.mode == ConsistencyMode::AllMustSucceed {
+                        return Err(ReplicationError::Replica {
+                            replica: index,
+                            error,
+                        });
+                    }
+                    self.primary.logger.log(
+                        LogLevel::Warn,
+                        Op::name(),
+                        format!("write failed on replica {}", index),
+                    );
+                    replicas.push(false);
+                }
+            }
+        }
+        Ok(Replicated { output, replicas })
+    }
+
+    /// Returns the primary context.
+    pub fn primary(&self) -> &C {
+        &self.primary.context
+    }
+
+    /// Returns the replica context at `index`, if there is one.
+    pub fn replica(&self, index: usize) -> Option<&C> {
+        self.replicas.get(index).map(|replica| &replica.context)
+    }
+
+    /// Returns the number of replicas.
+    pub fn replica_count(&self) -> usize {
+        self.replicas.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+    use crate::MemoryLogger;
+
+    /// Stores a value, failing on read-only connections.
+    struct Store;
+
+    impl ApiOperation<DatabaseContext, (&'static str, &'static str)> for Store {
+        type Output = usize;
+        type Error = String;
+
+        fn execute(
+            context: &mut DatabaseContext,
+            parameters: &(&'static str, &'static str),
+        ) -> Result<usize, String> {
+            if context.connection_pool().starts_with("readonly") {
+                return Err("read-only replica".to_string());
+            }
+            context
+                .cache_mut()
+                .insert(parameters.0.to_string(), parameters.1.to_string());
+            Ok(context.cache().len())
+        }
+
+        fn name() -> &'static str {
+            "store"
+        }
+    }
+
+    fn executor(
+        mode: ConsistencyMode,
+        logger: MemoryLogger,
+    ) -> ReplicatedExecutor<DatabaseContext> {
+        let mut executor = ReplicatedExecutor::new(DatabaseContext::new("primary".to_string()))
+            .with_consistency(mode)
+            .with_logger(logger);
+        executor.add_replica(DatabaseContext::new("replica".to_string()));
+        executor.add_replica(DatabaseContext::new("readonly".to_string()));
+        executor
+    }
+
+    #[test]
+    fn test_best_effort_returns_primary_result_and_logs_replica_failure() {
+        let logger = MemoryLogger::new();
+        let mut executor = executor(ConsistencyMode::BestEffort, logger.clone());
+
+        let result = executor
+            .execute_replicated(Store, &("user_1", "alice"))
+            .unwrap();
+
+        assert_eq!(result.output, 1);
+        assert_eq!(result.replicas, vec![true, false]);
+        assert!(!result.fully_replicated());
+        assert!(executor.primary().cache().contains_key("user_1"));
+        assert!(executor.replica(0).unwrap().cache().contains_key("user_1"));
+
+        let records = logger.records();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].level, LogLevel::Warn);
+        assert_eq!(records[0].operation, "store");
+        assert_eq!(records[0].message, "write failed on replica 1");
+    }
+
+    #[test]
+    fn test_all_must_succeed_reports_replica_failure() {
+        let logger = MemoryLogger::new();
+        let mut executor = executor(ConsistencyMode::AllMustSucceed, logger.clone());
+
+        let error = executor
+            .execute_replicated(Store, &("user_1", "alice"))
+            .unwrap_err();
+
+        assert_eq!(
+            error,
+            ReplicationError::Replica {
+                replica: 1,
+                error: "read-only replica".to_string()
+            }
+        );
+        assert!(logger.records().is_empty());
+    }
+}
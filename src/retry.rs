@@ -0,0 +1,303 @@
+//! Retrying failed operations with configurable backoff.
+
+use crate::{ApiExecutor, ApiOperation, Rng};
+use std::time::Duration;
+
+/// How backoff delays are randomized to keep retrying clients from synchronizing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Jitter {
+    /// Delays are used exactly as computed.
+    #[default]
+    None,
+
+    /// Delays are drawn uniformly from zero up to the computed delay.
+    Full,
+
+    /// Half the computed delay is kept and the other half is drawn uniformly.
+    Equal,
+}
+
+/// Describes how many times an operation is attempted and how long to wait between attempts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// The total number of attempts, including the first one.
+    max_attempts: u32,
+
+    /// The delay before the first retry.
+    initial_backoff: Duration,
+
+    /// The factor applied to the delay after each retry.
+    multiplier: f64,
+
+    /// The upper bound for any single delay.
+    max_backoff: Duration,
+
+    /// How delays are randomized.
+    jitter: Jitter,
+}
+
+impl RetryPolicy {
+    /// Creates a policy that attempts an operation up to `max_attempts` times without delay.
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            initial_backoff: Duration::ZERO,
+            multiplier: 1.0,
+            max_backoff: Duration::MAX,
+            jitter: Jitter::None,
+        }
+    }
+
+    /// Sets the delay before the first retry.
+    pub fn with_backoff(mut self, initial_backoff: Duration) -> Self {
+        self.initial_backoff = initial_backoff;
+        self
+    }
+
+    /// Sets the factor applied to the delay after each retry for exponential backoff.
+    pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier.max(1.0);
+        self
+    }
+
+    /// Sets the upper bound for any single delay.
+    pub fn with_max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Sets how delays are randomized.
+    pub fn with_jitter(mut self, jitter: Jitter) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Returns the total number of attempts, including the first one.
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// Returns the delay to wait after the given failed attempt (starting at 1).
+    pub fn backoff_for(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let factor = self.multiplier.powi(exponent);
+        let delay = self.initial_backoff.as_secs_f64() * factor;
+        if !delay.is_finite() || delay >= self.max_backoff.as_secs_f64() {
+            self.max_backoff
+        } else {
+            Duration::from_secs_f64(delay)
+        }
+    }
+
+    /// Returns the delay after the given failed attempt with jitter applied using `rng`.
+    ///
+    /// Without jitter this is the same as [`backoff_for`](Self::backoff_for) and `rng`
+    /// is not consulted.
+    pub fn jittered_backoff_for(&self, attempt: u32, rng: &mut dyn Rng) -> Duration {
+        self.jitter_with(attempt, || rng.next_f64())
+    }
+
+    /// Applies jitter to the delay for `attempt` using uniform samples from `sample`.
+    pub(crate) fn jitter_with(&self, attempt: u32, sample: impl FnOnce() -> f64) -> Duration {
+        let delay = self.backoff_for(attempt);
+        match self.jitter {
+            Jitter::None => delay,
+            Jitter::Full => delay.mul_f64(sample()),
+            Jitter::Equal => delay / 2 + (delay / 2).mul_f64(sample()),
+        }
+    }
+}
+
+/// Decides whether and after what delay a failed attempt is retried.
+///
+/// Implemented for [`RetryPolicy`], ignoring its jitter, and for any
+/// `FnMut(u32) -> Option<Duration>` closure, so custom schedules such as decorrelated
+/// jitter only take a closure.
+pub trait BackoffStrategy {
+    /// Returns the delay to wait after the given failed attempt (starting at 1), or
+    /// `None` to stop retrying.
+    fn next_delay(&mut self, attempt: u32) -> Option<Duration>;
+}
+
+impl BackoffStrategy for RetryPolicy {
+    fn next_delay(&mut self, attempt: u32) -> Option<Duration> {
+        (attempt < self.max_attempts).then(|| self.backoff_for(attempt))
+    }
+}
+
+impl<F: FnMut(u32) -> Option<Duration>> BackoffStrategy for F {
+    fn next_delay(&mut self, attempt: u32) -> Option<Duration> {
+        self(attempt)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new(3).with_backoff(Duration::from_millis(100))
+    }
+}
+
+impl<C> ApiExecutor<C> {
+    /// Executes an API operation, retrying failures according to `policy`.
+    ///
+    /// Returns the first successful output, or the error from the final attempt.
+    pub fn execute_with_retry<P, Op>(
+        &mut self,
+        op: Op,
+        parameters: &P,
+        policy: &RetryPolicy,
+    ) -> Result<Op::Output, Op::Error>
+    where
+        Op: ApiOperation<C, P>,
+    {
+        self.execute_with_retry_cb(op, parameters, policy, |_, _| {})
+    }
+
+    /// Executes an API operation with retries, reporting every failed attempt.
+    ///
+    /// `on_attempt` is called with the attempt number (starting at 1) and the error
+    /// after each failed attempt, before any backoff delay is applied.
+    pub fn execute_with_retry_cb<P, Op, F>(
+        &mut self,
+        _op: Op,
+        parameters: &P,
+        policy: &RetryPolicy,
+        mut on_attempt: F,
+    ) -> Result<Op::Output, Op::Error>
+    where
+        Op: ApiOperation<C, P>,
+        F: FnMut(u32, &Op::Error),
+    {
+        let mut attempt = 1;
+        loop {
+            match self.execute_observed::<P, Op>(parameters) {
+                Ok(output) => return Ok(output),
+                Err(error) => {
+                    on_attempt(attempt, &error);
+                    if attempt >= policy.max_attempts() {
+                        return Err(error);
+                    }
+                    let delay = policy.jitter_with(attempt, || self.rng.next_f64());
+                    if !delay.is_zero() {
+                        self.clock.sleep(delay);
+                    }
+                    attempt += 1;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+    use crate::{Clock, MockClock, SeededRng};
+
+    /// Fails until the context has recorded `parameters` transactions.
+    struct FlakyOperation;
+
+    impl ApiOperation<DatabaseContext, u32> for FlakyOperation {
+        type Output = u32;
+        type Error = String;
+
+        fn execute(context: &mut DatabaseContext, parameters: &u32) -> Result<u32, String> {
+            context.increment_transaction();
+            if context.transaction_count() < *parameters {
+                return Err(format!("attempt {} failed", context.transaction_count()));
+            }
+            Ok(context.transaction_count())
+        }
+    }
+
+    #[test]
+    fn test_retry_callback_fires_for_each_failed_attempt() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("retry".to_string()));
+        let policy = RetryPolicy::new(4);
+        let mut attempts = Vec::new();
+
+        let result = executor.execute_with_retry_cb(FlakyOperation, &4, &policy, |n, e| {
+            attempts.push((n, e.clone()));
+        });
+
+        assert_eq!(result, Ok(4));
+        assert_eq!(
+            attempts,
+            vec![
+                (1, "attempt 1 failed".to_string()),
+                (2, "attempt 2 failed".to_string()),
+                (3, "attempt 3 failed".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_retry_returns_last_error_when_exhausted() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("retry".to_string()));
+        let policy = RetryPolicy::new(2);
+
+        let result = executor.execute_with_retry(FlakyOperation, &5, &policy);
+
+        assert_eq!(result, Err("attempt 2 failed".to_string()));
+        assert_eq!(executor.context().transaction_count(), 2);
+    }
+
+    #[test]
+    fn test_backoff_grows_exponentially_up_to_max() {
+        let policy = RetryPolicy::new(5)
+            .with_backoff(Duration::from_millis(10))
+            .with_multiplier(2.0)
+            .with_max_backoff(Duration::from_millis(30));
+
+        assert_eq!(policy.backoff_for(1), Duration::from_millis(10));
+        assert_eq!(policy.backoff_for(2), Duration::from_millis(20));
+        assert_eq!(policy.backoff_for(3), Duration::from_millis(30));
+    }
+
+    #[test]
+    fn test_jittered_backoff_is_bounded_and_reproducible() {
+        let policy = RetryPolicy::new(4)
+            .with_backoff(Duration::from_millis(100))
+            .with_multiplier(2.0);
+        let full = policy.clone().with_jitter(Jitter::Full);
+        let equal = policy.clone().with_jitter(Jitter::Equal);
+
+        let delays = |policy: &RetryPolicy, seed| {
+            let mut rng = SeededRng::new(seed);
+            (1..=3)
+                .map(|attempt| policy.jittered_backoff_for(attempt, &mut rng))
+                .collect::<Vec<_>>()
+        };
+
+        for (attempt, delay) in (1..=3).zip(delays(&full, 7)) {
+            assert!(delay <= policy.backoff_for(attempt));
+        }
+        for (attempt, delay) in (1..=3).zip(delays(&equal, 7)) {
+            let base = policy.backoff_for(attempt);
+            assert!(delay >= base / 2 && delay <= base);
+        }
+        assert_eq!(delays(&full, 7), delays(&full, 7));
+        assert_ne!(delays(&full, 7), delays(&full, 8));
+    }
+
+    #[test]
+    fn test_retry_loop_sleeps_for_jittered_delays() {
+        let clock = MockClock::new();
+        let mut executor = ApiExecutor::new(DatabaseContext::new("retry".to_string()))
+            .with_clock(clock.clone())
+            .with_rng(SeededRng::new(3));
+        let policy = RetryPolicy::new(3)
+            .with_backoff(Duration::from_millis(100))
+            .with_jitter(Jitter::Full);
+        let start = clock.now();
+
+        executor
+            .execute_with_retry(FlakyOperation, &10, &policy)
+            .unwrap_err();
+
+        let mut rng = SeededRng::new(3);
+        let expected =
+            policy.jittered_backoff_for(1, &mut rng) + policy.jittered_backoff_for(2, &mut rng);
+        assert_eq!(clock.now() - start, expected);
+    }
+}
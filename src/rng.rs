@@ -0,0 +1,104 @@
+//! Injectable randomness for features such as retry jitter.
+
+use crate::ApiExecutor;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A source of random numbers used by randomized executor features.
+pub trait Rng: Send {
+    /// Returns the next random 64-bit value.
+    fn next_u64(&mut self) -> u64;
+
+    /// Returns a random value uniformly distributed in `[0, 1)`.
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// A small, fast, deterministic generator (SplitMix64).
+///
+/// Not suitable for cryptography. The same seed always yields the same sequence,
+/// which makes randomized behavior reproducible in tests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeededRng {
+    /// The generator state.
+    state: u64,
+}
+
+impl SeededRng {
+    /// Creates a generator producing the sequence determined by `seed`.
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Creates a generator seeded from the system time.
+    pub fn from_time() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos() as u64);
+        Self::new(nanos)
+    }
+}
+
+impl Rng for SeededRng {
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut value = self.state;
+        value = (value ^ (value >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        value = (value ^ (value >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        value ^ (value >> 31)
+    }
+}
+
+/// The random number generator installed on an executor, shared by its clones.
+#[derive(Clone)]
+pub(crate) struct RngHandle(Arc<Mutex<dyn Rng>>);
+
+impl RngHandle {
+    /// Returns a random value uniformly distributed in `[0, 1)`.
+    pub(crate) fn next_f64(&self) -> f64 {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .next_f64()
+    }
+}
+
+impl Default for RngHandle {
+    fn default() -> Self {
+        Self(Arc::new(Mutex::new(SeededRng::from_time())))
+    }
+}
+
+impl fmt::Debug for RngHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("RngHandle").finish()
+    }
+}
+
+impl<C> ApiExecutor<C> {
+    /// Installs the random number generator used for retry jitter.
+    pub fn with_rng(mut self, rng: impl Rng + 'static) -> Self {
+        self.rng = RngHandle(Arc::new(Mutex::new(rng)));
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_seed_yields_same_sequence() {
+        let mut first = SeededRng::new(42);
+        let mut second = SeededRng::new(42);
+
+        let a: Vec<u64> = (0..4).map(|_| first.next_u64()).collect();
+        let b: Vec<u64> = (0..4).map(|_| second.next_u64()).collect();
+
+        assert_eq!(a, b);
+        assert_ne!(a[0], a[1]);
+        assert!((0..1000).all(|_| (0.0..1.0).contains(&first.next_f64())));
+    }
+}
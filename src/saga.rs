@@ -0,0 +1,242 @@
+//! Multi-step sagas with compensating operations.
+
+use crate::{ApiExecutor, ApiOperation};
+use std::fmt;
+
+/// A single type-erased saga step.
+trait SagaStep<C, E> {
+    /// Runs the forward operation.
+    fn forward(&self, context: &mut C) -> Result<(), E>;
+
+    /// Runs the compensation, returning whether it succeeded.
+    fn compensate(&self, context: &mut C) -> bool;
+
+    /// Returns the diagnostic name of the forward operation.
+    fn forward_name(&self) -> &'static str;
+
+    /// Returns the diagnostic name of the compensation.
+    fn compensation_name(&self) -> &'static str;
+}
+
+/// A saga step built from a forward operation, its compensation and their parameters.
+struct OperationStep<Fwd, Comp, P> {
+    /// The parameters passed to both operations.
+    parameters: P,
+
+    /// Marker for the operation types of this step.
+    _operations: std::marker::PhantomData<fn() -> (Fwd, Comp)>,
+}
+
+impl<C, E, P, Fwd, Comp> SagaStep<C, E> for OperationStep<Fwd, Comp, P>
+where
+    Fwd: ApiOperation<C, P>,
+    Fwd::Error: Into<E>,
+    Comp: ApiOperation<C, P>,
+{
+    fn forward(&self, context: &mut C) -> Result<(), E> {
+        Fwd::execute(context, &self.parameters)
+            .map(|_| ())
+            .map_err(Into::into)
+    }
+
+    fn compensate(&self, context: &mut C) -> bool {
+        Comp::execute(context, &self.parameters).is_ok()
+    }
+
+    fn forward_name(&self) -> &'static str {
+        Fwd::name()
+    }
+
+    fn compensation_name(&self) -> &'static str {
+        Comp::name()
+    }
+}
+
+/// An ordered list of steps, each pairing a forward operation with its compensation.
+pub struct Saga<'a, C, E> {
+    /// The steps in execution order.
+    steps: Vec<Box<dyn SagaStep<C, E> + 'a>>,
+}
+
+impl<'a, C, E> Saga<'a, C, E> {
+    /// Creates an empty saga.
+    pub fn new() -> Self {
+        Self { steps: Vec::new() }
+    }
+
+    /// Appends a step that runs `Fwd` and is undone by `Comp`, both with `parameters`.
+    pub fn step<P, Fwd, Comp>(mut self, _forward: Fwd, _compensation: Comp, parameters: P) -> Self
+    where
+        P: 'a,
+        Fwd: ApiOperation<C, P> + 'a,
+        Fwd::Error: Into<E>,
+        Comp: ApiOperation<C, P> + 'a,
+    {
+        self.steps.push(Box::new(OperationStep::<Fwd, Comp, P> {
+            parameters,
+            _operations: std::marker::PhantomData,
+        }));
+        self
+    }
+
+    /// Returns the number of steps in the saga.
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    /// Returns true if the saga has no steps.
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+}
+
+impl<C, E> Default for Saga<'_, C, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C, E> fmt::Debug for Saga<'_, C, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Saga")
+            .field("steps", &self.steps.len())
+            .finish()
+    }
+}
+
+/// The error returned when a saga step fails and earlier steps have been compensated.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SagaError<E> {
+    /// The index of the step that failed.
+    pub failed_step: usize,
+
+    /// The error returned by the failed step.
+    pub error: E,
+
+    /// The indices of steps whose compensation also failed.
+    pub failed_compensations: Vec<usize>,
+}
+
+impl<C> ApiExecutor<C> {
+    /// Executes the steps of `saga` in order, rolling back on failure.
+    ///
+    /// When step `N` fails, the compensations for steps `N-1` down to `0` run in
+    /// reverse order before the error is returned. Every forward operation and
+    /// compensation is reported like `execute`.
+    pub fn execute_sequence_with_rollback_chain<E>(
+        &mut self,
+        saga: Saga<'_, C, E>,
+    ) -> Result<(), SagaError<E>> {
+        for (index, step) in saga.steps.iter().enumerate() {
+            let started = self.clock.now();
+            let result = step.forward(&mut self.context);
+            self.observe(step.forward_name(), started, result.is_ok());
+            if let Err(error) = result {
+                let failed_compensations = saga.steps[..index]
+                    .iter()
+                    .enumerate()
+                    .rev()
+                    .filter(|(_, completed)| {
+                        let started = self.clock.now();
+                        let compensated = completed.compensate(&mut self.context);
+                        self.observe(completed.compensation_name(), started, compensated);
+                        !compensated
+                    })
+                    .map(|(completed_index, _)| completed_index)
+                    .collect();
+                return Err(SagaError {
+                    failed_step: index,
+                    error,
+                    failed_compensations,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+
+    /// Appends an entry to the "log" cache entry.
+    fn log(context: &mut DatabaseContext, entry: &str) {
+        let log = context.cache_mut().entry("log".to_string()).or_default();
+        if !log.is_empty() {
+            log.push(',');
+        }
+        log.push_str(entry);
+    }
+
+    struct Reserve;
+    struct Release;
+
+    impl ApiOperation<DatabaseContext, String> for Reserve {
+        type Output = ();
+        type Error = String;
+
+        fn execute(context: &mut DatabaseContext, parameters: &String) -> Result<(), String> {
+            if parameters == "payment" {
+                return Err("payment declined".to_string());
+            }
+            log(context, &format!("reserve {}", parameters));
+            Ok(())
+        }
+    }
+
+    impl ApiOperation<DatabaseContext, String> for Release {
+        type Output = ();
+        type Error = ();
+
+        fn execute(context: &mut DatabaseContext, parameters: &String) -> Result<(), ()> {
+            log(context, &format!("release {}", parameters));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_saga_compensates_in_reverse_order() {
+        let mut executor =
+            ApiExecutor::new(DatabaseContext::new("saga".to_string())).with_audit_log();
+        let saga = Saga::<DatabaseContext, String>::new()
+            .step(Reserve, Release, "inventory".to_string())
+            .step(Reserve, Release, "shipping".to_string())
+            .step(Reserve, Release, "payment".to_string());
+
+        let error = executor
+            .execute_sequence_with_rollback_chain(saga)
+            .unwrap_err();
+
+        assert_eq!(error.failed_step, 2);
+        assert_eq!(error.error, "payment declined");
+        assert!(error.failed_compensations.is_empty());
+        assert_eq!(
+            executor.context().cache().get("log").unwrap(),
+            "reserve inventory,reserve shipping,release shipping,release inventory"
+        );
+        let audited: Vec<_> = executor
+            .audit_entries()
+            .iter()
+            .map(|entry| (entry.operation, entry.success))
+            .collect();
+        assert_eq!(audited.len(), 5);
+        assert_eq!(audited[2], (Reserve::name(), false));
+        assert_eq!(audited[3], (Release::name(), true));
+    }
+
+    #[test]
+    fn test_saga_completes_without_compensation() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("saga".to_string()));
+        let saga = Saga::<DatabaseContext, String>::new()
+            .step(Reserve, Release, "inventory".to_string())
+            .step(Reserve, Release, "shipping".to_string());
+
+        executor.execute_sequence_with_rollback_chain(saga).unwrap();
+
+        assert_eq!(
+            executor.context().cache().get("log").unwrap(),
+            "reserve inventory,reserve shipping"
+        );
+    }
+}
@@ -0,0 +1,135 @@
+//! Probabilistic sampling of detailed execution traces.
+
+use crate::log::LogLevel;
+use crate::{ApiExecutor, ApiOperation};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+impl<C> ApiExecutor<C> {
+    /// Traces only a fraction `rate` of
+    /// [`execute_with_sampling`](Self::execute_with_sampling) calls.
+    ///
+    /// `rate` is clamped to `[0, 1]`. Without sampling configured every call is traced.
+    pub fn with_trace_sampling(mut self, rate: f64) -> Self {
+        self.trace_sample_rate = Some(rate.clamp(0.0, 1.0));
+        self
+    }
+
+    /// Executes an operation and, if the execution is sampled, logs a debug span
+    /// recording its outcome and duration.
+    ///
+    /// When a [`CorrelationContext`](crate::CorrelationContext) is set, the decision is
+    /// derived from its request id so every execution within one trace is either traced
+    /// or not. Otherwise each call is decided with the executor's [`Rng`](crate::Rng).
+    pub fn execute_with_sampling<P, Op>(
+        &mut self,
+        op: Op,
+        parameters: &P,
+    ) -> Result<Op::Output, Op::Error>
+    where
+        Op: ApiOperation<C, P>,
+    {
+        let sampled = self.sample_trace();
+        let started = self.clock.now();
+        let result = self.execute(op, parameters);
+        if sampled {
+            let outcome = if result.is_ok() {
+                "succeeded"
+            } else {
+                "failed"
+            };
+            self.logger.log(
+                LogLevel::Debug,
+                Op::name(),
+                format!("span {} in {:?}", outcome, self.clock.now() - started),
+            );
+        }
+        result
+    }
+
+    /// Decides whether the next execution is traced.
+    fn sample_trace(&self) -> bool {
+        let Some(rate) = self.trace_sample_rate else {
+            return true;
+        };
+        let draw = match &self.correlation {
+            Some(correlation) => {
+                let mut hasher = DefaultHasher::new();
+                correlation.request_id.hash(&mut hasher);
+                (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+            }
+            None => self.rng.next_f64(),
+        };
+        draw < rate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+    use crate::{CorrelationContext, MemoryLogger, SeededRng};
+
+    struct Increment;
+
+    impl ApiOperation<DatabaseContext, ()> for Increment {
+        type Output = ();
+        type Error = ();
+
+        fn execute(context: &mut DatabaseContext, _parameters: &()) -> Result<(), ()> {
+            context.increment_transaction();
+            Ok(())
+        }
+    }
+
+    /// Runs `count` sampled executions and returns which of them were traced.
+    fn sampled_runs(
+        executor: &mut ApiExecutor<DatabaseContext>,
+        logger: &MemoryLogger,
+        count: usize,
+    ) -> Vec<bool> {
+        (0..count)
+            .map(|_| {
+                let before = logger.records().len();
+                executor.execute_with_sampling(Increment, &()).unwrap();
+                logger.records().len() > before
+            })
+            .collect()
+    }
+
+    fn executor(seed: u64, logger: &MemoryLogger) -> ApiExecutor<DatabaseContext> {
+        ApiExecutor::new(DatabaseContext::new("sampling".to_string()))
+            .with_rng(SeededRng::new(seed))
+            .with_logger(logger.clone())
+            .with_trace_sampling(0.5)
+    }
+
+    #[test]
+    fn test_half_of_executions_are_sampled_reproducibly() {
+        let first_logger = MemoryLogger::new();
+        let second_logger = MemoryLogger::new();
+
+        let first = sampled_runs(&mut executor(7, &first_logger), &first_logger, 1000);
+        let second = sampled_runs(&mut executor(7, &second_logger), &second_logger, 1000);
+
+        let traced = first.iter().filter(|&&sampled| sampled).count();
+        assert!((400..=600).contains(&traced), "traced {} of 1000", traced);
+        assert_eq!(first, second);
+        assert_eq!(first_logger.records()[0].level, LogLevel::Debug);
+    }
+
+    #[test]
+    fn test_decision_is_consistent_within_a_trace() {
+        let logger = MemoryLogger::new();
+        let mut executor = executor(7, &logger);
+
+        for request in 0..20 {
+            executor.set_correlation_context(Some(CorrelationContext::new(
+                format!("req-{}", request),
+                "span",
+            )));
+            let runs = sampled_runs(&mut executor, &logger, 10);
+            assert!(runs.iter().all(|&sampled| sampled == runs[0]));
+        }
+    }
+}
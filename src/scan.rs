@@ -0,0 +1,138 @@
+//! Repeated execution of operations that decide when to stop.
+
+use crate::ApiExecutor;
+use std::ops::ControlFlow;
+
+/// An operation that is run repeatedly until it produces a `Break` value.
+///
+/// Typical uses are paginated fetches that continue until an empty page is returned.
+/// Any state needed between iterations, such as a cursor, lives in the context.
+pub trait ScanOperation<C, P> {
+    /// The value produced when the scan stops.
+    type Break;
+
+    /// The value produced by each iteration that continues the scan.
+    type Continue;
+
+    /// The error type returned when an iteration fails.
+    type Error;
+
+    /// Runs one iteration of the scan.
+    fn execute(
+        context: &mut C,
+        parameters: &P,
+    ) -> Result<ControlFlow<Self::Break, Self::Continue>, Self::Error>;
+
+    /// Returns the diagnostic name of the operation, defaulting to its type name.
+    fn name() -> &'static str {
+        std::any::type_name::<Self>()
+    }
+}
+
+/// The result of a completed scan.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanOutcome<B, T> {
+    /// The values produced by every continuing iteration, in order.
+    pub values: Vec<T>,
+
+    /// The value produced by the final iteration.
+    pub result: B,
+}
+
+impl<C> ApiExecutor<C> {
+    /// Runs a scan operation until it breaks, accumulating the `Continue` values.
+    ///
+    /// Stops at the first error, discarding the values accumulated so far.
+    pub fn scan<P, Op>(
+        &mut self,
+        _op: Op,
+        parameters: &P,
+    ) -> Result<ScanOutcome<Op::Break, Op::Continue>, Op::Error>
+    where
+        Op: ScanOperation<C, P>,
+    {
+        let mut values = Vec::new();
+        loop {
+            let started = self.clock.now();
+            let result = Op::execute(&mut self.context, parameters);
+            self.observe(Op::name(), started, result.is_ok());
+            match result? {
+                ControlFlow::Continue(value) => values.push(value),
+                ControlFlow::Break(result) => return Ok(ScanOutcome { values, result }),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+
+    /// Fetches the page at the context's cursor, stopping at the first empty page.
+    struct FetchPage;
+
+    impl ScanOperation<DatabaseContext, usize> for FetchPage {
+        type Break = u32;
+        type Continue = Vec<String>;
+        type Error = String;
+
+        fn execute(
+            context: &mut DatabaseContext,
+            parameters: &usize,
+        ) -> Result<ControlFlow<u32, Vec<String>>, String> {
+            let page = context.transaction_count();
+            let key = format!("page_{}", page);
+            let items: Vec<String> = match context.cache().get(&key) {
+                Some(value) if value == "error" => return Err(key),
+                Some(value) => value
+                    .split(',')
+                    .take(*parameters)
+                    .map(str::to_string)
+                    .collect(),
+                None => Vec::new(),
+            };
+            if items.is_empty() {
+                return Ok(ControlFlow::Break(page));
+            }
+            context.increment_transaction();
+            Ok(ControlFlow::Continue(items))
+        }
+    }
+
+    fn context_with_pages(pages: &[&str]) -> DatabaseContext {
+        let mut context = DatabaseContext::new("scan".to_string());
+        for (index, page) in pages.iter().enumerate() {
+            context
+                .cache_mut()
+                .insert(format!("page_{}", index), page.to_string());
+        }
+        context
+    }
+
+    #[test]
+    fn test_scan_stops_on_break_and_accumulates() {
+        let mut executor = ApiExecutor::new(context_with_pages(&["a,b", "c"]));
+
+        let outcome = executor.scan(FetchPage, &10).unwrap();
+
+        assert_eq!(
+            outcome.values,
+            vec![
+                vec!["a".to_string(), "b".to_string()],
+                vec!["c".to_string()]
+            ]
+        );
+        assert_eq!(outcome.result, 2);
+    }
+
+    #[test]
+    fn test_scan_stops_on_error() {
+        let mut executor = ApiExecutor::new(context_with_pages(&["a", "error", "c"]));
+
+        let result = executor.scan(FetchPage, &10);
+
+        assert_eq!(result, Err("page_1".to_string()));
+        assert_eq!(executor.context().transaction_count(), 1);
+    }
+}
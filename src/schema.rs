@@ -0,0 +1,227 @@
+//! Validating dynamically dispatched outputs against their declared JSON Schema.
+
+use crate::{ApiExecutor, DispatchError, OperationId, Registry};
+use std::any::Any;
+use std::fmt;
+
+/// An output that does not conform to its operation's declared JSON Schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaViolation {
+    /// The operation whose output was rejected.
+    pub operation: OperationId,
+
+    /// A description of each way the output departs from the schema.
+    pub errors: Vec<String>,
+}
+
+impl fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "output of `{}` violates its schema: {}",
+            self.operation,
+            self.errors.join("; ")
+        )
+    }
+}
+
+impl std::error::Error for SchemaViolation {}
+
+/// Errors returned by [`ApiExecutor::execute_with_output_validation_schema`].
+#[derive(Debug)]
+pub enum OutputValidationError {
+    /// Dispatching the operation failed.
+    Dispatch(DispatchError),
+
+    /// The output could not be serialized for validation.
+    Encode {
+        /// The operation whose output failed to serialize.
+        operation: OperationId,
+
+        /// The underlying serialization error.
+        source: serde_json::Error,
+    },
+
+    /// The output does not conform to the declared schema.
+    SchemaViolation(SchemaViolation),
+}
+
+impl fmt::Display for OutputValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputValidationError::Dispatch(error) => error.fmt(f),
+            OutputValidationError::Encode { operation, source } => {
+                write!(f, "could not encode output of `{}`: {}", operation, source)
+            }
+            OutputValidationError::SchemaViolation(violation) => violation.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for OutputValidationError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            OutputValidationError::Dispatch(error) => Some(error),
+            OutputValidationError::Encode { source, .. } => Some(source),
+            OutputValidationError::SchemaViolation(violation) => Some(violation),
+        }
+    }
+}
+
+impl<C> ApiExecutor<C> {
+    /// Executes an operation from `registry` by name, checking its output against the
+    /// schema declared with [`Registry::register_with_output_schema`].
+    ///
+    /// Catches drift between the Rust output type and the published contract before the
+    /// output reaches a caller. Operations registered without a schema are not checked.
+    /// The operation is dispatched through [`execute_dynamic`](Self::execute_dynamic), so
+    /// it is reported like any other dispatch.
+    pub fn execute_with_output_validation_schema(
+        &mut self,
+        registry: &Registry<C>,
+        name: &str,
+        parameters: &dyn Any,
+    ) -> Result<Box<dyn Any + Send>, OutputValidationError> {
+        let output = self
+            .execute_dynamic(registry, name, parameters)
+            .map_err(OutputValidationError::Dispatch)?;
+        let (operation, registered) = registry
+            .get_entry(name)
+            .expect("dispatched operations are registered");
+        if let (Some(schema), Some(encode)) = (&registered.schema, &registered.encode) {
+            let value = encode(output.as_ref())
+                .map_err(|source| OutputValidationError::Encode { operation, source })?;
+            let errors: Vec<String> = match schema.validate(&value) {
+                Ok(()) => Vec::new(),
+                Err(errors) => errors.map(|error| error.to_string()).collect(),
+            };
+            if !errors.is_empty() {
+                return Err(OutputValidationError::SchemaViolation(SchemaViolation {
+                    operation,
+                    errors,
+                }));
+            }
+        }
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+    use crate::{ApiOperation, Identified};
+    use schemars::{schema_for, JsonSchema};
+    use serde::Serialize;
+
+    #[derive(Debug, PartialEq, Serialize, JsonSchema)]
+    struct User {
+        id: u32,
+        email: String,
+    }
+
+    /// The published contract, which still calls the field `email_address`.
+    #[derive(JsonSchema)]
+    #[allow(dead_code)]
+    struct PublishedUser {
+        id: u32,
+        email_address: String,
+    }
+
+    struct CreateUser;
+
+    impl Identified for CreateUser {
+        const OP_ID: OperationId = OperationId::new("create_user");
+    }
+
+    impl ApiOperation<DatabaseContext, String> for CreateUser {
+        type Output = User;
+        type Error = ();
+
+        fn execute(context: &mut DatabaseContext, parameters: &String) -> Result<User, ()> {
+            context.increment_transaction();
+            Ok(User {
+                id: context.transaction_count(),
+                email: parameters.clone(),
+            })
+        }
+    }
+
+    fn executor() -> ApiExecutor<DatabaseContext> {
+        ApiExecutor::new(DatabaseContext::new("schema".to_string()))
+    }
+
+    #[test]
+    fn test_conforming_output_passes() {
+        let mut registry = Registry::new();
+        registry
+            .register_with_output_schema(CreateUser, schema_for!(User))
+            .unwrap();
+
+        let mut executor = executor().with_audit_log();
+
+        let output = executor
+            .execute_with_output_validation_schema(
+                &registry,
+                "create_user",
+                &"alice@example.com".to_string(),
+            )
+            .unwrap();
+
+        assert_eq!(
+            output.downcast_ref::<User>(),
+            Some(&User {
+                id: 1,
+                email: "alice@example.com".to_string()
+            })
+        );
+        assert_eq!(executor.audit_entries().len(), 1);
+        assert_eq!(executor.audit_entries()[0].operation, "create_user");
+    }
+
+    #[test]
+    fn test_mismatched_schema_is_a_violation() {
+        let mut registry = Registry::new();
+        registry
+            .register_with_output_schema(CreateUser, schema_for!(PublishedUser))
+            .unwrap();
+
+        let result = executor().execute_with_output_validation_schema(
+            &registry,
+            "create_user",
+            &"alice@example.com".to_string(),
+        );
+
+        let Err(OutputValidationError::SchemaViolation(violation)) = result else {
+            panic!("expected a schema violation");
+        };
+        assert_eq!(violation.operation, CreateUser::OP_ID);
+        assert!(violation
+            .errors
+            .iter()
+            .any(|error| error.contains("email_address")));
+    }
+
+    #[test]
+    fn test_configured_allowlist_is_enforced() {
+        let mut registry = Registry::new();
+        registry
+            .register_with_output_schema(CreateUser, schema_for!(User))
+            .unwrap();
+        let mut executor = executor().with_allowlist(["read_user"]);
+
+        let result = executor.execute_with_output_validation_schema(
+            &registry,
+            "create_user",
+            &"alice@example.com".to_string(),
+        );
+
+        assert!(matches!(
+            result,
+            Err(OutputValidationError::Dispatch(
+                DispatchError::OperationForbidden(_)
+            ))
+        ));
+        assert_eq!(executor.context().transaction_count(), 0);
+    }
+}
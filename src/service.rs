@@ -0,0 +1,131 @@
+//! Adapter exposing API operations as `tower` services.
+
+use crate::ApiOperation;
+use std::future::{ready, Ready};
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+/// Runs an [`ApiOperation`] as a [`tower::Service`] over a shared context.
+///
+/// Each request is executed as the operation's parameters while holding the context
+/// lock, so the adapter can be cloned freely and mounted behind any tower middleware.
+pub struct ServiceAdapter<Op, C> {
+    /// The context shared by every clone of this adapter.
+    context: Arc<Mutex<C>>,
+
+    /// Marker for the operation type executed by this adapter.
+    _op: PhantomData<fn() -> Op>,
+}
+
+impl<Op, C> ServiceAdapter<Op, C> {
+    /// Creates a new adapter that owns the provided context.
+    pub fn new(context: C) -> Self {
+        Self::from_shared(Arc::new(Mutex::new(context)))
+    }
+
+    /// Creates a new adapter over an already shared context.
+    pub fn from_shared(context: Arc<Mutex<C>>) -> Self {
+        Self {
+            context,
+            _op: PhantomData,
+        }
+    }
+
+    /// Returns the shared context used by this adapter.
+    pub fn context(&self) -> &Arc<Mutex<C>> {
+        &self.context
+    }
+}
+
+impl<Op, C> Clone for ServiceAdapter<Op, C> {
+    fn clone(&self) -> Self {
+        Self::from_shared(Arc::clone(&self.context))
+    }
+}
+
+impl<Op, C> std::fmt::Debug for ServiceAdapter<Op, C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ServiceAdapter")
+            .field("operation", &std::any::type_name::<Op>())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<Op, C, P> tower::Service<P> for ServiceAdapter<Op, C>
+where
+    Op: ApiOperation<C, P>,
+{
+    type Response = Op::Output;
+    type Error = Op::Error;
+    type Future = Ready<Result<Op::Output, Op::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, parameters: P) -> Self::Future {
+        // A panic inside a previous operation must not take the service down with it.
+        let mut context = self
+            .context
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        ready(Op::execute(&mut context, &parameters))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+    use std::future::Future;
+    use std::pin::pin;
+    use std::task::{Wake, Waker};
+    use tower::Service;
+
+    struct NoopWaker;
+
+    impl Wake for NoopWaker {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    fn poll_once<F: Future>(future: F) -> F::Output {
+        let waker = Waker::from(Arc::new(NoopWaker));
+        let mut cx = Context::from_waker(&waker);
+        match pin!(future).poll(&mut cx) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("service future was not ready"),
+        }
+    }
+
+    struct CountTransactions;
+
+    impl ApiOperation<DatabaseContext, u32> for CountTransactions {
+        type Output = u32;
+        type Error = ();
+
+        fn execute(context: &mut DatabaseContext, parameters: &u32) -> Result<u32, ()> {
+            for _ in 0..*parameters {
+                context.increment_transaction();
+            }
+            Ok(context.transaction_count())
+        }
+    }
+
+    #[test]
+    fn test_service_adapter_sequential_requests() {
+        let mut service: ServiceAdapter<CountTransactions, _> =
+            ServiceAdapter::new(DatabaseContext::new("tower".to_string()));
+        let waker = Waker::from(Arc::new(NoopWaker));
+        let mut cx = Context::from_waker(&waker);
+
+        assert!(service.poll_ready(&mut cx).is_ready());
+        assert_eq!(poll_once(service.call(2)), Ok(2));
+
+        assert!(service.poll_ready(&mut cx).is_ready());
+        assert_eq!(poll_once(service.call(3)), Ok(5));
+
+        let context = service.context().lock().unwrap();
+        assert_eq!(context.transaction_count(), 5);
+    }
+}
@@ -0,0 +1,180 @@
+//! Injecting external services into operations alongside their context.
+
+use crate::ApiExecutor;
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// A container of services, such as HTTP clients or mailers, keyed by type.
+///
+/// Services hold dependencies that do not belong in the durable context. Clones share
+/// the same service instances.
+#[derive(Clone, Default)]
+pub struct Services {
+    /// The registered services, each stored under its own type.
+    entries: HashMap<TypeId, Arc<dyn Any + Send + Sync>>,
+}
+
+impl Services {
+    /// Creates an empty container.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `service`, replacing any previous service of the same type.
+    pub fn insert<S: Send + Sync + 'static>(&mut self, service: S) {
+        self.entries.insert(TypeId::of::<S>(), Arc::new(service));
+    }
+
+    /// Returns the service of type `S`, if one is registered.
+    pub fn get<S: 'static>(&self) -> Option<&S> {
+        self.entries
+            .get(&TypeId::of::<S>())
+            .and_then(|service| service.downcast_ref::<S>())
+    }
+
+    /// Returns true if a service of type `S` is registered.
+    pub fn contains<S: 'static>(&self) -> bool {
+        self.entries.contains_key(&TypeId::of::<S>())
+    }
+
+    /// Returns the number of registered services.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if no services are registered.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl fmt::Debug for Services {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Services")
+            .field("entries", &self.entries.len())
+            .finish()
+    }
+}
+
+/// An operation that uses injected [`Services`] in addition to its context.
+pub trait ApiOperationWithServices<C, P> {
+    /// The type returned by a successful operation execution.
+    type Output;
+
+    /// The error type returned when an operation fails.
+    type Error;
+
+    /// Execute the operation with the given context, services and parameters.
+    fn execute(
+        context: &mut C,
+        services: &Services,
+        parameters: &P,
+    ) -> Result<Self::Output, Self::Error>;
+
+    /// Returns the diagnostic name of the operation, defaulting to its type name.
+    fn name() -> &'static str {
+        std::any::type_name::<Self>()
+    }
+}
+
+impl<C> ApiExecutor<C> {
+    /// Registers a service for operations run through
+    /// [`execute_with_dependency_injection`](Self::execute_with_dependency_injection),
+    /// replacing any previous service of the same type.
+    pub fn register_service<S: Send + Sync + 'static>(&mut self, service: S) {
+        self.services.insert(service);
+    }
+
+    /// Returns the registered services.
+    pub fn services(&self) -> &Services {
+        &self.services
+    }
+
+    /// Executes an operation with this executor's context and registered services.
+    pub fn execute_with_dependency_injection<P, Op>(
+        &mut self,
+        _op: Op,
+        parameters: &P,
+    ) -> Result<Op::Output, Op::Error>
+    where
+        Op: ApiOperationWithServices<C, P>,
+    {
+        let started = self.clock.now();
+        let result = Op::execute(&mut self.context, &self.services, parameters);
+        self.observe(Op::name(), started, result.is_ok());
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+    use std::sync::Mutex;
+
+    /// A mailer that records messages instead of sending them.
+    #[derive(Default)]
+    struct MockMailer {
+        sent: Mutex<Vec<(String, String)>>,
+    }
+
+    impl MockMailer {
+        fn send(&self, to: &str, body: &str) {
+            self.sent
+                .lock()
+                .unwrap()
+                .push((to.to_string(), body.to_string()));
+        }
+    }
+
+    struct InviteUser;
+
+    impl ApiOperationWithServices<DatabaseContext, String> for InviteUser {
+        type Output = u32;
+        type Error = &'static str;
+
+        fn execute(
+            context: &mut DatabaseContext,
+            services: &Services,
+            parameters: &String,
+        ) -> Result<u32, &'static str> {
+            let mailer = services.get::<MockMailer>().ok_or("no mailer registered")?;
+            context.increment_transaction();
+            mailer.send(parameters, "You're invited!");
+            Ok(context.transaction_count())
+        }
+    }
+
+    #[test]
+    fn test_operation_uses_registered_mailer() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("services".to_string()));
+        executor.register_service(MockMailer::default());
+
+        let id = executor
+            .execute_with_dependency_injection(InviteUser, &"alice@example.com".to_string())
+            .unwrap();
+
+        assert_eq!(id, 1);
+        let mailer = executor.services().get::<MockMailer>().unwrap();
+        assert_eq!(
+            *mailer.sent.lock().unwrap(),
+            vec![(
+                "alice@example.com".to_string(),
+                "You're invited!".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_missing_service_is_reported_by_operation() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("services".to_string()));
+
+        let result = executor
+            .execute_with_dependency_injection(InviteUser, &"alice@example.com".to_string());
+
+        assert_eq!(result, Err("no mailer registered"));
+        assert_eq!(executor.context().transaction_count(), 0);
+    }
+}
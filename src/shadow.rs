@@ -0,0 +1,251 @@
+//! Shadow execution of candidate implementations on sampled traffic.
+
+use crate::log::LogLevel;
+use crate::{ApiExecutor, ApiOperation};
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+
+/// The entry point of a shadow implementation.
+type Implementation<C, P, O, E> = fn(&mut C, &P) -> Result<O, E>;
+
+/// A disagreement between a primary operation and its shadow.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShadowMismatch {
+    /// The name of the primary operation.
+    pub operation: &'static str,
+
+    /// The primary's result, rendered with `Debug`.
+    pub primary: String,
+
+    /// The shadow's result, rendered with `Debug`, or `"panicked"` if it panicked.
+    pub shadow: String,
+}
+
+/// A shadow implementation with the fraction of executions it runs on.
+#[derive(Clone)]
+struct Shadow {
+    /// The fraction of executions shadowed, in `[0, 1]`.
+    rate: f64,
+
+    /// An [`Implementation`] for the primary's context, parameter, output and error types.
+    implementation: Arc<dyn Any + Send + Sync>,
+}
+
+/// Shadow implementations by primary operation, and the mismatches they found.
+///
+/// Clones of an executor share the shadows registered before cloning.
+#[derive(Clone, Default)]
+pub(crate) struct ShadowMap {
+    /// A [`Shadow`] per `(Primary, P)` combination.
+    shadows: HashMap<TypeId, Shadow>,
+
+    /// The mismatches recorded so far, oldest first.
+    mismatches: Vec<ShadowMismatch>,
+}
+
+impl fmt::Debug for ShadowMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ShadowMap")
+            .field("shadows", &self.shadows.len())
+            .field("mismatches", &self.mismatches.len())
+            .finish()
+    }
+}
+
+impl<C: 'static> ApiExecutor<C> {
+    /// Runs `ShadowOp` alongside `Primary` on a fraction `rate` of
+    /// [`execute_weighted_sample_for_shadow_traffic`](Self::execute_weighted_sample_for_shadow_traffic)
+    /// calls with parameters of type `P`.
+    ///
+    /// `rate` is clamped to `[0, 1]`. Registering again replaces the previous shadow.
+    pub fn with_shadow<P, Primary, ShadowOp>(mut self, rate: f64) -> Self
+    where
+        P: 'static,
+        Primary: ApiOperation<C, P> + 'static,
+        ShadowOp: ApiOperation<C, P, Output = Primary::Output, Error = Primary::Error>,
+        Primary::Output: 'static,
+        Primary::Error: 'static,
+    {
+        let run: Implementation<C, P, Primary::Output, Primary::Error> = ShadowOp::execute;
+        self.shadows.shadows.insert(
+            TypeId::of::<(Primary, P)>(),
+            Shadow {
+                rate: rate.clamp(0.0, 1.0),
+                implementation: Arc::new(run),
+            },
+        );
+        self
+    }
+
+    /// Returns the mismatches between primaries and their shadows, oldest first.
+    pub fn shadow_mismatches(&self) -> &[ShadowMismatch] {
+        &self.shadows.mismatches
+    }
+
+    /// Removes and returns the mismatches recorded so far, oldest first.
+    ///
+    /// Executors shadowing live traffic should drain mismatches periodically so they do
+    /// not accumulate without bound.
+    pub fn take_shadow_mismatches(&mut self) -> Vec<ShadowMismatch> {
+        std::mem::take(&mut self.shadows.mismatches)
+    }
+
+    /// Executes an operation and, on sampled calls, its shadow against a clone of the
+    /// context, recording and logging a warning when their results differ.
+    ///
+    /// The shadow never affects the caller: its changes to the cloned context are
+    /// discarded and the primary's result is always returned. A shadow that panics is
+    /// recorded as a mismatch.
+    pub fn execute_weighted_sample_for_shadow_traffic<P, Op>(
+        &mut self,
+        op: Op,
+        parameters: &P,
+    ) -> Result<Op::Output, Op::Error>
+    where
+        C: Clone,
+        P: 'static,
+        Op: ApiOperation<C, P> + 'static,
+        Op::Output: PartialEq + fmt::Debug + 'static,
+        Op::Error: PartialEq + fmt::Debug + 'static,
+    {
+        let shadow = self
+            .shadows
+            .shadows
+            .get(&TypeId::of::<(Op, P)>())
+            .filter(|shadow| self.rng.next_f64() < shadow.rate)
+            .and_then(|shadow| {
+                shadow
+                    .implementation
+                    .downcast_ref::<Implementation<C, P, Op::Output, Op::Error>>()
+            })
+            .copied();
+        let Some(shadow) = shadow else {
+            return self.execute(op, parameters);
+        };
+
+        let mut shadow_context = self.context.clone();
+        let result = self.execute(op, parameters);
+        let shadow_result =
+            panic::catch_unwind(AssertUnwindSafe(|| shadow(&mut shadow_context, parameters)));
+        let shadow = match &shadow_result {
+            Ok(shadow_result) if *shadow_result == result => None,
+            Ok(shadow_result) => Some(format!("{:?}", shadow_result)),
+            Err(_) => Some("panicked".to_string()),
+        };
+        if let Some(shadow) = shadow {
+            let mismatch = ShadowMismatch {
+                operation: Op::name(),
+                primary: format!("{:?}", result),
+                shadow,
+            };
+            self.logger.log(
+                LogLevel::Warn,
+                Op::name(),
+                format!(
+                    "shadow mismatch: primary {}, shadow {}",
+                    mismatch.primary, mismatch.shadow
+                ),
+            );
+            self.shadows.mismatches.push(mismatch);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+    use crate::MemoryLogger;
+
+    struct QuoteTax;
+    struct QuoteTaxV2;
+
+    impl ApiOperation<DatabaseContext, u32> for QuoteTax {
+        type Output = u32;
+        type Error = ();
+
+        fn execute(context: &mut DatabaseContext, parameters: &u32) -> Result<u32, ()> {
+            context.increment_transaction();
+            Ok(parameters / 10)
+        }
+
+        fn name() -> &'static str {
+            "quote_tax"
+        }
+    }
+
+    impl ApiOperation<DatabaseContext, u32> for QuoteTaxV2 {
+        type Output = u32;
+        type Error = ();
+
+        fn execute(context: &mut DatabaseContext, parameters: &u32) -> Result<u32, ()> {
+            context.increment_transaction();
+            Ok((parameters + 5) / 10)
+        }
+    }
+
+    #[test]
+    fn test_mismatch_recorded_and_primary_result_returned() {
+        let logger = MemoryLogger::new();
+        let mut executor = ApiExecutor::new(DatabaseContext::new("shadow".to_string()))
+            .with_logger(logger.clone())
+            .with_shadow::<u32, QuoteTax, QuoteTaxV2>(1.0);
+
+        let agreeing = executor.execute_weighted_sample_for_shadow_traffic(QuoteTax, &100);
+        let disagreeing = executor.execute_weighted_sample_for_shadow_traffic(QuoteTax, &105);
+
+        assert_eq!(agreeing, Ok(10));
+        assert_eq!(disagreeing, Ok(10));
+        assert_eq!(
+            executor.shadow_mismatches(),
+            &[ShadowMismatch {
+                operation: "quote_tax",
+                primary: "Ok(10)".to_string(),
+                shadow: "Ok(11)".to_string(),
+            }]
+        );
+        assert_eq!(executor.context().transaction_count(), 2);
+        assert_eq!(logger.records()[0].level, LogLevel::Warn);
+    }
+
+    struct QuoteTaxPanicking;
+
+    impl ApiOperation<DatabaseContext, u32> for QuoteTaxPanicking {
+        type Output = u32;
+        type Error = ();
+
+        fn execute(_context: &mut DatabaseContext, _parameters: &u32) -> Result<u32, ()> {
+            panic!("candidate crashed");
+        }
+    }
+
+    #[test]
+    fn test_panicking_shadow_is_recorded_as_mismatch() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("shadow".to_string()))
+            .with_shadow::<u32, QuoteTax, QuoteTaxPanicking>(1.0);
+
+        let result = executor.execute_weighted_sample_for_shadow_traffic(QuoteTax, &100);
+
+        assert_eq!(result, Ok(10));
+        let mismatches = executor.take_shadow_mismatches();
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].shadow, "panicked");
+        assert!(executor.shadow_mismatches().is_empty());
+    }
+
+    #[test]
+    fn test_unsampled_calls_skip_the_shadow() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("shadow".to_string()))
+            .with_shadow::<u32, QuoteTax, QuoteTaxV2>(0.0);
+
+        executor
+            .execute_weighted_sample_for_shadow_traffic(QuoteTax, &105)
+            .unwrap();
+
+        assert!(executor.shadow_mismatches().is_empty());
+    }
+}
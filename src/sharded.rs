@@ -0,0 +1,263 @@
+//! Distributing operations across multiple contexts by weight.
+
+use crate::{ApiExecutor, ApiOperation};
+use std::fmt;
+
+/// Execution statistics for a single shard.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ShardStats {
+    /// The number of operations executed on the shard.
+    pub executions: u64,
+
+    /// The number of those operations that failed.
+    pub failures: u64,
+}
+
+/// A weighted shard holding its own executor.
+#[derive(Debug)]
+struct Shard<C> {
+    /// The executor owning the shard's context.
+    executor: ApiExecutor<C>,
+
+    /// The configured share of traffic for the shard.
+    weight: u32,
+
+    /// The running weight used by smooth weighted round-robin selection.
+    current: i64,
+
+    /// The shard's execution statistics.
+    stats: ShardStats,
+}
+
+/// Errors returned by [`ShardedExecutor::execute`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShardedError<E> {
+    /// The executor has no shards with a non-zero weight.
+    NoShards,
+
+    /// The operation failed on the given shard, and on every failover shard tried.
+    Operation {
+        /// The index of the last shard the operation ran on.
+        shard: usize,
+
+        /// The error returned by that shard.
+        error: E,
+    },
+}
+
+impl<E: fmt::Display> fmt::Display for ShardedError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShardedError::NoShards => f.write_str("no shards available"),
+            ShardedError::Operation { shard, error } => {
+                write!(f, "operation failed on shard {}: {}", shard, error)
+            }
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for ShardedError<E> {}
+
+/// An executor that spreads operations across weighted shards of context `C`.
+///
+/// Shards are chosen by smooth weighted round-robin, so over many calls each shard
+/// receives traffic in proportion to its weight.
+#[derive(Debug)]
+pub struct ShardedExecutor<C> {
+    /// The shards in registration order.
+    shards: Vec<Shard<C>>,
+
+    /// Whether a failed operation is retried on the remaining shards.
+    failover: bool,
+}
+
+impl<C> ShardedExecutor<C> {
+    /// Creates an executor without shards and with failover disabled.
+    pub fn new() -> Self {
+        Self {
+            shards: Vec::new(),
+            failover: false,
+        }
+    }
+
+    /// Adds a shard owning `context` that receives traffic proportional to `weight`.
+    pub fn with_shard(mut self, context: C, weight: u32) -> Self {
+        self.shards.push(Shard {
+            executor: ApiExecutor::new(context),
+            weight,
+            current: 0,
+            stats: ShardStats::default(),
+        });
+        self
+    }
+
+    /// Enables or disables retrying a failed operation on the other shards.
+    pub fn with_failover(mut self, failover: bool) -> Self {
+        self.failover = failover;
+        self
+    }
+
+    /// Executes an operation on the next shard chosen by weighted round-robin.
+    ///
+    /// With failover enabled, a failed operation is retried on the other shards in
+    /// order until one succeeds; the last error is returned if all of them fail.
+    pub fn execute<P, Op>(
+        &mut self,
+        _op: Op,
+        parameters: &P,
+    ) -> Result<Op::Output, ShardedError<Op::Error>>
+    where
+        Op: ApiOperation<C, P>,
+    {
+        let first = self.select().ok_or(ShardedError::NoShards)?;
+        let attempts = if self.failover { self.shards.len() } else { 1 };
+
+        let mut last_error = None;
+        for offset in 0..attempts {
+            let index = (first + offset) % self.shards.len();
+            let shard = &mut self.shards[index];
+            if offset > 0 && shard.weight == 0 {
+                continue;
+            }
+            shard.stats.executions += 1;
+            match Op::execute(&mut shard.executor.context, parameters) {
+                Ok(output) => return Ok(output),
+                Err(error) => {
+                    shard.stats.failures += 1;
+                    last_error = Some(ShardedError::Operation {
+                        shard: index,
+                        error,
+                    });
+                }
+            }
+        }
+        Err(last_error.unwrap_or(ShardedError::NoShards))
+    }
+
+    /// Returns the number of shards.
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Returns the statistics for the shard at `index`.
+    pub fn stats(&self, index: usize) -> Option<ShardStats> {
+        self.shards.get(index).map(|shard| shard.stats)
+    }
+
+    /// Returns an immutable reference to the context of the shard at `index`.
+    pub fn context(&self, index: usize) -> Option<&C> {
+        self.shards.get(index).map(|shard| shard.executor.context())
+    }
+
+    /// Returns a mutable reference to the context of the shard at `index`.
+    pub fn context_mut(&mut self, index: usize) -> Option<&mut C> {
+        self.shards
+            .get_mut(index)
+            .map(|shard| shard.executor.context_mut())
+    }
+
+    /// Picks the next shard using smooth weighted round-robin.
+    fn select(&mut self) -> Option<usize> {
+        let total: i64 = self.shards.iter().map(|shard| shard.weight as i64).sum();
+        if total == 0 {
+            return None;
+        }
+
+        for shard in &mut self.shards {
+            shard.current += shard.weight as i64;
+        }
+        let selected = self
+            .shards
+            .iter()
+            .enumerate()
+            .max_by_key(|(index, shard)| (shard.current, std::cmp::Reverse(*index)))
+            .map(|(index, _)| index)?;
+        self.shards[selected].current -= total;
+        Some(selected)
+    }
+}
+
+impl<C> Default for ShardedExecutor<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+
+    struct Record;
+
+    impl ApiOperation<DatabaseContext, ()> for Record {
+        type Output = String;
+        type Error = String;
+
+        fn execute(context: &mut DatabaseContext, _parameters: &()) -> Result<String, String> {
+            if context.connection_pool() == "offline" {
+                return Err("shard offline".to_string());
+            }
+            context.increment_transaction();
+            Ok(context.connection_pool().to_string())
+        }
+    }
+
+    fn shard(name: &str) -> DatabaseContext {
+        DatabaseContext::new(name.to_string())
+    }
+
+    #[test]
+    fn test_selection_matches_weights() {
+        let mut executor = ShardedExecutor::new()
+            .with_shard(shard("a"), 3)
+            .with_shard(shard("b"), 1)
+            .with_shard(shard("c"), 0);
+
+        for _ in 0..400 {
+            executor.execute(Record, &()).unwrap();
+        }
+
+        assert_eq!(executor.stats(0).unwrap().executions, 300);
+        assert_eq!(executor.stats(1).unwrap().executions, 100);
+        assert_eq!(executor.stats(2).unwrap().executions, 0);
+        assert_eq!(executor.context(0).unwrap().transaction_count(), 300);
+    }
+
+    #[test]
+    fn test_failover_to_next_shard() {
+        let mut executor = ShardedExecutor::new()
+            .with_shard(shard("offline"), 1)
+            .with_shard(shard("b"), 1)
+            .with_failover(true);
+
+        assert_eq!(executor.execute(Record, &()), Ok("b".to_string()));
+        assert_eq!(executor.execute(Record, &()), Ok("b".to_string()));
+        assert_eq!(
+            executor.stats(0),
+            Some(ShardStats {
+                executions: 1,
+                failures: 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_error_without_failover() {
+        let mut executor = ShardedExecutor::new()
+            .with_shard(shard("offline"), 1)
+            .with_shard(shard("b"), 1);
+
+        assert_eq!(
+            executor.execute(Record, &()),
+            Err(ShardedError::Operation {
+                shard: 0,
+                error: "shard offline".to_string()
+            })
+        );
+        assert_eq!(
+            ShardedExecutor::<DatabaseContext>::new().execute(Record, &()),
+            Err(ShardedError::NoShards)
+        );
+    }
+}
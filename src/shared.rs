@@ -0,0 +1,554 @@
+//! Thread-safe executor handles over a shared context.
+
+use crate::ApiOperation;
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Condvar, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+thread_local! {
+    /// Identifies the shared contexts that operations on this thread are running against.
+    static ACTIVE: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
+}
+
+/// A read-only API operation that only needs shared access to the context.
+///
+/// Queries can run concurrently with each other on a [`SharedApiExecutor`].
+pub trait ApiQuery<C, P> {
+    /// The type returned by a successful query.
+    type Output;
+
+    /// The error type returned when a query fails.
+    type Error;
+
+    /// Execute the query against the given context with the specified parameters.
+    fn query(context: &C, parameters: &P) -> Result<Self::Output, Self::Error>;
+}
+
+/// A counting semaphore bounding how many operations run at once.
+#[derive(Debug)]
+struct Semaphore {
+    /// The number of permits currently available.
+    available: Mutex<usize>,
+
+    /// Signalled whenever a permit is released.
+    released: Condvar,
+}
+
+impl Semaphore {
+    /// Creates a semaphore with `permits` permits.
+    fn new(permits: usize) -> Self {
+        Self {
+            available: Mutex::new(permits),
+            released: Condvar::new(),
+        }
+    }
+
+    /// Blocks until a permit is available and takes it.
+    fn acquire(&self) -> Permit<'_> {
+        let mut available = self
+            .available
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        while *available == 0 {
+            available = self
+                .released
+                .wait(available)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+        *available -= 1;
+        Permit { semaphore: self }
+    }
+}
+
+/// A permit that returns to its semaphore when dropped.
+struct Permit<'a> {
+    /// The semaphore the permit was taken from.
+    semaphore: &'a Semaphore,
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        let mut available = self
+            .semaphore
+            .available
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        *available += 1;
+        self.semaphore.released.notify_one();
+    }
+}
+
+/// Marks a shared context as in use by the current thread until dropped.
+struct ActiveMarker {
+    /// The address of the shared context.
+    id: usize,
+}
+
+impl ActiveMarker {
+    /// Marks the context identified by `id` as in use.
+    fn enter(id: usize) -> Self {
+        ACTIVE.with(|active| active.borrow_mut().push(id));
+        Self { id }
+    }
+
+    /// Returns true if the current thread is running an operation against `id`.
+    fn is_active(id: usize) -> bool {
+        ACTIVE.with(|active| active.borrow().contains(&id))
+    }
+}
+
+impl Drop for ActiveMarker {
+    fn drop(&mut self) {
+        ACTIVE.with(|active| {
+            let mut active = active.borrow_mut();
+            if let Some(position) = active.iter().rposition(|&id| id == self.id) {
+                active.remove(position);
+            }
+        });
+    }
+}
+
+/// The progress of an execution shared by concurrent callers with the same key.
+enum FlightState {
+    /// The leading caller is still running the operation.
+    Running,
+
+    /// The operation finished with this boxed `Result<Op::Output, Op::Error>`.
+    Done(Box<dyn Any + Send + Sync>),
+
+    /// The leading caller panicked before finishing, so waiters must run it themselves.
+    Abandoned,
+}
+
+/// An in-flight execution that concurrent callers with the same key wait on.
+struct Flight {
+    /// The progress of the execution.
+    state: Mutex<FlightState>,
+
+    /// Signalled once the execution finishes or is abandoned.
+    finished: Condvar,
+}
+
+/// In-flight executions by key, shared by every clone of an executor.
+type Flights = Arc<Mutex<HashMap<String, Arc<Flight>>>>;
+
+/// Unregisters a flight when its leading caller finishes, even by panicking.
+struct FlightGuard<'a> {
+    /// The registry the flight was registered in.
+    flights: &'a Mutex<HashMap<String, Arc<Flight>>>,
+
+    /// The key the flight was registered under.
+    key: &'a str,
+
+    /// The flight led by this caller.
+    flight: Arc<Flight>,
+}
+
+impl FlightGuard<'_> {
+    /// Publishes the leader's result to the waiting callers.
+    fn complete(self, result: Box<dyn Any + Send + Sync>) {
+        *self
+            .flight
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = FlightState::Done(result);
+    }
+}
+
+impl Drop for FlightGuard<'_> {
+    fn drop(&mut self) {
+        self.flights
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .remove(self.key);
+        let mut state = self
+            .flight
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if matches!(*state, FlightState::Running) {
+            *state = FlightState::Abandoned;
+        }
+        self.flight.finished.notify_all();
+    }
+}
+
+/// The error returned by [`SharedApiExecutor::execute_reentrant_guard`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReentrancyError<E> {
+    /// The call was made from inside an operation running on the same executor.
+    Reentered,
+
+    /// The operation ran and failed.
+    Operation(E),
+}
+
+impl<E: fmt::Display> fmt::Display for ReentrancyError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReentrancyError::Reentered => {
+                f.write_str("operation re-entered an executor it is already running on")
+            }
+            ReentrancyError::Operation(error) => write!(f, "operation failed: {}", error),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for ReentrancyError<E> {}
+
+/// A cloneable executor handle that shares one context across threads.
+///
+/// Operations take exclusive access to the context, while [`ApiQuery`] implementations
+/// share it and can run concurrently. Every clone shares the same context and the same
+/// optional concurrency limit.
+pub struct SharedApiExecutor<C> {
+    /// The context shared by every clone of this executor.
+    context: Arc<RwLock<C>>,
+
+    /// Limits how many operations and queries are in flight across all clones.
+    limit: Option<Arc<Semaphore>>,
+
+    /// Executions shared by `execute_single_flight`, by key.
+    flights: Flights,
+}
+
+impl<C> SharedApiExecutor<C> {
+    /// Creates a new shared executor that owns the provided context.
+    pub fn new(context: C) -> Self {
+        Self {
+            context: Arc::new(RwLock::new(context)),
+            limit: None,
+            flights: Flights::default(),
+        }
+    }
+
+    /// Limits the number of operations running at once across all clones to `permits`.
+    ///
+    /// Callers beyond the limit block until a running operation finishes, which keeps
+    /// resources such as connection pools from being exhausted.
+    pub fn with_concurrency_limit(mut self, permits: usize) -> Self {
+        self.limit = Some(Arc::new(Semaphore::new(permits.max(1))));
+        self
+    }
+
+    /// Executes an API operation with exclusive access to the shared context.
+    pub fn execute<P, Op>(&self, _op: Op, parameters: &P) -> Result<Op::Output, Op::Error>
+    where
+        Op: ApiOperation<C, P>,
+    {
+        let _permit = self.limit.as_deref().map(Semaphore::acquire);
+        let _active = ActiveMarker::enter(self.id());
+        Op::execute(&mut self.write(), parameters)
+    }
+
+    /// Executes an API operation, failing instead of deadlocking when called re-entrantly.
+    ///
+    /// An operation that calls back into the executor it is running on, for example
+    /// through a global handle, would otherwise block forever on the context lock.
+    pub fn execute_reentrant_guard<P, Op>(
+        &self,
+        op: Op,
+        parameters: &P,
+    ) -> Result<Op::Output, ReentrancyError<Op::Error>>
+    where
+        Op: ApiOperation<C, P>,
+    {
+        if ActiveMarker::is_active(self.id()) {
+            return Err(ReentrancyError::Reentered);
+        }
+        self.execute(op, parameters)
+            .map_err(ReentrancyError::Operation)
+    }
+
+    /// Executes an API operation once for all concurrent callers using the same `key`.
+    ///
+    /// The first caller runs the operation while later callers with the same key wait
+    /// and share its result, so a cache miss under load does not run an expensive
+    /// operation many times at once. Callers arriving after it finishes run it again.
+    /// If the first caller panics, or a waiter expects a different output type under
+    /// the same key, the waiter runs the operation itself.
+    pub fn execute_single_flight<P, Op>(
+        &self,
+        key: &str,
+        op: Op,
+        parameters: &P,
+    ) -> Result<Op::Output, Op::Error>
+    where
+        Op: ApiOperation<C, P>,
+        Op::Output: Clone + Send + Sync + 'static,
+        Op::Error: Clone + Send + Sync + 'static,
+    {
+        let mut flights = self
+            .flights
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(flight) = flights.get(key).cloned() {
+            drop(flights);
+            return match Self::wait_for::<Result<Op::Output, Op::Error>>(&flight) {
+                Some(result) => result,
+                None => self.execute(op, parameters),
+            };
+        }
+
+        let flight = Arc::new(Flight {
+            state: Mutex::new(FlightState::Running),
+            finished: Condvar::new(),
+        });
+        flights.insert(key.to_string(), Arc::clone(&flight));
+        drop(flights);
+
+        let guard = FlightGuard {
+            flights: &self.flights,
+            key,
+            flight,
+        };
+        let result = self.execute(op, parameters);
+        guard.complete(Box::new(result.clone()));
+        result
+    }
+
+    /// Protects a cache miss from stampedes; an alias of
+    /// [`execute_single_flight`](Self::execute_single_flight).
+    pub fn execute_with_output_cache_stampede_protection<P, Op>(
+        &self,
+        key: &str,
+        op: Op,
+        parameters: &P,
+    ) -> Result<Op::Output, Op::Error>
+    where
+        Op: ApiOperation<C, P>,
+        Op::Output: Clone + Send + Sync + 'static,
+        Op::Error: Clone + Send + Sync + 'static,
+    {
+        self.execute_single_flight(key, op, parameters)
+    }
+
+    /// Blocks until `flight` finishes, returning its result unless it was abandoned or
+    /// holds a result of another type.
+    fn wait_for<T: Clone + 'static>(flight: &Flight) -> Option<T> {
+        let mut state = flight
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        while matches!(*state, FlightState::Running) {
+            state = flight
+                .finished
+                .wait(state)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+        match &*state {
+            FlightState::Done(result) => result.downcast_ref::<T>().cloned(),
+            _ => None,
+        }
+    }
+
+    /// Executes a read-only query, sharing the context with other running queries.
+    pub fn query<P, Q>(&self, _query: Q, parameters: &P) -> Result<Q::Output, Q::Error>
+    where
+        Q: ApiQuery<C, P>,
+    {
+        let _permit = self.limit.as_deref().map(Semaphore::acquire);
+        let _active = ActiveMarker::enter(self.id());
+        Q::query(&self.read(), parameters)
+    }
+
+    /// Returns an identifier shared by every clone of this executor.
+    fn id(&self) -> usize {
+        Arc::as_ptr(&self.context) as *const () as usize
+    }
+
+    /// Returns shared access to the context, blocking while an operation runs.
+    pub fn read(&self) -> RwLockReadGuard<'_, C> {
+        self.context
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Returns exclusive access to the context, blocking while others hold it.
+    pub fn write(&self) -> RwLockWriteGuard<'_, C> {
+        self.context
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl<C> Clone for SharedApiExecutor<C> {
+    fn clone(&self) -> Self {
+        Self {
+            context: Arc::clone(&self.context),
+            limit: self.limit.clone(),
+            flights: Arc::clone(&self.flights),
+        }
+    }
+}
+
+impl<C: fmt::Debug> fmt::Debug for SharedApiExecutor<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedApiExecutor")
+            .field("context", &self.context)
+            .field("limited", &self.limit.is_some())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+    use std::sync::Barrier;
+    use std::thread;
+    use std::time::Duration;
+
+    /// The concurrency limit used by the load tests.
+    const PERMITS: usize = 2;
+
+    #[derive(Default)]
+    struct LoadContext {
+        /// The executor's semaphore, to see how many operations hold a permit.
+        limit: Option<Arc<Semaphore>>,
+        max_in_flight: usize,
+    }
+
+    /// Records how many operations hold a permit, including those waiting for the
+    /// context lock, while it runs.
+    struct SlowOperation;
+
+    impl ApiOperation<LoadContext, ()> for SlowOperation {
+        type Output = ();
+        type Error = ();
+
+        fn execute(context: &mut LoadContext, _parameters: &()) -> Result<(), ()> {
+            thread::sleep(Duration::from_millis(20));
+            let semaphore = context.limit.as_deref().expect("the executor is limited");
+            let available = *semaphore.available.lock().unwrap();
+            context.max_in_flight = context.max_in_flight.max(PERMITS - available);
+            Ok(())
+        }
+    }
+
+    struct Increment;
+
+    impl ApiOperation<DatabaseContext, ()> for Increment {
+        type Output = u32;
+        type Error = ();
+
+        fn execute(context: &mut DatabaseContext, _parameters: &()) -> Result<u32, ()> {
+            context.increment_transaction();
+            Ok(context.transaction_count())
+        }
+    }
+
+    #[test]
+    fn test_concurrency_limit_bounds_in_flight_operations() {
+        let executor =
+            SharedApiExecutor::new(LoadContext::default()).with_concurrency_limit(PERMITS);
+        executor.write().limit = executor.limit.clone();
+        let barrier = Arc::new(Barrier::new(8));
+
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let executor = executor.clone();
+                let barrier = Arc::clone(&barrier);
+                thread::spawn(move || {
+                    barrier.wait();
+                    executor.execute(SlowOperation, &()).unwrap()
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(executor.read().max_in_flight, PERMITS);
+    }
+
+    #[test]
+    fn test_operations_share_context_across_clones() {
+        let executor = SharedApiExecutor::new(DatabaseContext::new("shared".to_string()));
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let executor = executor.clone();
+                thread::spawn(move || executor.execute(Increment, &()).unwrap())
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(executor.read().transaction_count(), 4);
+    }
+
+    /// Loads a report slowly, counting each real execution as a transaction.
+    struct LoadReport;
+
+    impl ApiOperation<DatabaseContext, ()> for LoadReport {
+        type Output = u32;
+        type Error = ();
+
+        fn execute(context: &mut DatabaseContext, _parameters: &()) -> Result<u32, ()> {
+            thread::sleep(Duration::from_millis(50));
+            context.increment_transaction();
+            Ok(42)
+        }
+    }
+
+    #[test]
+    fn test_concurrent_calls_for_same_key_execute_once() {
+        let executor = SharedApiExecutor::new(DatabaseContext::new("shared".to_string()));
+        let barrier = Arc::new(Barrier::new(8));
+
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let executor = executor.clone();
+                let barrier = Arc::clone(&barrier);
+                thread::spawn(move || {
+                    barrier.wait();
+                    executor.execute_single_flight("report", LoadReport, &())
+                })
+            })
+            .collect();
+        let results: Vec<_> = handles
+            .into_iter()
+            .map(|handle| handle.join().unwrap())
+            .collect();
+
+        assert_eq!(results, vec![Ok(42); 8]);
+        assert_eq!(executor.read().transaction_count(), 1);
+        assert!(executor.flights.lock().unwrap().is_empty());
+    }
+
+    /// Calls back into the executor passed as its parameters.
+    struct Reenter;
+
+    impl ApiOperation<DatabaseContext, SharedApiExecutor<DatabaseContext>> for Reenter {
+        type Output = ();
+        type Error = ReentrancyError<()>;
+
+        fn execute(
+            context: &mut DatabaseContext,
+            parameters: &SharedApiExecutor<DatabaseContext>,
+        ) -> Result<(), ReentrancyError<()>> {
+            context.increment_transaction();
+            parameters
+                .execute_reentrant_guard(Increment, &())
+                .map(|_| ())
+        }
+    }
+
+    #[test]
+    fn test_reentrant_call_returns_error_instead_of_deadlocking() {
+        let executor = SharedApiExecutor::new(DatabaseContext::new("shared".to_string()));
+
+        let result = executor.execute_reentrant_guard(Reenter, &executor.clone());
+
+        assert_eq!(
+            result,
+            Err(ReentrancyError::Operation(ReentrancyError::Reentered))
+        );
+        assert_eq!(executor.read().transaction_count(), 1);
+        assert_eq!(executor.execute_reentrant_guard(Increment, &()), Ok(2));
+    }
+}
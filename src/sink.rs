@@ -0,0 +1,115 @@
+//! Operations that push many outputs to a sink as they produce them.
+
+use crate::ApiExecutor;
+
+/// An operation that produces outputs incrementally, pushing each one to a sink.
+///
+/// Suits operations such as splitting an import into records, where collecting every
+/// output before returning would hold them all in memory at once.
+pub trait ApiOperationSink<C, P, O> {
+    /// The error type returned when the operation fails.
+    type Error;
+
+    /// Executes the operation, passing each output to `sink` as soon as it is produced.
+    fn execute(context: &mut C, parameters: &P, sink: &mut dyn FnMut(O))
+        -> Result<(), Self::Error>;
+
+    /// Returns the diagnostic name of the operation, defaulting to its type name.
+    fn name() -> &'static str {
+        std::any::type_name::<Self>()
+    }
+}
+
+impl<C> ApiExecutor<C> {
+    /// Executes a sink operation, forwarding each output to `sink` as it is produced.
+    ///
+    /// Outputs pushed before a failure have already been forwarded when the error is
+    /// returned.
+    pub fn execute_into_sink<P, O, Op, F>(
+        &mut self,
+        _op: Op,
+        parameters: &P,
+        mut sink: F,
+    ) -> Result<(), Op::Error>
+    where
+        Op: ApiOperationSink<C, P, O>,
+        F: FnMut(O),
+    {
+        let started = self.clock.now();
+        let result = Op::execute(&mut self.context, parameters, &mut sink);
+        self.observe(Op::name(), started, result.is_ok());
+        result
+    }
+
+    /// Executes a sink operation and collects its outputs in the order they were pushed.
+    pub fn execute_collecting_sink<P, O, Op>(
+        &mut self,
+        op: Op,
+        parameters: &P,
+    ) -> Result<Vec<O>, Op::Error>
+    where
+        Op: ApiOperationSink<C, P, O>,
+    {
+        let mut outputs = Vec::new();
+        self.execute_into_sink(op, parameters, |output| outputs.push(output))?;
+        Ok(outputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+
+    /// Splits a comma-separated import into trimmed records.
+    struct SplitImport;
+
+    impl ApiOperationSink<DatabaseContext, String, String> for SplitImport {
+        type Error = String;
+
+        fn execute(
+            context: &mut DatabaseContext,
+            parameters: &String,
+            sink: &mut dyn FnMut(String),
+        ) -> Result<(), String> {
+            for record in parameters.split(',') {
+                if record.trim().is_empty() {
+                    return Err("empty record".to_string());
+                }
+                context.increment_transaction();
+                sink(record.trim().to_string());
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_outputs_received_in_order() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("sink".to_string()));
+
+        let records =
+            executor.execute_collecting_sink(SplitImport, &"alice, bob, carol".to_string());
+
+        assert_eq!(
+            records,
+            Ok(vec![
+                "alice".to_string(),
+                "bob".to_string(),
+                "carol".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_outputs_before_failure_are_forwarded() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("sink".to_string()));
+        let mut received = Vec::new();
+
+        let result = executor.execute_into_sink(SplitImport, &"alice,,bob".to_string(), |record| {
+            received.push(record)
+        });
+
+        assert_eq!(result, Err("empty record".to_string()));
+        assert_eq!(received, vec!["alice".to_string()]);
+    }
+}
@@ -0,0 +1,102 @@
+//! Serialized context snapshots captured when operations fail.
+
+use crate::{ApiExecutor, ApiOperation};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// The serialized state of a context at the moment an operation failed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContextSnapshot {
+    /// The context serialized as JSON.
+    state: serde_json::Value,
+}
+
+impl ContextSnapshot {
+    /// Serializes `context`, recording the serialization error itself if it fails.
+    fn capture<C: Serialize>(context: &C) -> Self {
+        let state = serde_json::to_value(context).unwrap_or_else(|error| {
+            serde_json::Value::String(format!("<unserializable context: {}>", error))
+        });
+        Self { state }
+    }
+
+    /// Returns the captured state as JSON.
+    pub fn state(&self) -> &serde_json::Value {
+        &self.state
+    }
+
+    /// Deserializes the captured state back into a context value.
+    pub fn restore<T: DeserializeOwned>(&self) -> Result<T, serde_json::Error> {
+        T::deserialize(&self.state)
+    }
+}
+
+impl<C: Serialize> ApiExecutor<C> {
+    /// Executes an operation, capturing a snapshot of the context if it fails.
+    ///
+    /// The snapshot is only taken on failure and reflects the context exactly as the
+    /// failing operation left it, for post-mortem debugging.
+    pub fn execute_debug<P, Op>(
+        &mut self,
+        _op: Op,
+        parameters: &P,
+    ) -> Result<Op::Output, (Op::Error, ContextSnapshot)>
+    where
+        Op: ApiOperation<C, P>,
+    {
+        self.execute_observed::<P, Op>(parameters)
+            .map_err(|error| (error, ContextSnapshot::capture(&self.context)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+    struct Ledger {
+        balance: i64,
+        entries: Vec<i64>,
+    }
+
+    struct Withdraw;
+
+    impl ApiOperation<Ledger, i64> for Withdraw {
+        type Output = i64;
+        type Error = String;
+
+        fn execute(context: &mut Ledger, parameters: &i64) -> Result<i64, String> {
+            if *parameters > context.balance {
+                return Err(format!("insufficient funds for {}", parameters));
+            }
+            context.balance -= parameters;
+            context.entries.push(-parameters);
+            Ok(context.balance)
+        }
+    }
+
+    #[test]
+    fn test_failure_returns_error_with_context_snapshot() {
+        let mut executor = ApiExecutor::new(Ledger {
+            balance: 100,
+            entries: Vec::new(),
+        });
+        executor.execute_debug(Withdraw, &30).unwrap();
+
+        let (error, snapshot) = executor.execute_debug(Withdraw, &500).unwrap_err();
+
+        assert_eq!(error, "insufficient funds for 500");
+        assert_eq!(
+            snapshot.state(),
+            &serde_json::json!({"balance": 70, "entries": [-30]})
+        );
+        assert_eq!(
+            snapshot.restore::<Ledger>().unwrap(),
+            Ledger {
+                balance: 70,
+                entries: vec![-30]
+            }
+        );
+    }
+}
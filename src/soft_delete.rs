@@ -0,0 +1,129 @@
+//! Hiding soft-deleted entities from reads unless explicitly requested.
+
+use crate::{ApiExecutor, ApiOperation};
+
+/// A context modelling entities with soft-delete flags.
+///
+/// The context's lookup helpers consult [`includes_deleted`](Self::includes_deleted),
+/// so find operations built on them skip soft-deleted entities without filtering
+/// themselves.
+pub trait SoftDeletable {
+    /// Returns true if reads should see soft-deleted entities.
+    fn includes_deleted(&self) -> bool;
+
+    /// Sets whether reads see soft-deleted entities.
+    fn set_include_deleted(&mut self, include: bool);
+}
+
+impl<C: SoftDeletable> ApiExecutor<C> {
+    /// Executes an operation with soft-deleted entities hidden from its reads.
+    ///
+    /// The previous setting is restored afterwards, so calls can nest.
+    pub fn execute_with_soft_delete_awareness<P, Op>(
+        &mut self,
+        op: Op,
+        parameters: &P,
+    ) -> Result<Op::Output, Op::Error>
+    where
+        Op: ApiOperation<C, P>,
+    {
+        self.execute_with_deleted_visibility(false, op, parameters)
+    }
+
+    /// Executes an operation whose reads also see soft-deleted entities, for
+    /// administrative tools and restores.
+    ///
+    /// The previous setting is restored afterwards, so calls can nest.
+    pub fn execute_including_deleted<P, Op>(
+        &mut self,
+        op: Op,
+        parameters: &P,
+    ) -> Result<Op::Output, Op::Error>
+    where
+        Op: ApiOperation<C, P>,
+    {
+        self.execute_with_deleted_visibility(true, op, parameters)
+    }
+
+    /// Executes an operation with the given soft-delete visibility.
+    fn execute_with_deleted_visibility<P, Op>(
+        &mut self,
+        include: bool,
+        op: Op,
+        parameters: &P,
+    ) -> Result<Op::Output, Op::Error>
+    where
+        Op: ApiOperation<C, P>,
+    {
+        let previous = self.context.includes_deleted();
+        self.context.set_include_deleted(include);
+        let result = self.execute(op, parameters);
+        self.context.set_include_deleted(previous);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    /// Stores user names by id alongside a soft-delete flag.
+    #[derive(Default)]
+    struct UserStore {
+        users: HashMap<u32, (String, bool)>,
+        include_deleted: bool,
+    }
+
+    impl UserStore {
+        fn find(&self, id: u32) -> Option<&String> {
+            self.users
+                .get(&id)
+                .filter(|(_, deleted)| self.include_deleted || !deleted)
+                .map(|(name, _)| name)
+        }
+    }
+
+    impl SoftDeletable for UserStore {
+        fn includes_deleted(&self) -> bool {
+            self.include_deleted
+        }
+
+        fn set_include_deleted(&mut self, include: bool) {
+            self.include_deleted = include;
+        }
+    }
+
+    struct FindUser;
+
+    impl ApiOperation<UserStore, u32> for FindUser {
+        type Output = Option<String>;
+        type Error = ();
+
+        fn execute(context: &mut UserStore, parameters: &u32) -> Result<Option<String>, ()> {
+            Ok(context.find(*parameters).cloned())
+        }
+    }
+
+    #[test]
+    fn test_find_excludes_deleted_unless_overridden() {
+        let mut store = UserStore::default();
+        store.users.insert(1, ("alice".to_string(), false));
+        store.users.insert(2, ("bob".to_string(), true));
+        let mut executor = ApiExecutor::new(store);
+
+        assert_eq!(
+            executor.execute_with_soft_delete_awareness(FindUser, &1),
+            Ok(Some("alice".to_string()))
+        );
+        assert_eq!(
+            executor.execute_with_soft_delete_awareness(FindUser, &2),
+            Ok(None)
+        );
+        assert_eq!(
+            executor.execute_including_deleted(FindUser, &2),
+            Ok(Some("bob".to_string()))
+        );
+        assert!(!executor.context().includes_deleted());
+    }
+}
@@ -0,0 +1,216 @@
+//! Spans carrying correlation baggage through nested operation executions.
+
+use crate::{ApiExecutor, ApiOperation, CorrelationContext, WithCorrelation};
+use std::collections::HashMap;
+
+/// A finished span of one operation execution.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpanRecord {
+    /// The name of the executed operation.
+    pub operation: &'static str,
+
+    /// The identifier of the request the span belongs to.
+    pub request_id: String,
+
+    /// The identifier of this span.
+    pub span_id: String,
+
+    /// The identifier of the enclosing span, or `None` for a root span.
+    pub parent_span_id: Option<String>,
+
+    /// The correlation baggage in effect, attached as span attributes.
+    pub attributes: HashMap<String, String>,
+
+    /// Whether the operation succeeded.
+    pub success: bool,
+}
+
+/// A context that collects the spans of operations run within it.
+pub trait SpanRecorder: WithCorrelation {
+    /// Records a finished span.
+    fn record_span(&mut self, span: SpanRecord);
+}
+
+/// Executes an operation in a child span of the context's current correlation context.
+///
+/// Operations that call other operations use this so the nested execution inherits the
+/// request id and baggage of its parent. The child span id is the parent's with the
+/// operation name appended. Without a current correlation context the operation runs
+/// without a span.
+pub fn execute_in_child_span<C, P, Op>(
+    context: &mut C,
+    parameters: &P,
+) -> Result<Op::Output, Op::Error>
+where
+    C: SpanRecorder,
+    Op: ApiOperation<C, P>,
+{
+    let Some(parent) = context.correlation().cloned() else {
+        return Op::execute(context, parameters);
+    };
+    let child = CorrelationContext {
+        span_id: format!("{}/{}", parent.span_id, Op::name()),
+        ..parent.clone()
+    };
+    let result = run_in_span::<C, P, Op>(context, parameters, child, Some(parent.span_id.clone()));
+    context.set_correlation(Some(parent));
+    result
+}
+
+/// Runs an operation with `span` installed and records it once the operation finishes.
+fn run_in_span<C, P, Op>(
+    context: &mut C,
+    parameters: &P,
+    span: CorrelationContext,
+    parent_span_id: Option<String>,
+) -> Result<Op::Output, Op::Error>
+where
+    C: SpanRecorder,
+    Op: ApiOperation<C, P>,
+{
+    context.set_correlation(Some(span.clone()));
+    let result = Op::execute(context, parameters);
+    context.record_span(SpanRecord {
+        operation: Op::name(),
+        request_id: span.request_id,
+        span_id: span.span_id,
+        parent_span_id,
+        attributes: span.baggage,
+        success: result.is_ok(),
+    });
+    result
+}
+
+impl<C> ApiExecutor<C> {
+    /// Executes an operation in a root span built from the executor's correlation
+    /// context, with the baggage attached as span attributes.
+    ///
+    /// The correlation context is installed on the context for the duration of the
+    /// call, so operations can read the baggage and nested executions through
+    /// [`execute_in_child_span`] inherit it. Without a correlation context the operation
+    /// runs without a span.
+    pub fn execute_in_span_with_baggage<P, Op>(
+        &mut self,
+        op: Op,
+        parameters: &P,
+    ) -> Result<Op::Output, Op::Error>
+    where
+        Op: ApiOperation<C, P>,
+        C: SpanRecorder,
+    {
+        let Some(correlation) = self.correlation.clone() else {
+            return self.execute(op, parameters);
+        };
+        let started = self.clock.now();
+        let result = run_in_span::<C, P, Op>(&mut self.context, parameters, correlation, None);
+        self.context.set_correlation(None);
+        self.observe(Op::name(), started, result.is_ok());
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct TracedContext {
+        correlation: Option<CorrelationContext>,
+        spans: Vec<SpanRecord>,
+    }
+
+    impl WithCorrelation for TracedContext {
+        fn correlation(&self) -> Option<&CorrelationContext> {
+            self.correlation.as_ref()
+        }
+
+        fn set_correlation(&mut self, correlation: Option<CorrelationContext>) {
+            self.correlation = correlation;
+        }
+    }
+
+    impl SpanRecorder for TracedContext {
+        fn record_span(&mut self, span: SpanRecord) {
+            self.spans.push(span);
+        }
+    }
+
+    struct CheckOut;
+    struct ReserveStock;
+
+    impl ApiOperation<TracedContext, u32> for CheckOut {
+        type Output = String;
+        type Error = String;
+
+        fn execute(context: &mut TracedContext, parameters: &u32) -> Result<String, String> {
+            execute_in_child_span::<_, _, ReserveStock>(context, parameters)
+        }
+
+        fn name() -> &'static str {
+            "check_out"
+        }
+    }
+
+    impl ApiOperation<TracedContext, u32> for ReserveStock {
+        type Output = String;
+        type Error = String;
+
+        fn execute(context: &mut TracedContext, parameters: &u32) -> Result<String, String> {
+            let tenant = context
+                .correlation()
+                .and_then(|correlation| correlation.baggage.get("tenant"))
+                .ok_or("no tenant in baggage")?;
+            Ok(format!("reserved {} for {}", parameters, tenant))
+        }
+
+        fn name() -> &'static str {
+            "reserve_stock"
+        }
+    }
+
+    #[test]
+    fn test_nested_execution_inherits_baggage() {
+        let correlation = CorrelationContext::new("req-7", "root").with_baggage("tenant", "acme");
+        let mut executor =
+            ApiExecutor::new(TracedContext::default()).with_correlation_context(correlation);
+
+        let output = executor.execute_in_span_with_baggage(CheckOut, &3);
+
+        assert_eq!(output, Ok("reserved 3 for acme".to_string()));
+        let spans = &executor.context().spans;
+        let summary: Vec<_> = spans
+            .iter()
+            .map(|span| {
+                (
+                    span.operation,
+                    span.span_id.as_str(),
+                    span.parent_span_id.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("reserve_stock", "root/reserve_stock", Some("root")),
+                ("check_out", "root", None),
+            ]
+        );
+        assert!(spans
+            .iter()
+            .all(
+                |span| span.attributes.get("tenant").map(String::as_str) == Some("acme")
+                    && span.request_id == "req-7"
+            ));
+        assert!(executor.context().correlation().is_none());
+    }
+
+    #[test]
+    fn test_without_correlation_no_span_is_recorded() {
+        let mut executor = ApiExecutor::new(TracedContext::default());
+
+        let output = executor.execute_in_span_with_baggage(CheckOut, &3);
+
+        assert_eq!(output, Err("no tenant in baggage".to_string()));
+        assert!(executor.context().spans.is_empty());
+    }
+}
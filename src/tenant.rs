@@ -0,0 +1,119 @@
+//! Scoping context storage to one tenant per call.
+
+use crate::{ApiExecutor, ApiOperation};
+
+/// A context whose storage is namespaced by the active tenant.
+///
+/// Operations read the active tenant when building cache keys or updating counters,
+/// so tenants sharing one context cannot see each other's data.
+pub trait Tenanted {
+    /// Returns the tenant the current operation runs for, if any.
+    fn active_tenant(&self) -> Option<&str>;
+
+    /// Sets or clears the active tenant.
+    fn set_active_tenant(&mut self, tenant: Option<String>);
+}
+
+impl<C: Tenanted> ApiExecutor<C> {
+    /// Executes an operation with `tenant_id` as the context's active tenant.
+    ///
+    /// The previously active tenant is restored afterwards, so calls can nest.
+    pub fn execute_for_tenant<P, Op>(
+        &mut self,
+        tenant_id: impl Into<String>,
+        op: Op,
+        parameters: &P,
+    ) -> Result<Op::Output, Op::Error>
+    where
+        Op: ApiOperation<C, P>,
+    {
+        let previous = self.context.active_tenant().map(str::to_string);
+        self.context.set_active_tenant(Some(tenant_id.into()));
+        let result = self.execute(op, parameters);
+        self.context.set_active_tenant(previous);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+
+    /// Wraps a database context, prefixing cache keys with the active tenant.
+    struct TenantContext {
+        database: DatabaseContext,
+        tenant: Option<String>,
+    }
+
+    impl TenantContext {
+        fn scoped_key(&self, key: &str) -> String {
+            format!("{}:{}", self.tenant.as_deref().unwrap_or("shared"), key)
+        }
+    }
+
+    impl Tenanted for TenantContext {
+        fn active_tenant(&self) -> Option<&str> {
+            self.tenant.as_deref()
+        }
+
+        fn set_active_tenant(&mut self, tenant: Option<String>) {
+            self.tenant = tenant;
+        }
+    }
+
+    struct CreateUser;
+
+    impl ApiOperation<TenantContext, (String, String)> for CreateUser {
+        type Output = ();
+        type Error = String;
+
+        fn execute(
+            context: &mut TenantContext,
+            parameters: &(String, String),
+        ) -> Result<(), String> {
+            let key = context.scoped_key(&parameters.0);
+            if context.database.cache().contains_key(&key) {
+                return Err(format!("duplicate user {}", parameters.0));
+            }
+            context
+                .database
+                .cache_mut()
+                .insert(key, parameters.1.clone());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_tenants_with_same_key_do_not_collide() {
+        let mut executor = ApiExecutor::new(TenantContext {
+            database: DatabaseContext::new("tenants".to_string()),
+            tenant: None,
+        });
+        let email = "alice@example.com".to_string();
+
+        executor
+            .execute_for_tenant(
+                "acme",
+                CreateUser,
+                &(email.clone(), "acme alice".to_string()),
+            )
+            .unwrap();
+        executor
+            .execute_for_tenant(
+                "globex",
+                CreateUser,
+                &(email.clone(), "globex alice".to_string()),
+            )
+            .unwrap();
+        let duplicate =
+            executor.execute_for_tenant("acme", CreateUser, &(email.clone(), "again".to_string()));
+
+        let cache = executor.context().database.cache();
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache["acme:alice@example.com"], "acme alice");
+        assert_eq!(cache["globex:alice@example.com"], "globex alice");
+        assert!(duplicate.is_err());
+        assert_eq!(executor.context().active_tenant(), None);
+    }
+}
@@ -0,0 +1,389 @@
+//! Deadlines with cooperative cancellation.
+
+use crate::ApiExecutor;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// A flag raised when an operation's deadline passes.
+///
+/// Cooperative operations poll [`is_cancelled`](Self::is_cancelled) and return early
+/// once it is set.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationFlag {
+    /// Shared with the executor that raises it.
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationFlag {
+    /// Creates a flag that has not been raised.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Raises the flag, asking the operation to stop.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns `true` once the operation has been asked to stop.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+/// An API operation that can observe a cancellation flag while it runs.
+pub trait CooperativeOperation<C, P> {
+    /// The type returned by a successful operation.
+    type Output;
+
+    /// The error type returned when the operation fails.
+    type Error;
+
+    /// Execute the operation, checking `cancel` to stop early when asked.
+    fn execute(
+        context: &mut C,
+        parameters: &P,
+        cancel: &CancellationFlag,
+    ) -> Result<Self::Output, Self::Error>;
+
+    /// Returns the diagnostic name of the operation, defaulting to its type name.
+    fn name() -> &'static str {
+        std::any::type_name::<Self>()
+    }
+}
+
+/// Per-operation time budgets applied by [`ApiExecutor::execute_bounded`].
+#[derive(Debug, Clone)]
+pub(crate) struct TimeoutMap {
+    /// Budgets configured for individual operations, by name.
+    by_name: HashMap<&'static str, Duration>,
+
+    /// The budget for operations without one of their own.
+    default: Duration,
+
+    /// How long a timed-out operation is given to stop after being cancelled.
+    grace: Duration,
+}
+
+impl Default for TimeoutMap {
+    fn default() -> Self {
+        Self {
+            by_name: HashMap::new(),
+            default: Duration::MAX,
+            grace: Duration::ZERO,
+        }
+    }
+}
+
+/// The error returned by [`ApiExecutor::execute_timed_out_graceful`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TimeoutError<E> {
+    /// The deadline passed before the operation finished.
+    TimedOut {
+        /// Whether the operation stopped within the grace period after being cancelled.
+        graceful: bool,
+    },
+
+    /// The operation finished in time but failed.
+    Operation(E),
+}
+
+impl<E: fmt::Display> fmt::Display for TimeoutError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeoutError::TimedOut { graceful: true } => {
+                write!(f, "operation timed out and stopped cleanly")
+            }
+            TimeoutError::TimedOut { graceful: false } => {
+                write!(f, "operation timed out and was abandoned")
+            }
+            TimeoutError::Operation(error) => write!(f, "operation failed: {}", error),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for TimeoutError<E> {}
+
+impl<C> ApiExecutor<C> {
+    /// Sets the time budget [`execute_bounded`](Self::execute_bounded) applies to the
+    /// operation named `name`.
+    pub fn set_operation_timeout(&mut self, name: &'static str, timeout: Duration) {
+        self.timeouts.by_name.insert(name, timeout);
+    }
+
+    /// Sets the time budget for operations without one of their own. Without a default,
+    /// such operations are not bounded.
+    pub fn with_default_operation_timeout(mut self, timeout: Duration) -> Self {
+        self.timeouts.default = timeout;
+        self
+    }
+
+    /// Sets how long a timed-out operation run by
+    /// [`execute_bounded`](Self::execute_bounded) is given to stop after being cancelled.
+    pub fn with_operation_timeout_grace(mut self, grace: Duration) -> Self {
+        self.timeouts.grace = grace;
+        self
+    }
+
+    /// Returns the time budget applied to the operation named `name`.
+    pub fn operation_timeout(&self, name: &str) -> Duration {
+        self.timeouts
+            .by_name
+            .get(name)
+            .copied()
+            .unwrap_or(self.timeouts.default)
+    }
+}
+
+impl<C: Clone + Send + 'static> ApiExecutor<C> {
+    /// Executes an operation with the time budget configured for its name, falling back
+    /// to the default budget.
+    ///
+    /// Behaves like [`execute_timed_out_graceful`](Self::execute_timed_out_graceful),
+    /// with the timeout and grace period taken from the executor so the policy lives in
+    /// one place rather than at every call site.
+    pub fn execute_bounded<P, Op>(
+        &mut self,
+        op: Op,
+        parameters: &P,
+    ) -> Result<Op::Output, TimeoutError<Op::Error>>
+    where
+        Op: CooperativeOperation<C, P> + 'static,
+        Op::Output: Send + 'static,
+        Op::Error: Send + 'static,
+        P: Clone + Send + 'static,
+    {
+        let timeout = self.operation_timeout(Op::name());
+        let grace = self.timeouts.grace;
+        self.execute_timed_out_graceful(op, parameters, timeout, grace)
+    }
+
+    /// Executes an operation with a deadline, cancelling it cooperatively when it passes.
+    ///
+    /// The operation runs on its own thread against a copy of the context. Once `timeout`
+    /// elapses the cancellation flag is raised and the executor waits up to `grace` for
+    /// the operation to stop before returning [`TimeoutError::TimedOut`]. An operation that
+    /// ignores the flag is abandoned. The executor's context is only updated when the
+    /// operation finishes before the deadline, so timed-out work never leaves partial
+    /// changes behind. The execution is reported like `execute`, with a timeout counting
+    /// as a failure.
+    ///
+    /// # Panics
+    ///
+    /// Resumes the operation's panic if it panics before the grace period ends.
+    pub fn execute_timed_out_graceful<P, Op>(
+        &mut self,
+        _op: Op,
+        parameters: &P,
+        timeout: Duration,
+        grace: Duration,
+    ) -> Result<Op::Output, TimeoutError<Op::Error>>
+    where
+        Op: CooperativeOperation<C, P> + 'static,
+        Op::Output: Send + 'static,
+        Op::Error: Send + 'static,
+        P: Clone + Send + 'static,
+    {
+        let started = self.clock.now();
+        let cancel = CancellationFlag::new();
+        let (sender, receiver) = mpsc::channel();
+        let mut context = self.context.clone();
+        let parameters = parameters.clone();
+        let flag = cancel.clone();
+        let worker = thread::spawn(move || {
+            let result = Op::execute(&mut context, &parameters, &flag);
+            let _ = sender.send((context, result));
+        });
+
+        let result = match receiver.recv_timeout(timeout) {
+            Ok((context, result)) => {
+                self.context = context;
+                result.map_err(TimeoutError::Operation)
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                cancel.cancel();
+                match receiver.recv_timeout(grace) {
+                    Ok(_) => Err(TimeoutError::TimedOut { graceful: true }),
+                    Err(RecvTimeoutError::Timeout) => {
+                        Err(TimeoutError::TimedOut { graceful: false })
+                    }
+                    Err(RecvTimeoutError::Disconnected) => resume_panic(worker),
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => resume_panic(worker),
+        };
+        self.observe(Op::name(), started, result.is_ok());
+        result
+    }
+}
+
+/// Propagates the panic of an operation thread that stopped without reporting back.
+fn resume_panic(worker: thread::JoinHandle<()>) -> ! {
+    match worker.join() {
+        Err(panic) => std::panic::resume_unwind(panic),
+        Ok(()) => unreachable!("the operation thread reports back unless it panics"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+    use std::time::Instant;
+
+    /// Processes work in small batches, stopping as soon as it is cancelled.
+    struct CooperativeImport;
+
+    impl CooperativeOperation<DatabaseContext, u32> for CooperativeImport {
+        type Output = u32;
+        type Error = ();
+
+        fn execute(
+            context: &mut DatabaseContext,
+            parameters: &u32,
+            cancel: &CancellationFlag,
+        ) -> Result<u32, ()> {
+            for _ in 0..*parameters {
+                if cancel.is_cancelled() {
+                    return Err(());
+                }
+                context.increment_transaction();
+                thread::sleep(Duration::from_millis(5));
+            }
+            Ok(context.transaction_count())
+        }
+
+        fn name() -> &'static str {
+            "cooperative_import"
+        }
+    }
+
+    /// Sleeps for the requested milliseconds without checking the flag.
+    struct StubbornImport;
+
+    impl CooperativeOperation<DatabaseContext, u64> for StubbornImport {
+        type Output = ();
+        type Error = ();
+
+        fn execute(
+            context: &mut DatabaseContext,
+            parameters: &u64,
+            _cancel: &CancellationFlag,
+        ) -> Result<(), ()> {
+            thread::sleep(Duration::from_millis(*parameters));
+            context.increment_transaction();
+            Ok(())
+        }
+
+        fn name() -> &'static str {
+            "stubborn_import"
+        }
+    }
+
+    fn executor() -> ApiExecutor<DatabaseContext> {
+        ApiExecutor::new(DatabaseContext::new("timeout".to_string()))
+    }
+
+    #[test]
+    fn test_completes_within_deadline() {
+        let mut executor = executor();
+
+        let result = executor.execute_timed_out_graceful(
+            CooperativeImport,
+            &3,
+            Duration::from_secs(5),
+            Duration::from_millis(100),
+        );
+
+        assert_eq!(result, Ok(3));
+        assert_eq!(executor.context().transaction_count(), 3);
+    }
+
+    #[test]
+    fn test_cooperative_operation_stops_within_grace_period() {
+        let mut executor = executor().with_audit_log();
+
+        let result = executor.execute_timed_out_graceful(
+            CooperativeImport,
+            &10_000,
+            Duration::from_millis(30),
+            Duration::from_secs(5),
+        );
+
+        assert_eq!(result, Err(TimeoutError::TimedOut { graceful: true }));
+        assert_eq!(executor.context().transaction_count(), 0);
+        assert_eq!(executor.audit_entries().len(), 1);
+        assert!(!executor.audit_entries()[0].success);
+    }
+
+    #[test]
+    fn test_uncooperative_operation_is_abandoned_after_grace_period() {
+        let mut executor = executor();
+        let started = Instant::now();
+
+        let result = executor.execute_timed_out_graceful(
+            StubbornImport,
+            &2_000,
+            Duration::from_millis(20),
+            Duration::from_millis(20),
+        );
+
+        assert_eq!(result, Err(TimeoutError::TimedOut { graceful: false }));
+        assert!(started.elapsed() < Duration::from_millis(1_000));
+        assert_eq!(executor.context().transaction_count(), 0);
+    }
+
+    #[test]
+    fn test_each_operation_bounded_by_its_configured_timeout() {
+        let mut executor = executor().with_default_operation_timeout(Duration::from_secs(30));
+        executor.set_operation_timeout("cooperative_import", Duration::from_secs(5));
+        executor.set_operation_timeout("stubborn_import", Duration::from_millis(20));
+
+        let cooperative = executor.execute_bounded(CooperativeImport, &10);
+        let stubborn = executor.execute_bounded(StubbornImport, &2_000);
+
+        assert_eq!(cooperative, Ok(10));
+        assert_eq!(stubborn, Err(TimeoutError::TimedOut { graceful: false }));
+        assert_eq!(executor.operation_timeout("other"), Duration::from_secs(30));
+    }
+
+    /// Panics instead of returning.
+    struct PanickingImport;
+
+    impl CooperativeOperation<DatabaseContext, ()> for PanickingImport {
+        type Output = ();
+        type Error = ();
+
+        fn execute(
+            _context: &mut DatabaseContext,
+            _parameters: &(),
+            _cancel: &CancellationFlag,
+        ) -> Result<(), ()> {
+            panic!("import crashed");
+        }
+    }
+
+    #[test]
+    fn test_operation_panic_is_propagated() {
+        let mut executor = executor();
+        let started = Instant::now();
+
+        let panic = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            executor.execute_timed_out_graceful(
+                PanickingImport,
+                &(),
+                Duration::from_secs(5),
+                Duration::from_secs(5),
+            )
+        }))
+        .unwrap_err();
+
+        assert_eq!(panic.downcast_ref::<&str>(), Some(&"import crashed"));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,277 @@
+//! Transactional contexts, scoped commit guards and multi-operation transactions.
+
+use crate::{ApiExecutor, ApiOperation};
+
+/// A context that can capture its state and later restore it.
+///
+/// Implementing this trait allows an executor to undo the effects of one or more
+/// operations, giving callers transaction-like control over the shared context.
+pub trait Transactional {
+    /// The captured state used to restore the context.
+    type Snapshot;
+
+    /// Captures the current state of the context.
+    fn snapshot(&self) -> Self::Snapshot;
+
+    /// Restores the context to a previously captured state.
+    fn restore(&mut self, snapshot: Self::Snapshot);
+}
+
+/// A guard over an executor's context that rolls back pending changes when dropped.
+///
+/// The guard is returned by [`ApiExecutor::execute_with_guard`]. Unless
+/// [`CommitGuard::commit`] is called, the context is restored to the state it had
+/// before the operation ran.
+pub struct CommitGuard<'a, C: Transactional> {
+    /// The context guarded by this instance.
+    context: &'a mut C,
+
+    /// The state to restore on drop, cleared once committed.
+    snapshot: Option<C::Snapshot>,
+}
+
+impl<'a, C: Transactional> CommitGuard<'a, C> {
+    /// Creates a guard that restores `snapshot` into `context` unless committed.
+    pub(crate) fn new(context: &'a mut C, snapshot: C::Snapshot) -> Self {
+        Self {
+            context,
+            snapshot: Some(snapshot),
+        }
+    }
+
+    /// Keeps the changes made to the context and releases the guard.
+    pub fn commit(mut self) {
+        self.snapshot = None;
+    }
+
+    /// Discards the changes made to the context and releases the guard.
+    pub fn rollback(self) {
+        drop(self);
+    }
+
+    /// Returns an immutable reference to the guarded context.
+    pub fn context(&self) -> &C {
+        self.context
+    }
+
+    /// Returns a mutable reference to the guarded context.
+    pub fn context_mut(&mut self) -> &mut C {
+        self.context
+    }
+}
+
+impl<C: Transactional> Drop for CommitGuard<'_, C> {
+    fn drop(&mut self) {
+        if let Some(snapshot) = self.snapshot.take() {
+            self.context.restore(snapshot);
+        }
+    }
+}
+
+/// A transaction over an executor that commits or rolls back automatically when dropped.
+///
+/// Any failed [`execute`](Self::execute) poisons the transaction. On drop, a healthy
+/// transaction keeps its changes and a poisoned one restores the context to the state
+/// it had when the transaction began, mirroring RAII database transactions.
+///
+/// Errors cannot be reported from `drop`, so callers that need to react to the outcome
+/// should call [`commit`](Self::commit) or [`rollback`](Self::rollback) explicitly.
+pub struct TransactionScope<'a, C: Transactional> {
+    /// The executor whose context is under transaction.
+    executor: &'a mut ApiExecutor<C>,
+
+    /// The state to restore on rollback, cleared once the transaction has finished.
+    snapshot: Option<C::Snapshot>,
+
+    /// Whether an operation has failed within the transaction.
+    poisoned: bool,
+}
+
+impl<C: Transactional> TransactionScope<'_, C> {
+    /// Executes an operation within the transaction, poisoning it on failure.
+    pub fn execute<P, Op>(&mut self, op: Op, parameters: &P) -> Result<Op::Output, Op::Error>
+    where
+        Op: ApiOperation<C, P>,
+    {
+        let result = self.executor.execute(op, parameters);
+        self.poisoned |= result.is_err();
+        result
+    }
+
+    /// Returns true if an operation has failed within the transaction.
+    pub fn is_poisoned(&self) -> bool {
+        self.poisoned
+    }
+
+    /// Keeps the changes made in the transaction, returning `false` if it was poisoned.
+    ///
+    /// A poisoned transaction is rolled back instead of committed.
+    pub fn commit(mut self) -> bool {
+        if self.poisoned {
+            return false;
+        }
+        self.snapshot = None;
+        true
+    }
+
+    /// Discards the changes made in the transaction.
+    pub fn rollback(mut self) {
+        self.poisoned = true;
+    }
+
+    /// Returns an immutable reference to the executor's context.
+    pub fn context(&self) -> &C {
+        self.executor.context()
+    }
+}
+
+impl<C: Transactional> Drop for TransactionScope<'_, C> {
+    fn drop(&mut self) {
+        if let Some(snapshot) = self.snapshot.take() {
+            if self.poisoned {
+                self.executor.context.restore(snapshot);
+            }
+        }
+    }
+}
+
+impl<C: Transactional> ApiExecutor<C> {
+    /// Begins a transaction that commits on drop unless one of its operations fails.
+    pub fn transaction(&mut self) -> TransactionScope<'_, C> {
+        let snapshot = self.context.snapshot();
+        TransactionScope {
+            executor: self,
+            snapshot: Some(snapshot),
+            poisoned: false,
+        }
+    }
+
+    /// Executes an API operation and returns its output together with a [`CommitGuard`].
+    ///
+    /// The operation's effects on the context persist only if the guard is committed.
+    /// If the operation fails, the context is restored before the error is returned.
+    pub fn execute_with_guard<P, Op>(
+        &mut self,
+        _op: Op,
+        parameters: &P,
+    ) -> Result<(Op::Output, CommitGuard<'_, C>), Op::Error>
+    where
+        Op: ApiOperation<C, P>,
+    {
+        let snapshot = self.context.snapshot();
+        match self.execute_observed::<P, Op>(parameters) {
+            Ok(output) => Ok((output, CommitGuard::new(&mut self.context, snapshot))),
+            Err(error) => {
+                self.context.restore(snapshot);
+                Err(error)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+
+    struct StoreValue;
+
+    impl ApiOperation<DatabaseContext, (String, String)> for StoreValue {
+        type Output = u32;
+        type Error = ();
+
+        fn execute(
+            context: &mut DatabaseContext,
+            parameters: &(String, String),
+        ) -> Result<u32, ()> {
+            context
+                .cache_mut()
+                .insert(parameters.0.clone(), parameters.1.clone());
+            context.increment_transaction();
+            Ok(context.transaction_count())
+        }
+    }
+
+    struct Reject;
+
+    impl ApiOperation<DatabaseContext, (String, String)> for Reject {
+        type Output = u32;
+        type Error = ();
+
+        fn execute(
+            _context: &mut DatabaseContext,
+            _parameters: &(String, String),
+        ) -> Result<u32, ()> {
+            Err(())
+        }
+    }
+
+    fn entry(key: &str) -> (String, String) {
+        (key.to_string(), "value".to_string())
+    }
+
+    #[test]
+    fn test_guard_rolls_back_on_drop() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("guard".to_string()));
+
+        {
+            let (count, guard) = executor
+                .execute_with_guard(StoreValue, &entry("key"))
+                .unwrap();
+            assert_eq!(count, 1);
+            assert_eq!(guard.context().transaction_count(), 1);
+        }
+
+        assert_eq!(executor.context().transaction_count(), 0);
+        assert!(executor.context().cache().is_empty());
+    }
+
+    #[test]
+    fn test_guard_commit_keeps_changes() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("guard".to_string()));
+
+        let (_, guard) = executor
+            .execute_with_guard(StoreValue, &entry("key"))
+            .unwrap();
+        guard.commit();
+
+        assert_eq!(executor.context().transaction_count(), 1);
+        assert_eq!(
+            executor.context().cache().get("key"),
+            Some(&"value".to_string())
+        );
+    }
+
+    #[test]
+    fn test_dropped_transaction_commits_unless_poisoned() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("scope".to_string()));
+
+        {
+            let mut transaction = executor.transaction();
+            transaction.execute(StoreValue, &entry("first")).unwrap();
+            transaction.execute(StoreValue, &entry("second")).unwrap();
+        }
+        assert_eq!(executor.context().transaction_count(), 2);
+
+        {
+            let mut transaction = executor.transaction();
+            transaction.execute(StoreValue, &entry("third")).unwrap();
+            transaction.execute(Reject, &entry("fourth")).unwrap_err();
+            assert!(transaction.is_poisoned());
+        }
+        assert_eq!(executor.context().transaction_count(), 2);
+        assert!(!executor.context().cache().contains_key("third"));
+    }
+
+    #[test]
+    fn test_poisoned_transaction_refuses_explicit_commit() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("scope".to_string()));
+
+        let mut transaction = executor.transaction();
+        transaction.execute(StoreValue, &entry("key")).unwrap();
+        transaction.execute(Reject, &entry("key")).unwrap_err();
+
+        assert!(!transaction.commit());
+        assert!(executor.context().cache().is_empty());
+    }
+}
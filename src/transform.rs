@@ -0,0 +1,145 @@
+//! Output transformers applied uniformly to operation results.
+
+use crate::{ApiExecutor, ApiOperation};
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// A transformer of outputs of type `O`.
+type Transformer<O> = Arc<dyn Fn(O) -> O + Send + Sync>;
+
+/// Output transformers registered on an executor, grouped by output type.
+///
+/// Clones of an executor share the transformers registered before cloning.
+#[derive(Clone, Default)]
+pub(crate) struct TransformerChain {
+    /// A `Vec<Transformer<O>>` per output type `O`, in registration order.
+    chains: HashMap<TypeId, Arc<dyn Any + Send + Sync>>,
+}
+
+impl TransformerChain {
+    /// Returns the transformers registered for outputs of type `O`.
+    fn chain<O: 'static>(&self) -> Option<&Vec<Transformer<O>>> {
+        self.chains
+            .get(&TypeId::of::<O>())
+            .and_then(|chain| chain.downcast_ref::<Vec<Transformer<O>>>())
+    }
+
+    /// Appends a transformer for outputs of type `O`.
+    fn push<O: 'static>(&mut self, transformer: Transformer<O>) {
+        let mut chain = self.chain::<O>().cloned().unwrap_or_default();
+        chain.push(transformer);
+        self.chains.insert(TypeId::of::<O>(), Arc::new(chain));
+    }
+}
+
+impl fmt::Debug for TransformerChain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TransformerChain")
+            .field("output_types", &self.chains.len())
+            .finish()
+    }
+}
+
+impl<C> ApiExecutor<C> {
+    /// Registers a transformer applied to every successful output of type `O` by
+    /// [`execute_transformed`](Self::execute_transformed).
+    ///
+    /// Transformers for the same type run in registration order, each receiving the
+    /// previous one's result. Useful for uniformly injecting computed fields.
+    pub fn with_output_transformer<O, F>(mut self, transformer: F) -> Self
+    where
+        O: 'static,
+        F: Fn(O) -> O + Send + Sync + 'static,
+    {
+        self.transformers.push::<O>(Arc::new(transformer));
+        self
+    }
+
+    /// Executes an operation and passes a successful output through every transformer
+    /// registered for its type.
+    ///
+    /// Outputs of types without transformers are returned unchanged.
+    pub fn execute_transformed<P, Op>(
+        &mut self,
+        op: Op,
+        parameters: &P,
+    ) -> Result<Op::Output, Op::Error>
+    where
+        Op: ApiOperation<C, P>,
+        Op::Output: 'static,
+    {
+        let output = self.execute(op, parameters)?;
+        Ok(match self.transformers.chain::<Op::Output>() {
+            Some(chain) => chain
+                .iter()
+                .fold(output, |output, transformer| transformer(output)),
+            None => output,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+
+    #[derive(Debug, PartialEq)]
+    struct User {
+        id: u32,
+        links: Vec<String>,
+    }
+
+    struct CreateUser;
+
+    impl ApiOperation<DatabaseContext, ()> for CreateUser {
+        type Output = User;
+        type Error = ();
+
+        fn execute(context: &mut DatabaseContext, _parameters: &()) -> Result<User, ()> {
+            context.increment_transaction();
+            Ok(User {
+                id: context.transaction_count(),
+                links: Vec::new(),
+            })
+        }
+    }
+
+    struct CountUsers;
+
+    impl ApiOperation<DatabaseContext, ()> for CountUsers {
+        type Output = u32;
+        type Error = ();
+
+        fn execute(context: &mut DatabaseContext, _parameters: &()) -> Result<u32, ()> {
+            Ok(context.transaction_count())
+        }
+    }
+
+    #[test]
+    fn test_transformers_apply_in_order() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("transform".to_string()))
+            .with_output_transformer(|mut user: User| {
+                user.links.push(format!("/users/{}", user.id));
+                user
+            })
+            .with_output_transformer(|mut user: User| {
+                let last = user.links.last().cloned().unwrap_or_default();
+                user.links.push(format!("{}/orders", last));
+                user
+            });
+
+        let user = executor.execute_transformed(CreateUser, &()).unwrap();
+        let count = executor.execute_transformed(CountUsers, &()).unwrap();
+
+        assert_eq!(
+            user,
+            User {
+                id: 1,
+                links: vec!["/users/1".to_string(), "/users/1/orders".to_string()]
+            }
+        );
+        assert_eq!(count, 1);
+    }
+}
@@ -0,0 +1,223 @@
+//! Running tuples of heterogeneous operations against one context.
+
+use crate::{ApiExecutor, ApiOperation};
+
+/// A tuple of `(operation, &parameters)` pairs that run in order against one context.
+///
+/// Implemented for tuples of two to six pairs.
+pub trait OperationTuple<C> {
+    /// A tuple holding each operation's result, in the same positions.
+    type Results;
+
+    /// Runs every operation in order against the executor's context, reporting each
+    /// like `execute` and continuing past failures.
+    fn execute_all(self, executor: &mut ApiExecutor<C>) -> Self::Results;
+}
+
+/// A tuple of results that can be summarized as an [`AllReport`].
+pub trait ResultTuple<E> {
+    /// A tuple holding each successful output, or `None` where the operation failed.
+    type Outputs;
+
+    /// Summarizes the results, converting the first error into `E`.
+    fn into_report(self) -> AllReport<Self::Outputs, E>;
+}
+
+/// A summary of a tuple of operations that may have partially failed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AllReport<O, E> {
+    /// Each successful output, or `None` where the operation failed.
+    pub outputs: O,
+
+    /// Whether each operation succeeded, by position.
+    pub succeeded: Vec<bool>,
+
+    /// The error of the first operation that failed, if any.
+    pub first_error: Option<E>,
+}
+
+impl<O, E> AllReport<O, E> {
+    /// Returns true if every operation succeeded.
+    pub fn all_succeeded(&self) -> bool {
+        self.first_error.is_none()
+    }
+}
+
+/// Implements [`OperationTuple`] and [`ResultTuple`] for one tuple arity.
+macro_rules! impl_tuples {
+    ($(($op:ident, $param:ident, $output:ident, $error:ident, $index:tt)),+) => {
+        impl<'a, C, $($op, $param),+> OperationTuple<C> for ($(($op, &'a $param),)+)
+        where
+            $($op: ApiOperation<C, $param>,)+
+        {
+            type Results = ($(Result<$op::Output, $op::Error>,)+);
+
+            fn execute_all(self, executor: &mut ApiExecutor<C>) -> Self::Results {
+                ($(executor.execute_observed::<$param, $op>((self.$index).1),)+)
+            }
+        }
+
+        impl<E, $($output, $error),+> ResultTuple<E> for ($(Result<$output, $error>,)+)
+        where
+            $($error: Into<E>,)+
+        {
+            type Outputs = ($(Option<$output>,)+);
+
+            fn into_report(self) -> AllReport<Self::Outputs, E> {
+                let mut succeeded = Vec::new();
+                let mut first_error: Option<E> = None;
+                let outputs = ($(
+                    match self.$index {
+                        Ok(output) => {
+                            succeeded.push(true);
+                            Some(output)
+                        }
+                        Err(error) => {
+                            succeeded.push(false);
+                            if first_error.is_none() {
+                                first_error = Some(error.into());
+                            }
+                            None
+                        }
+                    },
+                )+);
+                AllReport {
+                    outputs,
+                    succeeded,
+                    first_error,
+                }
+            }
+        }
+    };
+}
+
+impl_tuples!((Op0, P0, O0, E0, 0), (Op1, P1, O1, E1, 1));
+impl_tuples!(
+    (Op0, P0, O0, E0, 0),
+    (Op1, P1, O1, E1, 1),
+    (Op2, P2, O2, E2, 2)
+);
+impl_tuples!(
+    (Op0, P0, O0, E0, 0),
+    (Op1, P1, O1, E1, 1),
+    (Op2, P2, O2, E2, 2),
+    (Op3, P3, O3, E3, 3)
+);
+impl_tuples!(
+    (Op0, P0, O0, E0, 0),
+    (Op1, P1, O1, E1, 1),
+    (Op2, P2, O2, E2, 2),
+    (Op3, P3, O3, E3, 3),
+    (Op4, P4, O4, E4, 4)
+);
+impl_tuples!(
+    (Op0, P0, O0, E0, 0),
+    (Op1, P1, O1, E1, 1),
+    (Op2, P2, O2, E2, 2),
+    (Op3, P3, O3, E3, 3),
+    (Op4, P4, O4, E4, 4),
+    (Op5, P5, O5, E5, 5)
+);
+
+impl<C> ApiExecutor<C> {
+    /// Runs a tuple of operations in order and returns a tuple of their results.
+    ///
+    /// Every operation runs, even after an earlier one fails.
+    pub fn execute_all<T>(&mut self, operations: T) -> T::Results
+    where
+        T: OperationTuple<C>,
+    {
+        operations.execute_all(self)
+    }
+
+    /// Runs a tuple of operations and summarizes which of them succeeded.
+    ///
+    /// Saves destructuring every result when the caller only needs to know whether
+    /// everything succeeded and, if not, what the first error was.
+    pub fn execute_all_report<T, E>(
+        &mut self,
+        operations: T,
+    ) -> AllReport<<T::Results as ResultTuple<E>>::Outputs, E>
+    where
+        T: OperationTuple<C>,
+        T::Results: ResultTuple<E>,
+    {
+        operations.execute_all(self).into_report()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+
+    struct Store;
+    struct Fetch;
+    struct Count;
+
+    impl ApiOperation<DatabaseContext, (String, String)> for Store {
+        type Output = ();
+        type Error = String;
+
+        fn execute(
+            context: &mut DatabaseContext,
+            parameters: &(String, String),
+        ) -> Result<(), String> {
+            context
+                .cache_mut()
+                .insert(parameters.0.clone(), parameters.1.clone());
+            Ok(())
+        }
+    }
+
+    impl ApiOperation<DatabaseContext, String> for Fetch {
+        type Output = String;
+        type Error = &'static str;
+
+        fn execute(
+            context: &mut DatabaseContext,
+            parameters: &String,
+        ) -> Result<String, &'static str> {
+            context.cache().get(parameters).cloned().ok_or("not found")
+        }
+    }
+
+    impl ApiOperation<DatabaseContext, ()> for Count {
+        type Output = usize;
+        type Error = String;
+
+        fn execute(context: &mut DatabaseContext, _parameters: &()) -> Result<usize, String> {
+            Ok(context.cache().len())
+        }
+    }
+
+    #[test]
+    fn test_execute_all_returns_each_result() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("all".to_string()));
+        let entry = ("user_1".to_string(), "alice".to_string());
+        let key = "user_1".to_string();
+
+        let (stored, fetched) = executor.execute_all(((Store, &entry), (Fetch, &key)));
+
+        assert_eq!(stored, Ok(()));
+        assert_eq!(fetched, Ok("alice".to_string()));
+    }
+
+    #[test]
+    fn test_report_flags_middle_failure() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("all".to_string()));
+        let entry = ("user_1".to_string(), "alice".to_string());
+        let missing = "user_2".to_string();
+
+        let report = executor.execute_all_report::<_, String>((
+            (Store, &entry),
+            (Fetch, &missing),
+            (Count, &()),
+        ));
+
+        assert_eq!(report.succeeded, vec![true, false, true]);
+        assert_eq!(report.first_error, Some("not found".to_string()));
+        assert_eq!(report.outputs, (Some(()), None, Some(1)));
+        assert!(!report.all_succeeded());
+    }
+}
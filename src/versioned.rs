@@ -0,0 +1,208 @@
+//! Optimistic concurrency: retrying operations that lose a version race.
+
+use crate::{ApiExecutor, ApiOperation};
+use std::fmt;
+
+/// A context that tracks the version of the state it last read.
+///
+/// Operations compare this version with the stored one when writing and fail with a
+/// [`VersionConflict`] error if another writer got there first.
+pub trait Versioned {
+    /// Returns the version of the state the context last read.
+    fn version(&self) -> u64;
+
+    /// Re-reads the current state and its version from the store.
+    fn refresh_version(&mut self);
+}
+
+/// An error that may report a version conflict.
+pub trait VersionConflict {
+    /// Returns true if the operation failed because the stored version had changed.
+    fn is_version_conflict(&self) -> bool;
+}
+
+/// Errors returned by [`ApiExecutor::execute_retry_on_conflict`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConflictRetryError<E> {
+    /// Every attempt lost a version race.
+    PersistentConflict {
+        /// The number of attempts made, including the first one.
+        attempts: u32,
+
+        /// The conflict reported by the last attempt.
+        error: E,
+    },
+
+    /// The operation failed with an error other than a version conflict.
+    Operation(E),
+}
+
+impl<E: fmt::Display> fmt::Display for ConflictRetryError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConflictRetryError::PersistentConflict { attempts, error } => write!(
+                f,
+                "version conflict persisted after {} attempts: {}",
+                attempts, error
+            ),
+            ConflictRetryError::Operation(error) => error.fmt(f),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for ConflictRetryError<E> {}
+
+impl<C: Versioned> ApiExecutor<C> {
+    /// Executes an operation in a read-modify-write loop, refreshing the context's
+    /// version and re-running the operation whenever it reports a version conflict.
+    ///
+    /// The operation is attempted at most `max_retries + 1` times. Errors other than
+    /// conflicts are returned immediately.
+    pub fn execute_retry_on_conflict<P, Op>(
+        &mut self,
+        _op: Op,
+        parameters: &P,
+        max_retries: u32,
+    ) -> Result<Op::Output, ConflictRetryError<Op::Error>>
+    where
+        Op: ApiOperation<C, P>,
+        Op::Error: VersionConflict,
+    {
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let started = self.clock.now();
+            let result = Op::execute(&mut self.context, parameters);
+            self.observe(Op::name(), started, result.is_ok());
+            match result {
+                Ok(output) => return Ok(output),
+                Err(error) if !error.is_version_conflict() => {
+                    return Err(ConflictRetryError::Operation(error))
+                }
+                Err(error) if attempts > max_retries => {
+                    return Err(ConflictRetryError::PersistentConflict { attempts, error })
+                }
+                Err(_) => self.context.refresh_version(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A counter whose stored copy other writers may bump between a read and a write.
+    #[derive(Default)]
+    struct CounterContext {
+        value: u64,
+        read_version: u64,
+        stored_value: u64,
+        stored_version: u64,
+        racing_writers: u32,
+    }
+
+    impl CounterContext {
+        /// Simulates another client committing a write.
+        fn concurrent_write(&mut self) {
+            self.stored_value += 10;
+            self.stored_version += 1;
+        }
+    }
+
+    impl Versioned for CounterContext {
+        fn version(&self) -> u64 {
+            self.read_version
+        }
+
+        fn refresh_version(&mut self) {
+            self.value = self.stored_value;
+            self.read_version = self.stored_version;
+            if self.racing_writers > 0 {
+                self.racing_writers -= 1;
+                self.concurrent_write();
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum CounterError {
+        Conflict,
+        Overflow,
+    }
+
+    impl VersionConflict for CounterError {
+        fn is_version_conflict(&self) -> bool {
+            *self == CounterError::Conflict
+        }
+    }
+
+    struct Increment;
+
+    impl ApiOperation<CounterContext, u64> for Increment {
+        type Output = u64;
+        type Error = CounterError;
+
+        fn execute(context: &mut CounterContext, parameters: &u64) -> Result<u64, CounterError> {
+            let value = context
+                .value
+                .checked_add(*parameters)
+                .ok_or(CounterError::Overflow)?;
+            if context.version() != context.stored_version {
+                return Err(CounterError::Conflict);
+            }
+            context.value = value;
+            context.stored_value = value;
+            context.stored_version += 1;
+            context.read_version = context.stored_version;
+            Ok(value)
+        }
+    }
+
+    #[test]
+    fn test_conflict_on_first_attempt_succeeds_on_retry() {
+        let mut executor = ApiExecutor::new(CounterContext::default());
+        executor.context_mut().concurrent_write();
+
+        let value = executor.execute_retry_on_conflict(Increment, &1, 3);
+
+        assert_eq!(value, Ok(11));
+        assert_eq!(executor.context().stored_version, 2);
+    }
+
+    #[test]
+    fn test_exceeding_retry_limit_is_persistent_conflict() {
+        let mut executor = ApiExecutor::new(CounterContext {
+            racing_writers: 5,
+            ..CounterContext::default()
+        });
+        executor.context_mut().concurrent_write();
+
+        let result = executor.execute_retry_on_conflict(Increment, &1, 2);
+
+        assert_eq!(
+            result,
+            Err(ConflictRetryError::PersistentConflict {
+                attempts: 3,
+                error: CounterError::Conflict
+            })
+        );
+    }
+
+    #[test]
+    fn test_other_errors_are_not_retried() {
+        let mut executor = ApiExecutor::new(CounterContext {
+            value: u64::MAX,
+            racing_writers: 5,
+            ..CounterContext::default()
+        });
+
+        let result = executor.execute_retry_on_conflict(Increment, &1, 3);
+
+        assert_eq!(
+            result,
+            Err(ConflictRetryError::Operation(CounterError::Overflow))
+        );
+        assert_eq!(executor.context().racing_writers, 5);
+    }
+}
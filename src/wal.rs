@@ -0,0 +1,422 @@
+//! Write-ahead logging of mutating operations for crash recovery.
+
+use crate::{
+    ApiExecutor, ApiOperation, Identified, LogLevel, PipelineConfig, PipelineError,
+    PipelineRunError, PipelineStepConfig, Registry,
+};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// The progress of the operation recorded by a [`WalEntry`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WalStatus {
+    /// The operation was about to run and has not reported back; it may have been
+    /// interrupted by a crash.
+    Pending,
+
+    /// The operation succeeded.
+    Committed,
+
+    /// The operation failed, so it must not be replayed.
+    Aborted,
+}
+
+/// A record of one mutating operation in a write-ahead log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WalEntry {
+    /// The sequence number assigned by the sink, unique within the log.
+    pub sequence: u64,
+
+    /// The identifier of the operation, as registered in a [`Registry`].
+    pub operation: String,
+
+    /// The operation's parameters serialized as JSON.
+    pub parameters: serde_json::Value,
+
+    /// The progress of the operation.
+    pub status: WalStatus,
+}
+
+/// An error raised by a [`WalSink`] that could not durably record an entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WalSinkError {
+    /// A description of what went wrong.
+    message: String,
+}
+
+impl WalSinkError {
+    /// Creates an error from any displayable cause.
+    pub fn new(cause: impl fmt::Display) -> Self {
+        Self {
+            message: cause.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for WalSinkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for WalSinkError {}
+
+/// A durable destination for write-ahead log entries, such as an append-only file.
+///
+/// The sink assigns sequence numbers, so they stay unique when a durable log is
+/// reopened by a new executor after a restart.
+pub trait WalSink: Send + Sync {
+    /// Appends a pending entry for `operation` to the log, returning its sequence
+    /// number, or an error if it was not recorded durably.
+    fn append(&self, operation: &str, parameters: serde_json::Value) -> Result<u64, WalSinkError>;
+
+    /// Updates the status of the entry with the given sequence number.
+    fn set_status(&self, sequence: u64, status: WalStatus) -> Result<(), WalSinkError>;
+}
+
+/// A write-ahead log kept in memory, mainly for tests.
+///
+/// Clones share the same entries, so a handle can be kept after installing the sink.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryWal {
+    /// The entries logged so far, shared by every clone.
+    entries: Arc<Mutex<Vec<WalEntry>>>,
+}
+
+impl MemoryWal {
+    /// Creates an empty log.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a copy of the entries logged so far, oldest first.
+    pub fn entries(&self) -> Vec<WalEntry> {
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+}
+
+impl WalSink for MemoryWal {
+    fn append(&self, operation: &str, parameters: serde_json::Value) -> Result<u64, WalSinkError> {
+        let mut entries = self
+            .entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let sequence = entries.len() as u64;
+        entries.push(WalEntry {
+            sequence,
+            operation: operation.to_string(),
+            parameters,
+            status: WalStatus::Pending,
+        });
+        Ok(sequence)
+    }
+
+    fn set_status(&self, sequence: u64, status: WalStatus) -> Result<(), WalSinkError> {
+        let mut entries = self
+            .entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(entry) = entries.iter_mut().find(|entry| entry.sequence == sequence) {
+            entry.status = status;
+        }
+        Ok(())
+    }
+}
+
+/// The write-ahead log installed on an executor.
+///
+/// Clones of an executor share the sink.
+#[derive(Clone)]
+pub(crate) struct WalHandle {
+    /// The destination for entries.
+    sink: Arc<dyn WalSink>,
+}
+
+impl fmt::Debug for WalHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WalHandle").finish_non_exhaustive()
+    }
+}
+
+/// An error from [`ApiExecutor::execute_with_write_ahead_log`].
+#[derive(Debug)]
+pub enum WalError<E> {
+    /// The parameters could not be serialized, so the operation did not run.
+    Encode(serde_json::Error),
+
+    /// The entry could not be appended to the log, so the operation did not run.
+    Sink(WalSinkError),
+
+    /// The operation ran and failed; its entry was marked aborted.
+    Operation(E),
+}
+
+impl<E: fmt::Display> fmt::Display for WalError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WalError::Encode(error) => write!(f, "failed to encode parameters: {}", error),
+            WalError::Sink(error) => write!(f, "failed to append log entry: {}", error),
+            WalError::Operation(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl<E: fmt::Debug + fmt::Display> std::error::Error for WalError<E> {}
+
+/// An error from [`ApiExecutor::replay_wal`].
+#[derive(Debug)]
+pub enum WalReplayError {
+    /// An entry names an unknown operation or holds parameters it cannot decode.
+    Invalid(PipelineError),
+
+    /// A replayed operation failed.
+    Operation(PipelineRunError),
+}
+
+impl fmt::Display for WalReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WalReplayError::Invalid(error) => write!(f, "invalid log entry: {}", error),
+            WalReplayError::Operation(error) => write!(f, "replay failed: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for WalReplayError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            WalReplayError::Invalid(error) => Some(error),
+            WalReplayError::Operation(error) => Some(error),
+        }
+    }
+}
+
+impl<C> ApiExecutor<C> {
+    /// Records mutating operations run through `execute_with_write_ahead_log` in `sink`.
+    pub fn with_wal(mut self, sink: impl WalSink + 'static) -> Self {
+        self.wal = Some(WalHandle {
+            sink: Arc::new(sink),
+        });
+        self
+    }
+
+    /// Executes a mutating operation, logging it to the write-ahead log first.
+    ///
+    /// The entry is appended as pending before the operation runs, then marked
+    /// committed or aborted by its outcome, so entries still pending after a crash
+    /// identify interrupted work. If the entry cannot be appended, the operation does
+    /// not run. A failure to update the status is logged at error level and leaves the
+    /// entry pending, since the operation has already run. Without a log installed, the
+    /// operation just runs.
+    pub fn execute_with_write_ahead_log<P, Op>(
+        &mut self,
+        op: Op,
+        parameters: &P,
+    ) -> Result<Op::Output, WalError<Op::Error>>
+    where
+        Op: ApiOperation<C, P> + Identified,
+        P: Serialize,
+    {
+        let Some(wal) = self.wal.clone() else {
+            return self.execute(op, parameters).map_err(WalError::Operation);
+        };
+        let parameters_json = serde_json::to_value(parameters).map_err(WalError::Encode)?;
+        let sequence = wal
+            .sink
+            .append(Op::OP_ID.name(), parameters_json)
+            .map_err(WalError::Sink)?;
+
+        let result = self.execute(op, parameters);
+        let status = if result.is_ok() {
+            WalStatus::Committed
+        } else {
+            WalStatus::Aborted
+        };
+        if let Err(error) = wal.sink.set_status(sequence, status) {
+            self.logger.log(
+                LogLevel::Error,
+                Op::name(),
+                format!("failed to update log entry {}: {}", sequence, error),
+            );
+        }
+        result.map_err(WalError::Operation)
+    }
+
+    /// Replays logged operations against this executor's context, returning how many ran.
+    ///
+    /// Aborted entries are skipped; committed and pending entries run in the order
+    /// given, resolved by name through `registry`, where they must have been registered
+    /// with [`Registry::register_serde`]. Replayed operations are not logged again.
+    pub fn replay_wal(
+        &mut self,
+        registry: &Registry<C>,
+        entries: &[WalEntry],
+    ) -> Result<usize, WalReplayError> {
+        let config = PipelineConfig {
+            steps: entries
+                .iter()
+                .filter(|entry| entry.status != WalStatus::Aborted)
+                .map(|entry| PipelineStepConfig {
+                    operation: entry.operation.clone(),
+                    parameters: entry.parameters.clone(),
+                })
+                .collect(),
+        };
+        let pipeline = registry
+            .build_pipeline(&config)
+            .map_err(WalReplayError::Invalid)?;
+        let outputs = self
+            .execute_pipeline(&pipeline)
+            .map_err(WalReplayError::Operation)?;
+        Ok(outputs.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+    use crate::OperationId;
+
+    #[derive(Serialize, Deserialize)]
+    struct StoreProps {
+        key: String,
+        value: String,
+    }
+
+    struct Store;
+
+    impl Identified for Store {
+        const OP_ID: OperationId = OperationId::new("store");
+    }
+
+    impl ApiOperation<DatabaseContext, StoreProps> for Store {
+        type Output = ();
+        type Error = String;
+
+        fn execute(context: &mut DatabaseContext, parameters: &StoreProps) -> Result<(), String> {
+            if parameters.value.is_empty() {
+                return Err(format!("empty value for {}", parameters.key));
+            }
+            context
+                .cache_mut()
+                .insert(parameters.key.clone(), parameters.value.clone());
+            Ok(())
+        }
+    }
+
+    fn props(key: &str, value: &str) -> StoreProps {
+        StoreProps {
+            key: key.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_mutation_is_logged_and_committed() {
+        let wal = MemoryWal::new();
+        let mut executor =
+            ApiExecutor::new(DatabaseContext::new("wal".to_string())).with_wal(wal.clone());
+
+        executor
+            .execute_with_write_ahead_log(Store, &props("user_1", "alice"))
+            .unwrap();
+        executor
+            .execute_with_write_ahead_log(Store, &props("user_2", ""))
+            .unwrap_err();
+
+        let entries = wal.entries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].sequence, 0);
+        assert_eq!(entries[0].operation, "store");
+        assert_eq!(
+            entries[0].parameters,
+            serde_json::json!({"key": "user_1", "value": "alice"})
+        );
+        assert_eq!(entries[0].status, WalStatus::Committed);
+        assert_eq!(entries[1].status, WalStatus::Aborted);
+    }
+
+    /// A sink whose storage is unavailable.
+    struct FullDisk;
+
+    impl WalSink for FullDisk {
+        fn append(
+            &self,
+            _operation: &str,
+            _parameters: serde_json::Value,
+        ) -> Result<u64, WalSinkError> {
+            Err(WalSinkError::new("no space left on device"))
+        }
+
+        fn set_status(&self, _sequence: u64, _status: WalStatus) -> Result<(), WalSinkError> {
+            Err(WalSinkError::new("no space left on device"))
+        }
+    }
+
+    #[test]
+    fn test_failed_append_skips_the_operation() {
+        let mut executor =
+            ApiExecutor::new(DatabaseContext::new("wal".to_string())).with_wal(FullDisk);
+
+        let result = executor.execute_with_write_ahead_log(Store, &props("user_1", "alice"));
+
+        assert!(matches!(result, Err(WalError::Sink(_))));
+        assert!(executor.context().cache().is_empty());
+    }
+
+    #[test]
+    fn test_reopened_log_continues_sequence_numbers() {
+        let wal = MemoryWal::new();
+        let mut before_restart =
+            ApiExecutor::new(DatabaseContext::new("wal".to_string())).with_wal(wal.clone());
+        before_restart
+            .execute_with_write_ahead_log(Store, &props("user_1", "alice"))
+            .unwrap();
+        drop(before_restart);
+
+        let mut after_restart =
+            ApiExecutor::new(DatabaseContext::new("wal".to_string())).with_wal(wal.clone());
+        after_restart
+            .execute_with_write_ahead_log(Store, &props("user_2", ""))
+            .unwrap_err();
+
+        let entries = wal.entries();
+        assert_eq!(
+            entries
+                .iter()
+                .map(|entry| (entry.sequence, entry.status))
+                .collect::<Vec<_>>(),
+            vec![(0, WalStatus::Committed), (1, WalStatus::Aborted)]
+        );
+    }
+
+    #[test]
+    fn test_replay_reconstructs_context() {
+        let wal = MemoryWal::new();
+        let mut executor =
+            ApiExecutor::new(DatabaseContext::new("wal".to_string())).with_wal(wal.clone());
+        executor
+            .execute_with_write_ahead_log(Store, &props("user_1", "alice"))
+            .unwrap();
+        executor
+            .execute_with_write_ahead_log(Store, &props("user_2", ""))
+            .unwrap_err();
+        executor
+            .execute_with_write_ahead_log(Store, &props("user_3", "carol"))
+            .unwrap();
+
+        let mut registry = Registry::new();
+        registry.register_serde(Store).unwrap();
+        let mut recovered = ApiExecutor::new(DatabaseContext::new("recovered".to_string()));
+
+        let replayed = recovered.replay_wal(&registry, &wal.entries()).unwrap();
+
+        assert_eq!(replayed, 2);
+        assert_eq!(recovered.context().cache(), executor.context().cache());
+    }
+}
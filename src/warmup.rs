@@ -0,0 +1,106 @@
+//! Priming contexts for operations with cold-start costs.
+
+use crate::{ApiExecutor, ApiOperation};
+use std::any::TypeId;
+
+/// An operation that can prepare the context ahead of real traffic, such as by
+/// compiling queries or filling caches it reads from.
+pub trait Warmable<C> {
+    /// Primes `context` for later executions of this operation.
+    fn warm(context: &mut C);
+}
+
+impl<C> ApiExecutor<C> {
+    /// Primes the context for `Op`, typically at startup before serving requests.
+    ///
+    /// Always runs [`Warmable::warm`], even if `Op` was warmed before.
+    pub fn warmup<Op>(&mut self)
+    where
+        Op: Warmable<C> + 'static,
+    {
+        Op::warm(&mut self.context);
+        self.warmed.insert(TypeId::of::<Op>());
+    }
+
+    /// Returns true if `Op` has been warmed on this executor.
+    pub fn is_warmed<Op: 'static>(&self) -> bool {
+        self.warmed.contains(&TypeId::of::<Op>())
+    }
+
+    /// Executes an operation, warming the context for it first unless that already
+    /// happened through [`warmup`](Self::warmup) or an earlier call.
+    pub fn execute_with_warmup<P, Op>(
+        &mut self,
+        op: Op,
+        parameters: &P,
+    ) -> Result<Op::Output, Op::Error>
+    where
+        Op: ApiOperation<C, P> + Warmable<C> + 'static,
+    {
+        if !self.is_warmed::<Op>() {
+            self.warmup::<Op>();
+        }
+        self.execute(op, parameters)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::DatabaseContext;
+
+    /// Looks up a country name, loading the lookup table from the database on a miss.
+    struct CountryName;
+
+    impl ApiOperation<DatabaseContext, &'static str> for CountryName {
+        type Output = Option<String>;
+        type Error = ();
+
+        fn execute(
+            context: &mut DatabaseContext,
+            parameters: &&'static str,
+        ) -> Result<Option<String>, ()> {
+            let key = format!("country_{}", parameters);
+            if !context.cache().contains_key(&key) {
+                Self::warm(context);
+            }
+            Ok(context.cache().get(&key).cloned())
+        }
+    }
+
+    impl Warmable<DatabaseContext> for CountryName {
+        fn warm(context: &mut DatabaseContext) {
+            context.increment_transaction();
+            for (code, name) in [("fr", "France"), ("jp", "Japan")] {
+                context
+                    .cache_mut()
+                    .insert(format!("country_{}", code), name.to_string());
+            }
+        }
+    }
+
+    #[test]
+    fn test_warmup_primes_cache_for_later_execution() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("warmup".to_string()));
+
+        executor.warmup::<CountryName>();
+
+        assert!(executor.is_warmed::<CountryName>());
+        assert!(executor.context().cache().contains_key("country_fr"));
+        assert_eq!(executor.context().transaction_count(), 1);
+
+        let name = executor.execute(CountryName, &"jp").unwrap();
+        assert_eq!(name, Some("Japan".to_string()));
+        assert_eq!(executor.context().transaction_count(), 1);
+    }
+
+    #[test]
+    fn test_execute_with_warmup_warms_only_once() {
+        let mut executor = ApiExecutor::new(DatabaseContext::new("warmup".to_string()));
+
+        executor.execute_with_warmup(CountryName, &"fr").unwrap();
+        executor.execute_with_warmup(CountryName, &"jp").unwrap();
+
+        assert_eq!(executor.context().transaction_count(), 1);
+    }
+}